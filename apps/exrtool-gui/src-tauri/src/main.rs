@@ -1,35 +1,37 @@
-#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
-
-use anyhow::Result;
-use parking_lot::Mutex;
-use serde::{Deserialize, Serialize};
-use serde_json::json;
-use std::fs::OpenOptions;
-use std::io::Write as _;
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs::OpenOptions;
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH, Duration};
-
-use base64::engine::general_purpose::STANDARD as BASE64;
-use base64::Engine;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use exrtool_core::gamut::GamutMapping;
+#[cfg(feature = "use_ocio")]
+use exrtool_core::ocio::{Config as OcioConfig, Processor as OcioProcessor};
 use exrtool_core::{
-    apply_gamma, compute_image_stats, export_png, generate_preview, load_exr_basic,
-    parse_cube, srgb_encode, ImageStats, LoadedExr, Lut, PreviewImage, PreviewQuality,
+    apply_gamma, compute_image_stats, export_png, generate_preview_with, load_exr_basic,
+    parse_cube, srgb_encode, DisplayTransform, ImageStats, LoadedExr, Lut, PreviewImage,
+    PreviewPipeline, PreviewQuality, Primaries,
 };
-#[cfg(feature = "use_ocio")]
-use exrtool_core::ocio::{Config as OcioConfig, Processor as OcioProcessor};
-
-#[derive(Clone, Serialize, Deserialize)]
-struct LutPreset {
-    name: String,
-    src_space: String,
-    src_tf: String,
-    dst_space: String,
-    dst_tf: String,
-    size: u32,
-}
-
+
+#[derive(Clone, Serialize, Deserialize)]
+struct LutPreset {
+    name: String,
+    src_space: String,
+    src_tf: String,
+    dst_space: String,
+    dst_tf: String,
+    size: u32,
+}
+
 struct PresetState {
     presets: Vec<LutPreset>,
 }
@@ -46,66 +48,75 @@ struct TransformPreset {
     #[serde(default)]
     group: Option<String>,
 }
-
-struct AppState {
-    image: Option<LoadedExr>,
-    preview: Option<PreviewImage>,
-    scale: f32,       // preview座標→元画像座標への係数 (orig = preview * scale)
-    lut: Option<Lut>, // メモリ内LUT（即時プレビュー用）
-    allow_send: bool, // ログ送信許可
-    #[cfg(feature = "use_ocio")]
-    ocio_cfg: Option<OcioConfig>,
-    #[cfg(feature = "use_ocio")]
-    ocio_proc: Option<OcioProcessor>,
-    #[cfg(feature = "use_ocio")]
-    ocio_display: Option<String>,
-    #[cfg(feature = "use_ocio")]
-    ocio_view: Option<String>,
-}
-
-impl Default for AppState {
-    fn default() -> Self {
-        Self {
-            image: None,
-            preview: None,
-            scale: 1.0,
-            lut: None,
-            allow_send: false,
-            #[cfg(feature = "use_ocio")]
-            ocio_cfg: None,
-            #[cfg(feature = "use_ocio")]
-            ocio_proc: None,
-            #[cfg(feature = "use_ocio")]
-            ocio_display: None,
-            #[cfg(feature = "use_ocio")]
-            ocio_view: None,
-        }
-    }
-}
-
-struct OpenProgress {
-    cancel: AtomicBool,
-}
-
-impl Default for OpenProgress {
-    fn default() -> Self {
-        Self {
-            cancel: AtomicBool::new(false),
-        }
-    }
-}
-
-struct SeqFpsProgress {
-    cancel: AtomicBool,
-}
-
-impl Default for SeqFpsProgress {
-    fn default() -> Self {
-        Self { cancel: AtomicBool::new(false) }
-    }
-}
-
-#[derive(Clone, Serialize, Deserialize)]
+
+struct AppState {
+    image: Option<LoadedExr>,
+    preview: Option<PreviewImage>,
+    scale: f32,                  // preview座標→元画像座標への係数 (orig = preview * scale)
+    lut: Option<Lut>,            // メモリ内LUT（即時プレビュー用）
+    allow_send: bool,            // ログ送信許可
+    display: DisplayTransform,   // 表示変換（Standard / ACES RRT+ODT）
+    gamut: Option<GamutMapping>, // 色域マッピング（LUT前に適用）
+    oog_overlay: bool,           // 色域外ピクセルの縞表示
+    #[cfg(feature = "use_ocio")]
+    ocio_cfg: Option<OcioConfig>,
+    #[cfg(feature = "use_ocio")]
+    ocio_proc: Option<OcioProcessor>,
+    #[cfg(feature = "use_ocio")]
+    ocio_display: Option<String>,
+    #[cfg(feature = "use_ocio")]
+    ocio_view: Option<String>,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self {
+            image: None,
+            preview: None,
+            scale: 1.0,
+            lut: None,
+            allow_send: false,
+            display: DisplayTransform::Standard,
+            gamut: None,
+            oog_overlay: false,
+            #[cfg(feature = "use_ocio")]
+            ocio_cfg: None,
+            #[cfg(feature = "use_ocio")]
+            ocio_proc: None,
+            #[cfg(feature = "use_ocio")]
+            ocio_display: None,
+            #[cfg(feature = "use_ocio")]
+            ocio_view: None,
+        }
+    }
+}
+
+struct OpenProgress {
+    cancel: AtomicBool,
+}
+
+impl Default for OpenProgress {
+    fn default() -> Self {
+        Self {
+            cancel: AtomicBool::new(false),
+        }
+    }
+}
+
+struct SeqFpsProgress {
+    /// Shared with the batch executor, which stops starting files once set.
+    cancel: Arc<AtomicBool>,
+}
+
+impl Default for SeqFpsProgress {
+    fn default() -> Self {
+        Self {
+            cancel: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 struct AppConfig {
     send_logs: bool,
     progress_interval_ms: u64,
@@ -113,131 +124,138 @@ struct AppConfig {
     #[serde(default)]
     default_transform: String,
 }
-
+
 impl Default for AppConfig {
     fn default() -> Self {
         Self { send_logs: false, progress_interval_ms: 100, progress_pct_threshold: 0.5, default_transform: String::new() }
     }
 }
-
-const LOG_ENDPOINT: &str = "https://example.com/log";
-
-fn config_path() -> PathBuf {
-    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("config.json")
-}
-
-fn load_config() -> AppConfig {
-    std::fs::read_to_string(config_path())
-        .ok()
-        .and_then(|t| serde_json::from_str(&t).ok())
-        .unwrap_or_default()
-}
-
-fn save_config(cfg: &AppConfig) -> Result<(), String> {
-    let s = serde_json::to_string(cfg).map_err(|e| e.to_string())?;
-    std::fs::write(config_path(), s).map_err(|e| e.to_string())
-}
-
-fn send_async(line: String) {
-    std::thread::spawn(move || {
-        let client = reqwest::blocking::Client::builder()
-            .timeout(Duration::from_secs(5))
-            .build();
-        if let Ok(c) = client {
-            let _ = c
-                .post(LOG_ENDPOINT)
-                .json(&json!({ "log": line }))
-                .send();
-        }
-    });
-}
-
-#[tauri::command]
-async fn open_exr(
-    window: tauri::Window,
-    state: tauri::State<'_, Arc<Mutex<AppState>>>,
-    prog: tauri::State<'_, Arc<OpenProgress>>,
-    path: String,
-    max_size: u32,
-    exposure: f32,
-    gamma: f32,
-    lut_path: Option<String>,
-    high_quality: bool,
-) -> Result<(u32, u32, String), String> {
-    let pathbuf = PathBuf::from(&path);
-    log_append(&format!(
-        "open_exr: path='{}' max={} exp={} gamma={} lut={:?} hq={}",
-        path, max_size, exposure, gamma, lut_path, high_quality
-    ));
-    let img = exrtool_core::load_exr_basic(&pathbuf).map_err(|e| {
-        log_append(&format!("open_exr: load failed: {}", e));
-        e.to_string()
-    })?;
-    if let Some(ref p) = lut_path {
-        match std::fs::read_to_string(&p) {
-            Ok(t) => match parse_cube(&t) {
-                Ok(lut) => {
-                    state.lock().lut = Some(lut);
-                }
-                Err(e) => {
-                    log_append(&format!("open_exr: lut parse failed: {}", e));
-                }
-            },
-            Err(e) => {
-                log_append(&format!("open_exr: lut read failed '{}': {}", p, e));
-            }
-        }
-    }
-
-    prog.cancel.store(false, Ordering::SeqCst);
-    let s_lut = state.lock().lut.clone();
-    if s_lut.is_some() {
-        log_append("open_exr: using in-memory LUT");
-    } else if lut_path.is_some() {
-        log_append("open_exr: using external LUT path");
-    } else {
-        log_append("open_exr: no LUT");
-    }
-    let pq = if high_quality {
-        PreviewQuality::High
-    } else {
-        PreviewQuality::Fast
-    };
-    let preview = generate_preview(&img, max_size, exposure, gamma, s_lut.as_ref(), pq);
-    let png = image::RgbaImage::from_raw(preview.width, preview.height, preview.rgba8.clone())
-        .ok_or_else(|| "invalid image".to_string())?;
-    let mut buf: Vec<u8> = Vec::new();
-    image::DynamicImage::ImageRgba8(png)
-        .write_to(
-            &mut std::io::Cursor::new(&mut buf),
-            image::ImageOutputFormat::Png,
-        )
-        .map_err(|e| e.to_string())?;
-    let b64 = BASE64.encode(&buf);
-
-    let mut s = state.lock();
-    let scale = (img.width as f32 / preview.width as f32)
-        .max(img.height as f32 / preview.height as f32)
-        .max(1.0);
-    s.image = Some(img);
-    s.preview = Some(preview);
-    s.scale = scale;
-    log_append(&format!(
-        "open_exr: ok preview={}x{}",
-        s.preview.as_ref().unwrap().width,
-        s.preview.as_ref().unwrap().height
-    ));
-
-    window.emit("open-progress", 100.0).ok();
-
-    Ok((
-        s.preview.as_ref().unwrap().width,
-        s.preview.as_ref().unwrap().height,
-        b64,
-    ))
-}
-
-#[tauri::command]
+
+const LOG_ENDPOINT: &str = "https://example.com/log";
+
+fn config_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("config.json")
+}
+
+fn load_config() -> AppConfig {
+    std::fs::read_to_string(config_path())
+        .ok()
+        .and_then(|t| serde_json::from_str(&t).ok())
+        .unwrap_or_default()
+}
+
+fn save_config(cfg: &AppConfig) -> Result<(), String> {
+    let s = serde_json::to_string(cfg).map_err(|e| e.to_string())?;
+    std::fs::write(config_path(), s).map_err(|e| e.to_string())
+}
+
+fn send_async(line: String) {
+    std::thread::spawn(move || {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build();
+        if let Ok(c) = client {
+            let _ = c
+                .post(LOG_ENDPOINT)
+                .json(&json!({ "log": line }))
+                .send();
+        }
+    });
+}
+
+#[tauri::command]
+async fn open_exr(
+    window: tauri::Window,
+    state: tauri::State<'_, Arc<Mutex<AppState>>>,
+    prog: tauri::State<'_, Arc<OpenProgress>>,
+    path: String,
+    max_size: u32,
+    exposure: f32,
+    gamma: f32,
+    lut_path: Option<String>,
+    high_quality: bool,
+) -> Result<(u32, u32, String), String> {
+    let pathbuf = PathBuf::from(&path);
+    log_append(&format!(
+        "open_exr: path='{}' max={} exp={} gamma={} lut={:?} hq={}",
+        path, max_size, exposure, gamma, lut_path, high_quality
+    ));
+    let img = exrtool_core::load_exr_basic(&pathbuf).map_err(|e| {
+        log_append(&format!("open_exr: load failed: {}", e));
+        e.to_string()
+    })?;
+    if let Some(ref p) = lut_path {
+        match std::fs::read_to_string(&p) {
+            Ok(t) => match parse_cube(&t) {
+                Ok(lut) => {
+                    state.lock().lut = Some(lut);
+                }
+                Err(e) => {
+                    log_append(&format!("open_exr: lut parse failed: {}", e));
+                }
+            },
+            Err(e) => {
+                log_append(&format!("open_exr: lut read failed '{}': {}", p, e));
+            }
+        }
+    }
+
+    prog.cancel.store(false, Ordering::SeqCst);
+    let (s_lut, display, gamut, oog_overlay) = {
+        let s = state.lock();
+        (s.lut.clone(), s.display, s.gamut, s.oog_overlay)
+    };
+    if s_lut.is_some() {
+        log_append("open_exr: using in-memory LUT");
+    } else if lut_path.is_some() {
+        log_append("open_exr: using external LUT path");
+    } else {
+        log_append("open_exr: no LUT");
+    }
+    let pq = if high_quality {
+        PreviewQuality::High
+    } else {
+        PreviewQuality::Fast
+    };
+    let mut pipeline = PreviewPipeline::new(exposure, gamma, s_lut.as_ref());
+    pipeline.display = display;
+    pipeline.gamut = gamut;
+    pipeline.oog_overlay = oog_overlay;
+    let preview = generate_preview_with(&img, max_size, &pipeline, pq);
+    let png = image::RgbaImage::from_raw(preview.width, preview.height, preview.rgba8.clone())
+        .ok_or_else(|| "invalid image".to_string())?;
+    let mut buf: Vec<u8> = Vec::new();
+    image::DynamicImage::ImageRgba8(png)
+        .write_to(
+            &mut std::io::Cursor::new(&mut buf),
+            image::ImageOutputFormat::Png,
+        )
+        .map_err(|e| e.to_string())?;
+    let b64 = BASE64.encode(&buf);
+
+    let mut s = state.lock();
+    let scale = (img.width as f32 / preview.width as f32)
+        .max(img.height as f32 / preview.height as f32)
+        .max(1.0);
+    s.image = Some(img);
+    s.preview = Some(preview);
+    s.scale = scale;
+    log_append(&format!(
+        "open_exr: ok preview={}x{}",
+        s.preview.as_ref().unwrap().width,
+        s.preview.as_ref().unwrap().height
+    ));
+
+    window.emit("open-progress", 100.0).ok();
+
+    Ok((
+        s.preview.as_ref().unwrap().width,
+        s.preview.as_ref().unwrap().height,
+        b64,
+    ))
+}
+
+#[tauri::command]
 fn update_preview(
     state: tauri::State<'_, Arc<Mutex<AppState>>>,
     max_size: u32,
@@ -249,94 +267,98 @@ fn update_preview(
     use_state_lut: bool,
     high_quality: bool,
 ) -> Result<(u32, u32, String), String> {
-    // 事前にファイルからLUTを読み込んでおく（必要なら）
-    let lut_from_file: Option<Lut> = if !use_state_lut {
-        if let Some(p) = &lut_path {
-            match std::fs::read_to_string(p) {
-                Ok(t) => match parse_cube(&t) {
-                    Ok(l) => Some(l),
-                    Err(e) => {
-                        log_append(&format!("update_preview: lut parse failed: {}", e));
-                        None
-                    }
-                },
-                Err(e) => {
-                    log_append(&format!("update_preview: lut read failed '{}': {}", p, e));
-                    None
-                }
-            }
-        } else {
-            None
-        }
-    } else {
-        None
-    };
-
-    let mut s = state.lock();
-    let img = match s.image.as_ref() {
-        Some(img) => img,
-        None => {
-            let msg = "update_preview: image not loaded; call open_exr first";
-            log_append(msg);
-            return Err(msg.into());
-        }
-    };
-    let lut_ref = if use_state_lut {
-        s.lut.as_ref()
-    } else {
-        lut_from_file.as_ref()
-    };
-    if use_state_lut {
-        log_append(&format!(
-            "update_preview: use_state_lut={}, has_state_lut={}",
-            use_state_lut,
-            s.lut.is_some()
-        ));
-    } else {
-        log_append(&format!(
-            "update_preview: use_file_lut, file_lut_present={}",
-            lut_from_file.is_some()
-        ));
-    }
-    let pq = if high_quality {
-        PreviewQuality::High
-    } else {
-        PreviewQuality::Fast
-    };
-    let preview = generate_preview(img, max_size, exposure, gamma, lut_ref, pq);
-    let png = image::RgbaImage::from_raw(preview.width, preview.height, preview.rgba8.clone())
-        .ok_or_else(|| {
-            let msg = "update_preview: invalid preview buffer";
-            log_append(msg);
-            msg.to_string()
-        })?;
-    let mut buf: Vec<u8> = Vec::new();
-    image::DynamicImage::ImageRgba8(png)
-        .write_to(
-            &mut std::io::Cursor::new(&mut buf),
-            image::ImageOutputFormat::Png,
-        )
-        .map_err(|e| {
-            let msg = format!("update_preview: encode failed: {}", e);
-            log_append(&msg);
-            msg
-        })?;
-    let b64 = BASE64.encode(&buf);
-
-    s.scale = (img.width as f32 / preview.width as f32)
-        .max(img.height as f32 / preview.height as f32)
-        .max(1.0);
-    s.preview = Some(preview);
-    if !use_state_lut {
-        if let Some(l) = lut_from_file {
-            s.lut = Some(l);
-        }
-    }
-    Ok((
-        s.preview.as_ref().unwrap().width,
-        s.preview.as_ref().unwrap().height,
-        b64,
-    ))
+    // 事前にファイルからLUTを読み込んでおく（必要なら）
+    let lut_from_file: Option<Lut> = if !use_state_lut {
+        if let Some(p) = &lut_path {
+            match std::fs::read_to_string(p) {
+                Ok(t) => match parse_cube(&t) {
+                    Ok(l) => Some(l),
+                    Err(e) => {
+                        log_append(&format!("update_preview: lut parse failed: {}", e));
+                        None
+                    }
+                },
+                Err(e) => {
+                    log_append(&format!("update_preview: lut read failed '{}': {}", p, e));
+                    None
+                }
+            }
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+
+    let mut s = state.lock();
+    let img = match s.image.as_ref() {
+        Some(img) => img,
+        None => {
+            let msg = "update_preview: image not loaded; call open_exr first";
+            log_append(msg);
+            return Err(msg.into());
+        }
+    };
+    let lut_ref = if use_state_lut {
+        s.lut.as_ref()
+    } else {
+        lut_from_file.as_ref()
+    };
+    if use_state_lut {
+        log_append(&format!(
+            "update_preview: use_state_lut={}, has_state_lut={}",
+            use_state_lut,
+            s.lut.is_some()
+        ));
+    } else {
+        log_append(&format!(
+            "update_preview: use_file_lut, file_lut_present={}",
+            lut_from_file.is_some()
+        ));
+    }
+    let pq = if high_quality {
+        PreviewQuality::High
+    } else {
+        PreviewQuality::Fast
+    };
+    let mut pipeline = PreviewPipeline::new(exposure, gamma, lut_ref);
+    pipeline.display = s.display;
+    pipeline.gamut = s.gamut;
+    pipeline.oog_overlay = s.oog_overlay;
+    let preview = generate_preview_with(img, max_size, &pipeline, pq);
+    let png = image::RgbaImage::from_raw(preview.width, preview.height, preview.rgba8.clone())
+        .ok_or_else(|| {
+            let msg = "update_preview: invalid preview buffer";
+            log_append(msg);
+            msg.to_string()
+        })?;
+    let mut buf: Vec<u8> = Vec::new();
+    image::DynamicImage::ImageRgba8(png)
+        .write_to(
+            &mut std::io::Cursor::new(&mut buf),
+            image::ImageOutputFormat::Png,
+        )
+        .map_err(|e| {
+            let msg = format!("update_preview: encode failed: {}", e);
+            log_append(&msg);
+            msg
+        })?;
+    let b64 = BASE64.encode(&buf);
+
+    s.scale = (img.width as f32 / preview.width as f32)
+        .max(img.height as f32 / preview.height as f32)
+        .max(1.0);
+    s.preview = Some(preview);
+    if !use_state_lut {
+        if let Some(l) = lut_from_file {
+            s.lut = Some(l);
+        }
+    }
+    Ok((
+        s.preview.as_ref().unwrap().width,
+        s.preview.as_ref().unwrap().height,
+        b64,
+    ))
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -376,108 +398,108 @@ fn compute_waveform(preview: &PreviewImage, x_bins: usize, y_bins: usize) -> Wav
 
 #[tauri::command]
 fn image_stats(state: tauri::State<'_, Arc<Mutex<AppState>>>) -> Result<ImageStats, String> {
-    let s = state.lock();
-    if let Some(ref preview) = s.preview {
-        Ok(compute_image_stats(preview, 256))
-    } else {
-        Err("no preview".into())
-    }
-}
-
-#[tauri::command]
-fn image_waveform(state: tauri::State<'_, Arc<Mutex<AppState>>>) -> Result<Waveform, String> {
-    let s = state.lock();
-    if let Some(ref preview) = s.preview {
-        Ok(compute_waveform(preview, 256, 256))
-    } else {
-        Err("no preview".into())
-    }
-}
-
-#[tauri::command]
-fn probe_pixel(
-    state: tauri::State<'_, Arc<Mutex<AppState>>>,
-    px: u32,
-    py: u32,
-) -> Result<(f32, f32, f32, f32), String> {
-    let s = state.lock();
-    let (img, scale) = match (&s.image, s.scale) {
-        (Some(img), sc) => (img, sc),
-        _ => {
-            log_append("probe_pixel: image not loaded");
-            return Err("image not loaded".into());
-        }
-    };
-    let ox = ((px as f32) * scale).floor() as usize;
-    let oy = ((py as f32) * scale).floor() as usize;
-    let p = img.get_linear(ox, oy).ok_or_else(|| {
-        log_append(&format!("probe_pixel: out of range ({},{})", ox, oy));
-        "coordinate out of range".to_string()
-    })?;
-    Ok((p.r, p.g, p.b, p.a))
-}
-
-#[tauri::command]
-fn export_preview_png(
-    state: tauri::State<'_, Arc<Mutex<AppState>>>,
-    out_path: String,
-) -> Result<(), String> {
-    let s = state.lock();
-    let prev = s.preview.as_ref().ok_or_else(|| {
-        log_append("export_preview_png: no preview");
-        "preview not generated".to_string()
-    })?;
-    export_png(&PathBuf::from(&out_path), prev).map_err(|e| {
-        log_append(&format!("export_preview_png: failed '{}': {}", out_path, e));
-        e.to_string()
-    })
-}
-
-#[tauri::command]
-fn read_log() -> Result<String, String> {
-    match std::fs::read_to_string(log_path()) {
-        Ok(s) => Ok(s),
-        Err(e) => Ok(format!("<no log: {}>", e)),
-    }
-}
-
-#[tauri::command]
-fn clear_log() -> Result<(), String> {
-    std::fs::write(log_path(), "").map_err(|e| e.to_string())
-}
-
-#[tauri::command]
-fn cancel_open(prog: tauri::State<'_, Arc<OpenProgress>>) -> Result<(), String> {
-    prog.cancel.store(true, Ordering::SeqCst);
-    Ok(())
-}
-
-#[tauri::command]
-fn cancel_seq_fps(prog: tauri::State<'_, Arc<SeqFpsProgress>>) -> Result<(), String> {
-    prog.cancel.store(true, Ordering::SeqCst);
-    Ok(())
-}
-
-fn log_path() -> PathBuf {
-    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("exrtool-gui.log")
-}
-
+    let s = state.lock();
+    if let Some(ref preview) = s.preview {
+        Ok(compute_image_stats(preview, 256))
+    } else {
+        Err("no preview".into())
+    }
+}
+
+#[tauri::command]
+fn image_waveform(state: tauri::State<'_, Arc<Mutex<AppState>>>) -> Result<Waveform, String> {
+    let s = state.lock();
+    if let Some(ref preview) = s.preview {
+        Ok(compute_waveform(preview, 256, 256))
+    } else {
+        Err("no preview".into())
+    }
+}
+
+#[tauri::command]
+fn probe_pixel(
+    state: tauri::State<'_, Arc<Mutex<AppState>>>,
+    px: u32,
+    py: u32,
+) -> Result<(f32, f32, f32, f32), String> {
+    let s = state.lock();
+    let (img, scale) = match (&s.image, s.scale) {
+        (Some(img), sc) => (img, sc),
+        _ => {
+            log_append("probe_pixel: image not loaded");
+            return Err("image not loaded".into());
+        }
+    };
+    let ox = ((px as f32) * scale).floor() as usize;
+    let oy = ((py as f32) * scale).floor() as usize;
+    let p = img.get_linear(ox, oy).ok_or_else(|| {
+        log_append(&format!("probe_pixel: out of range ({},{})", ox, oy));
+        "coordinate out of range".to_string()
+    })?;
+    Ok((p.r, p.g, p.b, p.a))
+}
+
+#[tauri::command]
+fn export_preview_png(
+    state: tauri::State<'_, Arc<Mutex<AppState>>>,
+    out_path: String,
+) -> Result<(), String> {
+    let s = state.lock();
+    let prev = s.preview.as_ref().ok_or_else(|| {
+        log_append("export_preview_png: no preview");
+        "preview not generated".to_string()
+    })?;
+    export_png(&PathBuf::from(&out_path), prev).map_err(|e| {
+        log_append(&format!("export_preview_png: failed '{}': {}", out_path, e));
+        e.to_string()
+    })
+}
+
+#[tauri::command]
+fn read_log() -> Result<String, String> {
+    match std::fs::read_to_string(log_path()) {
+        Ok(s) => Ok(s),
+        Err(e) => Ok(format!("<no log: {}>", e)),
+    }
+}
+
+#[tauri::command]
+fn clear_log() -> Result<(), String> {
+    std::fs::write(log_path(), "").map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn cancel_open(prog: tauri::State<'_, Arc<OpenProgress>>) -> Result<(), String> {
+    prog.cancel.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+#[tauri::command]
+fn cancel_seq_fps(prog: tauri::State<'_, Arc<SeqFpsProgress>>) -> Result<(), String> {
+    prog.cancel.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+fn log_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("exrtool-gui.log")
+}
+
 fn log_append(msg: &str) {
-    let ts = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_millis();
-    let line = format!("[{}] {}\n", ts, msg);
-    if let Ok(mut f) = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(log_path())
-    {
-        let _ = f.write_all(line.as_bytes());
-    }
-    if load_config().send_logs {
-        send_async(line);
-    }
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let line = format!("[{}] {}\n", ts, msg);
+    if let Ok(mut f) = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path())
+    {
+        let _ = f.write_all(line.as_bytes());
+    }
+    if load_config().send_logs {
+        send_async(line);
+    }
 }
 
 fn install_panic_hook() {
@@ -498,308 +520,381 @@ fn install_panic_hook() {
     }));
 }
 
-fn generate_preview_progress(
-    img: &LoadedExr,
-    max_size: u32,
-    exposure: f32,
-    gamma: f32,
-    lut: Option<&Lut>,
-    window: &tauri::Window,
-    prog: &OpenProgress,
-    interval_ms: u64,
-    pct_threshold: f64,
-) -> Result<PreviewImage, String> {
-    use std::time::{Duration, Instant};
-    let (w, h) = (img.width as u32, img.height as u32);
-    let scale = if w <= max_size && h <= max_size {
-        1.0
-    } else {
-        (max_size as f32 / w as f32).min(max_size as f32 / h as f32)
-    };
-    let out_w = (w as f32 * scale).round().max(1.0) as u32;
-    let out_h = (h as f32 * scale).round().max(1.0) as u32;
-
-    let mut rgba8 = vec![0u8; (out_w * out_h * 4) as usize];
-    let _ = window.emit("open-progress", 0.0);
-    let mut last_emit = Instant::now();
-    let mut last_pct: f64 = 0.0;
-
-    for oy in 0..out_h {
-        for ox in 0..out_w {
-            let sx = (ox as f32) / scale;
-            let sy = (oy as f32) / scale;
-            let x0 = sx.floor().clamp(0.0, (w - 1) as f32) as i32;
-            let y0 = sy.floor().clamp(0.0, (h - 1) as f32) as i32;
-            let x1 = (x0 + 1).min(w as i32 - 1);
-            let y1 = (y0 + 1).min(h as i32 - 1);
-            let tx = (sx - x0 as f32).clamp(0.0, 1.0);
-            let ty = (sy - y0 as f32).clamp(0.0, 1.0);
-
-            let sample = |x: i32, y: i32| -> (f32, f32, f32, f32) {
-                let idx = (y as usize * img.width + x as usize) * 4;
-                (
-                    img.rgba_f32[idx + 0],
-                    img.rgba_f32[idx + 1],
-                    img.rgba_f32[idx + 2],
-                    img.rgba_f32[idx + 3],
-                )
-            };
-            let (r00, g00, b00, a00) = sample(x0, y0);
-            let (r10, g10, b10, a10) = sample(x1, y0);
-            let (r01, g01, b01, a01) = sample(x0, y1);
-            let (r11, g11, b11, a11) = sample(x1, y1);
-            let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
-            let r0 = lerp(r00, r10, tx);
-            let r1 = lerp(r01, r11, tx);
-            let mut r = lerp(r0, r1, ty);
-            let g0 = lerp(g00, g10, tx);
-            let g1 = lerp(g01, g11, tx);
-            let mut g = lerp(g0, g1, ty);
-            let b0 = lerp(b00, b10, tx);
-            let b1 = lerp(b01, b11, tx);
-            let mut b = lerp(b0, b1, ty);
-            let a0 = lerp(a00, a10, tx);
-            let a1 = lerp(a01, a11, tx);
-            let a = lerp(a0, a1, ty);
-
-            let m = 2.0f32.powf(exposure);
-            r *= m;
-            g *= m;
-            b *= m;
-
-            if let Some(l) = lut {
-                let rgb = l.apply([r, g, b]);
-                r = rgb[0];
-                g = rgb[1];
-                b = rgb[2];
-            }
-
-            let rgb = apply_gamma([r, g, b], gamma);
-            let (r8, g8, b8) = (
-                srgb_encode(rgb[0]),
-                srgb_encode(rgb[1]),
-                srgb_encode(rgb[2]),
-            );
-
-            let di = (oy * out_w + ox) as usize * 4;
-            rgba8[di + 0] = r8;
-            rgba8[di + 1] = g8;
-            rgba8[di + 2] = b8;
-            rgba8[di + 3] = (a.clamp(0.0, 1.0) * 255.0).round() as u8;
-        }
-        let pct = ((oy + 1) as f64 / out_h as f64) * 100.0;
-        if pct - last_pct >= pct_threshold
-            || last_emit.elapsed() >= Duration::from_millis(interval_ms)
-            || (oy + 1) == out_h
-        {
-            let _ = window.emit("open-progress", pct);
-            last_pct = pct;
-            last_emit = Instant::now();
-        }
-        if prog.cancel.load(Ordering::SeqCst) {
-            return Err("cancelled".to_string());
-        }
-    }
-
-    Ok(PreviewImage {
-        width: out_w,
-        height: out_h,
-        rgba8,
-    })
-}
-
-#[tauri::command]
-fn set_lut_1d(
-    state: tauri::State<'_, Arc<Mutex<AppState>>>,
-    src: String,
-    dst: String,
-    size: u32,
-) -> Result<(), String> {
-    use exrtool_core::{make_1d_lut, ColorSpace};
-    let parse = |s: &str| -> Result<ColorSpace, String> {
-        match s.to_ascii_lowercase().as_str() {
-            "linear" => Ok(ColorSpace::Linear),
-            "srgb" => Ok(ColorSpace::Srgb),
-            _ => Err(format!("unknown colorspace: {}", s)),
-        }
-    };
-    let text = make_1d_lut(parse(&src)?, parse(&dst)?, size as usize);
-    let lut = parse_cube(&text).map_err(|e| e.to_string())?;
-    state.lock().lut = Some(lut);
-    Ok(())
-}
-
-#[tauri::command]
-fn set_lut_3d(
-    state: tauri::State<'_, Arc<Mutex<AppState>>>,
-    src_space: String,
-    src_tf: String,
-    dst_space: String,
-    dst_tf: String,
-    size: u32,
-    clip_mode: String,
-) -> Result<(), String> {
-    use exrtool_core::{make_3d_lut_cube, ClipMode, Primaries, TransferFn};
+fn generate_preview_progress(
+    img: &LoadedExr,
+    max_size: u32,
+    exposure: f32,
+    gamma: f32,
+    lut: Option<&Lut>,
+    window: &tauri::Window,
+    prog: &OpenProgress,
+    interval_ms: u64,
+    pct_threshold: f64,
+) -> Result<PreviewImage, String> {
+    use std::time::{Duration, Instant};
+    let (w, h) = (img.width as u32, img.height as u32);
+    let scale = if w <= max_size && h <= max_size {
+        1.0
+    } else {
+        (max_size as f32 / w as f32).min(max_size as f32 / h as f32)
+    };
+    let out_w = (w as f32 * scale).round().max(1.0) as u32;
+    let out_h = (h as f32 * scale).round().max(1.0) as u32;
+
+    let mut rgba8 = vec![0u8; (out_w * out_h * 4) as usize];
+    let _ = window.emit("open-progress", 0.0);
+    let mut last_emit = Instant::now();
+    let mut last_pct: f64 = 0.0;
+
+    for oy in 0..out_h {
+        for ox in 0..out_w {
+            let sx = (ox as f32) / scale;
+            let sy = (oy as f32) / scale;
+            let x0 = sx.floor().clamp(0.0, (w - 1) as f32) as i32;
+            let y0 = sy.floor().clamp(0.0, (h - 1) as f32) as i32;
+            let x1 = (x0 + 1).min(w as i32 - 1);
+            let y1 = (y0 + 1).min(h as i32 - 1);
+            let tx = (sx - x0 as f32).clamp(0.0, 1.0);
+            let ty = (sy - y0 as f32).clamp(0.0, 1.0);
+
+            let sample = |x: i32, y: i32| -> (f32, f32, f32, f32) {
+                let idx = (y as usize * img.width + x as usize) * 4;
+                (
+                    img.rgba_f32[idx + 0],
+                    img.rgba_f32[idx + 1],
+                    img.rgba_f32[idx + 2],
+                    img.rgba_f32[idx + 3],
+                )
+            };
+            let (r00, g00, b00, a00) = sample(x0, y0);
+            let (r10, g10, b10, a10) = sample(x1, y0);
+            let (r01, g01, b01, a01) = sample(x0, y1);
+            let (r11, g11, b11, a11) = sample(x1, y1);
+            let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+            let r0 = lerp(r00, r10, tx);
+            let r1 = lerp(r01, r11, tx);
+            let mut r = lerp(r0, r1, ty);
+            let g0 = lerp(g00, g10, tx);
+            let g1 = lerp(g01, g11, tx);
+            let mut g = lerp(g0, g1, ty);
+            let b0 = lerp(b00, b10, tx);
+            let b1 = lerp(b01, b11, tx);
+            let mut b = lerp(b0, b1, ty);
+            let a0 = lerp(a00, a10, tx);
+            let a1 = lerp(a01, a11, tx);
+            let a = lerp(a0, a1, ty);
+
+            let m = 2.0f32.powf(exposure);
+            r *= m;
+            g *= m;
+            b *= m;
+
+            if let Some(l) = lut {
+                let rgb = l.apply([r, g, b]);
+                r = rgb[0];
+                g = rgb[1];
+                b = rgb[2];
+            }
+
+            let rgb = apply_gamma([r, g, b], gamma);
+            let (r8, g8, b8) = (
+                srgb_encode(rgb[0]),
+                srgb_encode(rgb[1]),
+                srgb_encode(rgb[2]),
+            );
+
+            let di = (oy * out_w + ox) as usize * 4;
+            rgba8[di + 0] = r8;
+            rgba8[di + 1] = g8;
+            rgba8[di + 2] = b8;
+            rgba8[di + 3] = (a.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+        let pct = ((oy + 1) as f64 / out_h as f64) * 100.0;
+        if pct - last_pct >= pct_threshold
+            || last_emit.elapsed() >= Duration::from_millis(interval_ms)
+            || (oy + 1) == out_h
+        {
+            let _ = window.emit("open-progress", pct);
+            last_pct = pct;
+            last_emit = Instant::now();
+        }
+        if prog.cancel.load(Ordering::SeqCst) {
+            return Err("cancelled".to_string());
+        }
+    }
+
+    Ok(PreviewImage {
+        width: out_w,
+        height: out_h,
+        rgba8,
+    })
+}
+
+#[tauri::command]
+fn set_lut_1d(
+    state: tauri::State<'_, Arc<Mutex<AppState>>>,
+    src: String,
+    dst: String,
+    size: u32,
+) -> Result<(), String> {
+    use exrtool_core::{make_1d_lut, ColorSpace};
+    let parse = |s: &str| -> Result<ColorSpace, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "linear" => Ok(ColorSpace::Linear),
+            "srgb" => Ok(ColorSpace::Srgb),
+            _ => Err(format!("unknown colorspace: {}", s)),
+        }
+    };
+    let text = make_1d_lut(parse(&src)?, parse(&dst)?, size as usize);
+    let lut = parse_cube(&text).map_err(|e| e.to_string())?;
+    state.lock().lut = Some(lut);
+    Ok(())
+}
+
+#[tauri::command]
+fn set_lut_3d(
+    state: tauri::State<'_, Arc<Mutex<AppState>>>,
+    src_space: String,
+    src_tf: String,
+    dst_space: String,
+    dst_tf: String,
+    size: u32,
+    clip_mode: String,
+    cat: Option<String>,
+) -> Result<(), String> {
+    use exrtool_core::{make_3d_lut_cube_with, ClipMode, Lut3dOptions, TransferFn};
     let parse_space = |s: &str| -> Result<Primaries, String> {
         match s.to_ascii_lowercase().as_str() {
             "srgb" | "rec709" | "linear" => Ok(Primaries::SrgbD65),
             "rec2020" | "bt2020" => Ok(Primaries::Rec2020D65),
+            "p3d65" | "p3-d65" => Ok(Primaries::P3D65),
             "acescg" | "ap1" => Ok(Primaries::ACEScgD60),
             "aces2065" | "ap0" | "aces" => Ok(Primaries::ACES2065_1D60),
             _ => Err(format!("unknown space: {}", s)),
         }
     };
-    let parse_tf = |s: &str| -> Result<TransferFn, String> {
-        match s.to_ascii_lowercase().as_str() {
-            "linear" => Ok(TransferFn::Linear),
-            "srgb" => Ok(TransferFn::Srgb),
-            "g24" | "gamma2.4" => Ok(TransferFn::Gamma24),
-            "g22" | "gamma2.2" => Ok(TransferFn::Gamma22),
-            _ => Err(format!("unknown transfer: {}", s)),
-        }
-    };
-    let _parse_clip = |s: &str| -> Result<ClipMode, String> {
+    let parse_tf = |s: &str| -> Result<TransferFn, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "linear" => Ok(TransferFn::Linear),
+            "srgb" => Ok(TransferFn::Srgb),
+            "g24" | "gamma2.4" => Ok(TransferFn::Gamma24),
+            "g22" | "gamma2.2" => Ok(TransferFn::Gamma22),
+            "acescct" => Ok(TransferFn::AcesCct),
+            _ => Err(format!("unknown transfer: {}", s)),
+        }
+    };
+    let mut opts = Lut3dOptions::new(
+        parse_space(&src_space)?,
+        parse_tf(&src_tf)?,
+        parse_space(&dst_space)?,
+        parse_tf(&dst_tf)?,
+    );
+    opts.size = size as usize;
+    opts.shaper_size = 1024;
+    opts.clip = clip_mode.parse::<ClipMode>().map_err(|e| e.to_string())?;
+    if let Some(c) = cat {
+        opts.cat = c.parse().map_err(|e: anyhow::Error| e.to_string())?;
+    }
+    let text = make_3d_lut_cube_with(&opts, |_| true)?;
+    let lut = parse_cube(&text).map_err(|e| e.to_string())?;
+    state.lock().lut = Some(lut);
+    Ok(())
+}
+
+/// `standard` または `aces-<odt>` を表示変換に変換
+fn parse_display(display: &str, input: Primaries) -> Result<DisplayTransform, String> {
+    let display = display.to_ascii_lowercase();
+    if display == "standard" {
+        return Ok(DisplayTransform::Standard);
+    }
+    let odt = display
+        .strip_prefix("aces-")
+        .ok_or_else(|| format!("unknown display transform: {}", display))?
+        .parse()
+        .map_err(|e: anyhow::Error| e.to_string())?;
+    Ok(DisplayTransform::Aces { input, odt })
+}
+
+/// 色域マッピングとOOGオーバーレイを設定（mode: none | clip | noclip | rgc）
+#[tauri::command]
+fn set_gamut_mapping(
+    state: tauri::State<'_, Arc<Mutex<AppState>>>,
+    mode: String,
+    working: String,
+    target: String,
+    overlay: bool,
+) -> Result<(), String> {
+    let gamut = if mode.eq_ignore_ascii_case("none") && !overlay {
+        None
+    } else {
+        let clip = if mode.eq_ignore_ascii_case("none") {
+            exrtool_core::ClipMode::NoClip
+        } else {
+            mode.parse().map_err(|e: anyhow::Error| e.to_string())?
+        };
+        Some(GamutMapping {
+            working: working.parse().map_err(|e: anyhow::Error| e.to_string())?,
+            target: target.parse().map_err(|e: anyhow::Error| e.to_string())?,
+            mode: clip,
+        })
+    };
+    log_append(&format!(
+        "set_gamut_mapping: {:?} overlay={}",
+        gamut, overlay
+    ));
+    let mut s = state.lock();
+    s.gamut = gamut;
+    s.oog_overlay = overlay;
+    Ok(())
+}
+
+#[tauri::command]
+fn set_display_transform(
+    state: tauri::State<'_, Arc<Mutex<AppState>>>,
+    display: String,
+    input_space: String,
+) -> Result<(), String> {
+    let input = input_space
+        .parse()
+        .map_err(|e: anyhow::Error| e.to_string())?;
+    let d = parse_display(&display, input)?;
+    log_append(&format!("set_display_transform: {:?}", d));
+    state.lock().display = d;
+    Ok(())
+}
+
+#[tauri::command]
+fn clear_lut(state: tauri::State<'_, Arc<Mutex<AppState>>>) -> Result<(), String> {
+    state.lock().lut = None;
+    Ok(())
+}
+
+#[tauri::command]
+fn read_metadata(path: String) -> Result<Vec<(String, String)>, String> {
+    let p = std::path::Path::new(&path);
+    // try core's read_metadata (works when feature `use_exr_crate` is enabled)
+    match exrtool_core::read_metadata(p) {
+        Ok(meta) => {
+            // Flatten headers into key-value pairs for simple display
+            let mut out: Vec<(String, String)> = Vec::new();
+            for (i, h) in meta.headers.iter().enumerate() {
+                out.push((
+                    format!("header{}.layer_name", i),
+                    h.layer_name.clone().unwrap_or_default(),
+                ));
+                out.push((
+                    format!("header{}.layer_pos", i),
+                    format!("{},{}", h.layer_position.0, h.layer_position.1),
+                ));
+                out.push((
+                    format!("header{}.layer_size", i),
+                    format!("{}x{}", h.layer_size.0, h.layer_size.1),
+                ));
+                out.push((
+                    format!("header{}.pixel_aspect", i),
+                    format!("{}", h.pixel_aspect),
+                ));
+                out.push((format!("header{}.line_order", i), h.line_order.clone()));
+            }
+            Ok(out)
+        }
+        Err(_e) => {
+            // Feature未有効など。空で返却（フロントはログに記録）
+            Ok(Vec::new())
+        }
+    }
+}
+
+#[tauri::command]
+fn make_lut(src: String, dst: String, size: u32, out_path: String) -> Result<(), String> {
+    use exrtool_core::{make_1d_lut, ColorSpace};
+    let parse = |s: &str| -> Result<ColorSpace, String> {
         match s.to_ascii_lowercase().as_str() {
-            "clip" => Ok(ClipMode::Clip),
-            "noclip" | "none" => Ok(ClipMode::NoClip),
-            _ => Err(format!("unknown clip mode: {}", s)),
+            "linear" => Ok(ColorSpace::Linear),
+            "srgb" => Ok(ColorSpace::Srgb),
+            _ => Err(format!("unknown colorspace: {}", s)),
         }
     };
-    let text = make_3d_lut_cube(
-        parse_space(&src_space)?,
-        parse_tf(&src_tf)?,
-        parse_space(&dst_space)?,
-        parse_tf(&dst_tf)?,
-        size as usize,
-        1024,
-    );
-    let lut = parse_cube(&text).map_err(|e| e.to_string())?;
-    state.lock().lut = Some(lut);
-    Ok(())
-}
-
-#[tauri::command]
-fn clear_lut(state: tauri::State<'_, Arc<Mutex<AppState>>>) -> Result<(), String> {
-    state.lock().lut = None;
-    Ok(())
-}
-
-#[tauri::command]
-fn read_metadata(path: String) -> Result<Vec<(String, String)>, String> {
-    let p = std::path::Path::new(&path);
-    // try core's read_metadata (works when feature `use_exr_crate` is enabled)
-    match exrtool_core::read_metadata(p) {
-        Ok(meta) => {
-            // Flatten headers into key-value pairs for simple display
-            let mut out: Vec<(String, String)> = Vec::new();
-            for (i, h) in meta.headers.iter().enumerate() {
-                out.push((
-                    format!("header{}.layer_name", i),
-                    h.layer_name.clone().unwrap_or_default(),
-                ));
-                out.push((
-                    format!("header{}.layer_pos", i),
-                    format!("{},{}", h.layer_position.0, h.layer_position.1),
-                ));
-                out.push((
-                    format!("header{}.layer_size", i),
-                    format!("{}x{}", h.layer_size.0, h.layer_size.1),
-                ));
-                out.push((
-                    format!("header{}.pixel_aspect", i),
-                    format!("{}", h.pixel_aspect),
-                ));
-                out.push((format!("header{}.line_order", i), h.line_order.clone()));
-            }
-            Ok(out)
-        }
-        Err(_e) => {
-            // Feature未有効など。空で返却（フロントはログに記録）
-            Ok(Vec::new())
-        }
-    }
-}
-
-#[tauri::command]
-fn make_lut(src: String, dst: String, size: u32, out_path: String) -> Result<(), String> {
-    use exrtool_core::{make_1d_lut, ColorSpace};
-    let parse = |s: &str| -> Result<ColorSpace, String> {
-        match s.to_ascii_lowercase().as_str() {
-            "linear" => Ok(ColorSpace::Linear),
-            "srgb" => Ok(ColorSpace::Srgb),
-            _ => Err(format!("unknown colorspace: {}", s)),
-        }
-    };
-    let text = make_1d_lut(parse(&src)?, parse(&dst)?, size as usize);
-    std::fs::write(out_path, text).map_err(|e| e.to_string())
-}
-
-#[tauri::command]
-fn make_lut3d(
-    window: tauri::Window,
-    src_space: String,
-    src_tf: String,
-    dst_space: String,
-    dst_tf: String,
-    size: u32,
-    out_path: String,
-) -> Result<(), String> {
-    use exrtool_core::{make_3d_lut_cube_progress, Primaries, TransferFn};
-    use std::sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    };
+    let text = make_1d_lut(parse(&src)?, parse(&dst)?, size as usize);
+    std::fs::write(out_path, text).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn make_lut3d(
+    window: tauri::Window,
+    src_space: String,
+    src_tf: String,
+    dst_space: String,
+    dst_tf: String,
+    size: u32,
+    out_path: String,
+    clip_mode: Option<String>,
+    cat: Option<String>,
+) -> Result<(), String> {
+    use exrtool_core::{make_3d_lut_cube_with, ClipMode, Lut3dOptions, TransferFn};
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
     let parse_space = |s: &str| -> Result<Primaries, String> {
         match s.to_ascii_lowercase().as_str() {
             "srgb" | "rec709" | "linear" => Ok(Primaries::SrgbD65),
             "rec2020" | "bt2020" => Ok(Primaries::Rec2020D65),
+            "p3d65" | "p3-d65" => Ok(Primaries::P3D65),
             "acescg" | "ap1" => Ok(Primaries::ACEScgD60),
             "aces2065" | "ap0" | "aces" => Ok(Primaries::ACES2065_1D60),
             _ => Err(format!("unknown space: {}", s)),
         }
     };
-    let parse_tf = |s: &str| -> Result<TransferFn, String> {
-        match s.to_ascii_lowercase().as_str() {
-            "linear" => Ok(TransferFn::Linear),
-            "srgb" => Ok(TransferFn::Srgb),
-            "g24" | "gamma2.4" => Ok(TransferFn::Gamma24),
-            "g22" | "gamma2.2" => Ok(TransferFn::Gamma22),
-            _ => Err(format!("unknown transfer: {}", s)),
-        }
-    };
-    let cancel = Arc::new(AtomicBool::new(false));
-    let cancel_clone = cancel.clone();
-    let id = window.listen("lut-cancel", move |_| {
-        cancel_clone.store(true, Ordering::SeqCst);
-    });
-    let _ = window.emit("lut-progress", 0.0);
-    let text = make_3d_lut_cube_progress(
-        parse_space(&src_space)?,
-        parse_tf(&src_tf)?,
-        parse_space(&dst_space)?,
-        parse_tf(&dst_tf)?,
-        size as usize,
-        1024,
-        |pct| {
-            let _ = window.emit("lut-progress", pct);
-            !cancel.load(Ordering::SeqCst)
-        },
-    );
-    window.unlisten(id);
-    match text {
-        Ok(t) => {
-            if cancel.load(Ordering::SeqCst) {
-                Err("cancelled".into())
-            } else {
-                let _ = window.emit("lut-progress", 100.0);
-                std::fs::write(out_path, t).map_err(|e| e.to_string())
-            }
-        }
-        Err(e) => Err(e),
-    }
-}
-
-#[tauri::command]
+    let parse_tf = |s: &str| -> Result<TransferFn, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "linear" => Ok(TransferFn::Linear),
+            "srgb" => Ok(TransferFn::Srgb),
+            "g24" | "gamma2.4" => Ok(TransferFn::Gamma24),
+            "g22" | "gamma2.2" => Ok(TransferFn::Gamma22),
+            "acescct" => Ok(TransferFn::AcesCct),
+            _ => Err(format!("unknown transfer: {}", s)),
+        }
+    };
+    let cancel = Arc::new(AtomicBool::new(false));
+    let cancel_clone = cancel.clone();
+    let id = window.listen("lut-cancel", move |_| {
+        cancel_clone.store(true, Ordering::SeqCst);
+    });
+    let _ = window.emit("lut-progress", 0.0);
+    let mut opts = Lut3dOptions::new(
+        parse_space(&src_space)?,
+        parse_tf(&src_tf)?,
+        parse_space(&dst_space)?,
+        parse_tf(&dst_tf)?,
+    );
+    opts.size = size as usize;
+    opts.shaper_size = 1024;
+    if let Some(c) = clip_mode {
+        opts.clip = c.parse::<ClipMode>().map_err(|e| e.to_string())?;
+    }
+    if let Some(c) = cat {
+        opts.cat = c.parse().map_err(|e: anyhow::Error| e.to_string())?;
+    }
+    let text = make_3d_lut_cube_with(&opts, |pct| {
+        let _ = window.emit("lut-progress", pct);
+        !cancel.load(Ordering::SeqCst)
+    });
+    window.unlisten(id);
+    match text {
+        Ok(t) => {
+            if cancel.load(Ordering::SeqCst) {
+                Err("cancelled".into())
+            } else {
+                let _ = window.emit("lut-progress", 100.0);
+                std::fs::write(out_path, t).map_err(|e| e.to_string())
+            }
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[tauri::command]
 fn lut_presets(state: tauri::State<'_, PresetState>) -> Result<Vec<LutPreset>, String> {
     Ok(state.presets.clone())
 }
@@ -821,55 +916,55 @@ fn transform_presets() -> Result<Vec<TransformPreset>, String> {
         ])
     }
 }
-
-#[tauri::command]
-fn get_log_permission(state: tauri::State<Arc<Mutex<AppState>>>) -> Result<bool, String> {
-    Ok(state.lock().allow_send)
-}
-
-#[tauri::command]
-fn set_log_permission(
-    state: tauri::State<Arc<Mutex<AppState>>>,
-    cfg: tauri::State<Arc<Mutex<AppConfig>>>,
-    allow: bool,
-) -> Result<(), String> {
-    {
-        let mut s = state.lock();
-        s.allow_send = allow;
-    }
-    {
-        let mut c = cfg.lock();
-        c.send_logs = allow;
-        save_config(&c)?;
-    }
-    Ok(())
-}
-
-#[tauri::command]
-fn write_log(s: String) -> Result<(), String> {
-    log_append(&s);
-    Ok(())
-}
-
-#[tauri::command]
-fn get_progress_config(cfg: tauri::State<Arc<Mutex<AppConfig>>>) -> Result<(u64, f64), String> {
-    let c = cfg.lock();
-    Ok((c.progress_interval_ms, c.progress_pct_threshold))
-}
-
-#[tauri::command]
+
+#[tauri::command]
+fn get_log_permission(state: tauri::State<Arc<Mutex<AppState>>>) -> Result<bool, String> {
+    Ok(state.lock().allow_send)
+}
+
+#[tauri::command]
+fn set_log_permission(
+    state: tauri::State<Arc<Mutex<AppState>>>,
+    cfg: tauri::State<Arc<Mutex<AppConfig>>>,
+    allow: bool,
+) -> Result<(), String> {
+    {
+        let mut s = state.lock();
+        s.allow_send = allow;
+    }
+    {
+        let mut c = cfg.lock();
+        c.send_logs = allow;
+        save_config(&c)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn write_log(s: String) -> Result<(), String> {
+    log_append(&s);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_progress_config(cfg: tauri::State<Arc<Mutex<AppConfig>>>) -> Result<(u64, f64), String> {
+    let c = cfg.lock();
+    Ok((c.progress_interval_ms, c.progress_pct_threshold))
+}
+
+#[tauri::command]
 fn set_progress_config(
-    cfg: tauri::State<Arc<Mutex<AppConfig>>>,
-    interval_ms: u64,
-    pct_threshold: f64,
-) -> Result<(), String> {
-    {
-        let mut c = cfg.lock();
-        c.progress_interval_ms = interval_ms;
-        c.progress_pct_threshold = pct_threshold;
-        save_config(&c)?;
-    }
-    Ok(())
+    cfg: tauri::State<Arc<Mutex<AppConfig>>>,
+    interval_ms: u64,
+    pct_threshold: f64,
+) -> Result<(), String> {
+    {
+        let mut c = cfg.lock();
+        c.progress_interval_ms = interval_ms;
+        c.progress_pct_threshold = pct_threshold;
+        save_config(&c)?;
+    }
+    Ok(())
 }
 
 #[tauri::command]
@@ -886,261 +981,506 @@ fn set_default_transform(cfg: tauri::State<Arc<Mutex<AppConfig>>>, label: String
     }
     Ok(())
 }
-// --- Video / Sequence commands ---
-#[derive(Serialize)]
-struct SeqSummary {
-    success: usize,
-    failure: usize,
-}
-#[tauri::command]
-async fn seq_fps(
-    window: tauri::Window,
-    prog: tauri::State<'_, Arc<SeqFpsProgress>>,
-    dir: String,
-    fps: f32,
-    attr: Option<String>,
-    recursive: bool,
-    dry_run: bool,
-    backup: bool,
-) -> Result<SeqSummary, String> {
-    #[cfg(feature = "exr_pure")]
-    {
-        use std::{collections::HashMap, path::PathBuf};
+// --- Video / Sequence commands ---
+#[derive(Serialize)]
+struct SeqSummary {
+    success: usize,
+    failure: usize,
+}
+#[tauri::command]
+async fn seq_fps(
+    window: tauri::Window,
+    prog: tauri::State<'_, Arc<SeqFpsProgress>>,
+    dir: String,
+    fps: f32,
+    attr: Option<String>,
+    recursive: bool,
+    dry_run: bool,
+    backup: bool,
+) -> Result<SeqSummary, String> {
+    #[cfg(feature = "exr_pure")]
+    {
+        use exrtool_core::executor::{execute, ExecOptions, Outcome};
+        use std::{collections::HashMap, path::PathBuf};
         let window_clone = window.clone();
         let prog = prog.inner().clone();
         let result = tauri::async_runtime::spawn_blocking(move || -> Result<SeqSummary, String> {
-            fn collect(
-                dir: &PathBuf,
-                recursive: bool,
-                out: &mut Vec<PathBuf>,
-            ) -> std::io::Result<()> {
-                for entry in std::fs::read_dir(dir)? {
-                    let e = entry?;
-                    let p = e.path();
-                    if p.is_dir() {
-                        if recursive {
-                            collect(&p, recursive, out)?;
-                        }
-                    } else if p
-                        .extension()
-                        .map(|s| s.to_string_lossy().to_ascii_lowercase())
-                        == Some("exr".into())
-                    {
-                        out.push(p);
-                    }
-                }
-                Ok(())
-            }
-            use std::time::{Duration, Instant};
+            fn collect(
+                dir: &PathBuf,
+                recursive: bool,
+                out: &mut Vec<PathBuf>,
+            ) -> std::io::Result<()> {
+                for entry in std::fs::read_dir(dir)? {
+                    let e = entry?;
+                    let p = e.path();
+                    if p.is_dir() {
+                        if recursive {
+                            collect(&p, recursive, out)?;
+                        }
+                    } else if p
+                        .extension()
+                        .map(|s| s.to_string_lossy().to_ascii_lowercase())
+                        == Some("exr".into())
+                    {
+                        out.push(p);
+                    }
+                }
+                Ok(())
+            }
+            use std::time::{Duration, Instant};
             let mut files = Vec::new();
-            let d = PathBuf::from(dir);
-            collect(&d, recursive, &mut files).map_err(|e| e.to_string())?;
-            files.sort_by(|a, b| a.file_name().unwrap().cmp(b.file_name().unwrap()));
+            let d = PathBuf::from(dir);
+            collect(&d, recursive, &mut files).map_err(|e| e.to_string())?;
+            files.sort_by(|a, b| a.file_name().unwrap().cmp(b.file_name().unwrap()));
             let total_files = files.len();
             let total = total_files.max(1) as f64;
-            prog.cancel.store(false, Ordering::SeqCst);
-            let _ = window_clone.emit("seq-progress", 0.0);
+            prog.cancel.store(false, Ordering::SeqCst);
+            let _ = window_clone.emit("seq-progress", 0.0);
             if dry_run {
                 let _ = window_clone.emit("seq-progress", 100.0);
                 return Ok(SeqSummary { success: total_files, failure: 0 });
             }
-            let mut map = HashMap::new();
-            map.insert(
-                attr.unwrap_or_else(|| "FramesPerSecond".into()),
-                format!("{}", fps),
-            );
-            let mut ok = 0usize;
-            let mut baks: Vec<PathBuf> = Vec::new();
+            let mut map = HashMap::new();
+            map.insert(
+                attr.unwrap_or_else(|| "framesPerSecond".into()),
+                exrtool_core::metadata::MetadataValue::Float(fps),
+            );
+            let exec = ExecOptions {
+                continue_on_error: true,
+                cancel: Some(prog.cancel.clone()),
+                ..Default::default()
+            };
+            let mut ok = 0usize;
+            let mut baks: Vec<PathBuf> = Vec::new();
             let mut last_emit = Instant::now();
-            let mut last_pct: f64 = 0.0;
-            for (i, f) in files.iter().enumerate() {
-                if prog.cancel.load(Ordering::SeqCst) {
-                    for b in baks { let _ = std::fs::remove_file(&b); }
-                    return Err("cancelled".into());
-                }
-                if backup && !dry_run {
-                    let bak = f.with_extension("exr.bak");
-                    if let Err(e) = std::fs::copy(&f, &bak) {
-                        log_append(&format!(
-                            "seq_fps backup failed {} -> {}: {}",
-                            f.display(),
-                            bak.display(),
-                            e
-                        ));
-                    } else {
-                        baks.push(bak);
-                    }
-                }
-                match exrtool_core::metadata::write_metadata(&f, &map, None) {
-                    Ok(_) => ok += 1,
-                    Err(e) => log_append(&format!("seq_fps failed {}: {}", f.display(), e)),
-                }
-                let pct = (((i as f64) + 1.0) / total * 100.0) as f64;
-                if pct - last_pct >= 0.5
-                    || last_emit.elapsed() >= Duration::from_millis(100)
-                    || (i + 1) == files.len()
-                {
-                    let _ = window_clone.emit("seq-progress", pct);
-                    last_pct = pct;
-                    last_emit = Instant::now();
-                }
-            }
-            if prog.cancel.load(Ordering::SeqCst) {
-                for b in baks { let _ = std::fs::remove_file(&b); }
-                return Err("cancelled".into());
-            }
-            if ok as f64 == total {
-                for b in baks {
-                    let _ = std::fs::remove_file(&b);
-                }
-            } else {
-                log_append("seq_fps: errors occurred; backups are kept");
-            }
+            let mut last_pct: f64 = 0.0;
+            let summary = execute(
+                &files,
+                &exec,
+                |f| {
+                    let mut bak = None;
+                    if backup {
+                        let path = f.with_extension("exr.bak");
+                        match std::fs::copy(f, &path) {
+                            Ok(_) => bak = Some(path),
+                            Err(e) => log_append(&format!(
+                                "seq_fps backup failed {} -> {}: {}",
+                                f.display(),
+                                path.display(),
+                                e
+                            )),
+                        }
+                    }
+                    exrtool_core::metadata::write_metadata(f, &map, None)?;
+                    Ok(bak)
+                },
+                |p| {
+                    let (status, error) = match p.outcome {
+                        Outcome::Done(bak) => {
+                            ok += 1;
+                            baks.extend(bak);
+                            ("written", None)
+                        }
+                        Outcome::Failed(e) => {
+                            log_append(&format!("seq_fps failed {}: {}", p.item.display(), e));
+                            ("failed", Some(e))
+                        }
+                        Outcome::Skipped => ("skipped", None),
+                        Outcome::Resumed => ("resumed", None),
+                    };
+                    let _ = window_clone.emit(
+                        "seq-item",
+                        json!({ "index": p.index, "path": p.item, "status": status, "error": error }),
+                    );
+                    let pct = p.completed as f64 / total * 100.0;
+                    if pct - last_pct >= 0.5
+                        || last_emit.elapsed() >= Duration::from_millis(100)
+                        || p.completed == p.total
+                    {
+                        let _ = window_clone.emit("seq-progress", pct);
+                        last_pct = pct;
+                        last_emit = Instant::now();
+                    }
+                },
+            )
+            .map_err(|e| e.to_string())?;
+            if summary.cancelled {
+                for b in baks { let _ = std::fs::remove_file(&b); }
+                return Err("cancelled".into());
+            }
+            if ok as f64 == total {
+                for b in baks {
+                    let _ = std::fs::remove_file(&b);
+                }
+            } else {
+                log_append("seq_fps: errors occurred; backups are kept");
+            }
             Ok(SeqSummary { success: ok, failure: total_files.saturating_sub(ok) })
         })
         .await
         .map_err(|e| e.to_string())?;
         result
-    }
-    #[cfg(not(feature = "exr_pure"))]
-    {
-        Err(
-            "This build does not include EXR metadata support. Rebuild with feature exr_pure."
-                .into(),
-        )
-    }
-}
-
-#[tauri::command]
-fn export_prores(
-    window: tauri::Window,
-    cfg: tauri::State<'_, Arc<Mutex<AppConfig>>>,
-    dir: String,
-    fps: f32,
-    colorspace: String,
-    out: String,
-    profile: String,
-    max_size: u32,
-    exposure: f32,
-    gamma: f32,
-    quality: String,
-) -> Result<(), String> {
-    use std::process::{Command, Stdio};
-    if Command::new("ffmpeg")
-        .arg("-version")
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status()
-        .is_err()
-    {
-        return Err("ffmpeg not found. Please install ffmpeg and ensure it's on PATH.".into());
-    }
-    let mut files: Vec<std::path::PathBuf> = Vec::new();
-    for entry in std::fs::read_dir(&dir).map_err(|e| e.to_string())? {
-        let e = entry.map_err(|e| e.to_string())?;
-        let p = e.path();
-        if p.is_file()
-            && p.extension()
-                .map(|s| s.to_string_lossy().to_ascii_lowercase())
-                == Some("exr".into())
-        {
-            files.push(p);
-        }
-    }
-    files.sort_by(|a, b| a.file_name().unwrap().cmp(b.file_name().unwrap()));
-    if files.is_empty() {
-        return Err("no EXR files found".into());
-    }
-    // LUT
-    let mut lut_obj = None;
-    let cs = colorspace.to_lowercase();
-    if cs != "linear:srgb" {
-        use exrtool_core::{make_3d_lut_cube, Primaries, TransferFn};
-        let (sp, dp) = if cs == "acescg:srgb" {
-            (Primaries::ACEScgD60, Primaries::SrgbD65)
-        } else if cs == "aces2065:srgb" {
-            (Primaries::ACES2065_1D60, Primaries::SrgbD65)
-        } else {
-            (Primaries::SrgbD65, Primaries::SrgbD65)
-        };
-        let text = make_3d_lut_cube(sp, TransferFn::Linear, dp, TransferFn::Srgb, 33, 1024);
-        lut_obj = Some(parse_cube(&text).map_err(|e| e.to_string())?);
-    }
-    // spawn ffmpeg
-    let mut child = Command::new("ffmpeg")
-        .arg("-y")
-        .arg("-f")
-        .arg("image2pipe")
-        .arg("-r")
-        .arg(format!("{}", fps))
-        .arg("-vcodec")
-        .arg("png")
-        .arg("-i")
-        .arg("-")
-        .arg("-c:v")
-        .arg("prores_ks")
-        .arg("-profile:v")
-        .arg(match profile.as_str() {
-            "422hq" => "3",
-            "422" => "2",
-            "4444" => "4",
-            _ => "3",
-        })
-        .arg(out)
-        .stdin(Stdio::piped())
-        .spawn()
-        .map_err(|e| e.to_string())?;
-    {
-        use image::ImageOutputFormat;
-        use std::io::Write;
-        use std::time::Instant;
-        let mut stdin = child.stdin.take().ok_or("failed to open ffmpeg stdin")?;
-        let total = files.len() as f64;
-        let cfg_lock = cfg.lock();
-        let interval_ms = cfg_lock.progress_interval_ms;
-        let pct_threshold = cfg_lock.progress_pct_threshold;
-        drop(cfg_lock);
-        let mut last_emit = Instant::now();
-        let mut last_pct: f64 = 0.0;
-        let _ = window.emit("video-progress", 0.0);
-        for (i, f) in files.iter().enumerate() {
-            let img = load_exr_basic(f).map_err(|e| e.to_string())?;
-            let pq = if quality.to_lowercase() == "high" {
-                PreviewQuality::High
-            } else {
-                PreviewQuality::Fast
-            };
-            let preview = generate_preview(&img, max_size, exposure, gamma, lut_obj.as_ref(), pq);
-            let buf = image::RgbaImage::from_raw(preview.width, preview.height, preview.rgba8)
-                .ok_or("invalid buffer")?;
-            let mut bytes: Vec<u8> = Vec::new();
-            image::DynamicImage::ImageRgba8(buf)
-                .write_to(
-                    &mut std::io::Cursor::new(&mut bytes),
-                    ImageOutputFormat::Png,
-                )
-                .map_err(|e| e.to_string())?;
-            stdin.write_all(&bytes).map_err(|e| e.to_string())?;
-            let _ = window.emit("video-progress", ((i as f64 + 1.0) / total * 100.0) as f64);
-        }
-    }
-    let status = child.wait().map_err(|e| e.to_string())?;
-    if !status.success() {
-        return Err(format!("ffmpeg exited with {:?}", status));
-    }
-    Ok(())
-}
-
+    }
+    #[cfg(not(feature = "exr_pure"))]
+    {
+        Err(
+            "This build does not include EXR metadata support. Rebuild with feature exr_pure."
+                .into(),
+        )
+    }
+}
+
+#[tauri::command]
+fn export_prores(
+    window: tauri::Window,
+    cfg: tauri::State<'_, Arc<Mutex<AppConfig>>>,
+    dir: String,
+    fps: f32,
+    colorspace: String,
+    out: String,
+    profile: String,
+    codec: Option<String>,
+    rate: Option<String>,
+    alpha: Option<bool>,
+    max_size: u32,
+    exposure: f32,
+    gamma: f32,
+    quality: String,
+    display: Option<String>,
+    color_tags: Option<String>,
+    burnins: Option<Vec<String>>,
+    mask: Option<String>,
+    slate: Option<exrtool_core::slate::Slate>,
+) -> Result<String, String> {
+    use exrtool_core::burnin::{BurnIn, BurnInOptions};
+    use exrtool_core::video::{
+        export_video, ffmpeg_available, sequence_files, CodecProfile, ColorTags, VideoColorspace,
+        VideoExportSettings,
+    };
+    use std::time::Instant;
+    let defaults = VideoExportSettings::default();
+    if !ffmpeg_available(&defaults.ffmpeg) {
+        return Err("ffmpeg not found. Please install ffmpeg and ensure it's on PATH.".into());
+    }
+    let files = sequence_files(std::path::Path::new(&dir)).map_err(|e| e.to_string())?;
+    if files.is_empty() {
+        return Err("no EXR files found".into());
+    }
+    let cs: VideoColorspace = colorspace
+        .parse()
+        .map_err(|e: anyhow::Error| e.to_string())?;
+    let display_tf = match display.as_deref() {
+        Some(d) => parse_display(d, cs.src)?,
+        None => DisplayTransform::Standard,
+    };
+    let mut settings = VideoExportSettings {
+        fps,
+        codec: CodecProfile::find(codec.as_deref().unwrap_or("prores"), Some(&profile))
+            .map_err(|e| e.to_string())?,
+        rate: match rate.as_deref().filter(|r| !r.trim().is_empty()) {
+            Some(r) => Some(r.parse().map_err(|e: anyhow::Error| e.to_string())?),
+            None => None,
+        },
+        alpha: alpha.unwrap_or(false),
+        colorspace: cs,
+        max_size,
+        exposure,
+        gamma,
+        quality: if quality.to_lowercase() == "high" {
+            PreviewQuality::High
+        } else {
+            PreviewQuality::Fast
+        },
+        display: display_tf,
+        color_tags: match color_tags.as_deref().filter(|t| !t.trim().is_empty()) {
+            Some(t) => t.parse::<ColorTags>().map_err(|e| e.to_string())?,
+            None => ColorTags::default(),
+        },
+        mask: match mask.as_deref().filter(|m| !m.trim().is_empty()) {
+            Some(m) => Some(m.parse().map_err(|e: anyhow::Error| e.to_string())?),
+            None => None,
+        },
+        slate,
+        ..defaults
+    };
+    let texts: Vec<_> = burnins
+        .unwrap_or_default()
+        .iter()
+        .filter(|t| !t.trim().is_empty())
+        .map(|t| t.parse().map_err(|e: anyhow::Error| e.to_string()))
+        .collect::<Result<_, _>>()?;
+    if !texts.is_empty() {
+        let opts = BurnInOptions {
+            texts,
+            lut: Some(display.clone().unwrap_or_else(|| colorspace.clone())),
+            rate: fps.to_string().parse().ok(),
+            ..Default::default()
+        };
+        settings.burnin = Some(BurnIn::new(&opts).map_err(|e| format!("{:#}", e))?);
+    }
+    let (interval_ms, pct_threshold) = {
+        let c = cfg.lock();
+        (c.progress_interval_ms, c.progress_pct_threshold)
+    };
+    let mut last_emit = Instant::now();
+    let mut last_pct: f64 = 0.0;
+    let _ = window.emit("video-progress", 0.0);
+    export_video(&files, std::path::Path::new(&out), &settings, None, |p| {
+        let pct = p.completed as f64 / p.total as f64 * 100.0;
+        if pct - last_pct >= pct_threshold
+            || last_emit.elapsed() >= Duration::from_millis(interval_ms)
+            || p.completed == p.total
+        {
+            let _ = window.emit("video-progress", pct);
+            last_pct = pct;
+            last_emit = Instant::now();
+        }
+        true
+    })
+    .map_err(|e| {
+        log_append(&format!("export_prores failed: {:#}", e));
+        format!("{:#}", e)
+    })?;
+    let tags = settings.resolved_color_tags().to_string();
+    log_append(&format!("export_prores: wrote {} ({})", out, tags));
+    Ok(tags)
+}
+
+#[tauri::command]
+fn export_stills(
+    window: tauri::Window,
+    cfg: tauri::State<'_, Arc<Mutex<AppConfig>>>,
+    dir: String,
+    out: String,
+    format: String,
+    jpeg_quality: Option<u8>,
+    tags: Option<String>,
+    max_size: u32,
+    gamma: f32,
+    quality: String,
+    display: Option<String>,
+    input_space: Option<String>,
+    mask: Option<String>,
+    slate: Option<exrtool_core::slate::Slate>,
+) -> Result<String, String> {
+    use exrtool_core::batch::{
+        ColourSettings, ItemStatus, Job, JobFile, RunOptions, JOB_FILE_VERSION,
+    };
+    use std::time::Instant;
+    // every EXR in the folder, with the folder taken literally
+    let escaped: String = dir
+        .chars()
+        .map(|c| {
+            if "[]*?".contains(c) {
+                format!("[{}]", c)
+            } else {
+                c.to_string()
+            }
+        })
+        .collect();
+    let job = Job {
+        name: None,
+        input: Some(format!("{}/*.exr", escaped)),
+        sequence: None,
+        frames: None,
+        output: out.clone(),
+        format: format.parse().map_err(|e: anyhow::Error| e.to_string())?,
+        jpeg_quality: jpeg_quality.unwrap_or(90),
+        tags: tags.filter(|t| !t.trim().is_empty()),
+        max_size,
+        quality: if quality.to_lowercase() == "high" {
+            PreviewQuality::High
+        } else {
+            PreviewQuality::Fast
+        },
+        colour: ColourSettings {
+            gamma,
+            display: display.unwrap_or_else(|| "standard".into()),
+            input_space: input_space.unwrap_or_else(|| "acescg".into()),
+            ..Default::default()
+        },
+        mask: match mask.as_deref().filter(|m| !m.trim().is_empty()) {
+            Some(m) => Some(m.parse().map_err(|e: anyhow::Error| e.to_string())?),
+            None => None,
+        },
+        slate,
+    };
+    let file = JobFile {
+        version: JOB_FILE_VERSION,
+        continue_on_error: true,
+        log: None,
+        jobs: vec![job],
+        base: PathBuf::new(),
+        legacy: false,
+    };
+    let (interval_ms, pct_threshold) = {
+        let c = cfg.lock();
+        (c.progress_interval_ms, c.progress_pct_threshold)
+    };
+    let mut last_emit = Instant::now();
+    let mut last_pct: f64 = 0.0;
+    let _ = window.emit("stills-progress", 0.0);
+    let report = file
+        .run(&RunOptions::default(), |r, done, total| {
+            if r.status == ItemStatus::Failed {
+                log_append(&format!(
+                    "export_stills failed {}: {}",
+                    r.input.display(),
+                    r.error.as_deref().unwrap_or_default()
+                ));
+            }
+            let pct = done as f64 / total as f64 * 100.0;
+            if pct - last_pct >= pct_threshold
+                || last_emit.elapsed() >= Duration::from_millis(interval_ms)
+                || done == total
+            {
+                let _ = window.emit("stills-progress", pct);
+                last_pct = pct;
+                last_emit = Instant::now();
+            }
+        })
+        .map_err(|e| {
+            log_append(&format!("export_stills failed: {:#}", e));
+            format!("{:#}", e)
+        })?;
+    let summary = report.summary().to_string();
+    log_append(&format!("export_stills: {} -> {} ({})", dir, out, summary));
+    Ok(summary)
+}
+
+#[tauri::command]
+fn export_anim(
+    window: tauri::Window,
+    cfg: tauri::State<'_, Arc<Mutex<AppConfig>>>,
+    dir: String,
+    out: String,
+    format: Option<String>,
+    fps: f32,
+    step: Option<usize>,
+    loops: Option<u16>,
+    colors: Option<u16>,
+    dither: Option<String>,
+    colorspace: String,
+    alpha: Option<bool>,
+    max_size: u32,
+    exposure: f32,
+    gamma: f32,
+    quality: String,
+    display: Option<String>,
+    burnins: Option<Vec<String>>,
+    mask: Option<String>,
+    slate: Option<exrtool_core::slate::Slate>,
+) -> Result<String, String> {
+    use exrtool_core::anim::{export_animation, AnimationFormat, AnimationSettings};
+    use exrtool_core::burnin::{BurnIn, BurnInOptions};
+    use exrtool_core::video::{sequence_files, FrameRange, VideoColorspace, VideoExportSettings};
+    use std::time::Instant;
+    let out_path = std::path::Path::new(&out);
+    let format = match format.as_deref().filter(|f| !f.trim().is_empty()) {
+        Some(f) => f.parse::<AnimationFormat>().map_err(|e| e.to_string())?,
+        None => {
+            AnimationFormat::from_path(out_path).ok_or("output must end in .gif, .png or .webp")?
+        }
+    };
+    let files = sequence_files(std::path::Path::new(&dir)).map_err(|e| e.to_string())?;
+    if files.is_empty() {
+        return Err("no EXR files found".into());
+    }
+    let cs: VideoColorspace = colorspace
+        .parse()
+        .map_err(|e: anyhow::Error| e.to_string())?;
+    let display_tf = match display.as_deref() {
+        Some(d) => parse_display(d, cs.src)?,
+        None => DisplayTransform::Standard,
+    };
+    let defaults = AnimationSettings::default();
+    let mut settings = AnimationSettings {
+        format,
+        loops: loops.unwrap_or(defaults.loops),
+        colors: colors.unwrap_or(defaults.colors),
+        dither: match dither.as_deref().filter(|d| !d.trim().is_empty()) {
+            Some(d) => d.parse().map_err(|e: anyhow::Error| e.to_string())?,
+            None => defaults.dither,
+        },
+        frames: VideoExportSettings {
+            fps,
+            range: FrameRange {
+                step: step.unwrap_or(1),
+                ..Default::default()
+            },
+            alpha: alpha.unwrap_or(false),
+            colorspace: cs,
+            max_size,
+            exposure,
+            gamma,
+            quality: if quality.to_lowercase() == "high" {
+                PreviewQuality::High
+            } else {
+                PreviewQuality::Fast
+            },
+            display: display_tf,
+            mask: match mask.as_deref().filter(|m| !m.trim().is_empty()) {
+                Some(m) => Some(m.parse().map_err(|e: anyhow::Error| e.to_string())?),
+                None => None,
+            },
+            slate,
+            ..defaults.frames
+        },
+    };
+    let texts: Vec<_> = burnins
+        .unwrap_or_default()
+        .iter()
+        .filter(|t| !t.trim().is_empty())
+        .map(|t| t.parse().map_err(|e: anyhow::Error| e.to_string()))
+        .collect::<Result<_, _>>()?;
+    if !texts.is_empty() {
+        let opts = BurnInOptions {
+            texts,
+            lut: Some(display.clone().unwrap_or_else(|| colorspace.clone())),
+            rate: fps.to_string().parse().ok(),
+            ..Default::default()
+        };
+        settings.frames.burnin = Some(BurnIn::new(&opts).map_err(|e| format!("{:#}", e))?);
+    }
+    let (interval_ms, pct_threshold) = {
+        let c = cfg.lock();
+        (c.progress_interval_ms, c.progress_pct_threshold)
+    };
+    let mut last_emit = Instant::now();
+    let mut last_pct: f64 = 0.0;
+    let _ = window.emit("anim-progress", 0.0);
+    export_animation(&files, out_path, &settings, None, |p| {
+        let pct = p.completed as f64 / p.total as f64 * 100.0;
+        if pct - last_pct >= pct_threshold
+            || last_emit.elapsed() >= Duration::from_millis(interval_ms)
+            || p.completed == p.total
+        {
+            let _ = window.emit("anim-progress", pct);
+            last_pct = pct;
+            last_emit = Instant::now();
+        }
+        true
+    })
+    .map_err(|e| {
+        log_append(&format!("export_anim failed: {:#}", e));
+        format!("{:#}", e)
+    })?;
+    let size = std::fs::metadata(out_path).map(|m| m.len()).unwrap_or(0);
+    let summary = format!("{}, {} KiB", format, size.div_ceil(1024));
+    log_append(&format!("export_anim: wrote {} ({})", out, summary));
+    Ok(summary)
+}
+
 fn main() {
     install_panic_hook();
     log_append("boot: starting tauri builder");
-    let preset_path =
-        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../../config/luts.presets.json");
-    let presets: Vec<LutPreset> = std::fs::read_to_string(&preset_path)
-        .ok()
-        .and_then(|t| serde_json::from_str(&t).ok())
-        .unwrap_or_default();
-
+    let preset_path =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../../config/luts.presets.json");
+    let presets: Vec<LutPreset> = std::fs::read_to_string(&preset_path)
+        .ok()
+        .and_then(|t| serde_json::from_str(&t).ok())
+        .unwrap_or_default();
+
     let cfg = load_config();
     let app_state = AppState { allow_send: cfg.send_logs, ..Default::default() };
     let cfg_state = Arc::new(Mutex::new(cfg));
@@ -1162,24 +1502,28 @@ fn main() {
             probe_pixel,
             export_preview_png,
             read_log,
-            clear_log,
-            cancel_open,
-            cancel_seq_fps,
-            set_lut_1d,
-            set_lut_3d,
-            clear_lut,
-            lut_presets,
-            read_metadata,
-            make_lut,
-            make_lut3d,
-            set_log_permission,
-            get_log_permission,
-            get_progress_config,
-            set_progress_config,
-            write_log,
-            seq_fps,
-            export_prores,
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
-}
+            clear_log,
+            cancel_open,
+            cancel_seq_fps,
+            set_lut_1d,
+            set_lut_3d,
+            clear_lut,
+            set_display_transform,
+            set_gamut_mapping,
+            lut_presets,
+            read_metadata,
+            make_lut,
+            make_lut3d,
+            set_log_permission,
+            get_log_permission,
+            get_progress_config,
+            set_progress_config,
+            write_log,
+            seq_fps,
+            export_prores,
+            export_stills,
+            export_anim,
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
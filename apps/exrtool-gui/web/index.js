@@ -4,9 +4,9 @@
   const STATS_BINS = 256;            // ヒストグラム/波形のビン数
   const UPDATE_DEBOUNCE_MS = 120;    // プレビュー更新のデバウンス
   let invoke = null; // 解決済みの invoke（nullなら未解決）
-  let imgW = 0, imgH = 0;
+  let imgW = 0, imgH = 0;
   let useStateLutEnabled = true; // LUT in-memory 使用フラグ（既定ON固定）
-  let pipetteFixed = false; // スポイト固定
+  let pipetteFixed = false; // スポイト固定
   let stats = null;
   let waveform = null;
   let scopeChannel = 'rgb';
@@ -15,39 +15,39 @@
   let compareMode = false;
   let currImgData = null;
   let prevImgData = null;
-
-  // 簡易デバウンス
-  function debounce(fn, ms) {
-    let t = null;
-    return (...args) => {
-      if (t) clearTimeout(t);
-      t = setTimeout(() => fn(...args), ms);
-    };
-  }
-
-  function getEl(id) {
-    const el = document.getElementById(id);
-    if (!el) console.error(`element #${id} not found`);
-    return el;
-  }
-
-  function appendLog(msg) {
-    const logbox = document.getElementById('logbox');
-    const ts = new Date().toISOString();
-    const line = `[${ts}] ${msg}\n`;
-    if (logbox) {
-      logbox.textContent += line;
-      logbox.scrollTop = logbox.scrollHeight;
-    }
-    console.log(line);
-  }
-
-  // UI + ファイル両方へログ（可能なら）
-  async function logBoth(msg) {
-    appendLog(msg);
-    try { if (invoke || await ensureTauriReady()) { await invoke('write_log', { s: msg }); } } catch (_) {}
-  }
-
+
+  // 簡易デバウンス
+  function debounce(fn, ms) {
+    let t = null;
+    return (...args) => {
+      if (t) clearTimeout(t);
+      t = setTimeout(() => fn(...args), ms);
+    };
+  }
+
+  function getEl(id) {
+    const el = document.getElementById(id);
+    if (!el) console.error(`element #${id} not found`);
+    return el;
+  }
+
+  function appendLog(msg) {
+    const logbox = document.getElementById('logbox');
+    const ts = new Date().toISOString();
+    const line = `[${ts}] ${msg}\n`;
+    if (logbox) {
+      logbox.textContent += line;
+      logbox.scrollTop = logbox.scrollHeight;
+    }
+    console.log(line);
+  }
+
+  // UI + ファイル両方へログ（可能なら）
+  async function logBoth(msg) {
+    appendLog(msg);
+    try { if (invoke || await ensureTauriReady()) { await invoke('write_log', { s: msg }); } } catch (_) {}
+  }
+
   function showError(msg) {
     let el = document.getElementById('errordiv');
     if (!el) {
@@ -135,7 +135,7 @@
       processExportQueue();
     }
   }
-
+
   async function ensureTauriReady(timeoutMs = 5000) {
     const start = Date.now();
     while (Date.now() - start < timeoutMs) {
@@ -248,72 +248,72 @@
     }
     document.body.appendChild(ctxMenu);
   });
-
-  function drawHistogram(s) {
-    const cv = getEl('hist');
-    if (!cv || !s) return;
-    const ctx = cv.getContext('2d');
-    ctx.clearRect(0,0,cv.width,cv.height);
-    const channels = scopeChannel === 'rgb' ? ['r','g','b'] : [scopeChannel];
-    const colors = { r:'red', g:'green', b:'blue' };
-    for (const ch of channels) {
-      const hist = s['hist_' + ch];
-      if (!hist) continue;
-      const max = Math.max(...hist) || 1;
-      ctx.strokeStyle = colors[ch];
-      ctx.beginPath();
-      hist.forEach((v,i)=>{
-        const h = Math.min(cv.height, (v/max)*cv.height*scopeScale);
-        ctx.moveTo(i, cv.height);
-        ctx.lineTo(i, cv.height - h);
-      });
-      ctx.stroke();
-    }
-  }
-
-  function drawWaveform(wf) {
-    const cv = getEl('waveform');
-    if (!cv || !wf) return;
-    const ctx = cv.getContext('2d');
-    const width = cv.width;
-    const height = cv.height;
-    ctx.clearRect(0,0,width,height);
-    const channels = scopeChannel === 'rgb' ? ['r','g','b'] : [scopeChannel];
-    const colors = { r:'red', g:'green', b:'blue' };
-    const xb = wf.x_bins;
-    const yb = wf.y_bins;
-    const sx = width / xb;
-    const sy = height / yb;
-    ctx.globalAlpha = 1;
-    for (const ch of channels) {
-      const arr = wf[ch];
-      if (!arr) continue;
-      ctx.fillStyle = colors[ch];
-      for (let x=0; x<xb; x++) {
-        for (let y=0; y<yb; y++) {
-          const c = arr[x*yb + y];
-          if (c>0) {
-            const alpha = Math.min(1, c * scopeScale / 10);
-            ctx.globalAlpha = alpha;
-            ctx.fillRect(x*sx, height - (y+1)*sy, sx, sy);
-          }
-        }
-      }
-    }
-    ctx.globalAlpha = 1;
-  }
-
+
+  function drawHistogram(s) {
+    const cv = getEl('hist');
+    if (!cv || !s) return;
+    const ctx = cv.getContext('2d');
+    ctx.clearRect(0,0,cv.width,cv.height);
+    const channels = scopeChannel === 'rgb' ? ['r','g','b'] : [scopeChannel];
+    const colors = { r:'red', g:'green', b:'blue' };
+    for (const ch of channels) {
+      const hist = s['hist_' + ch];
+      if (!hist) continue;
+      const max = Math.max(...hist) || 1;
+      ctx.strokeStyle = colors[ch];
+      ctx.beginPath();
+      hist.forEach((v,i)=>{
+        const h = Math.min(cv.height, (v/max)*cv.height*scopeScale);
+        ctx.moveTo(i, cv.height);
+        ctx.lineTo(i, cv.height - h);
+      });
+      ctx.stroke();
+    }
+  }
+
+  function drawWaveform(wf) {
+    const cv = getEl('waveform');
+    if (!cv || !wf) return;
+    const ctx = cv.getContext('2d');
+    const width = cv.width;
+    const height = cv.height;
+    ctx.clearRect(0,0,width,height);
+    const channels = scopeChannel === 'rgb' ? ['r','g','b'] : [scopeChannel];
+    const colors = { r:'red', g:'green', b:'blue' };
+    const xb = wf.x_bins;
+    const yb = wf.y_bins;
+    const sx = width / xb;
+    const sy = height / yb;
+    ctx.globalAlpha = 1;
+    for (const ch of channels) {
+      const arr = wf[ch];
+      if (!arr) continue;
+      ctx.fillStyle = colors[ch];
+      for (let x=0; x<xb; x++) {
+        for (let y=0; y<yb; y++) {
+          const c = arr[x*yb + y];
+          if (c>0) {
+            const alpha = Math.min(1, c * scopeScale / 10);
+            ctx.globalAlpha = alpha;
+            ctx.fillRect(x*sx, height - (y+1)*sy, sx, sy);
+          }
+        }
+      }
+    }
+    ctx.globalAlpha = 1;
+  }
+
   async function refreshScopes() {
-    try {
-      if (!(invoke || await ensureTauriReady())) return;
-      const [s, wf] = await Promise.all([
-        invoke('image_stats'),
-        invoke('image_waveform'),
-      ]);
-      stats = s; waveform = wf;
-      drawHistogram(stats);
-      drawWaveform(waveform);
-    } catch (e) { console.error('refreshScopes failed', e); }
+    try {
+      if (!(invoke || await ensureTauriReady())) return;
+      const [s, wf] = await Promise.all([
+        invoke('image_stats'),
+        invoke('image_waveform'),
+      ]);
+      stats = s; waveform = wf;
+      drawHistogram(stats);
+      drawWaveform(waveform);
+    } catch (e) { console.error('refreshScopes failed', e); }
   }
 
   function updateInfoText() {
@@ -346,12 +346,12 @@
   async function openExr() {
     const pathEl = getEl('path');
     // OCIO要素の参照は必要時のみ取得（openExr内では未使用）
-    const cv = getEl('cv');
-    const info = getEl('info');
-    if (!pathEl || !cv || !info) return;
-    const ctx = cv.getContext('2d');
-
-    const path = pathEl.value.trim();
+    const cv = getEl('cv');
+    const info = getEl('info');
+    if (!pathEl || !cv || !info) return;
+    const ctx = cv.getContext('2d');
+
+    const path = pathEl.value.trim();
     const lutPath = null; // 外部LUT読込は廃止
     try {
       if (!(await ensureTauriReady())) throw new Error('Tauri API が利用できません');
@@ -378,20 +378,20 @@
         appendLog(`open ok: ${w}x${h}`);
       };
       img.src = 'data:image/png;base64,' + b64;
-      await loadMetadata(path);
-      await refreshScopes();
-    } catch (e) {
-      if (String(e).includes('cancelled')) {
-        appendLog('読み込みキャンセル');
-      } else {
-        appendLog('読み込み失敗: ' + e);
-        alert('読み込み失敗: ' + e);
-      }
-    } finally {
-      // progress UI は未配線のため no-op
-    }
-  }
-
+      await loadMetadata(path);
+      await refreshScopes();
+    } catch (e) {
+      if (String(e).includes('cancelled')) {
+        appendLog('読み込みキャンセル');
+      } else {
+        appendLog('読み込み失敗: ' + e);
+        alert('読み込み失敗: ' + e);
+      }
+    } finally {
+      // progress UI は未配線のため no-op
+    }
+  }
+
   async function loadMetadata(path) {
     if (!attrTable) return;
     const tbody = attrTable.querySelector('tbody');
@@ -410,7 +410,7 @@
       }
     } catch (e) { appendLog('metadata読み込み失敗: ' + e); }
   }
-
+
   document.addEventListener('DOMContentLoaded', () => {
     // Tabs
     const tabBtnPreview = document.getElementById('tab-btn-preview');
@@ -429,8 +429,8 @@
     const openBtn = getEl('open');
     const browseBtn = getEl('browse');
     const saveBtn = getEl('save');
-    const cv = getEl('cv');
-    const pathEl = getEl('path');
+    const cv = getEl('cv');
+    const pathEl = getEl('path');
     // HQ/LUT UIは廃止（既定ON）
     const hqEl = null;
     const lutSize = null;
@@ -449,13 +449,13 @@
     const viewRgbBtn = getEl('btn-view-rgb');
     const viewAlphaBtn = getEl('btn-view-alpha');
     const compareBtn = getEl('btn-compare');
-
+
     useStateLutEnabled = true;
-    scopeChannelEl?.addEventListener('change', () => {
-      scopeChannel = scopeChannelEl.value;
-      drawHistogram(stats);
-      drawWaveform(waveform);
-    });
+    scopeChannelEl?.addEventListener('change', () => {
+      scopeChannel = scopeChannelEl.value;
+      drawHistogram(stats);
+      drawWaveform(waveform);
+    });
     scopeScaleEl?.addEventListener('change', () => {
       scopeScale = parseInt(scopeScaleEl.value)||1;
       drawHistogram(stats);
@@ -488,7 +488,7 @@
       else if (e.key === 'l' || e.key === 'ArrowRight') setChannel('a');
       else if (e.key === 'k' || e.key === 'ArrowUp' || e.key === 'ArrowDown') toggleCompare();
     });
-
+
     if (openBtn) openBtn.addEventListener('click', openExr);
 
     // Transform 一覧ロード（Resolve風）
@@ -561,6 +561,42 @@
       } catch (e) { appendLog('Transform適用失敗: ' + e); }
     });
 
+    // 表示変換（Standard / ACES RRT+ODT）
+    const displayTransformEl = getEl('display-transform');
+    const displayInputEl = getEl('display-input');
+    async function applyDisplayTransform() {
+      try {
+        if (!(await ensureTauriReady())) return;
+        const display = displayTransformEl?.value || 'standard';
+        const inputSpace = displayInputEl?.value || 'acescg';
+        await invoke('set_display_transform', { display, inputSpace });
+        updateLater();
+        await logBoth('Display適用: ' + display + ' (input=' + inputSpace + ')');
+      } catch (e) { appendLog('Display適用失敗: ' + e); }
+    }
+    if (displayTransformEl) displayTransformEl.addEventListener('change', applyDisplayTransform);
+    if (displayInputEl) displayInputEl.addEventListener('change', applyDisplayTransform);
+
+    // 色域マッピング（作業色域は Display の Input を共用）
+    const gamutModeEl = getEl('gamut-mode');
+    const gamutTargetEl = getEl('gamut-target');
+    const oogOverlayEl = getEl('oog-overlay');
+    async function applyGamutMapping() {
+      try {
+        if (!(await ensureTauriReady())) return;
+        const mode = gamutModeEl?.value || 'none';
+        const working = displayInputEl?.value || 'acescg';
+        const target = gamutTargetEl?.value || 'srgb';
+        const overlay = !!oogOverlayEl?.checked;
+        await invoke('set_gamut_mapping', { mode, working, target, overlay });
+        updateLater();
+        await logBoth(`Gamut適用: ${mode} ${working}->${target} overlay=${overlay}`);
+      } catch (e) { appendLog('Gamut適用失敗: ' + e); }
+    }
+    for (const el of [gamutModeEl, gamutTargetEl, oogOverlayEl, displayInputEl]) {
+      if (el) el.addEventListener('change', applyGamutMapping);
+    }
+
     // Settings: 既定Transformの保存
     if (defaultTransformEl) defaultTransformEl.addEventListener('change', async () => {
       try {
@@ -570,23 +606,23 @@
         await logBoth('Default Transform 保存: ' + label);
       } catch (e) { appendLog('Default Transform 保存失敗: ' + e); }
     });
-
-    // load config
-    (async () => {
-      try {
-        if (!(await ensureTauriReady())) return;
-        const [ms, pct] = await invoke('get_progress_config');
-        if (progIntervalEl) progIntervalEl.value = ms;
-        if (progThreshEl) progThreshEl.value = pct;
-        const allow = await invoke('get_log_permission');
-        if (logConsentEl) logConsentEl.checked = allow;
-      } catch (_) {}
-    })();
-
-    logConsentEl?.addEventListener('change', async () => {
-      try { if (invoke || await ensureTauriReady()) { await invoke('set_log_permission', { allow: !!logConsentEl.checked }); } } catch (_) {}
-    });
-
+
+    // load config
+    (async () => {
+      try {
+        if (!(await ensureTauriReady())) return;
+        const [ms, pct] = await invoke('get_progress_config');
+        if (progIntervalEl) progIntervalEl.value = ms;
+        if (progThreshEl) progThreshEl.value = pct;
+        const allow = await invoke('get_log_permission');
+        if (logConsentEl) logConsentEl.checked = allow;
+      } catch (_) {}
+    })();
+
+    logConsentEl?.addEventListener('change', async () => {
+      try { if (invoke || await ensureTauriReady()) { await invoke('set_log_permission', { allow: !!logConsentEl.checked }); } } catch (_) {}
+    });
+
     const saveProgress = debounce(async () => {
       try {
         if (!(await ensureTauriReady())) return;
@@ -599,7 +635,7 @@
     progThreshEl?.addEventListener('input', saveProgress);
     progIntervalResetBtn?.addEventListener('click', () => { if (progIntervalEl) { progIntervalEl.value = '100'; saveProgress(); } });
     progThreshResetBtn?.addEventListener('click', () => { if (progThreshEl) { progThreshEl.value = '0.5'; saveProgress(); } });
-
+
     if (browseBtn) browseBtn.addEventListener('click', async () => {
       try {
         const t = window.__TAURI__;
@@ -613,9 +649,9 @@
         }
       } catch (e) { appendLog('ファイルダイアログ失敗: ' + e); }
     });
-
+
     // 属性テーブルは閲覧専用のため、追加・編集・削除は不可
-
+
     if (saveBtn) saveBtn.addEventListener('click', async () => {
       try {
         if (!(await ensureTauriReady())) return;
@@ -634,41 +670,41 @@
         alert('保存しました: ' + out);
       } catch (e) { alert('保存に失敗: ' + e); }
     });
-
-    if (cv) {
-      cv.addEventListener('mousemove', async (ev) => {
-        if (imgW === 0 || pipetteFixed) return;
-        const rect = cv.getBoundingClientRect();
-        const x = Math.floor((ev.clientX - rect.left));
-        const y = Math.floor((ev.clientY - rect.top));
-        try {
-          if (!(invoke || await ensureTauriReady())) return;
-          const [r,g,b,a] = await invoke('probe_pixel', { px: x, py: y });
-          const readout = getEl('readout');
-          if (readout) readout.textContent = `x:${x}, y:${y}  linear: R ${r.toFixed(6)}  G ${g.toFixed(6)}  B ${b.toFixed(6)}  A ${a.toFixed(6)}`;
-        } catch (_) { /* ignore */ }
-      });
-      cv.addEventListener('click', async (ev) => {
-        if (imgW === 0) return;
-        const rect = cv.getBoundingClientRect();
-        const x = Math.floor((ev.clientX - rect.left));
-        const y = Math.floor((ev.clientY - rect.top));
-        if (!pipetteFixed) {
-          try {
-            if (!(invoke || await ensureTauriReady())) return;
-            const [r,g,b,a] = await invoke('probe_pixel', { px: x, py: y });
-            const text = `x:${x}, y:${y}  linear: R ${r.toFixed(6)}  G ${g.toFixed(6)}  B ${b.toFixed(6)}  A ${a.toFixed(6)}`;
-            const readout = getEl('readout');
-            if (readout) readout.textContent = text;
-            try { await navigator.clipboard.writeText(text); } catch (_) {}
-            pipetteFixed = true;
-          } catch (_) { /* ignore */ }
-        } else {
-          pipetteFixed = false;
-        }
-      });
-    }
-
+
+    if (cv) {
+      cv.addEventListener('mousemove', async (ev) => {
+        if (imgW === 0 || pipetteFixed) return;
+        const rect = cv.getBoundingClientRect();
+        const x = Math.floor((ev.clientX - rect.left));
+        const y = Math.floor((ev.clientY - rect.top));
+        try {
+          if (!(invoke || await ensureTauriReady())) return;
+          const [r,g,b,a] = await invoke('probe_pixel', { px: x, py: y });
+          const readout = getEl('readout');
+          if (readout) readout.textContent = `x:${x}, y:${y}  linear: R ${r.toFixed(6)}  G ${g.toFixed(6)}  B ${b.toFixed(6)}  A ${a.toFixed(6)}`;
+        } catch (_) { /* ignore */ }
+      });
+      cv.addEventListener('click', async (ev) => {
+        if (imgW === 0) return;
+        const rect = cv.getBoundingClientRect();
+        const x = Math.floor((ev.clientX - rect.left));
+        const y = Math.floor((ev.clientY - rect.top));
+        if (!pipetteFixed) {
+          try {
+            if (!(invoke || await ensureTauriReady())) return;
+            const [r,g,b,a] = await invoke('probe_pixel', { px: x, py: y });
+            const text = `x:${x}, y:${y}  linear: R ${r.toFixed(6)}  G ${g.toFixed(6)}  B ${b.toFixed(6)}  A ${a.toFixed(6)}`;
+            const readout = getEl('readout');
+            if (readout) readout.textContent = text;
+            try { await navigator.clipboard.writeText(text); } catch (_) {}
+            pipetteFixed = true;
+          } catch (_) { /* ignore */ }
+        } else {
+          pipetteFixed = false;
+        }
+      });
+    }
+
     // live update (debounced)
     async function updatePreview() {
       try {
@@ -693,29 +729,29 @@
           renderPreview();
         };
         img.src = 'data:image/png;base64,' + b64;
-        await refreshScopes();
-        showError('');
-      } catch (e) {
-        appendLog('update失敗: ' + e);
-        showError('update失敗: ' + e);
-      }
-    }
-
+        await refreshScopes();
+        showError('');
+      } catch (e) {
+        appendLog('update失敗: ' + e);
+        showError('update失敗: ' + e);
+      }
+    }
+
     const updateLater = debounce(updatePreview, UPDATE_DEBOUNCE_MS);
-
+
     // LUT生成機能は削除
-
+
     // Transform適用ボタンは廃止（変更時に自動適用）
-
+
     /* Preset UI 廃止
     if (lutPreset) lutPreset.addEventListener('change', async () => {
-      const val = lutPreset.value;
-      if (!val) return;
-      const [src, dst] = val.split('-');
-      if (lutSrc) lutSrc.value = src;
-      if (lutDst) lutDst.value = dst;
-      try {
-        if (!(await ensureTauriReady())) return;
+      const val = lutPreset.value;
+      if (!val) return;
+      const [src, dst] = val.split('-');
+      if (lutSrc) lutSrc.value = src;
+      if (lutDst) lutDst.value = dst;
+      try {
+        if (!(await ensureTauriReady())) return;
         const size = ((src === 'linear' || src === 'srgb') && (dst === 'linear' || dst === 'srgb'))
           ? (parseInt(lutSize?.value ?? '1024',10) || 1024)
           : (parseInt(lutSize?.value ?? '33',10) || 33);
@@ -725,87 +761,87 @@
           const dstTf = (dst === 'srgb') ? 'srgb' : (dst === 'g22' ? 'g22' : (dst === 'g24' ? 'g24' : 'linear'));
           await invoke('set_lut_3d', { srcSpace: src, srcTf: 'linear', dstSpace: dst, dstTf: dstTf, size: Math.max(17, Math.min(65, size)), clipMode: 'clip' });
         }
-        if (useStateLut) useStateLut.checked = true;
-        updateLater();
-        appendLog('Preset適用: ' + src + ' -> ' + dst);
-      } catch (e) { appendLog('Preset適用失敗: ' + e); }
-    });
-
+        if (useStateLut) useStateLut.checked = true;
+        updateLater();
+        appendLog('Preset適用: ' + src + ' -> ' + dst);
+      } catch (e) { appendLog('Preset適用失敗: ' + e); }
+    });
+
     // LUT解除は廃止（常時メモリLUT使用）
-
+
     if (lutPreset) lutPreset.dispatchEvent(new Event('change'));
     */
-
-    // --- OCIO settings ---
-    const ocioDiv = getEl('ocio-settings');
-    const ocioDisplay = getEl('ocio-display');
-    const ocioView = getEl('ocio-view');
-    const applyOcio = getEl('apply-ocio');
-
-    async function refreshOcioViews() {
-      if (!ocioDisplay || !ocioView) return;
-      try {
-        if (!(await ensureTauriReady())) return;
-        const views = await invoke('ocio_views', { display: ocioDisplay.value });
-        ocioView.innerHTML = (views || []).map(v => `<option>${v}</option>`).join('');
-      } catch (_) {}
-    }
-
-    async function initOcio() {
-      if (!ocioDiv || !ocioDisplay || !ocioView) return;
-      try {
-        if (!(await ensureTauriReady())) return;
-        const displays = await invoke('ocio_displays');
-        if (!Array.isArray(displays) || displays.length === 0) return;
-        ocioDiv.style.display = 'block';
-        ocioDisplay.innerHTML = displays.map(d => `<option>${d}</option>`).join('');
-        const sel = await invoke('ocio_selection');
-        if (Array.isArray(sel)) {
-          if (sel[0]) ocioDisplay.value = sel[0];
-          await refreshOcioViews();
-          if (sel[1]) ocioView.value = sel[1];
-        } else {
-          await refreshOcioViews();
-        }
-      } catch (_) {}
-    }
-
-    if (ocioDisplay) ocioDisplay.addEventListener('change', refreshOcioViews);
-    if (applyOcio) applyOcio.addEventListener('click', async () => {
-      try {
-        if (!(await ensureTauriReady())) return;
-        await invoke('set_ocio_display_view', { display: ocioDisplay?.value, view: ocioView?.value });
-        updateLater();
-      } catch (e) { appendLog('OCIO設定失敗: ' + e); }
-    });
-
-    initOcio();
-
-    // 早期にTauri注入が完了するケース向け
-    ensureTauriReady(2000);
-    appendLog('UI ready');
-
-    // ログ表示/消去
-    const showLogBtn = getEl('showlog');
-    const clearLogBtn = getEl('clearlog');
-    if (showLogBtn) showLogBtn.addEventListener('click', async () => {
-      try {
-        if (!(await ensureTauriReady())) return;
-        const text = await invoke('read_log');
-        const box = getEl('logbox');
-        if (box) { box.textContent = text || '<empty>'; box.scrollTop = box.scrollHeight; }
-      } catch (e) { appendLog('ログ取得失敗: ' + e); }
-    });
-    if (clearLogBtn) clearLogBtn.addEventListener('click', async () => {
-      try {
-        if (!(await ensureTauriReady())) return;
-        await invoke('clear_log');
-        const box = getEl('logbox');
-        if (box) box.textContent = '';
-        appendLog('ログを消去しました');
-      } catch (e) { appendLog('ログ消去失敗: ' + e); }
-    });
-  });
+
+    // --- OCIO settings ---
+    const ocioDiv = getEl('ocio-settings');
+    const ocioDisplay = getEl('ocio-display');
+    const ocioView = getEl('ocio-view');
+    const applyOcio = getEl('apply-ocio');
+
+    async function refreshOcioViews() {
+      if (!ocioDisplay || !ocioView) return;
+      try {
+        if (!(await ensureTauriReady())) return;
+        const views = await invoke('ocio_views', { display: ocioDisplay.value });
+        ocioView.innerHTML = (views || []).map(v => `<option>${v}</option>`).join('');
+      } catch (_) {}
+    }
+
+    async function initOcio() {
+      if (!ocioDiv || !ocioDisplay || !ocioView) return;
+      try {
+        if (!(await ensureTauriReady())) return;
+        const displays = await invoke('ocio_displays');
+        if (!Array.isArray(displays) || displays.length === 0) return;
+        ocioDiv.style.display = 'block';
+        ocioDisplay.innerHTML = displays.map(d => `<option>${d}</option>`).join('');
+        const sel = await invoke('ocio_selection');
+        if (Array.isArray(sel)) {
+          if (sel[0]) ocioDisplay.value = sel[0];
+          await refreshOcioViews();
+          if (sel[1]) ocioView.value = sel[1];
+        } else {
+          await refreshOcioViews();
+        }
+      } catch (_) {}
+    }
+
+    if (ocioDisplay) ocioDisplay.addEventListener('change', refreshOcioViews);
+    if (applyOcio) applyOcio.addEventListener('click', async () => {
+      try {
+        if (!(await ensureTauriReady())) return;
+        await invoke('set_ocio_display_view', { display: ocioDisplay?.value, view: ocioView?.value });
+        updateLater();
+      } catch (e) { appendLog('OCIO設定失敗: ' + e); }
+    });
+
+    initOcio();
+
+    // 早期にTauri注入が完了するケース向け
+    ensureTauriReady(2000);
+    appendLog('UI ready');
+
+    // ログ表示/消去
+    const showLogBtn = getEl('showlog');
+    const clearLogBtn = getEl('clearlog');
+    if (showLogBtn) showLogBtn.addEventListener('click', async () => {
+      try {
+        if (!(await ensureTauriReady())) return;
+        const text = await invoke('read_log');
+        const box = getEl('logbox');
+        if (box) { box.textContent = text || '<empty>'; box.scrollTop = box.scrollHeight; }
+      } catch (e) { appendLog('ログ取得失敗: ' + e); }
+    });
+    if (clearLogBtn) clearLogBtn.addEventListener('click', async () => {
+      try {
+        if (!(await ensureTauriReady())) return;
+        await invoke('clear_log');
+        const box = getEl('logbox');
+        if (box) box.textContent = '';
+        appendLog('ログを消去しました');
+      } catch (e) { appendLog('ログ消去失敗: ' + e); }
+    });
+  });
     // --- Side panel tabs ---
     const sideBtnColor = getEl('side-tab-btn-color');
     const sideBtnInfo = getEl('side-tab-btn-info');
@@ -867,23 +903,23 @@
     });
 
     // ProRes output browse
-    if (browseProresOutBtn) browseProresOutBtn.addEventListener('click', async () => {
-      await logBoth('browse-prores-out clicked');
-      try {
-        if (!(await ensureTauriReady())) return;
-        const t = window.__TAURI__;
-        const saveDlg = (t && t.dialog && t.dialog.save) || (t && t.tauri && t.tauri.dialog && t.tauri.dialog.save) || null;
-        if (saveDlg) {
-          const sel = await saveDlg({ filters: [{ name: 'ProRes MOV', extensions: ['mov'] }], defaultPath: proresOutEl?.value || undefined });
-          if (sel && proresOutEl) proresOutEl.value = sel;
-          await logBoth(`出力選択: ${proresOutEl?.value || ''}`);
-        } else {
-          const p = prompt('出力MOVのパスを入力 (.mov)'); if (p && proresOutEl) proresOutEl.value = p;
-          await logBoth(`出力入力: ${proresOutEl?.value || ''}`);
-        }
-      } catch (e) { appendLog('出力選択失敗: ' + e); }
-    });
-
+    if (browseProresOutBtn) browseProresOutBtn.addEventListener('click', async () => {
+      await logBoth('browse-prores-out clicked');
+      try {
+        if (!(await ensureTauriReady())) return;
+        const t = window.__TAURI__;
+        const saveDlg = (t && t.dialog && t.dialog.save) || (t && t.tauri && t.tauri.dialog && t.tauri.dialog.save) || null;
+        if (saveDlg) {
+          const sel = await saveDlg({ filters: [{ name: 'Movie', extensions: ['mov', 'mxf', 'mp4'] }], defaultPath: proresOutEl?.value || undefined });
+          if (sel && proresOutEl) proresOutEl.value = sel;
+          await logBoth(`出力選択: ${proresOutEl?.value || ''}`);
+        } else {
+          const p = prompt('出力MOVのパスを入力 (.mov)'); if (p && proresOutEl) proresOutEl.value = p;
+          await logBoth(`出力入力: ${proresOutEl?.value || ''}`);
+        }
+      } catch (e) { appendLog('出力選択失敗: ' + e); }
+    });
+
     // Export PNG (Batch)
     if (exportPngBatchBtn) exportPngBatchBtn.addEventListener('click', async () => {
       try {
@@ -900,38 +936,148 @@
     });
 
     // Export ProRes
-    const exportProresBtn = getEl('export-prores');
-    if (exportProresBtn) exportProresBtn.addEventListener('click', async () => {
-      try {
-        if (!(await ensureTauriReady())) return;
-        const dir = seqDirEl?.value?.trim(); const out = proresOutEl?.value?.trim();
-        if (!dir) { alert('Sequence Folder を指定してください'); return; }
-        if (!out) { alert('出力MOVのパスを指定してください'); return; }
-        const fps = parseFloat(proresFpsEl?.value ?? '24') || 24;
-        const colorspace = (proresCsEl?.value || 'linear:srgb');
-        const profile = (proresProfileEl?.value || '422hq');
-        const maxSize = parseInt(proresMaxEl?.value ?? '2048', 10) || 2048;
+    const exportProresBtn = getEl('export-prores');
+    if (exportProresBtn) exportProresBtn.addEventListener('click', async () => {
+      try {
+        if (!(await ensureTauriReady())) return;
+        const dir = seqDirEl?.value?.trim(); const out = proresOutEl?.value?.trim();
+        if (!dir) { alert('Sequence Folder を指定してください'); return; }
+        if (!out) { alert('出力MOVのパスを指定してください'); return; }
+        const fps = parseFloat(proresFpsEl?.value ?? '24') || 24;
+        const colorspace = (proresCsEl?.value || 'linear:srgb');
+        const [codec, profile] = (proresProfileEl?.value || 'prores:422hq').split(':');
+        const rate = getEl('prores-rate')?.value?.trim() || null;
+        const alpha = !!getEl('prores-alpha')?.checked;
+        const maxSize = parseInt(proresMaxEl?.value ?? '2048', 10) || 2048;
         const exposure = 0;
         const gamma = ((()=>{ const v=(proresTfEl?.value||'g22'); if (v==='g24') return 2.4; if (v==='linear') return 1.0; return 2.2; })());
-        const quality = (proresQualityEl?.value || 'High');
-        await logBoth(`export_prores: dir=${dir} out=${out} fps=${fps} cs=${colorspace} profile=${profile}`);
-
-        // listen progress
-        const t = window.__TAURI__;
-        if (t && t.event && t.event.listen && proresProg) {
-          proresProg.style.display = 'block'; proresProg.value = 0;
-          const unlisten = await t.event.listen('video-progress', (e) => { try { proresProg.value = e.payload; } catch(_){} });
+        const quality = (proresQualityEl?.value || 'High');
+        const display = (getEl('prores-display')?.value || 'standard');
+        const colorTags = (getEl('prores-color-tags')?.value || null);
+        const burnins = (getEl('prores-burnins')?.value || '').split('\n').map(s => s.trim()).filter(Boolean);
+        const maskAspect = getEl('prores-mask')?.value || '';
+        const maskOpacity = parseFloat(getEl('prores-mask-opacity')?.value ?? '1');
+        const mask = maskAspect ? (maskOpacity < 1 ? `${maskAspect}@${maskOpacity}` : maskAspect) : null;
+        const slateField = (id) => getEl(id)?.value?.trim() || null;
+        const slate = getEl('prores-slate')?.checked ? {
+          project: slateField('prores-slate-project'),
+          shot: slateField('prores-slate-shot'),
+          version: slateField('prores-slate-version'),
+          artist: slateField('prores-slate-artist'),
+          notes: slateField('prores-slate-notes'),
+        } : null;
+        await logBoth(`export_prores: dir=${dir} out=${out} fps=${fps} cs=${colorspace} display=${display} codec=${codec} profile=${profile}`);
+
+        // listen progress
+        const t = window.__TAURI__;
+        if (t && t.event && t.event.listen && proresProg) {
+          proresProg.style.display = 'block'; proresProg.value = 0;
+          const unlisten = await t.event.listen('video-progress', (e) => { try { proresProg.value = e.payload; } catch(_){} });
           try {
-            await invoke('export_prores', { dir, fps, colorspace, out, profile, maxSize, exposure, gamma, quality });
-            appendLog('ProRes出力完了: ' + out);
-            alert('出力完了: ' + out);
+            const tags = await invoke('export_prores', { dir, fps, colorspace, out, codec, profile, rate, alpha, maxSize, exposure, gamma, quality, display, colorTags, burnins, mask, slate });
+            appendLog(`ProRes出力完了: ${out} (${tags})`);
+            alert(`出力完了: ${out}\n色タグ: ${tags}`);
           } finally { unlisten(); proresProg.style.display = 'none'; }
         } else {
-          await invoke('export_prores', { dir, fps, colorspace, out, profile, maxSize, exposure, gamma, quality });
-          alert('出力完了: ' + out);
+          const tags = await invoke('export_prores', { dir, fps, colorspace, out, codec, profile, rate, alpha, maxSize, exposure, gamma, quality, display, colorTags, burnins, mask, slate });
+          alert(`出力完了: ${out}\n色タグ: ${tags}`);
         }
-      } catch (e) { appendLog('ProRes出力失敗: ' + e); alert('ProRes出力失敗: ' + e); }
-    });
-})();
+      } catch (e) { appendLog('ProRes出力失敗: ' + e); alert('ProRes出力失敗: ' + e); }
+    });
+
+    // Export Stills（表示変換・マスク・スレート等は ProRes の設定を使う）
+    const exportStillsBtn = getEl('export-stills');
+    if (exportStillsBtn) exportStillsBtn.addEventListener('click', async () => {
+      try {
+        if (!(await ensureTauriReady())) return;
+        const dir = seqDirEl?.value?.trim(); const out = getEl('stills-out')?.value?.trim();
+        if (!dir) { alert('Sequence Folder を指定してください'); return; }
+        if (!out) { alert('出力パスを指定してください（#### はフレーム番号）'); return; }
+        const format = getEl('stills-format')?.value || 'png16';
+        const jpegQuality = parseInt(getEl('stills-jpeg-quality')?.value ?? '90', 10) || 90;
+        const tags = getEl('stills-tags')?.value || null;
+        const maxSize = parseInt(proresMaxEl?.value ?? '2048', 10) || 2048;
+        const gamma = ((()=>{ const v=(proresTfEl?.value||'g22'); if (v==='g24') return 2.4; if (v==='linear') return 1.0; return 2.2; })());
+        const quality = (proresQualityEl?.value || 'High');
+        const display = (getEl('prores-display')?.value || 'standard');
+        const inputSpace = (proresCsEl?.value || 'linear:srgb').split(':')[0].replace('linear', 'srgb');
+        const maskAspect = getEl('prores-mask')?.value || '';
+        const maskOpacity = parseFloat(getEl('prores-mask-opacity')?.value ?? '1');
+        const mask = maskAspect ? (maskOpacity < 1 ? `${maskAspect}@${maskOpacity}` : maskAspect) : null;
+        const slateField = (id) => getEl(id)?.value?.trim() || null;
+        const slate = getEl('prores-slate')?.checked ? {
+          project: slateField('prores-slate-project'),
+          shot: slateField('prores-slate-shot'),
+          version: slateField('prores-slate-version'),
+          artist: slateField('prores-slate-artist'),
+          notes: slateField('prores-slate-notes'),
+        } : null;
+        await logBoth(`export_stills: dir=${dir} out=${out} format=${format} display=${display} tags=${tags || 'auto'}`);
+        const args = { dir, out, format, jpegQuality, tags, maxSize, gamma, quality, display, inputSpace, mask, slate };
+        const t = window.__TAURI__;
+        const prog = getEl('stills-progress');
+        let summary;
+        if (t && t.event && t.event.listen && prog) {
+          prog.style.display = 'block'; prog.value = 0;
+          const unlisten = await t.event.listen('stills-progress', (e) => { try { prog.value = e.payload; } catch(_){} });
+          try { summary = await invoke('export_stills', args); } finally { unlisten(); prog.style.display = 'none'; }
+        } else {
+          summary = await invoke('export_stills', args);
+        }
+        appendLog(`静止画出力完了: ${out} (${summary})`);
+        alert(`出力完了: ${out}\n${summary}`);
+      } catch (e) { appendLog('静止画出力失敗: ' + e); alert('静止画出力失敗: ' + e); }
+    });
+
+    // Export Animation（ffmpeg 不要。色変換・マスク・スレート・焼き込みは ProRes の設定を使う）
+    const exportAnimBtn = getEl('export-anim');
+    if (exportAnimBtn) exportAnimBtn.addEventListener('click', async () => {
+      try {
+        if (!(await ensureTauriReady())) return;
+        const dir = seqDirEl?.value?.trim(); const out = getEl('anim-out')?.value?.trim();
+        if (!dir) { alert('Sequence Folder を指定してください'); return; }
+        if (!out) { alert('出力パスを指定してください（.gif / .png / .webp）'); return; }
+        const format = getEl('anim-format')?.value || null;
+        const fps = parseFloat(getEl('anim-fps')?.value ?? '12') || 12;
+        const step = parseInt(getEl('anim-step')?.value ?? '1', 10) || 1;
+        const maxSize = parseInt(getEl('anim-max')?.value ?? '960', 10) || 960;
+        const loops = parseInt(getEl('anim-loops')?.value ?? '0', 10) || 0;
+        const colors = parseInt(getEl('anim-colors')?.value ?? '256', 10) || 256;
+        const dither = getEl('anim-dither')?.value || 'floyd-steinberg';
+        const alpha = !!getEl('anim-alpha')?.checked;
+        const colorspace = (proresCsEl?.value || 'linear:srgb');
+        const exposure = 0;
+        const gamma = ((()=>{ const v=(proresTfEl?.value||'g22'); if (v==='g24') return 2.4; if (v==='linear') return 1.0; return 2.2; })());
+        const quality = (proresQualityEl?.value || 'High');
+        const display = (getEl('prores-display')?.value || 'standard');
+        const burnins = (getEl('prores-burnins')?.value || '').split('\n').map(s => s.trim()).filter(Boolean);
+        const maskAspect = getEl('prores-mask')?.value || '';
+        const maskOpacity = parseFloat(getEl('prores-mask-opacity')?.value ?? '1');
+        const mask = maskAspect ? (maskOpacity < 1 ? `${maskAspect}@${maskOpacity}` : maskAspect) : null;
+        const slateField = (id) => getEl(id)?.value?.trim() || null;
+        const slate = getEl('prores-slate')?.checked ? {
+          project: slateField('prores-slate-project'),
+          shot: slateField('prores-slate-shot'),
+          version: slateField('prores-slate-version'),
+          artist: slateField('prores-slate-artist'),
+          notes: slateField('prores-slate-notes'),
+        } : null;
+        await logBoth(`export_anim: dir=${dir} out=${out} format=${format || 'auto'} fps=${fps} step=${step} colors=${colors} dither=${dither}`);
+        const args = { dir, out, format, fps, step, loops, colors, dither, colorspace, alpha, maxSize, exposure, gamma, quality, display, burnins, mask, slate };
+        const t = window.__TAURI__;
+        const prog = getEl('anim-progress');
+        let summary;
+        if (t && t.event && t.event.listen && prog) {
+          prog.style.display = 'block'; prog.value = 0;
+          const unlisten = await t.event.listen('anim-progress', (e) => { try { prog.value = e.payload; } catch(_){} });
+          try { summary = await invoke('export_anim', args); } finally { unlisten(); prog.style.display = 'none'; }
+        } else {
+          summary = await invoke('export_anim', args);
+        }
+        appendLog(`アニメーション出力完了: ${out} (${summary})`);
+        alert(`出力完了: ${out}\n${summary}`);
+      } catch (e) { appendLog('アニメーション出力失敗: ' + e); alert('アニメーション出力失敗: ' + e); }
+    });
+})();
     proresFpsResetBtn?.addEventListener('click', () => { if (proresFpsEl) proresFpsEl.value = '24'; });
     proresMaxResetBtn?.addEventListener('click', () => { if (proresMaxEl) proresMaxEl.value = '2048'; });
@@ -1,433 +1,2335 @@
-use anyhow::{Context, Result};
-use clap::{Parser, Subcommand, ValueEnum};
-use exrtool_core::{export_png, generate_preview, load_exr_basic, parse_cube, make_1d_lut, ColorSpace, PreviewQuality, ClipMode};
-use std::fs;
-use std::path::PathBuf;
-
-#[derive(Parser)]
-#[command(name = "exrtool")]
-#[command(about = "EXR プレビューとピクセル検査のCLI", long_about = None)]
-struct Cli {
-    /// OCIO config: "aces1.3" or file path
-    #[arg(long)]
-    ocio: Option<String>,
-    #[command(subcommand)]
-    command: Commands,
-}
-
-#[derive(Subcommand)]
-enum Commands {
-    /// プレビューPNGを書き出し
-    Preview {
-        /// 入力EXR
-        input: PathBuf,
-        /// 出力PNGパス
-        #[arg(short, long)]
-        out: PathBuf,
-        /// 収まる最大サイズ
-        #[arg(long, default_value_t = 2048)]
-        max_size: u32,
-        /// 露出（stop単位）
-        #[arg(long, default_value_t = 0.0)]
-        exposure: f32,
-        /// ガンマ（0で無効）
-        #[arg(long, default_value_t = 2.2)]
-        gamma: f32,
-        /// .cubeファイル（任意）
-        #[arg(long)]
-        lut: Option<PathBuf>,
-        /// 高品質リサイズ
-        #[arg(long, value_enum, default_value_t = Quality::Fast)]
-        quality: Quality,
-    },
-    /// 単一EXRのFPS属性を設定（feature `exr_pure` 必要）
-    FpsSet {
-        /// 入力EXR
-        #[arg(long)]
-        input: PathBuf,
-        /// FPS値（float）
-        #[arg(long)]
-        fps: f32,
-        /// 属性名（既定: FramesPerSecond）
-        #[arg(long, default_value = "FramesPerSecond")]
-        attr: String,
-        /// 変更せずに対象のみ表示
-        #[arg(long, default_value_t = false)]
-        dry_run: bool,
-        /// 上書き時に .bak を作成
-        #[arg(long, default_value_t = true)]
-        backup: bool,
-    },
-    /// 連番EXRのFPS属性を一括設定（feature `exr_pure` 必要）
-    SeqFps {
-        /// ディレクトリ
-        #[arg(long)]
-        dir: PathBuf,
-        /// FPS値（float）
-        #[arg(long)]
-        fps: f32,
-        /// 属性名（既定: FramesPerSecond）
-        #[arg(long, default_value = "FramesPerSecond")]
-        attr: String,
-        /// 再帰的に走査
-        #[arg(long, default_value_t = false)]
-        recursive: bool,
-        /// 変更せずに対象のみ表示
-        #[arg(long, default_value_t = false)]
-        dry_run: bool,
-        /// 上書き時に .bak を作成
-        #[arg(long, default_value_t = true)]
-        backup: bool,
-    },
-    /// 連番EXRからProRes動画を生成（ffmpeg必要）
-    Prores {
-        /// ディレクトリ
-        #[arg(long)]
-        dir: PathBuf,
-        /// FPS
-        #[arg(long, default_value_t = 24.0)]
-        fps: f32,
-        /// 出力ファイル（.mov）
-        #[arg(long)]
-        out: PathBuf,
-        /// ffmpeg コーデック（例: prores_ks, libx264）
-        #[arg(long, default_value = "prores_ks")]
-        codec: String,
-        /// 色空間変換: linear:srgb | acescg:srgb | aces2065:srgb
-        #[arg(long, default_value = "linear:srgb")]
-        colorspace: String,
-        /// プロファイル: 422hq/422/4444 等
-        #[arg(long, default_value = "422hq")]
-        profile: String,
-        /// 最大辺サイズ
-        #[arg(long, default_value_t = 2048)]
-        max_size: u32,
-        /// 露出（stop）
-        #[arg(long, default_value_t = 0.0)]
-        exposure: f32,
-        /// ガンマ
-        #[arg(long, default_value_t = 2.2)]
-        gamma: f32,
-        /// 高品質リサイズ
-        #[arg(long, value_enum, default_value_t = Quality::High)]
-        quality: Quality,
-    },
-
-    /// メタデータを表示（feature `exr_pure` 必要）
-    Metadata {
-        /// 入力EXR
-        input: PathBuf,
-        /// 出力形式: table | json
-        #[arg(long, default_value = "table")]
-        format: String,
-    },
-
-    /// 指定座標のリニア値を表示
-    Probe {
-        /// 入力EXR
-        input: PathBuf,
-        /// x座標
-        #[arg(long)]
-        x: usize,
-        /// y座標
-        #[arg(long)]
-        y: usize,
-    },
-
-    /// 1D LUT(.cube) を生成（トーンカーブ変換）
-    MakeLut1D {
-        /// 変換元: linear | srgb
-        #[arg(long)]
-        src: String,
-        /// 変換先: linear | srgb
-        #[arg(long)]
-        dst: String,
-        /// テーブルサイズ（例: 1024）
-        #[arg(long, default_value_t = 1024)]
-        size: usize,
-        /// 出力パス（.cube）
-        #[arg(short, long)]
-        out: PathBuf,
-    },
-
-    /// 3D LUT(.cube) を生成（色域+トーン変換）
-    MakeLut3D {
-        /// 変換元primaries: srgb | rec2020 | acescg | aces2065
-        #[arg(long)]
-        src_space: String,
-        /// 変換元トーン: linear | srgb | g24 | g22
-        #[arg(long, default_value = "linear")]
-        src_tf: String,
-        /// 変換先primaries: srgb | rec2020 | acescg | aces2065
-        #[arg(long)]
-        dst_space: String,
-        /// 変換先トーン: linear | srgb | g24 | g22
-        #[arg(long, default_value = "srgb")]
-        dst_tf: String,
-        /// テーブルサイズ（既定: 33）
-        #[arg(long, default_value_t = 33)]
-        size: usize,
-        /// 1D シェーパーサイズ（0で無効）
-        #[arg(long, default_value_t = 1024)]
-        shaper_size: usize,
-        /// 出力パス（.cube）
-        #[arg(short, long)]
-        out: PathBuf,
-    },
-
-    /// ルールファイルに基づき処理を適用
-    Apply {
-        /// ルールファイル(YAML)
-        #[arg(long)]
-        rules: PathBuf,
-        /// 実行内容のみ表示
-        #[arg(long)]
-        dry_run: bool,
-        /// 出力を上書きする際にバックアップ(.bak)を作成
-        #[arg(long)]
-        backup: bool,
-    },
-}
-
-#[derive(Clone, ValueEnum)]
-enum Quality { Fast, High }
-
-fn main() -> Result<()> {
-    let cli = Cli::parse();
-    // OCIO連携は現在ビルド無効（use_ocio feature）。対応時に実装を復帰。
-    match cli.command {
-        Commands::Preview { input, out, max_size, exposure, gamma, lut, quality } => {
-            let img = load_exr_basic(&input)?;
-            let lut_obj = if let Some(p) = lut {
-                let txt = fs::read_to_string(p)?;
-                Some(parse_cube(&txt)?)
-            } else { None };
-            let pq = match quality { Quality::Fast => PreviewQuality::Fast, Quality::High => PreviewQuality::High };
-            let preview = generate_preview(&img, max_size, exposure, gamma, lut_obj.as_ref(), #[cfg(feature = "use_ocio")] None, pq);
-            export_png(&out, &preview)?;
-            println!(
-                "w={} h={} => {}",
-                preview.width,
-                preview.height,
-                out.display()
-            );
-        }
-        Commands::Probe { input, x, y } => {
-            let img = load_exr_basic(&input)?;
-            let px = img
-                .get_linear(x, y)
-                .with_context(|| format!("座標が範囲外: {},{}", x, y))?;
-            println!(
-                "linear RGBA: {:.7} {:.7} {:.7} {:.7}",
-                px.r, px.g, px.b, px.a
-            );
-        }
-        Commands::MakeLut1D {
-            src,
-            dst,
-            size,
-            out,
-        } => {
-            let parse_cs = |s: &str| -> Result<ColorSpace> {
-                match s.to_ascii_lowercase().as_str() {
-                    "linear" => Ok(ColorSpace::Linear),
-                    "srgb" => Ok(ColorSpace::Srgb),
-                    _ => Err(anyhow::anyhow!("unknown colorspace: {}", s)),
-                }
-            };
-            let cs_src = parse_cs(&src)?;
-            let cs_dst = parse_cs(&dst)?;
-            let text = make_1d_lut(cs_src, cs_dst, size);
-            fs::write(&out, text)?;
-            println!(
-                "LUT saved: {} ({} -> {}, size={})",
-                out.display(),
-                src,
-                dst,
-                size
-            );
-        }
-        Commands::MakeLut3D { src_space, src_tf, dst_space, dst_tf, size, shaper_size, out } => {
-            use exrtool_core::{Primaries, TransferFn, make_3d_lut_cube};
-            let parse_space = |s:&str| -> Result<Primaries> { match s.to_ascii_lowercase().as_str() {
-                "srgb"|"rec709" => Ok(Primaries::SrgbD65),
-                "rec2020"|"bt2020" => Ok(Primaries::Rec2020D65),
-                "acescg"|"ap1" => Ok(Primaries::ACEScgD60),
-                "aces2065"|"ap0"|"aces" => Ok(Primaries::ACES2065_1D60),
-                _ => Err(anyhow::anyhow!("unknown space: {}", s)) } };
-            let parse_tf = |s:&str| -> Result<TransferFn> { match s.to_ascii_lowercase().as_str() {
-                "linear" => Ok(TransferFn::Linear),
-                "srgb" => Ok(TransferFn::Srgb),
-                "g24"|"gamma2.4" => Ok(TransferFn::Gamma24),
-                "g22"|"gamma2.2" => Ok(TransferFn::Gamma22),
-                _ => Err(anyhow::anyhow!("unknown transfer: {}", s)) } };
-            let parse_clip = |s:&str| -> Result<ClipMode> { match s.to_ascii_lowercase().as_str() {
-                "clip" => Ok(ClipMode::Clip),
-                "noclip"|"none" => Ok(ClipMode::NoClip),
-                _ => Err(anyhow::anyhow!("unknown clip mode: {}", s)) } };
-            let sp = parse_space(&src_space)?; let dt = parse_space(&dst_space)?;
-            let st = parse_tf(&src_tf)?; let tt = parse_tf(&dst_tf)?;
-            let text = make_3d_lut_cube(sp, st, dt, tt, size, shaper_size);
-            fs::write(&out, text)?;
-            println!("3D LUT saved: {} ({} {} -> {} {}, size={} shaper={})", out.display(), src_space, src_tf, dst_space, dst_tf, size, shaper_size);
-        }
-        Commands::Apply { rules, dry_run, backup } => {
-            exrtool_core::apply_rules_file(&rules, dry_run, backup)?;
-        }
-        Commands::FpsSet { input, fps, attr, dry_run, backup } => {
-            #[cfg(feature = "exr_pure")]
-            {
-                use std::collections::HashMap;
-                if dry_run {
-                    println!("would write {}={} to {}", attr, fps, input.display());
-                    return Ok(());
-                }
-                let bak_path = if backup {
-                    let bak = input.with_extension("exr.bak");
-                    if let Err(e) = fs::copy(&input, &bak) {
-                        eprintln!("backup failed {} -> {}: {}", input.display(), bak.display(), e);
-                        None
-                    } else {
-                        Some(bak)
-                    }
-                } else {
-                    None
-                };
-                let mut map = HashMap::new();
-                map.insert(attr.clone(), format!("{}", fps));
-                match exrtool_core::metadata::write_metadata(&input, &map, None) {
-                    Ok(_) => {
-                        println!("wrote {}={} to {}", attr, fps, input.display());
-                        if let Some(b) = bak_path { let _ = fs::remove_file(b); }
-                    }
-                    Err(e) => {
-                        eprintln!("failed {}: {}", input.display(), e);
-                    }
-                }
-            }
-            #[cfg(not(feature = "exr_pure"))]
-            {
-                eprintln!("fps-set requires --features exr_pure");
-            }
-        }
-        Commands::SeqFps { dir, fps, attr, recursive, dry_run, backup } => {
-            #[cfg(feature = "exr_pure")]
-            {
-                use std::collections::HashMap;
-                fn collect(p: &PathBuf, recursive: bool, out: &mut Vec<PathBuf>) -> Result<()> {
-                    for entry in fs::read_dir(p)? {
-                        let e = entry?; let path = e.path();
-                        if path.is_dir() { if recursive { collect(&path, recursive, out)?; } }
-                        else if path.extension().map(|s| s.to_string_lossy().to_ascii_lowercase()) == Some("exr".into()) {
-                            out.push(path);
-                        }
-                    }
-                    Ok(())
-                }
-                let mut files = Vec::new();
-                collect(&dir, recursive, &mut files)?;
-                files.sort_by(|a,b| a.file_name().unwrap().cmp(b.file_name().unwrap()));
-                if files.is_empty() { println!("no EXR files found in {}", dir.display()); return Ok(()); }
-                println!("target files: {}", files.len());
-                if dry_run {
-                    for f in files { println!("{}", f.display()); }
-                    return Ok(());
-                }
-                let mut map = HashMap::new();
-                map.insert(attr.clone(), format!("{}", fps));
-                for f in files {
-                    // write in-place or with backup via core save.rs if available; here do naive: out=None => overwrite
-                    match exrtool_core::metadata::write_metadata(&f, &map, None) {
-                        Ok(_) => println!("wrote {}={} to {}", attr, fps, f.display()),
-                        Err(e) => eprintln!("failed {}: {}", f.display(), e),
-                    }
-                }
-            }
-            #[cfg(not(feature = "exr_pure"))]
-            {
-                eprintln!("seq-fps requires --features exr_pure");
-            }
-        }
-        Commands::Prores { dir, fps, out, codec, colorspace, profile, max_size, exposure, gamma, quality } => {
-            use std::process::{Command, Stdio};
-            // check ffmpeg
-            if Command::new("ffmpeg").arg("-version").stdout(Stdio::null()).stderr(Stdio::null()).status().is_err() {
-                eprintln!("ffmpeg not found. Please install ffmpeg and ensure it's on PATH.");
-                return Ok(());
-            }
-            // gather exr files
-            let mut files: Vec<PathBuf> = Vec::new();
-            for entry in fs::read_dir(&dir)? { let e=entry?; let p=e.path(); if p.is_file() && p.extension().map(|s| s.to_string_lossy().to_ascii_lowercase())==Some("exr".into()) { files.push(p); } }
-            files.sort_by(|a,b| a.file_name().unwrap().cmp(b.file_name().unwrap()));
-            if files.is_empty() { eprintln!("no EXR files in {}", dir.display()); return Ok(()); }
-            // prepare LUT based on colorspace
-            let mut lut_obj = None;
-            let cs = colorspace.to_lowercase();
-            if cs != "linear:srgb" {
-                use exrtool_core::{make_3d_lut_cube, Primaries, TransferFn};
-                let (sp, dp) = if cs=="acescg:srgb" { (Primaries::ACEScgD60, Primaries::SrgbD65) } else if cs=="aces2065:srgb" { (Primaries::ACES2065_1D60, Primaries::SrgbD65) } else { (Primaries::SrgbD65, Primaries::SrgbD65) };
-                let text = make_3d_lut_cube(sp, TransferFn::Linear, dp, TransferFn::Srgb, 33, 1024);
-                lut_obj = Some(parse_cube(&text)?);
-            }
-            // spawn ffmpeg
-            let mut cmd = Command::new("ffmpeg");
-            cmd.arg("-y")
-                .arg("-f").arg("image2pipe")
-                .arg("-r").arg(format!("{}", fps))
-                .arg("-vcodec").arg("png")
-                .arg("-i").arg("-")
-                .arg("-c:v").arg(&codec);
-            if codec.contains("prores") {
-                cmd.arg("-profile:v")
-                    .arg(match profile.as_str() { "422hq"=>"3", "422"=>"2", "4444"=>"4", _=>"3" });
-            }
-            let mut child = cmd
-                .arg(out.as_os_str())
-                .stdin(Stdio::piped())
-                .spawn()
-                .context("failed to spawn ffmpeg")?;
-            {
-                use image::{ImageBuffer, Rgba};
-                let mut stdin = child.stdin.take().unwrap();
-                for f in files {
-                    let img = load_exr_basic(&f)?;
-                    let pq = match quality { Quality::Fast=>PreviewQuality::Fast, Quality::High=>PreviewQuality::High };
-                    let preview = generate_preview(&img, max_size, exposure, gamma, lut_obj.as_ref(), #[cfg(feature = "use_ocio")] None, pq);
-                    // encode PNG to ffmpeg stdin
-                    let buf = image::RgbaImage::from_raw(preview.width, preview.height, preview.rgba8).expect("invalid buffer");
-                    let mut bytes: Vec<u8> = Vec::new();
-                    image::DynamicImage::ImageRgba8(buf).write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageOutputFormat::Png)?;
-                    use std::io::Write;
-                    stdin.write_all(&bytes)?;
-                }
-            }
-            let status = child.wait()?;
-            if !status.success() { eprintln!("ffmpeg exited with status {:?}", status); }
-            else { println!("wrote {}", out.display()); }
-        }
-        Commands::Metadata { input, format } => {
-            // coreのread_metadataを呼び出し（feature未有効時はErr）
-            match exrtool_core::read_metadata(&input) {
-                Ok(meta) => {
-                    if format.to_lowercase() == "json" {
-                        println!("{}", serde_json::to_string_pretty(&meta).unwrap_or("{}".into()));
-                    } else {
-                        // 簡易表形式
-                        for (i, h) in meta.headers.iter().enumerate() {
-                            println!("# Header {}", i);
-                            println!("  layer_name   : {}", h.layer_name.as_deref().unwrap_or(""));
-                            println!("  layer_pos    : {},{}", h.layer_position.0, h.layer_position.1);
-                            println!("  layer_size   : {}x{}", h.layer_size.0, h.layer_size.1);
-                            println!("  pixel_aspect : {}", h.pixel_aspect);
-                            println!("  line_order   : {}", h.line_order);
-                        }
-                    }
-                }
-                Err(e) => {
-                    eprintln!("metadata取得に失敗: {}\nヒント: CLIを feature 'exr_pure' でビルドしてください\n  cargo run -p exrtool-cli --features exr_pure -- metadata <in.exr>", e);
-                }
-            }
-        }
-    }
-    Ok(())
-}
+use anyhow::{Context, Result};
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use exrtool_core::anim::{AnimationFormat, Dither};
+use exrtool_core::batch::OutputFormat;
+use exrtool_core::burnin::{BurnIn, BurnInOptions, BurnInText};
+use exrtool_core::gamut::GamutMapping;
+use exrtool_core::metadata::PartTarget;
+use exrtool_core::slate::{AspectMask, Slate};
+use exrtool_core::timecode::FrameRate;
+use exrtool_core::video::{
+    AudioInput, BitDepth, CodecProfile, ColorTags, FitMode, FrameRange, RateControl, Resize,
+    VideoColorspace,
+};
+use exrtool_core::{
+    export_png, generate_preview_with, load_exr_basic, make_1d_lut, parse_cube, ClipMode,
+    ColorProcessor, ColorSpace, DisplayTransform, PreviewPipeline, PreviewQuality, Primaries,
+    TransferFn,
+};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser)]
+#[command(name = "exrtool")]
+#[command(about = "EXR プレビューとピクセル検査のCLI", long_about = None)]
+struct Cli {
+    /// OCIO config のパス（feature use_ocio 有効時は組み込みの "aces1.3" も可）
+    #[arg(long, global = true)]
+    ocio: Option<String>,
+    /// OCIO 変換元の色空間（既定: scene_linear ロール）
+    #[arg(long, global = true)]
+    from: Option<String>,
+    /// OCIO 変換先の色空間
+    #[arg(long, global = true)]
+    to: Option<String>,
+    /// OCIO ディスプレイ（--ocio-view と併用、--to の代わり）
+    #[arg(long, global = true)]
+    ocio_display: Option<String>,
+    /// OCIO ビュー
+    #[arg(long, global = true)]
+    ocio_view: Option<String>,
+    /// OCIO ルック（例: "+grade, -neutral"）
+    #[arg(long, global = true)]
+    look: Option<String>,
+    /// OCIO コンテキスト変数（例: SHOT=sh010、複数指定可）
+    #[arg(long = "ocio-var", global = true)]
+    ocio_vars: Vec<String>,
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// プレビューPNGを書き出し
+    Preview {
+        /// 入力EXR
+        input: PathBuf,
+        /// 出力PNGパス
+        #[arg(short, long)]
+        out: PathBuf,
+        /// 収まる最大サイズ
+        #[arg(long, default_value_t = 2048)]
+        max_size: u32,
+        /// 露出（stop単位）
+        #[arg(long, default_value_t = 0.0)]
+        exposure: f32,
+        /// ガンマ（0で無効）
+        #[arg(long, default_value_t = 2.2)]
+        gamma: f32,
+        /// .cubeファイル（任意）
+        #[arg(long)]
+        lut: Option<PathBuf>,
+        /// 高品質リサイズ
+        #[arg(long, value_enum, default_value_t = Quality::Fast)]
+        quality: Quality,
+        /// 表示変換: standard | raw | aces-srgb | aces-rec709 | aces-p3d65 | aces-rec2100-pq
+        #[arg(long, default_value = "standard")]
+        display: String,
+        /// ACES表示変換時の入力primaries: acescg | aces2065 | srgb | rec2020 | p3d65
+        #[arg(long, default_value = "acescg")]
+        input_space: String,
+        #[command(flatten)]
+        gamut: GamutArgs,
+        /// アスペクトマスク（例: 2.39 | 1.85 | 16:9、@0.5 で不透明度50%）
+        #[arg(long)]
+        mask: Option<AspectMask>,
+        #[command(flatten)]
+        burnin: BurnInArgs,
+    },
+    /// 単一EXRのFPS属性を設定（feature `exr_pure` 必要）
+    FpsSet {
+        /// 入力EXR
+        #[arg(long)]
+        input: PathBuf,
+        /// FPS値（float）
+        #[arg(long)]
+        fps: f32,
+        /// 属性名（既定: framesPerSecond。標準属性は Rational で書き込み）
+        #[arg(long, default_value = "framesPerSecond")]
+        attr: String,
+        /// 変更せずに対象のみ表示
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+        /// 既存属性の型が異なっていても上書き
+        #[arg(long)]
+        force_type: bool,
+        /// 書き換え後にチャンクデータがバイト単位で一致するか検証
+        #[arg(long)]
+        verify: bool,
+        /// 書き込み先パート: first | all | shared | パート名（マルチパートEXR）
+        #[arg(long, default_value = "first")]
+        part: PartTarget,
+        /// 上書き前に .exr.bak を作成（書き込み成功時は残し、失敗時は削除。--backup false で無効）
+        #[arg(long, default_value_t = true, action = ArgAction::Set)]
+        backup: bool,
+    },
+    /// 連番EXRのFPS属性を一括設定（feature `exr_pure` 必要）
+    SeqFps {
+        /// ディレクトリ
+        #[arg(long)]
+        dir: PathBuf,
+        /// FPS値（float）
+        #[arg(long)]
+        fps: f32,
+        /// 属性名（既定: framesPerSecond。標準属性は Rational で書き込み）
+        #[arg(long, default_value = "framesPerSecond")]
+        attr: String,
+        /// 再帰的に走査
+        #[arg(long, default_value_t = false)]
+        recursive: bool,
+        /// 変更せずに対象のみ表示
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+        /// 既存属性の型が異なっていても上書き
+        #[arg(long)]
+        force_type: bool,
+        /// 書き換え後にチャンクデータがバイト単位で一致するか検証
+        #[arg(long)]
+        verify: bool,
+        /// 書き込み先パート: first | all | shared | パート名（マルチパートEXR）
+        #[arg(long, default_value = "first")]
+        part: PartTarget,
+        /// 上書き前に .exr.bak を作成（書き込み成功時は残し、失敗時は削除。--backup false で無効）
+        #[arg(long, default_value_t = true, action = ArgAction::Set)]
+        backup: bool,
+        #[command(flatten)]
+        parallel: ParallelArgs,
+        /// 進捗を記録するチェックポイントファイル（中断後に同じ指定で再実行すると続きから処理）
+        #[arg(long)]
+        checkpoint: Option<PathBuf>,
+    },
+    /// 連番EXRの各フレームに timeCode と framesPerSecond を書き込み（--check で連続性のみ検証）
+    SeqTc {
+        /// ディレクトリ
+        #[arg(long)]
+        dir: PathBuf,
+        /// タイムコードのレート: 23.976 | 24 | 25 | 29.97 | 30 | 50 | 59.94 | 60
+        #[arg(long)]
+        fps: FrameRate,
+        /// 先頭フレームのタイムコード（例: 01:00:00:00、ドロップは 01:00:00;00）
+        #[arg(long, required_unless_present_any = ["from_frame_number", "check"], conflicts_with = "from_frame_number")]
+        start: Option<String>,
+        /// ファイル名のフレーム番号からタイムコードを算出
+        #[arg(long)]
+        from_frame_number: bool,
+        /// ドロップフレーム（29.97 / 59.94 のみ）
+        #[arg(long)]
+        drop_frame: bool,
+        /// 書き込まずに既存 timeCode の連続性を検証
+        #[arg(long)]
+        check: bool,
+        /// 再帰的に走査
+        #[arg(long, default_value_t = false)]
+        recursive: bool,
+        /// 変更せずに割り当て結果のみ表示
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+        /// 既存属性の型が異なっていても上書き
+        #[arg(long)]
+        force_type: bool,
+        /// 書き換え後にチャンクデータがバイト単位で一致するか検証
+        #[arg(long)]
+        verify: bool,
+        /// 書き込み先パート: first | all | shared | パート名（マルチパートEXR）
+        #[arg(long, default_value = "first")]
+        part: PartTarget,
+        /// 上書き前に .exr.bak を作成（書き込み成功時は残し、失敗時は削除。--backup false で無効）
+        #[arg(long, default_value_t = true, action = ArgAction::Set)]
+        backup: bool,
+    },
+    /// 連番EXRからProRes動画を生成（ffmpeg必要）
+    Prores {
+        /// ディレクトリ
+        #[arg(long)]
+        dir: PathBuf,
+        /// FPS
+        #[arg(long, default_value_t = 24.0)]
+        fps: f32,
+        /// 出力ファイル（.mov / .mxf / .mp4 など）
+        #[arg(long)]
+        out: PathBuf,
+        // 他のサブコマンドとのサイズ差を抑えるため Box に入れる
+        #[command(flatten)]
+        codec: Box<CodecArgs>,
+        #[command(flatten)]
+        frames: Box<FrameArgs>,
+        /// 音声ファイルを別トラックとして追加（複数指定可）: パス[,start=1001 | start=01:00:00:00][,channels=0+1]
+        /// start は音声の先頭に合わせるフレーム番号かタイムコード（既定: 連番の先頭フレーム）。書き出し範囲に合わせて切り詰める
+        #[arg(long = "audio")]
+        audio: Vec<AudioInput>,
+        /// 色空間変換 src:dst（例: linear:srgb | acescg:srgb | aces2065:rec2020）
+        #[arg(long, default_value = "linear:srgb")]
+        colorspace: VideoColorspace,
+        /// 色タグの上書き（既定は表示変換から自動）: プリセット srgb | rec709 | rec2020 | p3d65 | pq | hlg、
+        /// または primaries= / trc= / matrix= / range= をカンマ区切りで（例: rec709,range=pc）
+        #[arg(long)]
+        color_tags: Option<ColorTags>,
+        /// ffmpeg へ渡すフレームのビット深度: 8 | 16
+        #[arg(long, default_value = "16")]
+        bit_depth: BitDepth,
+        /// アルファを保持（ProRes 4444 / 4444xq のみ、他のプロファイルではエラー）
+        #[arg(long)]
+        alpha: bool,
+        /// ffmpeg の実行ファイル
+        #[arg(long, default_value = "ffmpeg")]
+        ffmpeg: PathBuf,
+        /// 最大辺サイズ（--resize 指定時は使わない）
+        #[arg(long, default_value_t = 2048)]
+        max_size: u32,
+        /// 露出（stop）
+        #[arg(long, default_value_t = 0.0)]
+        exposure: f32,
+        /// ガンマ
+        #[arg(long, default_value_t = 2.2)]
+        gamma: f32,
+        /// 高品質リサイズ
+        #[arg(long, value_enum, default_value_t = Quality::High)]
+        quality: Quality,
+        /// 表示変換: standard | raw | aces-srgb | aces-rec709 | aces-p3d65 | aces-rec2100-pq（ACES指定時は --colorspace を無視）
+        #[arg(long, default_value = "standard")]
+        display: String,
+        /// ACES表示変換時の入力primaries: acescg | aces2065 | srgb | rec2020 | p3d65
+        #[arg(long, default_value = "acescg")]
+        input_space: String,
+        #[command(flatten)]
+        gamut: GamutArgs,
+        /// アスペクトマスク（例: 2.39 | 1.85 | 16:9、@0.5 で不透明度50%）
+        #[arg(long)]
+        mask: Option<AspectMask>,
+        #[command(flatten)]
+        burnin: BurnInArgs,
+        // codec と同じく、他のサブコマンドとのサイズ差を抑えるため Box に入れる
+        #[command(flatten)]
+        slate: Box<SlateArgs>,
+        #[command(flatten)]
+        parallel: ParallelArgs,
+    },
+    /// 連番EXRを静止画の連番（PNG 8/16bit・TIFF 8/16bit/float・JPEG・DPX 10bit）として書き出し
+    Stills {
+        /// 入力連番（例: renders/beauty.####.exr、renders/beauty.%04d.exr）
+        #[arg(long)]
+        sequence: String,
+        /// 書き出すフレーム範囲（例: 1001-1100、既定: 存在する全フレーム）
+        #[arg(long)]
+        frames: Option<exrtool_core::batch::FrameRange>,
+        /// 出力パスのテンプレート。#### はパディング付きフレーム番号（例: out/beauty.####.dpx、out/{stem}.{ext}）
+        #[arg(long, default_value = "{dir}/{stem}.{ext}")]
+        out: String,
+        /// 形式: png | png16 | jpeg | tiff | tiff16 | tiff-float | dpx
+        #[arg(long, default_value = "png16")]
+        format: OutputFormat,
+        /// JPEG 品質（1-100）
+        #[arg(long, default_value_t = 90)]
+        jpeg_quality: u8,
+        /// 埋め込む色タグ（既定は表示変換から自動、LUT/OCIO 使用時はなし）: プリセット srgb | rec709 | rec2020 | p3d65、
+        /// primaries= / trc= のカンマ区切り、または none
+        #[arg(long)]
+        tags: Option<String>,
+        /// 最大辺サイズ
+        #[arg(long, default_value_t = 2048)]
+        max_size: u32,
+        /// 露出（stop）
+        #[arg(long, default_value_t = 0.0)]
+        exposure: f32,
+        /// ガンマ
+        #[arg(long, default_value_t = 2.2)]
+        gamma: f32,
+        /// .cube LUT
+        #[arg(long)]
+        lut: Option<PathBuf>,
+        /// 高品質リサイズ
+        #[arg(long, value_enum, default_value_t = Quality::High)]
+        quality: Quality,
+        /// 表示変換: standard | raw | aces-srgb | aces-rec709 | aces-p3d65 | aces-rec2100-pq
+        #[arg(long, default_value = "standard")]
+        display: String,
+        /// 入力のprimaries: acescg | aces2065 | srgb | rec2020 | p3d65
+        #[arg(long, default_value = "acescg")]
+        input_space: String,
+        /// 色域マッピング: none | clip | noclip | rgc
+        #[arg(long, default_value = "none")]
+        gamut: String,
+        /// マッピング先の色域: srgb | rec2020 | p3d65 | acescg
+        #[arg(long, default_value = "srgb")]
+        gamut_target: String,
+        /// 色域外のピクセルをマゼンタの縞で表示
+        #[arg(long)]
+        oog_overlay: bool,
+        /// アスペクトマスク（例: 2.39 | 1.85 | 16:9、@0.5 で不透明度50%）
+        #[arg(long)]
+        mask: Option<AspectMask>,
+        // prores と同じく、他のサブコマンドとのサイズ差を抑えるため Box に入れる
+        #[command(flatten)]
+        slate: Box<SlateArgs>,
+        #[command(flatten)]
+        parallel: ParallelArgs,
+        /// 実行内容のみ表示
+        #[arg(long)]
+        dry_run: bool,
+        /// 出力を上書きする際にバックアップ(.bak)を作成
+        #[arg(long)]
+        backup: bool,
+        /// 失敗しても残りのフレームを続行
+        #[arg(long)]
+        continue_on_error: bool,
+        /// 進捗を記録するチェックポイントファイル（中断後に同じ指定で再実行すると続きから処理）
+        #[arg(long)]
+        checkpoint: Option<PathBuf>,
+    },
+    /// 連番EXRからアニメーション（GIF / APNG / WebP）を生成（ffmpeg 不要、チケット添付用の短いレビュー）
+    Anim {
+        /// ディレクトリ
+        #[arg(long)]
+        dir: PathBuf,
+        /// 出力ファイル（.gif / .png / .webp）
+        #[arg(long)]
+        out: PathBuf,
+        /// 形式: gif | apng | webp（既定: --out の拡張子から）
+        #[arg(long)]
+        format: Option<AnimationFormat>,
+        /// FPS
+        #[arg(long, default_value_t = 12.0)]
+        fps: f32,
+        #[command(flatten)]
+        frames: Box<FrameArgs>,
+        /// 再生回数（0 で無限ループ）
+        #[arg(long, default_value_t = 0)]
+        loops: u16,
+        /// GIF のフレームごとのパレット色数（2-256）
+        #[arg(long, default_value_t = 256)]
+        colors: u16,
+        /// GIF のディザ: floyd-steinberg（既定）| ordered（Bayer、静止したショットでちらつかない）| none
+        #[arg(long, default_value = "floyd-steinberg")]
+        dither: Dither,
+        /// 色空間変換 src:dst（例: linear:srgb | acescg:srgb）
+        #[arg(long, default_value = "linear:srgb")]
+        colorspace: VideoColorspace,
+        /// APNG / WebP に埋め込む色タグの上書き（既定は表示変換から自動）: プリセット srgb | rec709 | rec2020 | p3d65
+        #[arg(long)]
+        color_tags: Option<ColorTags>,
+        /// アルファを保持（GIF は不透明度50%未満を透明に）
+        #[arg(long)]
+        alpha: bool,
+        /// 最大辺サイズ（--resize 指定時は使わない）
+        #[arg(long, default_value_t = 960)]
+        max_size: u32,
+        /// 露出（stop）
+        #[arg(long, default_value_t = 0.0)]
+        exposure: f32,
+        /// ガンマ
+        #[arg(long, default_value_t = 2.2)]
+        gamma: f32,
+        /// 高品質リサイズ
+        #[arg(long, value_enum, default_value_t = Quality::High)]
+        quality: Quality,
+        /// 表示変換: standard | raw | aces-srgb | aces-rec709 | aces-p3d65 | aces-rec2100-pq（ACES指定時は --colorspace を無視）
+        #[arg(long, default_value = "standard")]
+        display: String,
+        /// ACES表示変換時の入力primaries: acescg | aces2065 | srgb | rec2020 | p3d65
+        #[arg(long, default_value = "acescg")]
+        input_space: String,
+        #[command(flatten)]
+        gamut: GamutArgs,
+        /// アスペクトマスク（例: 2.39 | 1.85 | 16:9、@0.5 で不透明度50%）
+        #[arg(long)]
+        mask: Option<AspectMask>,
+        #[command(flatten)]
+        burnin: BurnInArgs,
+        // prores と同じく、他のサブコマンドとのサイズ差を抑えるため Box に入れる
+        #[command(flatten)]
+        slate: Box<SlateArgs>,
+        #[command(flatten)]
+        parallel: ParallelArgs,
+    },
+
+    /// メタデータを表示（feature `exr_pure` 必要）。`metadata diff` で属性を比較
+    #[command(args_conflicts_with_subcommands = true)]
+    Metadata {
+        #[command(subcommand)]
+        action: Option<MetadataAction>,
+        /// 入力EXR
+        input: Option<PathBuf>,
+        /// 出力形式: table | json
+        #[arg(long, default_value = "table")]
+        format: String,
+    },
+
+    /// 指定座標のリニア値を表示
+    Probe {
+        /// 入力EXR
+        input: PathBuf,
+        /// x座標
+        #[arg(long)]
+        x: usize,
+        /// y座標
+        #[arg(long)]
+        y: usize,
+    },
+
+    /// 1D LUT(.cube) を生成（トーンカーブ変換）
+    MakeLut1D {
+        /// 変換元: linear | srgb
+        #[arg(long)]
+        src: String,
+        /// 変換先: linear | srgb
+        #[arg(long)]
+        dst: String,
+        /// テーブルサイズ（例: 1024）
+        #[arg(long, default_value_t = 1024)]
+        size: usize,
+        /// 出力パス（.cube）
+        #[arg(short, long)]
+        out: PathBuf,
+    },
+
+    /// 3D LUT(.cube) を生成（色域+トーン変換）
+    MakeLut3D {
+        /// 変換元primaries: srgb | rec2020 | p3d65 | acescg | aces2065
+        #[arg(long)]
+        src_space: String,
+        /// 変換元トーン: linear | srgb | g24 | g22 | acescct
+        #[arg(long, default_value = "linear")]
+        src_tf: String,
+        /// 変換先primaries: srgb | rec2020 | p3d65 | acescg | aces2065
+        #[arg(long)]
+        dst_space: String,
+        /// 変換先トーン: linear | srgb | g24 | g22 | acescct
+        #[arg(long, default_value = "srgb")]
+        dst_tf: String,
+        /// テーブルサイズ（既定: 33）
+        #[arg(long, default_value_t = 33)]
+        size: usize,
+        /// 1D シェーパーサイズ（0で無効）
+        #[arg(long, default_value_t = 1024)]
+        shaper_size: usize,
+        /// 色域外の扱い: clip | noclip | rgc（ACES Reference Gamut Compression）
+        #[arg(long, default_value = "clip")]
+        clip: String,
+        /// 白色点変換: bradford | cat02 | cat16 | vonkries | none
+        #[arg(long, default_value = "bradford")]
+        cat: String,
+        #[command(flatten)]
+        rgc: RgcArgs,
+        /// 出力パス（.cube）
+        #[arg(short, long)]
+        out: PathBuf,
+    },
+
+    /// 色域変換の3x3行列とその逆行列を表示（Nuke ColorMatrix / OCIO 形式）
+    Matrix {
+        /// 変換元primaries: srgb | rec2020 | p3d65 | acescg | aces2065
+        #[arg(long)]
+        src: String,
+        /// 変換先primaries: srgb | rec2020 | p3d65 | acescg | aces2065
+        #[arg(long)]
+        dst: String,
+        /// 白色点変換: bradford | cat02 | cat16 | vonkries | none
+        #[arg(long, default_value = "bradford")]
+        cat: String,
+        /// 出力形式: all | nuke | ocio
+        #[arg(long, default_value = "all")]
+        format: String,
+    },
+
+    /// ACES Output Transform (RRT+ODT) を3D LUT(.cube) に焼き込み
+    MakeLutAces {
+        /// 入力primaries: acescg | aces2065 | srgb | rec2020 | p3d65
+        #[arg(long, default_value = "acescg")]
+        src_space: String,
+        /// 入力トーン（既定: acescct。linearだと0..1しかカバーしない）
+        #[arg(long, default_value = "acescct")]
+        src_tf: String,
+        /// ODT: srgb | rec709 | p3d65 | rec2100-pq
+        #[arg(long, default_value = "srgb")]
+        odt: String,
+        /// テーブルサイズ（既定: 65）
+        #[arg(long, default_value_t = 65)]
+        size: usize,
+        /// 出力パス（.cube）
+        #[arg(short, long)]
+        out: PathBuf,
+    },
+
+    /// メタデータルール(RuleFile: set/unset/copy/from_filename)をEXRに適用
+    MetaApply {
+        /// ルールファイル(YAML、docs/rules.yml 形式)
+        #[arg(long)]
+        rules: PathBuf,
+        /// 入力EXRまたはディレクトリ（複数可）
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// ディレクトリを再帰的に走査
+        #[arg(long)]
+        recursive: bool,
+        /// 書き込まずに差分のみ表示
+        #[arg(long)]
+        dry_run: bool,
+        /// 書き換え前に .exr.bak を作成
+        #[arg(long)]
+        backup: bool,
+        /// 既存属性の型が異なっていても上書き
+        #[arg(long)]
+        force_type: bool,
+        /// 書き換え後にチャンクデータがバイト単位で一致するか検証
+        #[arg(long)]
+        verify: bool,
+        /// 書き込み先パート: first | all | shared | パート名（ルールファイルの part より優先）
+        #[arg(long)]
+        part: Option<PartTarget>,
+        /// ファイルごとの結果をJSONで保存
+        #[arg(long)]
+        report: Option<PathBuf>,
+    },
+
+    /// OCIO config の色空間・ディスプレイ・ビュー・ルックを一覧表示（--ocio 必須）
+    OcioInfo,
+
+    /// ジョブファイル（version: 1、または旧形式のリスト）に基づきプレビューを一括書き出し
+    Apply {
+        /// ジョブファイル(YAML/JSON)
+        #[arg(long)]
+        rules: PathBuf,
+        /// 実行内容のみ表示
+        #[arg(long)]
+        dry_run: bool,
+        /// 出力を上書きする際にバックアップ(.bak)を作成
+        #[arg(long)]
+        backup: bool,
+        /// 失敗しても残りの処理を続行（ジョブファイルの continue_on_error より優先）
+        #[arg(long)]
+        continue_on_error: bool,
+        /// 実行ログ（.csv または .json）。省略時はジョブファイルの log
+        #[arg(long)]
+        log: Option<PathBuf>,
+        #[command(flatten)]
+        parallel: ParallelArgs,
+        /// 進捗を記録するチェックポイントファイル（中断後に同じ指定で再実行すると続きから処理）
+        #[arg(long)]
+        checkpoint: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum MetadataAction {
+    /// 2つのEXRの全属性をパートごとに比較。ディレクトリや3つ以上の入力では連番モード（フレーム間で変化する属性と値の範囲）
+    Diff {
+        /// 比較するEXR 2つ、または連番のディレクトリ／ファイル群
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// 入力が2ファイルでも連番モードで比較
+        #[arg(long)]
+        sequence: bool,
+        /// ディレクトリを再帰的に走査
+        #[arg(long)]
+        recursive: bool,
+        /// 比較から除外する属性名（globパターン、複数指定可。例: capDate, "render*"）
+        #[arg(long)]
+        ignore: Vec<String>,
+        /// 出力形式: table | json
+        #[arg(long, default_value = "table")]
+        format: String,
+    },
+    /// ファイル・パートごとの属性を JSON / CSV に書き出し、または XMP サイドカー（.xmp）を生成
+    Export {
+        /// EXRファイルまたはディレクトリ
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// ディレクトリを再帰的に走査
+        #[arg(long)]
+        recursive: bool,
+        /// 出力形式: json | csv | xmp
+        #[arg(long, default_value = "json")]
+        format: String,
+        /// 出力ファイル（json/csv。省略時は標準出力）、xmp では出力ディレクトリ（省略時はEXRの隣）
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// export した JSON / CSV を編集後にEXRへ書き戻す（変更のある属性のみ。ヘッダーのみ書き換え）
+    Import {
+        /// JSON または CSV（拡張子で判定）。相対パスはこのファイルのディレクトリ基準
+        table: PathBuf,
+        /// 変更せずに対象のみ表示
+        #[arg(long)]
+        dry_run: bool,
+        /// 表にない属性を削除（必須属性とレイアウト属性は残す）
+        #[arg(long)]
+        remove_missing: bool,
+        /// 既存属性の型が異なっていても上書き
+        #[arg(long)]
+        force_type: bool,
+        /// 書き換え後にチャンクデータがバイト単位で一致するか検証
+        #[arg(long)]
+        verify: bool,
+        /// 上書き前に .exr.bak を作成（書き込み成功時は残し、失敗時は削除。--backup false で無効）
+        #[arg(long, default_value_t = true, action = ArgAction::Set)]
+        backup: bool,
+    },
+}
+
+#[derive(Clone, ValueEnum)]
+enum Quality { Fast, High }
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let ocio = ocio_processor(&cli)?;
+    // 並列処理（prores）ではスレッド間で共有する
+    let ocio_shared = ocio.as_ref().map(|(p, _)| p.as_ref());
+    let ocio_proc = ocio_shared.map(|p| p as &dyn ColorProcessor);
+    // OCIO の出力がディスプレイ参照（display/view や --to のディスプレイ色空間）なら standard の sRGB エンコードは掛けない
+    let ocio_is_display = ocio.as_ref().is_some_and(|(_, display)| *display);
+    // スレートの色変換欄（OCIO 使用時）
+    let ocio_view_label = ocio_proc.and(match (&cli.ocio_display, &cli.ocio_view, &cli.to) {
+        (Some(d), Some(v), _) => Some(format!("OCIO {} / {}", d, v)),
+        (_, _, Some(to)) => Some(format!("OCIO -> {}", to)),
+        _ => None,
+    });
+    match cli.command {
+        Commands::Preview {
+            input,
+            out,
+            max_size,
+            exposure,
+            gamma,
+            lut,
+            quality,
+            display,
+            input_space,
+            gamut,
+            mask,
+            burnin,
+        } => {
+            let img = load_exr_basic(&input)?;
+            let burnin = burnin.build(
+                lut.as_deref()
+                    .and_then(|p| p.file_stem())
+                    .map(|s| s.to_string_lossy().into_owned()),
+                None,
+            )?;
+            let lut_obj = if let Some(p) = lut {
+                let txt = fs::read_to_string(p)?;
+                Some(parse_cube(&txt)?)
+            } else { None };
+            let pq = match quality { Quality::Fast => PreviewQuality::Fast, Quality::High => PreviewQuality::High };
+            let mut pipeline = PreviewPipeline::new(exposure, gamma, lut_obj.as_ref());
+            pipeline.display = parse_display(&display, &input_space)?;
+            if ocio_is_display && pipeline.display == DisplayTransform::Standard {
+                pipeline.display = DisplayTransform::Raw;
+            }
+            pipeline.gamut = gamut.mapping(&input_space)?;
+            pipeline.oog_overlay = gamut.oog_overlay;
+            pipeline.ocio = ocio_proc;
+            let mut preview = generate_preview_with(&img, max_size, &pipeline, pq);
+            if let Some(m) = &mask {
+                m.apply(&mut preview);
+            }
+            if let Some(b) = &burnin {
+                b.draw(&mut preview, &input)?;
+            }
+            export_png(&out, &preview)?;
+            println!(
+                "w={} h={} => {}",
+                preview.width,
+                preview.height,
+                out.display()
+            );
+        }
+        Commands::Probe { input, x, y } => {
+            let img = load_exr_basic(&input)?;
+            let px = img
+                .get_linear(x, y)
+                .with_context(|| format!("座標が範囲外: {},{}", x, y))?;
+            println!(
+                "linear RGBA: {:.7} {:.7} {:.7} {:.7}",
+                px.r, px.g, px.b, px.a
+            );
+        }
+        Commands::MakeLut1D {
+            src,
+            dst,
+            size,
+            out,
+        } => {
+            let parse_cs = |s: &str| -> Result<ColorSpace> {
+                match s.to_ascii_lowercase().as_str() {
+                    "linear" => Ok(ColorSpace::Linear),
+                    "srgb" => Ok(ColorSpace::Srgb),
+                    _ => Err(anyhow::anyhow!("unknown colorspace: {}", s)),
+                }
+            };
+            let cs_src = parse_cs(&src)?;
+            let cs_dst = parse_cs(&dst)?;
+            let text = make_1d_lut(cs_src, cs_dst, size);
+            fs::write(&out, text)?;
+            println!(
+                "LUT saved: {} ({} -> {}, size={})",
+                out.display(),
+                src,
+                dst,
+                size
+            );
+        }
+        Commands::MakeLut3D {
+            src_space,
+            src_tf,
+            dst_space,
+            dst_tf,
+            size,
+            shaper_size,
+            clip,
+            cat,
+            rgc,
+            out,
+        } => {
+            use exrtool_core::{make_3d_lut_cube_with, Lut3dOptions};
+            let sp = parse_space(&src_space)?; let dt = parse_space(&dst_space)?;
+            let st = parse_tf(&src_tf)?; let tt = parse_tf(&dst_tf)?;
+            let mut opts = Lut3dOptions::new(sp, st, dt, tt);
+            opts.size = size;
+            opts.shaper_size = shaper_size;
+            opts.clip = rgc.clip_mode(&clip)?;
+            opts.cat = cat.parse()?;
+            let text = make_3d_lut_cube_with(&opts, |_| true).map_err(|e| anyhow::anyhow!(e))?;
+            fs::write(&out, text)?;
+            println!(
+                "3D LUT saved: {} ({} {} -> {} {}, size={} shaper={} clip={})",
+                out.display(),
+                src_space,
+                src_tf,
+                dst_space,
+                dst_tf,
+                size,
+                shaper_size,
+                clip
+            );
+        }
+        Commands::Matrix {
+            src,
+            dst,
+            cat,
+            format,
+        } => {
+            use exrtool_core::{rgb_conversion_matrix, ChromaticAdaptation};
+            let cat_kind: ChromaticAdaptation = cat.parse()?;
+            let m = rgb_conversion_matrix(parse_space(&src)?, parse_space(&dst)?, cat_kind);
+            let inv = m
+                .try_inverse()
+                .ok_or_else(|| anyhow::anyhow!("matrix is not invertible"))?;
+            let fmt = format.to_ascii_lowercase();
+            if !matches!(fmt.as_str(), "all" | "nuke" | "ocio") {
+                anyhow::bail!("unknown format: {}", format);
+            }
+            let name = format!(
+                "{}_to_{}",
+                src.to_ascii_lowercase(),
+                dst.to_ascii_lowercase()
+            );
+            let inv_name = format!(
+                "{}_to_{}",
+                dst.to_ascii_lowercase(),
+                src.to_ascii_lowercase()
+            );
+            println!("# {} -> {} (cat={})", src, dst, cat_kind.name());
+            for (label, mat) in [(&name, &m), (&inv_name, &inv)] {
+                if fmt != "ocio" {
+                    println!("{}", nuke_color_matrix(label, mat));
+                }
+                if fmt != "nuke" {
+                    println!("# {}\n{}", label, ocio_matrix_transform(mat));
+                }
+            }
+        }
+        Commands::MakeLutAces {
+            src_space,
+            src_tf,
+            odt,
+            size,
+            out,
+        } => {
+            let sp = parse_space(&src_space)?;
+            let st = parse_tf(&src_tf)?;
+            let odt_kind: exrtool_core::aces::AcesOdt = odt.parse()?;
+            let text = exrtool_core::make_aces_lut_cube(sp, st, odt_kind, size);
+            fs::write(&out, text)?;
+            println!(
+                "ACES LUT saved: {} ({} {} -> {}, size={})",
+                out.display(),
+                src_space,
+                src_tf,
+                odt_kind.label(),
+                size
+            );
+        }
+        Commands::Stills {
+            sequence,
+            frames,
+            out,
+            format,
+            jpeg_quality,
+            tags,
+            max_size,
+            exposure,
+            gamma,
+            lut,
+            quality,
+            display,
+            input_space,
+            gamut,
+            gamut_target,
+            oog_overlay,
+            mask,
+            slate,
+            parallel,
+            dry_run,
+            backup,
+            continue_on_error,
+            checkpoint,
+        } => {
+            use exrtool_core::batch::{
+                ColourSettings, GamutSettings, Job, JobFile, OcioSettings, RunOptions,
+                JOB_FILE_VERSION,
+            };
+            // 1ジョブのジョブファイルとして apply と同じ executor で実行する（OCIO はジョブ側で読み込む）
+            let ocio = match cli.ocio.clone() {
+                Some(config) => Some(OcioSettings {
+                    config,
+                    from: cli.from.clone(),
+                    to: cli.to.clone(),
+                    display: cli.ocio_display.clone(),
+                    view: cli.ocio_view.clone(),
+                    looks: cli.look.clone(),
+                    vars: parse_ocio_vars(&cli.ocio_vars)?
+                        .into_iter()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect(),
+                }),
+                None => None,
+            };
+            let no_gamut = gamut.eq_ignore_ascii_case("none");
+            let gamut = (!no_gamut || oog_overlay).then(|| GamutSettings {
+                mode: if no_gamut { "noclip".into() } else { gamut },
+                target: gamut_target,
+                overlay: oog_overlay,
+            });
+            let job = Job {
+                name: None,
+                input: None,
+                sequence: Some(sequence),
+                frames,
+                output: out,
+                format,
+                jpeg_quality,
+                tags,
+                max_size,
+                quality: match quality {
+                    Quality::Fast => PreviewQuality::Fast,
+                    Quality::High => PreviewQuality::High,
+                },
+                colour: ColourSettings {
+                    exposure,
+                    gamma,
+                    lut,
+                    display,
+                    input_space,
+                    gamut,
+                    ocio,
+                },
+                mask,
+                slate: slate.build(None),
+            };
+            let file = JobFile {
+                version: JOB_FILE_VERSION,
+                continue_on_error,
+                log: None,
+                jobs: vec![job],
+                base: PathBuf::new(),
+                legacy: false,
+            };
+            let opts = RunOptions {
+                dry_run,
+                backup,
+                continue_on_error,
+                checkpoint,
+                threads: parallel.jobs,
+                memory_limit: parallel.memory_limit,
+                cancel: None,
+            };
+            let report = run_job_file(&file, &opts)?;
+            let summary = report.summary();
+            println!("{} ({})", summary, format);
+            if summary.failed > 0 {
+                anyhow::bail!("{} 件の処理に失敗しました", summary.failed);
+            }
+        }
+        Commands::Apply {
+            rules,
+            dry_run,
+            backup,
+            continue_on_error,
+            log,
+            parallel,
+            checkpoint,
+        } => {
+            use exrtool_core::batch::{JobFile, RunOptions};
+            let file = JobFile::from_path(&rules)
+                .with_context(|| format!("ジョブファイルの読み込みに失敗: {}", rules.display()))?;
+            let opts = RunOptions {
+                dry_run,
+                backup,
+                continue_on_error,
+                checkpoint,
+                threads: parallel.jobs,
+                memory_limit: parallel.memory_limit,
+                cancel: None,
+            };
+            let report = run_job_file(&file, &opts)?;
+            let summary = report.summary();
+            println!("{}", summary);
+            if let Some(log) = log.or_else(|| file.log.as_ref().map(|l| file.base.join(l))) {
+                if log
+                    .extension()
+                    .is_some_and(|e| e.eq_ignore_ascii_case("csv"))
+                {
+                    let mut w = csv::Writer::from_path(&log)?;
+                    w.write_record([
+                        "job", "input", "output", "frame", "status", "error", "millis",
+                    ])?;
+                    for r in &report.items {
+                        w.write_record([
+                            r.job.clone(),
+                            r.input.display().to_string(),
+                            r.output.display().to_string(),
+                            r.frame.map(|f| f.to_string()).unwrap_or_default(),
+                            r.status.to_string(),
+                            r.error.clone().unwrap_or_default(),
+                            r.millis.to_string(),
+                        ])?;
+                    }
+                    w.flush()?;
+                } else {
+                    fs::write(
+                        &log,
+                        serde_json::to_string_pretty(
+                            &serde_json::json!({ "summary": summary, "run": report }),
+                        )?,
+                    )?;
+                }
+                println!("log: {}", log.display());
+            }
+            if summary.failed > 0 {
+                anyhow::bail!("{} 件の処理に失敗しました", summary.failed);
+            }
+        }
+        Commands::FpsSet {
+            input,
+            fps,
+            attr,
+            dry_run,
+            force_type,
+            verify,
+            part,
+            backup,
+        } => {
+            #[cfg(feature = "exr_pure")]
+            {
+                use exrtool_core::metadata::{
+                    edit_metadata, write_with_backup, MetadataValue, WriteOptions,
+                };
+                use std::collections::HashMap;
+                if dry_run {
+                    println!("would write {}={} to {}", attr, fps, input.display());
+                    return Ok(());
+                }
+                let mut map = HashMap::new();
+                map.insert(attr.clone(), MetadataValue::Float(fps));
+                let opts = WriteOptions {
+                    force_type,
+                    verify,
+                    part,
+                    ..Default::default()
+                };
+                let (placements, bak) =
+                    write_with_backup(&input, backup, || edit_metadata(&input, &map, &[], &opts))
+                        .with_context(|| format!("failed {}", input.display()))?;
+                println!("wrote {}={} to {}", attr, fps, input.display());
+                for p in &placements {
+                    println!("  {}", p);
+                }
+                if let Some(b) = bak {
+                    println!("backup {}", b.display());
+                }
+            }
+            #[cfg(not(feature = "exr_pure"))]
+            {
+                let _ = (input, fps, attr, dry_run, force_type, verify, part, backup);
+                eprintln!("fps-set requires --features exr_pure");
+            }
+        }
+        Commands::SeqFps {
+            dir,
+            fps,
+            attr,
+            recursive,
+            dry_run,
+            force_type,
+            verify,
+            part,
+            backup,
+            parallel,
+            checkpoint,
+        } => {
+            #[cfg(feature = "exr_pure")]
+            {
+                use exrtool_core::executor::{execute, ExecOptions, Outcome};
+                use exrtool_core::metadata::{
+                    edit_metadata, write_with_backup, MetadataValue, WriteOptions,
+                };
+                use std::collections::HashMap;
+                let mut files = Vec::new();
+                collect_exr_files(&dir, recursive, &mut files)?;
+                files.sort_by(|a,b| a.file_name().unwrap().cmp(b.file_name().unwrap()));
+                if files.is_empty() { println!("no EXR files found in {}", dir.display()); return Ok(()); }
+                println!("target files: {}", files.len());
+                if dry_run {
+                    for f in files { println!("{}", f.display()); }
+                    return Ok(());
+                }
+                let mut map = HashMap::new();
+                map.insert(attr.clone(), MetadataValue::Float(fps));
+                let opts = WriteOptions {
+                    force_type,
+                    verify,
+                    part,
+                    ..Default::default()
+                };
+                let exec = ExecOptions {
+                    threads: parallel.jobs,
+                    memory_limit: parallel.memory_limit,
+                    checkpoint,
+                    continue_on_error: true,
+                    ..Default::default()
+                };
+                let pb = progress_bar(files.len());
+                let summary = execute(
+                    &files,
+                    &exec,
+                    |f| write_with_backup(f, backup, || edit_metadata(f, &map, &[], &opts)),
+                    |p| {
+                        pb.set_position(p.completed as u64);
+                        pb.suspend(|| match p.outcome {
+                            Outcome::Done((placements, bak)) => {
+                                let parts: Vec<_> = placements
+                                    .iter()
+                                    .flat_map(|p| p.parts.iter().map(String::as_str))
+                                    .collect();
+                                println!(
+                                    "wrote {}={} to {} [{}]",
+                                    attr,
+                                    fps,
+                                    p.item.display(),
+                                    parts.join(", ")
+                                );
+                                if let Some(b) = bak {
+                                    println!("backup {}", b.display());
+                                }
+                            }
+                            Outcome::Failed(e) => eprintln!("failed {}: {}", p.item.display(), e),
+                            Outcome::Resumed => println!("resumed {}", p.item.display()),
+                            Outcome::Skipped => {}
+                        });
+                    },
+                )?;
+                pb.finish_and_clear();
+                println!(
+                    "written={} failed={} resumed={}",
+                    summary.done, summary.failed, summary.resumed
+                );
+                if summary.failed > 0 {
+                    anyhow::bail!("{} ファイルの処理に失敗しました", summary.failed);
+                }
+            }
+            #[cfg(not(feature = "exr_pure"))]
+            {
+                let _ = (
+                    dir, fps, attr, recursive, dry_run, force_type, verify, part, backup, parallel,
+                    checkpoint,
+                );
+                eprintln!("seq-fps requires --features exr_pure");
+            }
+        }
+        Commands::SeqTc {
+            dir,
+            fps,
+            start,
+            from_frame_number,
+            drop_frame,
+            check,
+            recursive,
+            dry_run,
+            force_type,
+            verify,
+            part,
+            backup,
+        } => {
+            use exrtool_core::metadata::{edit_metadata, write_with_backup, WriteOptions};
+            use exrtool_core::timecode::{
+                check_continuity, plan_stamps, read_timecode, stamp_attributes, StampOptions,
+                StampStart, TimeCode,
+            };
+            let mut files = Vec::new();
+            collect_exr_files(&dir, recursive, &mut files)?;
+            files.sort_by(|a, b| a.file_name().unwrap().cmp(b.file_name().unwrap()));
+            if files.is_empty() {
+                println!("no EXR files found in {}", dir.display());
+                return Ok(());
+            }
+            if check {
+                let mut frames = Vec::with_capacity(files.len());
+                for f in &files {
+                    match read_timecode(f) {
+                        Ok(tc) => frames.push((f.clone(), tc)),
+                        Err(e) => anyhow::bail!("failed {}: {}", f.display(), e),
+                    }
+                }
+                let issues = check_continuity(&frames, fps);
+                for i in &issues {
+                    println!("{}", i);
+                }
+                println!("files={} issues={}", files.len(), issues.len());
+                if !issues.is_empty() {
+                    anyhow::bail!("タイムコードが連続していません");
+                }
+                return Ok(());
+            }
+            let start = match start {
+                Some(t) if !from_frame_number => StampStart::Timecode(TimeCode::parse_at(&t, fps)?),
+                _ => StampStart::FrameNumber,
+            };
+            let plan = plan_stamps(
+                &files,
+                &StampOptions {
+                    rate: fps,
+                    drop_frame,
+                    start,
+                },
+            )?;
+            println!("target files: {}", plan.len());
+            if dry_run {
+                for (f, tc) in &plan {
+                    println!("{} {}", tc.display_at(fps), f.display());
+                }
+                return Ok(());
+            }
+            let opts = WriteOptions {
+                force_type,
+                verify,
+                part,
+                ..Default::default()
+            };
+            let mut failed = 0;
+            for (f, tc) in &plan {
+                match write_with_backup(f, backup, || {
+                    edit_metadata(f, &stamp_attributes(*tc, fps), &[], &opts)
+                }) {
+                    Ok((_, bak)) => {
+                        println!(
+                            "wrote timeCode={} framesPerSecond={} to {}",
+                            tc.display_at(fps),
+                            fps,
+                            f.display()
+                        );
+                        if let Some(b) = bak {
+                            println!("backup {}", b.display());
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("failed {}: {}", f.display(), e);
+                        failed += 1;
+                    }
+                }
+            }
+            if failed > 0 {
+                anyhow::bail!("{} ファイルの処理に失敗しました", failed);
+            }
+        }
+        Commands::Prores {
+            dir,
+            fps,
+            out,
+            codec,
+            frames,
+            audio,
+            colorspace,
+            color_tags,
+            bit_depth,
+            alpha,
+            ffmpeg,
+            max_size,
+            exposure,
+            gamma,
+            quality,
+            display,
+            input_space,
+            gamut,
+            mask,
+            burnin,
+            slate,
+            parallel,
+        } => {
+            use exrtool_core::video::{
+                export_video, ffmpeg_available, sequence_files, VideoExportSettings,
+            };
+            let profile = codec.build()?;
+            if !ffmpeg_available(&ffmpeg) {
+                anyhow::bail!("ffmpeg が見つかりません（{}）。インストールして PATH を通すか --ffmpeg で指定してください", ffmpeg.display());
+            }
+            let files = sequence_files(&dir)?;
+            if files.is_empty() {
+                anyhow::bail!("no EXR files in {}", dir.display());
+            }
+            let mut display_tf = parse_display(&display, &input_space)?;
+            if ocio_is_display && display_tf == DisplayTransform::Standard {
+                display_tf = DisplayTransform::Raw;
+            }
+            let settings = VideoExportSettings {
+                ffmpeg,
+                fps,
+                codec: profile,
+                rate: codec.rate.clone(),
+                pix_fmt: codec.pix_fmt.clone(),
+                bit_depth,
+                alpha,
+                range: frames.range(),
+                resize: frames.resize(),
+                audio,
+                colorspace,
+                max_size,
+                exposure,
+                gamma,
+                quality: match quality {
+                    Quality::Fast => PreviewQuality::Fast,
+                    Quality::High => PreviewQuality::High,
+                },
+                display: display_tf,
+                gamut: gamut.mapping(&input_space)?,
+                oog_overlay: gamut.oog_overlay,
+                threads: parallel.jobs,
+                memory_limit: parallel.memory_limit,
+                color_tags: color_tags.unwrap_or_default(),
+                mask,
+                slate: slate.build(ocio_view_label.as_deref()),
+                burnin: burnin.build(
+                    Some(if display_tf == DisplayTransform::Standard {
+                        colorspace.to_string()
+                    } else {
+                        display.clone()
+                    }),
+                    fps.to_string().parse().ok(),
+                )?,
+            };
+            let pb = progress_bar(files.len());
+            let result = export_video(&files, &out, &settings, ocio_shared, |p| {
+                pb.set_length(p.total as u64);
+                pb.set_position(p.completed as u64);
+                true
+            });
+            pb.finish_and_clear();
+            result?;
+            println!("wrote {}", out.display());
+            println!("色タグ: {}", settings.resolved_color_tags());
+        }
+        Commands::Anim {
+            dir,
+            out,
+            format,
+            fps,
+            frames,
+            loops,
+            colors,
+            dither,
+            colorspace,
+            color_tags,
+            alpha,
+            max_size,
+            exposure,
+            gamma,
+            quality,
+            display,
+            input_space,
+            gamut,
+            mask,
+            burnin,
+            slate,
+            parallel,
+        } => {
+            use exrtool_core::anim::{export_animation, AnimationSettings};
+            use exrtool_core::video::{sequence_files, VideoExportSettings};
+            let format = match format.or_else(|| AnimationFormat::from_path(&out)) {
+                Some(f) => f,
+                None => anyhow::bail!("--out の拡張子から形式が分かりません（.gif / .png / .webp）。--format で指定してください"),
+            };
+            let files = sequence_files(&dir)?;
+            if files.is_empty() {
+                anyhow::bail!("no EXR files in {}", dir.display());
+            }
+            let mut display_tf = parse_display(&display, &input_space)?;
+            if ocio_is_display && display_tf == DisplayTransform::Standard {
+                display_tf = DisplayTransform::Raw;
+            }
+            let settings = AnimationSettings {
+                format,
+                loops,
+                colors,
+                dither,
+                frames: VideoExportSettings {
+                    fps,
+                    alpha,
+                    range: frames.range(),
+                    resize: frames.resize(),
+                    colorspace,
+                    max_size,
+                    exposure,
+                    gamma,
+                    quality: match quality {
+                        Quality::Fast => PreviewQuality::Fast,
+                        Quality::High => PreviewQuality::High,
+                    },
+                    display: display_tf,
+                    gamut: gamut.mapping(&input_space)?,
+                    oog_overlay: gamut.oog_overlay,
+                    threads: parallel.jobs,
+                    memory_limit: parallel.memory_limit,
+                    color_tags: color_tags.unwrap_or_default(),
+                    mask,
+                    slate: slate.build(ocio_view_label.as_deref()),
+                    burnin: burnin.build(
+                        Some(if display_tf == DisplayTransform::Standard {
+                            colorspace.to_string()
+                        } else {
+                            display.clone()
+                        }),
+                        fps.to_string().parse().ok(),
+                    )?,
+                    ..Default::default()
+                },
+            };
+            let pb = progress_bar(files.len());
+            let result = export_animation(&files, &out, &settings, ocio_shared, |p| {
+                pb.set_length(p.total as u64);
+                pb.set_position(p.completed as u64);
+                true
+            });
+            pb.finish_and_clear();
+            result?;
+            let size = fs::metadata(&out).map(|m| m.len()).unwrap_or(0);
+            println!(
+                "wrote {} ({}, {} KiB)",
+                out.display(),
+                format,
+                size.div_ceil(1024)
+            );
+        }
+        Commands::MetaApply {
+            rules,
+            inputs,
+            recursive,
+            dry_run,
+            backup,
+            force_type,
+            verify,
+            part,
+            report,
+        } => {
+            use exrtool_core::rules::{FileStatus, RuleEngine, RuleRunOptions};
+            let engine = RuleEngine::from_path(&rules)
+                .with_context(|| format!("ルールファイルの読み込みに失敗: {}", rules.display()))?;
+            let mut files = Vec::new();
+            for p in &inputs {
+                if p.is_dir() {
+                    collect_exr_files(p, recursive, &mut files)?;
+                } else {
+                    files.push(p.clone());
+                }
+            }
+            if files.is_empty() {
+                anyhow::bail!("EXRファイルが見つかりません");
+            }
+            let opts = RuleRunOptions {
+                dry_run,
+                backup,
+                force_type,
+                verify,
+                part,
+            };
+            let mut reports = Vec::with_capacity(files.len());
+            let (mut changed, mut failed) = (0, 0);
+            for f in &files {
+                let r = engine.apply(f, &opts);
+                let label = match &r.status {
+                    FileStatus::Unchanged => "unchanged".to_string(),
+                    FileStatus::DryRun => "dry-run".to_string(),
+                    FileStatus::Written { backup: Some(b) } => {
+                        format!("written (backup: {})", b.display())
+                    }
+                    FileStatus::Written { backup: None } => "written".to_string(),
+                    FileStatus::Failed { error } => format!("FAILED: {}", error),
+                };
+                println!("{} [{}]", f.display(), label);
+                for c in &r.changes {
+                    println!("  {}", c);
+                }
+                for n in &r.notes {
+                    println!("  # {}", n);
+                }
+                for p in &r.placements {
+                    println!("  @ {}", p);
+                }
+                if !r.changes.is_empty() {
+                    changed += 1;
+                }
+                if matches!(r.status, FileStatus::Failed { .. }) {
+                    failed += 1;
+                }
+                reports.push(r);
+            }
+            println!(
+                "files={} changed={} failed={}{}",
+                files.len(),
+                changed,
+                failed,
+                if dry_run { " (dry-run)" } else { "" }
+            );
+            if let Some(path) = report {
+                fs::write(&path, serde_json::to_string_pretty(&reports)?)?;
+                println!("report: {}", path.display());
+            }
+            if failed > 0 {
+                anyhow::bail!("{} ファイルの処理に失敗しました", failed);
+            }
+        }
+        Commands::OcioInfo => {
+            let path = cli
+                .ocio
+                .as_deref()
+                .context("--ocio <config.ocio> を指定してください")?;
+            let cfg = OcioSession::open(path, &cli.ocio_vars)?.cfg;
+            println!("# colorspaces");
+            for cs in cfg.colorspaces() {
+                let family = cfg.family(&cs).unwrap_or_default();
+                if family.is_empty() {
+                    println!("  {}", cs);
+                } else {
+                    println!("  {} ({})", cs, family);
+                }
+            }
+            println!("# displays");
+            for d in cfg.displays() {
+                println!("  {}: {}", d, cfg.views(&d).join(", "));
+            }
+            println!("# looks");
+            for l in cfg.looks() {
+                println!("  {}", l);
+            }
+        }
+        Commands::Metadata {
+            action:
+                Some(MetadataAction::Diff {
+                    inputs,
+                    sequence,
+                    recursive,
+                    ignore,
+                    format,
+                }),
+            ..
+        } => {
+            use exrtool_core::diff::{diff_files, diff_sequence, DiffOptions};
+            let opts = DiffOptions { ignore };
+            let json = format.eq_ignore_ascii_case("json");
+            if inputs.len() == 2 && !sequence && inputs.iter().all(|p| p.is_file()) {
+                let diff = diff_files(&inputs[0], &inputs[1], &opts)?;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&diff)?);
+                } else {
+                    print_file_diff(&diff);
+                }
+            } else {
+                let mut files = Vec::new();
+                for p in &inputs {
+                    if p.is_dir() {
+                        collect_exr_files(p, recursive, &mut files)?;
+                    } else {
+                        files.push(p.clone());
+                    }
+                }
+                if files.len() < 2 {
+                    anyhow::bail!("連番モードには2つ以上のEXRが必要です");
+                }
+                let diff = diff_sequence(&files, &opts)?;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&diff)?);
+                } else {
+                    print_sequence_diff(&diff);
+                }
+            }
+        }
+        Commands::Metadata {
+            action:
+                Some(MetadataAction::Export {
+                    inputs,
+                    recursive,
+                    format,
+                    output,
+                }),
+            ..
+        } => {
+            use exrtool_core::sidecar::{export_file, xmp_packet, xmp_path, MetadataTable};
+            let mut files = Vec::new();
+            for p in &inputs {
+                if p.is_dir() {
+                    collect_exr_files(p, recursive, &mut files)?;
+                } else {
+                    files.push(p.clone());
+                }
+            }
+            files.sort();
+            if files.is_empty() {
+                anyhow::bail!("EXRが見つかりません");
+            }
+            match format.to_ascii_lowercase().as_str() {
+                "xmp" => {
+                    if let Some(dir) = &output {
+                        fs::create_dir_all(dir)?;
+                    }
+                    for f in &files {
+                        let parts = exrtool_core::metadata::read_part_attributes(f)
+                            .with_context(|| format!("{}", f.display()))?;
+                        let xmp = xmp_path(f);
+                        let dest = match &output {
+                            Some(dir) => dir.join(xmp.file_name().unwrap()),
+                            None => xmp,
+                        };
+                        fs::write(&dest, xmp_packet(&parts))?;
+                        println!("wrote {}", dest.display());
+                    }
+                }
+                fmt @ ("json" | "csv") => {
+                    // 表のパスは出力先ディレクトリ基準の相対パス（import 時と同じ基準）
+                    let base = match &output {
+                        Some(o) => o.parent().map(Path::to_path_buf).unwrap_or_default(),
+                        None => PathBuf::new(),
+                    };
+                    let mut records = Vec::new();
+                    for f in &files {
+                        for mut r in export_file(f)? {
+                            r.file = relative_to(&r.file, &base);
+                            records.push(r);
+                        }
+                    }
+                    let mut text = Vec::new();
+                    if fmt == "json" {
+                        serde_json::to_writer_pretty(&mut text, &records)?;
+                        text.push(b'\n');
+                    } else {
+                        let table = MetadataTable::from_records(&records);
+                        let mut w = csv::Writer::from_writer(&mut text);
+                        w.write_record(&table.columns)?;
+                        for row in &table.rows {
+                            w.write_record(row)?;
+                        }
+                        w.flush()?;
+                    }
+                    match &output {
+                        Some(o) => {
+                            fs::write(o, text)?;
+                            println!("wrote {} ({} records)", o.display(), records.len());
+                        }
+                        None => std::io::Write::write_all(&mut std::io::stdout(), &text)?,
+                    }
+                }
+                other => anyhow::bail!("unknown format: {} (json | csv | xmp)", other),
+            }
+        }
+        Commands::Metadata {
+            action:
+                Some(MetadataAction::Import {
+                    table,
+                    dry_run,
+                    remove_missing,
+                    force_type,
+                    verify,
+                    backup,
+                }),
+            ..
+        } => {
+            use exrtool_core::metadata::{write_with_backup, WriteOptions};
+            use exrtool_core::sidecar::{
+                apply_changes, group_by_file, plan_import, ImportOptions, MetadataTable,
+                SidecarRecord,
+            };
+            let text =
+                fs::read_to_string(&table).with_context(|| format!("{}", table.display()))?;
+            let is_csv = table
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("csv"));
+            let records: Vec<SidecarRecord> = if is_csv {
+                let mut r = csv::Reader::from_reader(text.as_bytes());
+                let columns = r.headers()?.iter().map(String::from).collect();
+                let rows = r
+                    .records()
+                    .map(|row| Ok(row?.iter().map(String::from).collect()))
+                    .collect::<anyhow::Result<_>>()?;
+                MetadataTable { columns, rows }.to_records()?
+            } else {
+                serde_json::from_str(&text).with_context(|| format!("{}", table.display()))?
+            };
+            let base = table.parent().map(Path::to_path_buf).unwrap_or_default();
+            let import = ImportOptions { remove_missing };
+            let mut changes = Vec::new();
+            for r in &records {
+                let change = plan_import(r, &base, &import)?;
+                if !change.is_empty() {
+                    changes.push(change);
+                }
+            }
+            println!("records={} changes={}", records.len(), changes.len());
+            if dry_run {
+                for c in &changes {
+                    println!("{}", c);
+                }
+                return Ok(());
+            }
+            let opts = WriteOptions {
+                force_type,
+                verify,
+                ..Default::default()
+            };
+            let mut failed = 0;
+            // 同じファイルの複数パートへの変更は 1 回の書き換え（バックアップも 1 回）にまとめる
+            for (file, group) in group_by_file(&changes) {
+                match write_with_backup(file, backup, || apply_changes(file, &group, &opts)) {
+                    Ok((_, bak)) => {
+                        for c in &group {
+                            println!("wrote {}", c);
+                        }
+                        if let Some(b) = bak {
+                            println!("backup {}", b.display());
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("failed {}: {}", file.display(), e);
+                        failed += 1;
+                    }
+                }
+            }
+            if failed > 0 {
+                anyhow::bail!("{} ファイルの処理に失敗しました", failed);
+            }
+        }
+        Commands::Metadata { input, format, .. } => {
+            let input = input.context("入力EXRを指定してください")?;
+            // coreのread_metadataを呼び出し（feature未有効時はErr）
+            match exrtool_core::read_metadata(&input) {
+                Ok(meta) => {
+                    if format.to_lowercase() == "json" {
+                        println!("{}", serde_json::to_string_pretty(&meta).unwrap_or("{}".into()));
+                    } else {
+                        // 簡易表形式
+                        for (i, h) in meta.headers.iter().enumerate() {
+                            println!("# Header {}", i);
+                            println!("  layer_name   : {}", h.layer_name.as_deref().unwrap_or(""));
+                            println!("  layer_pos    : {},{}", h.layer_position.0, h.layer_position.1);
+                            println!("  layer_size   : {}x{}", h.layer_size.0, h.layer_size.1);
+                            println!("  pixel_aspect : {}", h.pixel_aspect);
+                            println!("  line_order   : {}", h.line_order);
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("metadata取得に失敗: {}\nヒント: CLIを feature 'exr_pure' でビルドしてください\n  cargo run -p exrtool-cli --features exr_pure -- metadata <in.exr>", e);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `base` 以下のパスは相対パスに、それ以外は絶対パスにする
+fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let abs = |p: &Path| {
+        fs::canonicalize(if p.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            p
+        })
+        .ok()
+    };
+    match (abs(path), abs(base)) {
+        (Some(p), Some(b)) => p.strip_prefix(&b).map(Path::to_path_buf).unwrap_or(p),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// 属性値の表示（存在しない場合は "-"）
+fn show_value(v: &Option<exrtool_core::metadata::MetadataValue>) -> String {
+    v.as_ref()
+        .map_or_else(|| "-".to_string(), |v| v.to_string())
+}
+
+/// 2ファイル比較の結果を表形式で表示
+fn print_file_diff(diff: &exrtool_core::diff::FileDiff) {
+    if diff.differences.is_empty() {
+        println!("no differences");
+        return;
+    }
+    let a = diff.a.file_name().map_or_else(
+        || diff.a.display().to_string(),
+        |n| n.to_string_lossy().into_owned(),
+    );
+    let b = diff.b.file_name().map_or_else(
+        || diff.b.display().to_string(),
+        |n| n.to_string_lossy().into_owned(),
+    );
+    let rows: Vec<[String; 4]> = diff
+        .differences
+        .iter()
+        .map(|d| {
+            [
+                d.part.clone(),
+                d.key.clone(),
+                show_value(&d.a),
+                show_value(&d.b),
+            ]
+        })
+        .collect();
+    let header = ["part".to_string(), "attribute".to_string(), a, b];
+    let mut width = header.clone().map(|h| h.chars().count());
+    for r in &rows {
+        for (w, c) in width.iter_mut().zip(r) {
+            *w = (*w).max(c.chars().count());
+        }
+    }
+    for r in std::iter::once(&header).chain(&rows) {
+        println!(
+            "{:<w0$}  {:<w1$}  {:<w2$}  {}",
+            r[0],
+            r[1],
+            r[2],
+            r[3],
+            w0 = width[0],
+            w1 = width[1],
+            w2 = width[2]
+        );
+    }
+    println!("differences={}", rows.len());
+}
+
+/// 連番比較の結果を表示（値ごとのフレーム数、数値・タイムコードは範囲も）
+fn print_sequence_diff(diff: &exrtool_core::diff::SequenceDiff) {
+    const SHOWN: usize = 5;
+    for v in &diff.varying {
+        let range = match (&v.min, &v.max) {
+            (Some(min), Some(max)) => format!(" range {} .. {}", min, max),
+            _ => String::new(),
+        };
+        println!("{} {}: {} values{}", v.part, v.key, v.values.len(), range);
+        for c in v.values.iter().take(SHOWN) {
+            println!(
+                "  {} x{} (first: {})",
+                show_value(&c.value),
+                c.count,
+                c.first.display()
+            );
+        }
+        if v.values.len() > SHOWN {
+            println!("  ... {} more", v.values.len() - SHOWN);
+        }
+    }
+    for (f, e) in &diff.failed {
+        println!("FAILED {}: {}", f.display(), e);
+    }
+    println!(
+        "frames={} constant={} varying={} failed={}",
+        diff.frames,
+        diff.constant,
+        diff.varying.len(),
+        diff.failed.len()
+    );
+}
+
+/// ディレクトリ内の .exr を名前順に収集
+fn collect_exr_files(dir: &std::path::Path, recursive: bool, out: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<std::io::Result<_>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            if recursive {
+                collect_exr_files(&path, recursive, out)?;
+            }
+        } else if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("exr"))
+        {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// `--ocio` の config。feature `use_ocio` 有効時は OpenColorIO（FFI）、無効時は純Rust実装で読み込む
+struct OcioSession {
+    #[cfg(feature = "use_ocio")]
+    cfg: exrtool_core::ocio::Config,
+    #[cfg(feature = "use_ocio")]
+    ctx: exrtool_core::ocio::Context,
+    #[cfg(not(feature = "use_ocio"))]
+    cfg: exrtool_core::ocio_config::Config,
+}
+
+/// スレッド間で共有できる OCIO Processor
+type SharedProcessor = Box<dyn ColorProcessor + Send + Sync>;
+
+/// `KEY=VALUE` 形式のコンテキスト変数を分解
+fn parse_ocio_vars(vars: &[String]) -> Result<Vec<(&str, &str)>> {
+    vars.iter()
+        .map(|v| {
+            v.split_once('=')
+                .with_context(|| format!("--ocio-var は KEY=VALUE 形式です: {}", v))
+        })
+        .collect()
+}
+
+impl OcioSession {
+    #[cfg(feature = "use_ocio")]
+    fn open(path: &str, vars: &[String]) -> Result<Self> {
+        use exrtool_core::ocio::Config;
+        // "aces1.3" は OCIO 2.2+ の組み込み CG config
+        let uri = if path.eq_ignore_ascii_case("aces1.3") {
+            "ocio://cg-config-v1.0.0_aces-v1.3_ocio-v2.1"
+        } else {
+            path
+        };
+        let cfg = Config::from_file(std::path::Path::new(uri))?;
+        let mut ctx = cfg.context()?;
+        for (k, v) in parse_ocio_vars(vars)? {
+            ctx.set_var(k, v)?;
+        }
+        Ok(Self { cfg, ctx })
+    }
+
+    #[cfg(not(feature = "use_ocio"))]
+    fn open(path: &str, vars: &[String]) -> Result<Self> {
+        let p = std::path::Path::new(path);
+        if !p.exists() {
+            anyhow::bail!(
+                "OCIO config が見つかりません: {}（組み込み config は feature use_ocio が必要）",
+                path
+            );
+        }
+        let mut cfg = exrtool_core::ocio_config::Config::from_file(p)?;
+        for (k, v) in parse_ocio_vars(vars)? {
+            cfg.set_context_var(k, v);
+        }
+        Ok(Self { cfg })
+    }
+
+    #[cfg(feature = "use_ocio")]
+    fn processor(&self, src: &str, dst: &str, looks: Option<&str>) -> Result<SharedProcessor> {
+        Ok(Box::new(self.cfg.processor_ex(
+            Some(&self.ctx),
+            src,
+            dst,
+            looks,
+        )?))
+    }
+
+    #[cfg(not(feature = "use_ocio"))]
+    fn processor(&self, src: &str, dst: &str, looks: Option<&str>) -> Result<SharedProcessor> {
+        Ok(Box::new(self.cfg.processor_with_looks(
+            src,
+            dst,
+            looks.unwrap_or(""),
+        )?))
+    }
+
+    #[cfg(feature = "use_ocio")]
+    fn display_view(
+        &self,
+        src: &str,
+        display: &str,
+        view: &str,
+        looks: Option<&str>,
+    ) -> Result<SharedProcessor> {
+        Ok(Box::new(self.cfg.processor_display_view_ex(
+            Some(&self.ctx),
+            Some(src),
+            display,
+            view,
+            looks,
+        )?))
+    }
+
+    #[cfg(not(feature = "use_ocio"))]
+    fn display_view(
+        &self,
+        src: &str,
+        display: &str,
+        view: &str,
+        looks: Option<&str>,
+    ) -> Result<SharedProcessor> {
+        Ok(Box::new(self.cfg.processor_display_view_with_looks(
+            src,
+            display,
+            view,
+            looks.unwrap_or(""),
+        )?))
+    }
+}
+
+/// --ocio と --from/--to または --ocio-display/--ocio-view（+ --look）から Processor を構築。
+/// 出力がディスプレイ参照の値かどうかも返す
+fn ocio_processor(cli: &Cli) -> Result<Option<(SharedProcessor, bool)>> {
+    let Some(path) = cli.ocio.as_deref() else {
+        return Ok(None);
+    };
+    if matches!(cli.command, Commands::OcioInfo) {
+        return Ok(None);
+    }
+    let session = OcioSession::open(path, &cli.ocio_vars)?;
+    let cfg = &session.cfg;
+    let src = match &cli.from {
+        Some(s) => s.clone(),
+        None => cfg
+            .role("scene_linear")
+            .context("--from を指定してください（config に scene_linear ロールがありません）")?,
+    };
+    let looks = cli.look.as_deref();
+    let proc = match (&cli.to, &cli.ocio_display, &cli.ocio_view) {
+        (Some(dst), None, None) => (
+            session.processor(&src, dst, looks)?,
+            cfg.is_display_colorspace(dst),
+        ),
+        (None, display, Some(view)) => {
+            let display = match display {
+                Some(d) => d.clone(),
+                None => cfg
+                    .displays()
+                    .into_iter()
+                    .next()
+                    .context("config にディスプレイがありません")?,
+            };
+            (session.display_view(&src, &display, view, looks)?, true)
+        }
+        (None, _, None) => anyhow::bail!("--ocio には --to か --ocio-view が必要です"),
+        _ => anyhow::bail!("--to と --ocio-display/--ocio-view は同時に指定できません"),
+    };
+    Ok(Some(proc))
+}
+
+/// Nuke の ColorMatrix ノード（.nk にそのまま貼り付け可能）
+fn nuke_color_matrix(name: &str, m: &nalgebra::Matrix3<f64>) -> String {
+    let mut s = String::from("ColorMatrix {\n matrix {\n");
+    for r in 0..3 {
+        s.push_str(&format!(
+            "     {{{:.10} {:.10} {:.10}}}\n",
+            m[(r, 0)],
+            m[(r, 1)],
+            m[(r, 2)]
+        ));
+    }
+    s.push_str(&format!("   }}\n name ColorMatrix_{}\n}}", name));
+    s
+}
+
+/// OCIO の MatrixTransform（4x4、行優先）
+fn ocio_matrix_transform(m: &nalgebra::Matrix3<f64>) -> String {
+    let mut v = Vec::with_capacity(16);
+    for r in 0..3 {
+        for c in 0..3 {
+            v.push(format!("{:.10}", m[(r, c)]));
+        }
+        v.push("0".into());
+    }
+    v.extend(["0", "0", "0", "1"].map(String::from));
+    format!("- !<MatrixTransform> {{matrix: [{}]}}", v.join(", "))
+}
+
+/// ACES Reference Gamut Compression のパラメータ
+#[derive(clap::Args, Debug)]
+struct RgcArgs {
+    /// RGC しきい値 R,G,B（既定: 0.815,0.803,0.880）
+    #[arg(long, value_delimiter = ',')]
+    rgc_threshold: Option<Vec<f64>>,
+    /// RGC リミット C,M,Y（既定: 1.147,1.264,1.312）
+    #[arg(long, value_delimiter = ',')]
+    rgc_limit: Option<Vec<f64>>,
+    /// RGC カーブの指数（既定: 1.2）
+    #[arg(long)]
+    rgc_power: Option<f64>,
+}
+
+impl RgcArgs {
+    fn clip_mode(&self, mode: &str) -> Result<ClipMode> {
+        let triple = |name: &str, v: &[f64]| -> Result<[f64; 3]> {
+            <[f64; 3]>::try_from(v)
+                .map_err(|_| anyhow::anyhow!("--{} needs 3 comma-separated values", name))
+        };
+        let mut clip: ClipMode = mode.parse()?;
+        if let ClipMode::Compress(p) = &mut clip {
+            if let Some(t) = &self.rgc_threshold {
+                p.threshold = triple("rgc-threshold", t)?;
+            }
+            if let Some(l) = &self.rgc_limit {
+                p.limit = triple("rgc-limit", l)?;
+            }
+            if let Some(pw) = self.rgc_power {
+                p.power = pw;
+            }
+            p.validate()?;
+        }
+        Ok(clip)
+    }
+}
+
+/// プレビュー/動画書き出しの色域マッピング（作業色域は --input-space）
+#[derive(clap::Args, Debug)]
+struct GamutArgs {
+    /// 色域マッピング: none | clip | noclip | rgc
+    #[arg(long, default_value = "none")]
+    gamut: String,
+    /// マッピング先の色域: srgb | rec2020 | p3d65 | acescg
+    #[arg(long, default_value = "srgb")]
+    gamut_target: String,
+    /// 色域外（RGC時は圧縮対象）のピクセルをマゼンタの縞で表示
+    #[arg(long)]
+    oog_overlay: bool,
+    #[command(flatten)]
+    rgc: RgcArgs,
+}
+
+impl GamutArgs {
+    fn mapping(&self, input_space: &str) -> Result<Option<GamutMapping>> {
+        if self.gamut.eq_ignore_ascii_case("none") && !self.oog_overlay {
+            return Ok(None);
+        }
+        // --oog-overlay だけ指定された場合は変換せずに表示のみ
+        let mode = if self.gamut.eq_ignore_ascii_case("none") {
+            ClipMode::NoClip
+        } else {
+            self.rgc.clip_mode(&self.gamut)?
+        };
+        Ok(Some(GamutMapping {
+            working: parse_space(input_space)?,
+            target: parse_space(&self.gamut_target)?,
+            mode,
+        }))
+    }
+}
+
+/// 焼き込み（preview / prores）。内蔵のビットマップフォントで描画（ASCII のみ）
+#[derive(clap::Args, Debug)]
+struct BurnInArgs {
+    /// 焼き込むテキスト「位置=テンプレート」（複数指定可、同じ位置は順に重ねる）。
+    /// 位置: top-left | top | top-right | center | bottom-left（既定） | bottom | bottom-right。
+    /// 変数: {frame} {frame:04} {timecode} {shot} {filename} {stem} {attr:名前} {date} {now:%H:%M} {lut} {width} {height}
+    #[arg(long = "burnin")]
+    burnin: Vec<BurnInText>,
+    /// 文字の大きさ（フォント1ドットのピクセル数、0 = 画像の高さから自動）
+    #[arg(long, default_value_t = 0)]
+    burnin_scale: u32,
+    /// 文字の背景ボックスの不透明度（0 でボックスなし）
+    #[arg(long, default_value_t = 0.5)]
+    burnin_box: f32,
+    /// ファイル名に当てる正規表現。名前付きグループを変数として使える（例: "(?P<shot>sh\d+)_(?P<task>\w+)"）
+    #[arg(long)]
+    burnin_regex: Option<String>,
+    /// {lut} に入れる名前（既定: preview は --lut のファイル名、prores は色変換/表示変換名）
+    #[arg(long)]
+    lut_name: Option<String>,
+}
+
+impl BurnInArgs {
+    fn build(&self, lut: Option<String>, rate: Option<FrameRate>) -> Result<Option<BurnIn>> {
+        if self.burnin.is_empty() {
+            return Ok(None);
+        }
+        let opts = BurnInOptions {
+            texts: self.burnin.clone(),
+            scale: self.burnin_scale,
+            box_opacity: self.burnin_box,
+            filename_regex: self.burnin_regex.clone(),
+            lut: self.lut_name.clone().or(lut),
+            rate,
+            ..Default::default()
+        };
+        Ok(Some(BurnIn::new(&opts)?))
+    }
+}
+
+/// prores のコーデック指定（core の CODEC_PROFILES から選ぶ）
+#[derive(clap::Args, Debug)]
+struct CodecArgs {
+    /// コーデック: prores | dnxhr | h264 | h265（ffmpeg のエンコーダ名 prores_ks / dnxhd / libx264 / libx265 も可）
+    #[arg(long, default_value = "prores")]
+    codec: String,
+    /// プロファイル（省略時はコーデックごとの既定）
+    /// prores: proxy | lt | 422 | 422hq（既定）| 4444 | 4444xq、dnxhr: lb | sq | hq（既定）| hqx | 444、
+    /// h264: high（既定, 8bit）| high10、h265: main（既定, 8bit）| main10
+    #[arg(long)]
+    profile: Option<String>,
+    /// h264 / h265 のレート指定: CRF（例: 18, crf=18）またはビットレート（例: 20M, bitrate=8000k）
+    #[arg(long)]
+    rate: Option<RateControl>,
+    /// エンコード時のピクセルフォーマット（既定はプロファイルごと。dnxhr 444 は gbrp10le も可）
+    #[arg(long)]
+    pix_fmt: Option<String>,
+}
+
+impl CodecArgs {
+    fn build(&self) -> anyhow::Result<&'static CodecProfile> {
+        CodecProfile::find(&self.codec, self.profile.as_deref())
+    }
+}
+
+/// prores / anim で書き出すフレームの範囲と出力解像度
+#[derive(clap::Args, Debug)]
+struct FrameArgs {
+    /// 開始フレーム番号（ファイル名の連番）
+    #[arg(long)]
+    in_frame: Option<u64>,
+    /// 終了フレーム番号
+    #[arg(long)]
+    out_frame: Option<u64>,
+    /// 開始/終了フレームの前後に加えるハンドルのフレーム数
+    #[arg(long, default_value_t = 0)]
+    handles: u64,
+    /// N フレームごとに書き出す
+    #[arg(long, default_value_t = 1)]
+    step: usize,
+    /// 出力解像度（例: 1920x1080, 2048x858）。EXR の pixelAspectRatio を考慮して正方ピクセルに変換
+    #[arg(long)]
+    resize: Option<Resize>,
+    /// --resize への合わせ方: fit（収まる大きさ）| fill（覆って切り抜き）| crop（拡縮なしで切り抜き）| pad（収めて黒で埋める）
+    #[arg(long, default_value = "pad")]
+    fit: FitMode,
+}
+
+impl FrameArgs {
+    fn range(&self) -> FrameRange {
+        FrameRange {
+            first: self.in_frame,
+            last: self.out_frame,
+            handles: self.handles,
+            step: self.step,
+        }
+    }
+
+    fn resize(&self) -> Option<Resize> {
+        self.resize.map(|r| Resize {
+            mode: self.fit,
+            ..r
+        })
+    }
+}
+
+/// prores / anim の先頭に入れるスレート。ショット/色変換/フレーム範囲は未指定なら自動
+#[derive(clap::Args, Debug)]
+struct SlateArgs {
+    /// スレートを入れる（--slate-* のいずれかを指定した場合も有効）
+    #[arg(long)]
+    slate: bool,
+    /// プロジェクト名
+    #[arg(long)]
+    slate_project: Option<String>,
+    /// ショット名（既定: ファイル名から）
+    #[arg(long)]
+    slate_shot: Option<String>,
+    /// バージョン
+    #[arg(long)]
+    slate_version: Option<String>,
+    /// アーティスト
+    #[arg(long)]
+    slate_artist: Option<String>,
+    /// 日付（既定: 今日）
+    #[arg(long)]
+    slate_date: Option<String>,
+    /// 備考
+    #[arg(long)]
+    slate_notes: Option<String>,
+    /// スレートを表示するフレーム数
+    #[arg(long, default_value_t = 1)]
+    slate_hold: u32,
+}
+
+impl SlateArgs {
+    fn build(&self, colour: Option<&str>) -> Option<Slate> {
+        let fields = [
+            &self.slate_project,
+            &self.slate_shot,
+            &self.slate_version,
+            &self.slate_artist,
+            &self.slate_date,
+            &self.slate_notes,
+        ];
+        if !self.slate && fields.iter().all(|f| f.is_none()) {
+            return None;
+        }
+        Some(Slate {
+            project: self.slate_project.clone(),
+            shot: self.slate_shot.clone(),
+            version: self.slate_version.clone(),
+            artist: self.slate_artist.clone(),
+            date: self.slate_date.clone(),
+            colour: colour.map(str::to_string),
+            notes: self.slate_notes.clone(),
+            hold: self.slate_hold,
+            ..Default::default()
+        })
+    }
+}
+
+/// 並列実行の設定（apply / seq-fps / prores）
+#[derive(clap::Args, Debug)]
+struct ParallelArgs {
+    /// 並列数（0 = CPUコア数）
+    #[arg(short = 'j', long, default_value_t = 0)]
+    jobs: usize,
+    /// 同時に処理するフレームのメモリ上限（例: 4G, 512M）。解像度から見積もり
+    #[arg(long, value_parser = parse_size)]
+    memory_limit: Option<u64>,
+}
+
+fn parse_size(s: &str) -> Result<u64> {
+    exrtool_core::executor::parse_size(s)
+}
+
+/// ジョブファイルを実行し、進捗バーと1件ごとの結果を表示（apply / stills）
+fn run_job_file(
+    file: &exrtool_core::batch::JobFile,
+    opts: &exrtool_core::batch::RunOptions,
+) -> Result<exrtool_core::batch::RunReport> {
+    use exrtool_core::batch::ItemStatus;
+    let pb = progress_bar(0);
+    let report = file.run(opts, |r, done, total| {
+        pb.set_length(total as u64);
+        pb.set_position(done as u64);
+        pb.suspend(|| match r.status {
+            ItemStatus::Failed => eprintln!(
+                "failed [{}] {}: {}",
+                r.job,
+                r.input.display(),
+                r.error.as_deref().unwrap_or_default()
+            ),
+            ItemStatus::DryRun => println!(
+                "process [{}] {} -> {}",
+                r.job,
+                r.input.display(),
+                r.output.display()
+            ),
+            ItemStatus::Written => println!(
+                "saved [{}] {} -> {}",
+                r.job,
+                r.input.display(),
+                r.output.display()
+            ),
+            ItemStatus::Resumed => println!(
+                "resumed [{}] {} -> {}",
+                r.job,
+                r.input.display(),
+                r.output.display()
+            ),
+            ItemStatus::Skipped => {}
+        });
+    })?;
+    pb.finish_and_clear();
+    Ok(report)
+}
+
+/// 進捗バー（stderr が端末でない場合は表示しない）。各行の出力は `suspend` 内で行う
+fn progress_bar(total: usize) -> ProgressBar {
+    let pb = ProgressBar::new(total as u64);
+    pb.set_style(
+        ProgressStyle::with_template(
+            "{bar:40.cyan/blue} {pos}/{len} [{elapsed_precise}, 残り {eta}] {msg}",
+        )
+        .unwrap(),
+    );
+    pb
+}
+
+fn parse_space(s: &str) -> Result<Primaries> {
+    s.parse()
+}
+
+fn parse_tf(s: &str) -> Result<TransferFn> {
+    match s.to_ascii_lowercase().as_str() {
+        "linear" => Ok(TransferFn::Linear),
+        "srgb" => Ok(TransferFn::Srgb),
+        "g24" | "gamma2.4" => Ok(TransferFn::Gamma24),
+        "g22" | "gamma2.2" => Ok(TransferFn::Gamma22),
+        "acescct" => Ok(TransferFn::AcesCct),
+        _ => Err(anyhow::anyhow!("unknown transfer: {}", s)),
+    }
+}
+
+/// `standard`、`raw` または `aces-<odt>` を表示変換に変換
+fn parse_display(s: &str, input_space: &str) -> Result<DisplayTransform> {
+    // 入力色域は ACES 表示変換のときだけ必要
+    let input = if s.to_ascii_lowercase().starts_with("aces-") {
+        parse_space(input_space)?
+    } else {
+        Primaries::ACEScgD60
+    };
+    DisplayTransform::parse(s, input)
+}
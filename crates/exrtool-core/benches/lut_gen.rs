@@ -12,6 +12,7 @@ fn bench_lut_gen(c: &mut Criterion) {
                     Primaries::Rec2020D65,
                     TransferFn::Srgb,
                     size,
+                    0,
                 ));
             });
         });
@@ -10,6 +10,7 @@ fn bench_make_3d_lut(c: &mut Criterion) {
                 Primaries::Rec2020D65,
                 TransferFn::Srgb,
                 black_box(33),
+                0,
             )
         })
     });
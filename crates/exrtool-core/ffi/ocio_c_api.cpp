@@ -1,5 +1,6 @@
 #include "ocio_c_api.h"
 #include <OpenColorIO/OpenColorIO.h>
+#include <cstring>
 
 namespace OCIO = OCIO_NAMESPACE;
 
@@ -100,4 +101,182 @@ void ocio_processor_apply_rgb(OcioProcessor proc, float rgb[3]) {
     (*p)->applyRGB(rgb);
 }
 
+int ocio_processor_apply_rgba(OcioProcessor proc, float *rgba, long width, long height) {
+    if (!proc || !rgba || width <= 0 || height <= 0) return -1;
+    auto p = static_cast<OCIO::ConstCPUProcessorRcPtr*>(proc);
+    try {
+        OCIO::PackedImageDesc img(rgba, width, height, 4);
+        (*p)->apply(img);
+        return 0;
+    } catch (...) {
+        return -1;
+    }
+}
+
+int ocio_config_num_colorspaces(OcioConfig cfg) {
+    if (!cfg) return 0;
+    auto c = static_cast<OCIO::ConstConfigRcPtr*>(cfg);
+    try {
+        return (*c)->getNumColorSpaces();
+    } catch (...) {
+        return 0;
+    }
+}
+
+const char* ocio_config_get_colorspace_name(OcioConfig cfg, int index) {
+    if (!cfg) return nullptr;
+    auto c = static_cast<OCIO::ConstConfigRcPtr*>(cfg);
+    try {
+        return (*c)->getColorSpaceNameByIndex(index);
+    } catch (...) {
+        return nullptr;
+    }
+}
+
+const char* ocio_config_get_colorspace_family(OcioConfig cfg, const char *name) {
+    if (!cfg || !name) return nullptr;
+    auto c = static_cast<OCIO::ConstConfigRcPtr*>(cfg);
+    try {
+        OCIO::ConstColorSpaceRcPtr cs = (*c)->getColorSpace(name);
+        return cs ? cs->getFamily() : nullptr;
+    } catch (...) {
+        return nullptr;
+    }
+}
+
+int ocio_config_is_display_colorspace(OcioConfig cfg, const char *name) {
+    if (!cfg || !name) return 0;
+    auto c = static_cast<OCIO::ConstConfigRcPtr*>(cfg);
+    try {
+        OCIO::ConstColorSpaceRcPtr cs = (*c)->getColorSpace(name);
+        if (!cs) return 0;
+        if (cs->getReferenceSpaceType() == OCIO::REFERENCE_SPACE_DISPLAY) return 1;
+        for (int d = 0; d < (*c)->getNumDisplays(); ++d) {
+            const char *display = (*c)->getDisplay(d);
+            for (int v = 0; v < (*c)->getNumViews(display); ++v) {
+                const char *view = (*c)->getView(display, v);
+                const char *vcs = (*c)->getDisplayViewColorSpaceName(display, view);
+                if (vcs && std::strcmp(vcs, "<USE_DISPLAY_NAME>") == 0) vcs = display;
+                OCIO::ConstColorSpaceRcPtr other = vcs ? (*c)->getColorSpace(vcs) : nullptr;
+                if (other && std::strcmp(other->getName(), cs->getName()) == 0) return 1;
+            }
+        }
+        return 0;
+    } catch (...) {
+        return 0;
+    }
+}
+
+const char* ocio_config_get_role_colorspace(OcioConfig cfg, const char *role) {
+    if (!cfg || !role) return nullptr;
+    auto c = static_cast<OCIO::ConstConfigRcPtr*>(cfg);
+    try {
+        return (*c)->getColorSpaceNameByRole(role);
+    } catch (...) {
+        return nullptr;
+    }
+}
+
+int ocio_config_num_looks(OcioConfig cfg) {
+    if (!cfg) return 0;
+    auto c = static_cast<OCIO::ConstConfigRcPtr*>(cfg);
+    try {
+        return (*c)->getNumLooks();
+    } catch (...) {
+        return 0;
+    }
+}
+
+const char* ocio_config_get_look_name(OcioConfig cfg, int index) {
+    if (!cfg) return nullptr;
+    auto c = static_cast<OCIO::ConstConfigRcPtr*>(cfg);
+    try {
+        return (*c)->getLookNameByIndex(index);
+    } catch (...) {
+        return nullptr;
+    }
+}
+
+OcioContext ocio_config_create_context(OcioConfig cfg) {
+    if (!cfg) return nullptr;
+    auto c = static_cast<OCIO::ConstConfigRcPtr*>(cfg);
+    try {
+        OCIO::ContextRcPtr ctx = (*c)->getCurrentContext()->createEditableCopy();
+        return new OCIO::ContextRcPtr(ctx);
+    } catch (...) {
+        return nullptr;
+    }
+}
+
+void ocio_context_release(OcioContext ctx) {
+    if (!ctx) return;
+    auto x = static_cast<OCIO::ContextRcPtr*>(ctx);
+    delete x;
+}
+
+int ocio_context_set_var(OcioContext ctx, const char *name, const char *value) {
+    if (!ctx || !name || !value) return -1;
+    auto x = static_cast<OCIO::ContextRcPtr*>(ctx);
+    try {
+        (*x)->setStringVar(name, value);
+        return 0;
+    } catch (...) {
+        return -1;
+    }
+}
+
+static OCIO::ConstContextRcPtr context_or_current(OCIO::ConstConfigRcPtr &cfg, OcioContext ctx) {
+    if (ctx) return *static_cast<OCIO::ContextRcPtr*>(ctx);
+    return cfg->getCurrentContext();
+}
+
+OcioProcessor ocio_config_get_processor_ex(OcioConfig cfg, OcioContext ctx, const char *src, const char *dst, const char *looks) {
+    if (!cfg || !src || !dst) return nullptr;
+    auto c = static_cast<OCIO::ConstConfigRcPtr*>(cfg);
+    try {
+        OCIO::ConstContextRcPtr context = context_or_current(*c, ctx);
+        OCIO::ConstProcessorRcPtr proc;
+        if (looks && *looks) {
+            OCIO::LookTransformRcPtr lt = OCIO::LookTransform::Create();
+            lt->setSrc(src);
+            lt->setDst(dst);
+            lt->setLooks(looks);
+            proc = (*c)->getProcessor(context, lt, OCIO::TRANSFORM_DIR_FORWARD);
+        } else {
+            proc = (*c)->getProcessor(context, src, dst);
+        }
+        OCIO::ConstCPUProcessorRcPtr cpu = proc->getDefaultCPUProcessor();
+        return new OCIO::ConstCPUProcessorRcPtr(cpu);
+    } catch (...) {
+        return nullptr;
+    }
+}
+
+OcioProcessor ocio_config_get_processor_display_view_ex(OcioConfig cfg, OcioContext ctx, const char *src, const char *display, const char *view, const char *looks) {
+    if (!cfg || !display || !view) return nullptr;
+    auto c = static_cast<OCIO::ConstConfigRcPtr*>(cfg);
+    try {
+        OCIO::ConstContextRcPtr context = context_or_current(*c, ctx);
+        const char *input = (src && *src) ? src : (*c)->getColorSpaceNameByRole("scene_linear");
+        OCIO::GroupTransformRcPtr group = OCIO::GroupTransform::Create();
+        if (looks && *looks) {
+            OCIO::LookTransformRcPtr lt = OCIO::LookTransform::Create();
+            lt->setSrc(input);
+            lt->setDst(input);
+            lt->setLooks(looks);
+            group->appendTransform(lt);
+        }
+        OCIO::DisplayViewTransformRcPtr dvt = OCIO::DisplayViewTransform::Create();
+        dvt->setSrc(input);
+        dvt->setDisplay(display);
+        dvt->setView(view);
+        group->appendTransform(dvt);
+        OCIO::ConstProcessorRcPtr proc = (*c)->getProcessor(context, group, OCIO::TRANSFORM_DIR_FORWARD);
+        OCIO::ConstCPUProcessorRcPtr cpu = proc->getDefaultCPUProcessor();
+        return new OCIO::ConstCPUProcessorRcPtr(cpu);
+    } catch (...) {
+        return nullptr;
+    }
+}
+
 }
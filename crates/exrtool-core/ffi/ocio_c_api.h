@@ -5,6 +5,7 @@ extern "C" {
 
 typedef void* OcioConfig;
 typedef void* OcioProcessor;
+typedef void* OcioContext;
 
 OcioConfig ocio_config_from_file(const char *path);
 void ocio_config_release(OcioConfig cfg);
@@ -16,6 +17,30 @@ const char* ocio_config_get_view_name(OcioConfig cfg, const char *display, int i
 OcioProcessor ocio_config_get_processor_display_view(OcioConfig cfg, const char *display, const char *view);
 void ocio_processor_release(OcioProcessor proc);
 void ocio_processor_apply_rgb(OcioProcessor proc, float rgb[3]);
+/* Applies to `width * height` packed RGBA pixels. Returns 0 on success. */
+int ocio_processor_apply_rgba(OcioProcessor proc, float *rgba, long width, long height);
+
+int ocio_config_num_colorspaces(OcioConfig cfg);
+const char* ocio_config_get_colorspace_name(OcioConfig cfg, int index);
+const char* ocio_config_get_colorspace_family(OcioConfig cfg, const char *name);
+/* Returns an empty string when the role is not defined. */
+const char* ocio_config_get_role_colorspace(OcioConfig cfg, const char *role);
+/* 1 when the colour space is display-referred (a v2 display colour space or
+   the colour space of a display's view), 0 otherwise. */
+int ocio_config_is_display_colorspace(OcioConfig cfg, const char *name);
+int ocio_config_num_looks(OcioConfig cfg);
+const char* ocio_config_get_look_name(OcioConfig cfg, int index);
+
+/* Editable copy of the config's current context (environment/search path). */
+OcioContext ocio_config_create_context(OcioConfig cfg);
+void ocio_context_release(OcioContext ctx);
+int ocio_context_set_var(OcioContext ctx, const char *name, const char *value);
+
+/* `ctx` and `looks` may be NULL. With looks, a LookTransform src -> dst is used. */
+OcioProcessor ocio_config_get_processor_ex(OcioConfig cfg, OcioContext ctx, const char *src, const char *dst, const char *looks);
+/* `ctx`, `src` (defaults to the scene_linear role) and `looks` may be NULL.
+   Looks are applied in `src` before the display/view. */
+OcioProcessor ocio_config_get_processor_display_view_ex(OcioConfig cfg, OcioContext ctx, const char *src, const char *display, const char *view, const char *looks);
 
 #ifdef __cplusplus
 }
@@ -0,0 +1,603 @@
+//! ACES 1.x Output Transforms (RRT + ODT) evaluated in pure Rust.
+//!
+//! The maths follows the aces-dev 1.3 reference CTL (`RRT.ctl`,
+//! `ODT.Academy.*.ctl`, `ACESlib.SSTS.ctl`) so the results match the Academy
+//! transforms without requiring an OpenColorIO installation.
+
+use anyhow::{anyhow, Result};
+use nalgebra::{Matrix3, Vector3};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::{bradford_adapt_matrix, rgb_to_rgb_matrix, rgb_to_xyz_matrix, xyz_white, Primaries};
+
+/// Output devices supported by [`OutputTransform`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AcesOdt {
+    /// sRGB monitor, 100 nits, dim surround (`ODT.Academy.RGBmonitor_100nits_dim`)
+    Srgb,
+    /// Rec.709 / BT.1886, 100 nits, dim surround (`ODT.Academy.Rec709_100nits_dim`)
+    Rec709,
+    /// P3-D65 cinema, 48 nits, gamma 2.6 (`ODT.Academy.P3D65_48nits`)
+    P3D65,
+    /// Rec.2100 PQ, 1000 nits (`RRTODT.Academy.Rec2020_1000nits_15nits_ST2084`)
+    Rec2100Pq1000,
+}
+
+impl AcesOdt {
+    pub const ALL: [AcesOdt; 4] = [
+        AcesOdt::Srgb,
+        AcesOdt::Rec709,
+        AcesOdt::P3D65,
+        AcesOdt::Rec2100Pq1000,
+    ];
+
+    /// Short identifier accepted by [`FromStr`].
+    pub fn name(self) -> &'static str {
+        match self {
+            AcesOdt::Srgb => "srgb",
+            AcesOdt::Rec709 => "rec709",
+            AcesOdt::P3D65 => "p3d65",
+            AcesOdt::Rec2100Pq1000 => "rec2100-pq",
+        }
+    }
+
+    /// Human readable label, e.g. for UI lists and LUT titles.
+    pub fn label(self) -> &'static str {
+        match self {
+            AcesOdt::Srgb => "ACES 1.3 sRGB (100 nits, dim)",
+            AcesOdt::Rec709 => "ACES 1.3 Rec.709 (100 nits, dim)",
+            AcesOdt::P3D65 => "ACES 1.3 P3-D65 (48 nits)",
+            AcesOdt::Rec2100Pq1000 => "ACES 1.3 Rec.2100 PQ (1000 nits)",
+        }
+    }
+}
+
+impl FromStr for AcesOdt {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "srgb" => Ok(AcesOdt::Srgb),
+            "rec709" | "bt709" => Ok(AcesOdt::Rec709),
+            "p3d65" | "p3-d65" => Ok(AcesOdt::P3D65),
+            "rec2100-pq" | "rec2100pq" | "pq" | "pq1000" => Ok(AcesOdt::Rec2100Pq1000),
+            _ => Err(anyhow!("unknown ACES ODT: {}", s)),
+        }
+    }
+}
+
+// ---- Constants from ACESlib ----
+const HALF_MIN: f64 = 5.960_464_48e-8;
+const HALF_MAX: f64 = 65504.0;
+const TINY: f64 = 1e-10;
+
+const RRT_GLOW_GAIN: f64 = 0.05;
+const RRT_GLOW_MID: f64 = 0.08;
+const RRT_RED_SCALE: f64 = 0.82;
+const RRT_RED_PIVOT: f64 = 0.03;
+const RRT_RED_HUE: f64 = 0.0;
+const RRT_RED_WIDTH: f64 = 135.0;
+const RRT_SAT_FACTOR: f64 = 0.96;
+const ODT_SAT_FACTOR: f64 = 0.93;
+const DIM_SURROUND_GAMMA: f64 = 0.9811;
+const CINEMA_WHITE: f64 = 48.0;
+const CINEMA_BLACK: f64 = 0.02;
+
+// B-spline basis used by the segmented splines (row-vector convention).
+const SPLINE_M: [[f64; 3]; 3] = [[0.5, -1.0, 0.5], [-1.0, 1.0, 0.5], [0.5, 0.0, 0.0]];
+
+/// Quadratic B-spline tone curve in log10 space (`segmented_spline_c5/c9`, `ssts`).
+#[derive(Debug, Clone)]
+struct Spline {
+    coefs_low: Vec<f64>,
+    coefs_high: Vec<f64>,
+    min: [f64; 2],
+    mid: [f64; 2],
+    max: [f64; 2],
+    slope_low: f64,
+    slope_high: f64,
+}
+
+fn spline_basis(cf: [f64; 3]) -> [f64; 3] {
+    let m = SPLINE_M;
+    [
+        cf[0] * m[0][0] + cf[1] * m[1][0] + cf[2] * m[2][0],
+        cf[0] * m[0][1] + cf[1] * m[1][1] + cf[2] * m[2][1],
+        cf[0] * m[0][2] + cf[1] * m[1][2] + cf[2] * m[2][2],
+    ]
+}
+
+fn spline_segment(coefs: &[f64], frac: f64) -> f64 {
+    let knots = coefs.len() - 2;
+    let coord = (knots - 1) as f64 * frac;
+    let j = (coord as usize).min(knots - 2);
+    let t = coord - j as f64;
+    let b = spline_basis([coefs[j], coefs[j + 1], coefs[j + 2]]);
+    t * t * b[0] + t * b[1] + b[2]
+}
+
+/// Returns `t + j` for the segment that produces `logy` (inverse of `spline_segment`).
+fn spline_segment_inv(coefs: &[f64], logy: f64) -> f64 {
+    let knots = coefs.len() - 2;
+    let knot_y: Vec<f64> = (0..knots)
+        .map(|i| (coefs[i] + coefs[i + 1]) / 2.0)
+        .collect();
+    let j = (0..knots - 1)
+        .find(|&i| logy > knot_y[i] && logy <= knot_y[i + 1])
+        .unwrap_or(if logy <= knot_y[0] { 0 } else { knots - 2 });
+    let b = spline_basis([coefs[j], coefs[j + 1], coefs[j + 2]]);
+    let c = b[2] - logy;
+    let d = (b[1] * b[1] - 4.0 * b[0] * c).max(0.0).sqrt();
+    let t = (2.0 * c) / (-d - b[1]);
+    t + j as f64
+}
+
+impl Spline {
+    fn eval(&self, x: f64) -> f64 {
+        let logx = x.max(HALF_MIN).log10();
+        let (lmin, lmid, lmax) = (
+            self.min[0].log10(),
+            self.mid[0].log10(),
+            self.max[0].log10(),
+        );
+        let logy = if logx <= lmin {
+            logx * self.slope_low + (self.min[1].log10() - self.slope_low * lmin)
+        } else if logx < lmid {
+            spline_segment(&self.coefs_low, (logx - lmin) / (lmid - lmin))
+        } else if logx < lmax {
+            spline_segment(&self.coefs_high, (logx - lmid) / (lmax - lmid))
+        } else {
+            logx * self.slope_high + (self.max[1].log10() - self.slope_high * lmax)
+        };
+        10f64.powf(logy)
+    }
+
+    /// Inverse for flat end slopes (`inv_ssts`).
+    fn eval_inv(&self, y: f64) -> f64 {
+        let (lmin, lmid, lmax) = (
+            self.min[0].log10(),
+            self.mid[0].log10(),
+            self.max[0].log10(),
+        );
+        let logy = y.max(TINY).log10();
+        let logx = if logy <= self.min[1].log10() {
+            lmin
+        } else if logy <= self.mid[1].log10() {
+            let inc = (lmid - lmin) / (self.coefs_low.len() - 3) as f64;
+            lmin + spline_segment_inv(&self.coefs_low, logy) * inc
+        } else if logy < self.max[1].log10() {
+            let inc = (lmax - lmid) / (self.coefs_high.len() - 3) as f64;
+            lmid + spline_segment_inv(&self.coefs_high, logy) * inc
+        } else {
+            lmax
+        };
+        10f64.powf(logx)
+    }
+}
+
+fn rrt_spline() -> Spline {
+    Spline {
+        coefs_low: vec![
+            -4.0000000000,
+            -4.0000000000,
+            -3.1573765773,
+            -0.4852499958,
+            1.8477324706,
+            1.8477324706,
+        ],
+        coefs_high: vec![
+            -0.7185482425,
+            2.0810307172,
+            3.6681241237,
+            4.0000000000,
+            4.0000000000,
+            4.0000000000,
+        ],
+        min: [0.18 * 2f64.powf(-15.0), 0.0001],
+        mid: [0.18, 4.8],
+        max: [0.18 * 2f64.powf(18.0), 10000.0],
+        slope_low: 0.0,
+        slope_high: 0.0,
+    }
+}
+
+fn odt_48nits_spline(rrt: &Spline) -> Spline {
+    Spline {
+        coefs_low: vec![
+            -1.6989700043,
+            -1.6989700043,
+            -1.4779000000,
+            -1.2291000000,
+            -0.8648000000,
+            -0.4480000000,
+            0.0051800000,
+            0.4511080334,
+            0.9113744414,
+            0.9113744414,
+        ],
+        coefs_high: vec![
+            0.5154386965,
+            0.8470437783,
+            1.1358000000,
+            1.3802000000,
+            1.5197000000,
+            1.5985000000,
+            1.6467000000,
+            1.6746091357,
+            1.6878733390,
+            1.6878733390,
+        ],
+        min: [rrt.eval(0.18 * 2f64.powf(-6.5)), 0.02],
+        mid: [rrt.eval(0.18), 4.8],
+        max: [rrt.eval(0.18 * 2f64.powf(6.5)), 48.0],
+        slope_low: 0.0,
+        slope_high: 0.04,
+    }
+}
+
+// ---- Single Stage Tone Scale (HDR output transforms) ----
+const MIN_STOP_SDR: f64 = -6.5;
+const MAX_STOP_SDR: f64 = 6.5;
+const MIN_STOP_RRT: f64 = -15.0;
+const MAX_STOP_RRT: f64 = 18.0;
+const MIN_LUM_SDR: f64 = 0.02;
+const MAX_LUM_SDR: f64 = 48.0;
+const MIN_LUM_RRT: f64 = 0.0001;
+const MAX_LUM_RRT: f64 = 10000.0;
+
+fn interpolate_1d(table: [[f64; 2]; 2], p: f64) -> f64 {
+    if p <= table[0][0] {
+        table[0][1]
+    } else if p >= table[1][0] {
+        table[1][1]
+    } else {
+        let s = (p - table[0][0]) / (table[1][0] - table[0][0]);
+        table[0][1] * (1.0 - s) + table[1][1] * s
+    }
+}
+
+fn ssts_spline(min_lum: f64, max_lum: f64, exp_shift: f64) -> Spline {
+    let aces_min = 0.18
+        * 2f64.powf(interpolate_1d(
+            [
+                [MIN_LUM_RRT.log10(), MIN_STOP_RRT],
+                [MIN_LUM_SDR.log10(), MIN_STOP_SDR],
+            ],
+            min_lum.log10(),
+        ));
+    let aces_max = 0.18
+        * 2f64.powf(interpolate_1d(
+            [
+                [MAX_LUM_SDR.log10(), MAX_STOP_SDR],
+                [MAX_LUM_RRT.log10(), MAX_STOP_RRT],
+            ],
+            max_lum.log10(),
+        ));
+    // (x, y, slope)
+    let lo = (aces_min, min_lum, 0.0f64);
+    let mid = (0.18f64, 4.8f64, 1.55f64);
+    let hi = (aces_max, max_lum, 0.0f64);
+    let knot = |p: (f64, f64, f64), inc: f64, sign: f64| {
+        p.2 * (p.0.log10() + sign * 0.5 * inc) + (p.1.log10() - p.2 * p.0.log10())
+    };
+
+    let inc_low = (mid.0.log10() - lo.0.log10()) / 3.0;
+    let pct_low = interpolate_1d(
+        [[MIN_STOP_RRT, 0.18], [MIN_STOP_SDR, 0.35]],
+        (lo.0 / 0.18).log2(),
+    );
+    let c_low = [
+        knot(lo, inc_low, -1.0),
+        knot(lo, inc_low, 1.0),
+        lo.1.log10() + pct_low * (mid.1.log10() - lo.1.log10()),
+        knot(mid, inc_low, -1.0),
+        knot(mid, inc_low, 1.0),
+    ];
+
+    let inc_high = (hi.0.log10() - mid.0.log10()) / 3.0;
+    let pct_high = interpolate_1d(
+        [[MAX_STOP_SDR, 0.89], [MAX_STOP_RRT, 0.90]],
+        (hi.0 / 0.18).log2(),
+    );
+    let c_high = [
+        knot(mid, inc_high, -1.0),
+        knot(mid, inc_high, 1.0),
+        mid.1.log10() + pct_high * (hi.1.log10() - mid.1.log10()),
+        knot(hi, inc_high, -1.0),
+        knot(hi, inc_high, 1.0),
+    ];
+
+    let shift = |x: f64| 2f64.powf(x.log2() - exp_shift);
+    Spline {
+        coefs_low: vec![c_low[0], c_low[1], c_low[2], c_low[3], c_low[4], c_low[4]],
+        coefs_high: vec![
+            c_high[0], c_high[1], c_high[2], c_high[3], c_high[4], c_high[4],
+        ],
+        min: [shift(lo.0), lo.1],
+        mid: [shift(mid.0), mid.1],
+        max: [shift(hi.0), hi.1],
+        slope_low: lo.2,
+        slope_high: hi.2,
+    }
+}
+
+/// SSTS parameters for the given luminance range with `y_mid` nits at 18% grey.
+fn ssts_params(y_min: f64, y_mid: f64, y_max: f64) -> Spline {
+    let default = ssts_spline(y_min, y_max, 0.0);
+    let exp_shift = default.eval_inv(y_mid).log2() - 0.18f64.log2();
+    ssts_spline(y_min, y_max, exp_shift)
+}
+
+// ---- Colour helpers ----
+fn rgb_2_saturation(rgb: [f64; 3]) -> f64 {
+    let max = rgb[0].max(rgb[1]).max(rgb[2]);
+    let min = rgb[0].min(rgb[1]).min(rgb[2]);
+    (max.max(TINY) - min.max(TINY)) / max.max(1e-2)
+}
+
+fn rgb_2_yc(rgb: [f64; 3]) -> f64 {
+    let [r, g, b] = rgb;
+    let chroma = (b * (b - g) + g * (g - r) + r * (r - b)).max(0.0).sqrt();
+    (b + g + r + 1.75 * chroma) / 3.0
+}
+
+fn rgb_2_hue(rgb: [f64; 3]) -> Option<f64> {
+    if rgb[0] == rgb[1] && rgb[1] == rgb[2] {
+        // hue is undefined for neutrals
+        return None;
+    }
+    let hue = (3f64.sqrt() * (rgb[1] - rgb[2]))
+        .atan2(2.0 * rgb[0] - rgb[1] - rgb[2])
+        .to_degrees();
+    Some(if hue < 0.0 { hue + 360.0 } else { hue })
+}
+
+fn center_hue(hue: f64, center: f64) -> f64 {
+    let h = hue - center;
+    if h < -180.0 {
+        h + 360.0
+    } else if h > 180.0 {
+        h - 360.0
+    } else {
+        h
+    }
+}
+
+fn sigmoid_shaper(x: f64) -> f64 {
+    let t = (1.0 - (x / 2.0).abs()).max(0.0);
+    let y = 1.0 + x.signum() * (1.0 - t * t);
+    y / 2.0
+}
+
+fn glow_fwd(yc_in: f64, glow_gain_in: f64, glow_mid: f64) -> f64 {
+    if yc_in <= 2.0 / 3.0 * glow_mid {
+        glow_gain_in
+    } else if yc_in >= 2.0 * glow_mid {
+        0.0
+    } else {
+        glow_gain_in * (glow_mid / yc_in - 0.5)
+    }
+}
+
+fn cubic_basis_shaper(x: f64, w: f64) -> f64 {
+    const M: [[f64; 4]; 4] = [
+        [-1.0 / 6.0, 3.0 / 6.0, -3.0 / 6.0, 1.0 / 6.0],
+        [3.0 / 6.0, -6.0 / 6.0, 3.0 / 6.0, 0.0],
+        [-3.0 / 6.0, 0.0, 3.0 / 6.0, 0.0],
+        [1.0 / 6.0, 4.0 / 6.0, 1.0 / 6.0, 0.0],
+    ];
+    if x <= -w / 2.0 || x >= w / 2.0 {
+        return 0.0;
+    }
+    let coord = (x + w / 2.0) * 4.0 / w;
+    let j = coord as usize;
+    let t = coord - j as f64;
+    let mono = [t * t * t, t * t, t, 1.0];
+    let col = match j {
+        3 => 0,
+        2 => 1,
+        1 => 2,
+        0 => 3,
+        _ => return 0.0,
+    };
+    let y: f64 = (0..4).map(|i| mono[i] * M[i][col]).sum();
+    y * 1.5
+}
+
+fn sat_adjust(rgb: Vector3<f64>, sat: f64, rgb2y: Vector3<f64>) -> Vector3<f64> {
+    let y = rgb.dot(&rgb2y);
+    rgb * sat + Vector3::repeat(y * (1.0 - sat))
+}
+
+fn moncurve_r(y: f64, gamma: f64, offs: f64) -> f64 {
+    let yb = (offs * gamma / ((gamma - 1.0) * (1.0 + offs))).powf(gamma);
+    let rs = ((gamma - 1.0) / offs).powf(gamma - 1.0) * ((1.0 + offs) / gamma).powf(gamma);
+    if y >= yb {
+        (1.0 + offs) * y.powf(1.0 / gamma) - offs
+    } else {
+        y * rs
+    }
+}
+
+/// SMPTE ST 2084 inverse EOTF: absolute luminance (cd/m²) to PQ code value.
+pub fn pq_encode(nits: f64) -> f64 {
+    const M1: f64 = 0.1593017578125;
+    const M2: f64 = 78.84375;
+    const C1: f64 = 0.8359375;
+    const C2: f64 = 18.8515625;
+    const C3: f64 = 18.6875;
+    let lm = (nits.max(0.0) / 10000.0).powf(M1);
+    ((C1 + C2 * lm) / (1.0 + C3 * lm)).powf(M2)
+}
+
+/// ACEScct encoding (linear AP1 to log code value).
+pub fn acescct_encode(lin: f64) -> f64 {
+    if lin <= 0.0078125 {
+        10.540_237_741_654_5 * lin + 0.072_905_534_195_835_5
+    } else {
+        (lin.log2() + 9.72) / 17.52
+    }
+}
+
+/// ACEScct decoding (log code value to linear AP1).
+pub fn acescct_decode(cct: f64) -> f64 {
+    if cct <= 0.155_251_141_552_511 {
+        (cct - 0.072_905_534_195_835_5) / 10.540_237_741_654_5
+    } else if cct < (HALF_MAX.log2() + 9.72) / 17.52 {
+        2f64.powf(cct * 17.52 - 9.72)
+    } else {
+        HALF_MAX
+    }
+}
+
+fn mul(m: &Matrix3<f64>, v: [f64; 3]) -> [f64; 3] {
+    let r = m * Vector3::new(v[0], v[1], v[2]);
+    [r.x, r.y, r.z]
+}
+
+/// A prepared ACES Output Transform (RRT + ODT) for one display.
+///
+/// Input is scene-linear ACES2065-1 (AP0); output is display code values in
+/// `[0, 1]` encoded for the target device.
+#[derive(Debug, Clone)]
+pub struct OutputTransform {
+    odt: AcesOdt,
+    ap0_to_ap1: Matrix3<f64>,
+    ap1_to_ap0: Matrix3<f64>,
+    ap1_to_xyz: Matrix3<f64>,
+    xyz_to_ap1: Matrix3<f64>,
+    ap1_rgb2y: Vector3<f64>,
+    d60_to_d65: Matrix3<f64>,
+    xyz_to_display: Matrix3<f64>,
+    display_to_xyz: Matrix3<f64>,
+    rrt: Spline,
+    tone: Spline,
+}
+
+impl OutputTransform {
+    pub fn new(odt: AcesOdt) -> Self {
+        let display = match odt {
+            AcesOdt::Srgb | AcesOdt::Rec709 => Primaries::SrgbD65,
+            AcesOdt::P3D65 => Primaries::P3D65,
+            AcesOdt::Rec2100Pq1000 => Primaries::Rec2020D65,
+        };
+        let ap1_to_xyz = rgb_to_xyz_matrix(Primaries::ACEScgD60);
+        let rrt = rrt_spline();
+        let tone = match odt {
+            AcesOdt::Rec2100Pq1000 => ssts_params(0.0001, 15.0, 1000.0),
+            _ => odt_48nits_spline(&rrt),
+        };
+        OutputTransform {
+            odt,
+            ap0_to_ap1: rgb_to_rgb_matrix(Primaries::ACES2065_1D60, Primaries::ACEScgD60),
+            ap1_to_ap0: rgb_to_rgb_matrix(Primaries::ACEScgD60, Primaries::ACES2065_1D60),
+            ap1_to_xyz,
+            xyz_to_ap1: ap1_to_xyz.try_inverse().unwrap(),
+            ap1_rgb2y: ap1_to_xyz.row(1).transpose(),
+            d60_to_d65: bradford_adapt_matrix(
+                xyz_white(Primaries::ACES2065_1D60),
+                xyz_white(Primaries::SrgbD65),
+            ),
+            xyz_to_display: rgb_to_xyz_matrix(display).try_inverse().unwrap(),
+            display_to_xyz: rgb_to_xyz_matrix(display),
+            rrt,
+            tone,
+        }
+    }
+
+    pub fn odt(&self) -> AcesOdt {
+        self.odt
+    }
+
+    /// RRT "sweeteners": glow, red modifier, AP0 → AP1 and global desaturation.
+    fn rrt_sweeteners(&self, aces: [f64; 3]) -> Vector3<f64> {
+        let mut aces = aces;
+        let saturation = rgb_2_saturation(aces);
+        let yc_in = rgb_2_yc(aces);
+        let s = sigmoid_shaper((saturation - 0.4) / 0.2);
+        let added_glow = 1.0 + glow_fwd(yc_in, RRT_GLOW_GAIN * s, RRT_GLOW_MID);
+        aces = aces.map(|c| c * added_glow);
+
+        if let Some(hue) = rgb_2_hue(aces) {
+            let weight = cubic_basis_shaper(center_hue(hue, RRT_RED_HUE), RRT_RED_WIDTH);
+            aces[0] += weight * saturation * (RRT_RED_PIVOT - aces[0]) * (1.0 - RRT_RED_SCALE);
+        }
+
+        let aces = aces.map(|c| c.max(0.0));
+        let pre = mul(&self.ap0_to_ap1, aces).map(|c| c.clamp(0.0, HALF_MAX));
+        sat_adjust(Vector3::from(pre), RRT_SAT_FACTOR, self.ap1_rgb2y)
+    }
+
+    /// Reference Rendering Transform: ACES2065-1 → OCES (AP0, nits-like scale).
+    pub fn rrt(&self, aces: [f64; 3]) -> [f64; 3] {
+        let pre = self.rrt_sweeteners(aces);
+        let post = [
+            self.rrt.eval(pre.x),
+            self.rrt.eval(pre.y),
+            self.rrt.eval(pre.z),
+        ];
+        mul(&self.ap1_to_ap0, post)
+    }
+
+    fn dark_to_dim(&self, lin: Vector3<f64>) -> Vector3<f64> {
+        let xyz = self.ap1_to_xyz * lin;
+        let sum = xyz.x + xyz.y + xyz.z;
+        let div = if sum == 0.0 { TINY } else { sum };
+        let (x, y) = (xyz.x / div, xyz.y / div);
+        let big_y = xyz.y.max(0.0).powf(DIM_SURROUND_GAMMA);
+        let yy = y.max(TINY);
+        let xyz = Vector3::new(x * big_y / yy, big_y, (1.0 - x - y) * big_y / yy);
+        self.xyz_to_ap1 * xyz
+    }
+
+    /// Full output transform in double precision.
+    pub fn apply_f64(&self, aces: [f64; 3]) -> [f64; 3] {
+        match self.odt {
+            AcesOdt::Rec2100Pq1000 => {
+                let (y_min, y_max) = (0.0001, 1000.0);
+                let pre = self.rrt_sweeteners(aces);
+                let post = pre.map(|c| self.tone.eval(c));
+                let lin = post.map(|c| (c - y_min) / (y_max - y_min));
+                // limit to the Rec.2020 gamut, then adapt the ACES white to D65
+                let xyz = self.ap1_to_xyz * lin;
+                let limited = (self.xyz_to_display * xyz).map(|c| c.clamp(0.0, 1.0));
+                let xyz = self.d60_to_d65 * (self.display_to_xyz * limited);
+                let out = (self.xyz_to_display * xyz).map(|c| c.max(0.0));
+                // stretch black: map linear CV back to absolute nits with 0 at black
+                [
+                    pq_encode(out.x * y_max),
+                    pq_encode(out.y * y_max),
+                    pq_encode(out.z * y_max),
+                ]
+            }
+            odt => {
+                let oces = self.rrt(aces);
+                let pre = mul(&self.ap0_to_ap1, oces);
+                let lin =
+                    Vector3::from(pre.map(|c| {
+                        (self.tone.eval(c) - CINEMA_BLACK) / (CINEMA_WHITE - CINEMA_BLACK)
+                    }));
+                let lin = if odt == AcesOdt::P3D65 {
+                    lin
+                } else {
+                    let dim = self.dark_to_dim(lin);
+                    sat_adjust(dim, ODT_SAT_FACTOR, self.ap1_rgb2y)
+                };
+                let xyz = self.d60_to_d65 * (self.ap1_to_xyz * lin);
+                let out = (self.xyz_to_display * xyz).map(|c| c.clamp(0.0, 1.0));
+                match odt {
+                    AcesOdt::Srgb => out.map(|c| moncurve_r(c, 2.4, 0.055)).into(),
+                    AcesOdt::Rec709 => out.map(|c| c.powf(1.0 / 2.4)).into(),
+                    _ => out.map(|c| c.powf(1.0 / 2.6)).into(),
+                }
+            }
+        }
+    }
+
+    /// Apply the output transform to one ACES2065-1 pixel.
+    pub fn apply(&self, aces: [f32; 3]) -> [f32; 3] {
+        let out = self.apply_f64([aces[0] as f64, aces[1] as f64, aces[2] as f64]);
+        [out[0] as f32, out[1] as f32, out[2] as f32]
+    }
+}
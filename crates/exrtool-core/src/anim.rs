@@ -0,0 +1,693 @@
+//! Animated review clips without ffmpeg: render an EXR sequence through the
+//! preview pipeline and encode it as GIF, APNG or animated WebP with pure
+//! Rust encoders.
+//!
+//! Frames are rendered exactly as for [`crate::video::export_video`] (range
+//! and step, size, colour, mask, burn-ins and slate) and written as they
+//! come in, so long sequences never sit in memory. GIF frames get their own
+//! palette, trained with NeuQuant and applied with optional dithering; WebP
+//! frames are lossless.
+
+use anyhow::{anyhow, bail, Context, Result};
+use color_quant::NeuQuant;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::executor::{execute, ExecOptions, Outcome};
+use crate::stills::icc_profile;
+use crate::video::{render_frame, BitDepth, Frame, VideoExportSettings, VideoProgress};
+use crate::{ColorProcessor, DisplayTransform};
+
+/// Container of an animated clip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnimationFormat {
+    #[default]
+    Gif,
+    Apng,
+    Webp,
+}
+
+impl AnimationFormat {
+    pub const ALL: [AnimationFormat; 3] = [
+        AnimationFormat::Gif,
+        AnimationFormat::Apng,
+        AnimationFormat::Webp,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::Apng => "apng",
+            AnimationFormat::Webp => "webp",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::Apng => "png",
+            AnimationFormat::Webp => "webp",
+        }
+    }
+
+    /// The format named by the extension of `path` (`.gif`, `.png`,
+    /// `.apng`, `.webp`).
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+
+    /// Units of the frame delays the container stores, per second.
+    fn ticks_per_second(self) -> f64 {
+        match self {
+            AnimationFormat::Gif => 100.0,
+            AnimationFormat::Apng | AnimationFormat::Webp => 1000.0,
+        }
+    }
+}
+
+impl FromStr for AnimationFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "gif" => AnimationFormat::Gif,
+            "apng" | "png" => AnimationFormat::Apng,
+            "webp" => AnimationFormat::Webp,
+            _ => bail!("unknown animation format: {} (gif, apng, webp)", s),
+        })
+    }
+}
+
+impl fmt::Display for AnimationFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// How GIF frames hide the steps between palette colours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dither {
+    /// Nearest palette colour.
+    None,
+    /// Error diffusion; smoothest gradients, but the noise moves from frame
+    /// to frame.
+    #[default]
+    FloydSteinberg,
+    /// 8x8 Bayer pattern; a fixed grain that stays put on static shots.
+    Ordered,
+}
+
+impl FromStr for Dither {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "none" => Dither::None,
+            "fs" | "floyd-steinberg" => Dither::FloydSteinberg,
+            "ordered" | "bayer" => Dither::Ordered,
+            _ => bail!("unknown dither: {} (none, floyd-steinberg, ordered)", s),
+        })
+    }
+}
+
+impl fmt::Display for Dither {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Dither::None => "none",
+            Dither::FloydSteinberg => "floyd-steinberg",
+            Dither::Ordered => "ordered",
+        })
+    }
+}
+
+/// Everything that decides how a sequence becomes an animated clip.
+#[derive(Debug, Clone)]
+pub struct AnimationSettings {
+    pub format: AnimationFormat,
+    /// Times the clip plays; 0 loops forever.
+    pub loops: u16,
+    /// GIF palette size per frame, 2 to 256.
+    pub colors: u16,
+    pub dither: Dither,
+    /// Frame rate, range and step, size, colour, mask, burn-ins and slate,
+    /// as for a movie. `alpha` keeps transparency (GIF: pixels less than
+    /// half opaque). The ffmpeg, codec, rate, bit depth and audio settings
+    /// do not apply.
+    pub frames: VideoExportSettings,
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        AnimationSettings {
+            format: AnimationFormat::default(),
+            loops: 0,
+            colors: 256,
+            dither: Dither::default(),
+            frames: VideoExportSettings {
+                max_size: 960,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl AnimationSettings {
+    /// Check the settings that do not depend on the frames.
+    pub fn validate(&self) -> Result<()> {
+        let frames = &self.frames;
+        if !(frames.fps.is_finite() && frames.fps > 0.0) {
+            bail!("invalid fps: {}", frames.fps);
+        }
+        if frames.range.step == 0 {
+            bail!("frame step must be at least 1");
+        }
+        if !(2..=256).contains(&self.colors) {
+            bail!("palette size must be 2-256: {}", self.colors);
+        }
+        if !frames.audio.is_empty() {
+            bail!("{} clips have no audio", self.format);
+        }
+        Ok(())
+    }
+}
+
+/// Encode `files` into the animated clip `out`. Frames are rendered and
+/// encoded in parallel and written in order; all must come out the same
+/// size. `progress` returns `false` to cancel, which fails with
+/// "cancelled". Nothing is left at `out` when the export fails.
+pub fn export_animation(
+    files: &[PathBuf],
+    out: &Path,
+    settings: &AnimationSettings,
+    ocio: Option<&(dyn ColorProcessor + Send + Sync)>,
+    progress: impl FnMut(&VideoProgress) -> bool,
+) -> Result<()> {
+    settings.validate()?;
+    let result = write_animation(files, out, settings, ocio, progress);
+    if result.is_err() {
+        let _ = fs::remove_file(out);
+    }
+    result
+}
+
+fn write_animation(
+    files: &[PathBuf],
+    out: &Path,
+    settings: &AnimationSettings,
+    ocio: Option<&(dyn ColorProcessor + Send + Sync)>,
+    mut progress: impl FnMut(&VideoProgress) -> bool,
+) -> Result<()> {
+    let video = VideoExportSettings {
+        bit_depth: BitDepth::Eight,
+        ..settings.frames.clone()
+    };
+    let files = &video.range.select(files)?[..];
+    let Some((first_path, rest)) = files.split_first() else {
+        bail!("no EXR frames to encode");
+    };
+    // the display transforms include their own gamut conversion
+    let lut = match video.display {
+        DisplayTransform::Standard => video.colorspace.lut()?,
+        _ => None,
+    };
+    let first = render_frame(first_path, &video, lut.as_ref(), ocio)
+        .with_context(|| format!("{}", first_path.display()))?;
+    let size = (first.width, first.height);
+    let slate = match video.slate.as_ref().filter(|s| s.hold > 0) {
+        Some(slate) => {
+            let img = slate
+                .with_defaults(files, &video.colour_label())
+                .render(size.0, size.1)
+                .context("slate")?;
+            Some((Frame::new(&img, &video), slate.hold))
+        }
+        None => None,
+    };
+    // an OCIO view says nothing about the colour it produces
+    let icc = match ocio {
+        Some(_) => None,
+        None => icc_profile(&video.resolved_color_tags()),
+    };
+    let count = files.len() + slate.is_some() as usize;
+    let mut writer = Writer::create(out, settings, size, count as u32, icc.as_deref())
+        .with_context(|| format!("{}", out.display()))?;
+    let ticks = settings.format.ticks_per_second();
+    let mut clock = Clock {
+        fps: video.exact_fps(),
+        slot: 0,
+    };
+    if let Some((frame, hold)) = slate {
+        let delay = clock.next(hold as u64, ticks);
+        writer
+            .write(encode(frame, settings)?, size, delay)
+            .context("slate")?;
+    }
+
+    let mut failure: Option<anyhow::Error> = None;
+    let cancel = Arc::new(AtomicBool::new(false));
+    let mut deliver = |frame: Result<Encoded, String>, path: &Path, completed: usize| {
+        if failure.is_none() {
+            let delay = clock.next(1, ticks);
+            failure = frame
+                .map_err(|e| anyhow!(e))
+                .and_then(|f| writer.write(f, size, delay))
+                .err()
+                .map(|e| e.context(format!("{}", path.display())));
+        }
+        let keep_going = failure.is_none()
+            && progress(&VideoProgress {
+                completed,
+                total: files.len(),
+                frame: path,
+            });
+        if !keep_going {
+            cancel.store(true, Ordering::SeqCst);
+        }
+    };
+    deliver(
+        encode(first, settings).map_err(|e| format!("{:#}", e)),
+        first_path,
+        1,
+    );
+
+    let exec = ExecOptions {
+        threads: video.threads,
+        memory_limit: video.memory_limit,
+        ordered: true,
+        cancel: Some(cancel.clone()),
+        ..Default::default()
+    };
+    let run = if cancel.load(Ordering::SeqCst) {
+        Ok(Default::default())
+    } else {
+        execute(
+            rest,
+            &exec,
+            |f| {
+                let frame = render_frame(f, &video, lut.as_ref(), ocio)?;
+                if (frame.width, frame.height) != size {
+                    bail!(
+                        "frame is {}x{}, expected {}x{} like the first frame",
+                        frame.width,
+                        frame.height,
+                        size.0,
+                        size.1
+                    );
+                }
+                encode(frame, settings)
+            },
+            |p| {
+                let frame = match p.outcome {
+                    Outcome::Done(f) => Ok(f),
+                    Outcome::Failed(e) => Err(e),
+                    Outcome::Skipped | Outcome::Resumed => return,
+                };
+                deliver(frame, p.item, p.completed + 1);
+            },
+        )
+    };
+    let stop = run
+        .err()
+        .or(failure)
+        .or_else(|| cancel.load(Ordering::SeqCst).then(|| anyhow!("cancelled")));
+    if let Some(e) = stop {
+        return Err(e);
+    }
+    writer
+        .finish()
+        .with_context(|| format!("{}", out.display()))
+}
+
+/// Frame delays on the `fps` clock, rounded per frame so the rounding never
+/// adds up to drift.
+struct Clock {
+    fps: f64,
+    slot: u64,
+}
+
+impl Clock {
+    /// Delay of the frame covering the next `slots` frames, in `1/ticks` s.
+    fn next(&mut self, slots: u64, ticks: f64) -> u32 {
+        let at = |slot: u64| (slot as f64 * ticks / self.fps).round() as u64;
+        let delay = at(self.slot + slots) - at(self.slot);
+        self.slot += slots;
+        delay.min(u32::MAX as u64) as u32
+    }
+}
+
+/// A frame ready for the container.
+enum Encoded {
+    /// GIF: palette indices, the RGB palette and the transparent index.
+    Indexed {
+        pixels: Vec<u8>,
+        palette: Vec<u8>,
+        transparent: Option<u8>,
+    },
+    /// APNG: RGB(A) rows.
+    Raw(Vec<u8>),
+    /// WebP: a lossless VP8L bitstream.
+    Vp8l(Vec<u8>),
+}
+
+/// Encode a rendered RGB(A) frame on its own, so it can run on the worker
+/// threads.
+fn encode(frame: Frame, settings: &AnimationSettings) -> Result<Encoded> {
+    let channels = if settings.frames.alpha { 4 } else { 3 };
+    Ok(match settings.format {
+        AnimationFormat::Gif => quantize(
+            &frame.data,
+            channels,
+            frame.width as usize,
+            settings.colors as usize,
+            settings.dither,
+        ),
+        AnimationFormat::Apng => Encoded::Raw(frame.data),
+        AnimationFormat::Webp => {
+            let color = match channels {
+                4 => image_webp::ColorType::Rgba8,
+                _ => image_webp::ColorType::Rgb8,
+            };
+            let mut webp = Vec::new();
+            image_webp::WebPEncoder::new(&mut webp)
+                .encode(&frame.data, frame.width, frame.height, color)
+                .map_err(|e| anyhow!("WebP: {}", e))?;
+            // a still without metadata is RIFF, WEBP and the VP8L chunk
+            if webp.get(12..16) != Some(&b"VP8L"[..]) {
+                bail!("WebP encoder wrote no VP8L chunk");
+            }
+            let Some(&[a, b, c, d]) = webp.get(16..20) else {
+                bail!("WebP encoder wrote no VP8L chunk size");
+            };
+            let len = u32::from_le_bytes([a, b, c, d]) as usize;
+            let Some(chunk) = webp.get(20..20 + len) else {
+                bail!("WebP VP8L chunk is shorter than its size ({} bytes)", len);
+            };
+            Encoded::Vp8l(chunk.to_vec())
+        }
+    })
+}
+
+/// Map RGB(A) pixels to at most `colors` palette entries. Pixels less than
+/// half opaque go to an extra transparent entry. Frames with few colours,
+/// like slates and mattes, keep them exactly.
+fn quantize(data: &[u8], channels: usize, width: usize, colors: usize, dither: Dither) -> Encoded {
+    let clear = |px: &[u8]| channels == 4 && px[3] < 128;
+    let transparent = data.chunks_exact(channels).any(clear);
+    let colors = if transparent { colors - 1 } else { colors }.max(2);
+    let opaque = || {
+        data.chunks_exact(channels)
+            .filter(|px| !clear(px))
+            .map(|px| [px[0], px[1], px[2]])
+    };
+    let t = transparent.then_some(colors as u8);
+
+    if let Some(exact) = exact_palette(opaque(), colors) {
+        let mut palette = vec![0; exact.len() * 3];
+        for (c, &i) in &exact {
+            palette[i as usize * 3..i as usize * 3 + 3].copy_from_slice(c);
+        }
+        let pixels = |t: Option<u8>| {
+            data.chunks_exact(channels)
+                .map(|px| match t {
+                    Some(t) if clear(px) => t,
+                    _ => exact[&[px[0], px[1], px[2]]],
+                })
+                .collect()
+        };
+        // the transparent entry follows the colours actually used
+        let t = t.map(|_| exact.len() as u8);
+        if transparent {
+            palette.resize(palette.len() + 3, 0);
+        }
+        return Encoded::Indexed {
+            pixels: pixels(t),
+            palette,
+            transparent: t,
+        };
+    }
+
+    let sample: Vec<u8> = opaque().flat_map(|[r, g, b]| [r, g, b, 255]).collect();
+    let nq = NeuQuant::new(10, colors, &sample);
+    let mut palette = nq.color_map_rgb();
+    let index = |c: [f32; 3]| {
+        let c = c.map(|v| v.round().clamp(0.0, 255.0) as u8);
+        nq.index_of(&[c[0], c[1], c[2], 255]) as u8
+    };
+    let pixel = |px: &[u8]| [px[0] as f32, px[1] as f32, px[2] as f32];
+    let mut pixels = vec![0; data.len() / channels];
+    match dither {
+        Dither::None => {
+            for (i, px) in data.chunks_exact(channels).enumerate() {
+                pixels[i] = match t {
+                    Some(t) if clear(px) => t,
+                    _ => index(pixel(px)),
+                };
+            }
+        }
+        Dither::Ordered => {
+            let spread = 255.0 / (colors as f32).cbrt();
+            for (i, px) in data.chunks_exact(channels).enumerate() {
+                pixels[i] = match t {
+                    Some(t) if clear(px) => t,
+                    _ => {
+                        let offset = bayer8(i % width, i / width) * spread;
+                        index(pixel(px).map(|v| v + offset))
+                    }
+                };
+            }
+        }
+        Dither::FloydSteinberg => {
+            // errors for this row and the next, one pixel of margin each side
+            let mut err = vec![[0f32; 3]; width + 2];
+            let mut below = vec![[0f32; 3]; width + 2];
+            for (y, row) in data.chunks_exact(width * channels).enumerate() {
+                for (x, px) in row.chunks_exact(channels).enumerate() {
+                    let i = y * width + x;
+                    if let Some(t) = t.filter(|_| clear(px)) {
+                        pixels[i] = t;
+                        continue;
+                    }
+                    let want = pixel(px).map(|v| v.clamp(0.0, 255.0));
+                    let want = [0, 1, 2].map(|k| (want[k] + err[x + 1][k]).clamp(0.0, 255.0));
+                    let idx = index(want);
+                    pixels[i] = idx;
+                    let got = &palette[idx as usize * 3..idx as usize * 3 + 3];
+                    for k in 0..3 {
+                        let e = want[k] - got[k] as f32;
+                        err[x + 2][k] += e * 7.0 / 16.0;
+                        below[x][k] += e * 3.0 / 16.0;
+                        below[x + 1][k] += e * 5.0 / 16.0;
+                        below[x + 2][k] += e / 16.0;
+                    }
+                }
+                std::mem::swap(&mut err, &mut below);
+                below.fill([0.0; 3]);
+            }
+        }
+    }
+    if transparent {
+        palette.resize(palette.len() + 3, 0);
+    }
+    Encoded::Indexed {
+        pixels,
+        palette,
+        transparent: t,
+    }
+}
+
+/// Palette indices of the distinct colours, if there are at most `colors`.
+fn exact_palette(
+    pixels: impl Iterator<Item = [u8; 3]>,
+    colors: usize,
+) -> Option<HashMap<[u8; 3], u8>> {
+    let mut map = HashMap::new();
+    for c in pixels {
+        if !map.contains_key(&c) {
+            if map.len() == colors {
+                return None;
+            }
+            map.insert(c, map.len() as u8);
+        }
+    }
+    Some(map)
+}
+
+/// Threshold of the 8x8 Bayer matrix at `(x, y)`, in -0.5..0.5.
+fn bayer8(x: usize, y: usize) -> f32 {
+    let (x, y) = (x & 7, y & 7);
+    let xy = x ^ y;
+    let m = ((xy & 1) << 5)
+        | ((y & 1) << 4)
+        | ((xy & 2) << 2)
+        | ((y & 2) << 1)
+        | ((xy & 4) >> 1)
+        | ((y & 4) >> 2);
+    (m as f32 + 0.5) / 64.0 - 0.5
+}
+
+/// The open container, receiving frames in order.
+enum Writer {
+    Gif(gif::Encoder<BufWriter<File>>),
+    Apng(png::Writer<BufWriter<File>>),
+    Webp(BufWriter<File>),
+}
+
+impl Writer {
+    fn create(
+        out: &Path,
+        settings: &AnimationSettings,
+        (width, height): (u32, u32),
+        frames: u32,
+        icc: Option<&[u8]>,
+    ) -> Result<Writer> {
+        let alpha = settings.frames.alpha;
+        let mut file = BufWriter::new(File::create(out)?);
+        Ok(match settings.format {
+            AnimationFormat::Gif => {
+                let (Ok(w), Ok(h)) = (u16::try_from(width), u16::try_from(height)) else {
+                    bail!(
+                        "GIF frames are at most 65535x65535, not {}x{}",
+                        width,
+                        height
+                    );
+                };
+                let mut gif = gif::Encoder::new(file, w, h, &[])?;
+                // the loop extension counts repeats after the first play
+                if settings.loops != 1 {
+                    gif.set_repeat(match settings.loops {
+                        0 => gif::Repeat::Infinite,
+                        n => gif::Repeat::Finite(n - 1),
+                    })?;
+                }
+                Writer::Gif(gif)
+            }
+            AnimationFormat::Apng => {
+                let mut info = png::Info::with_size(width, height);
+                info.color_type = match alpha {
+                    true => png::ColorType::Rgba,
+                    false => png::ColorType::Rgb,
+                };
+                info.bit_depth = png::BitDepth::Eight;
+                info.icc_profile = icc.map(|p| p.to_vec().into());
+                let mut png = png::Encoder::with_info(file, info)?;
+                png.set_animated(frames, settings.loops as u32)?;
+                Writer::Apng(png.write_header()?)
+            }
+            AnimationFormat::Webp => {
+                if width > 16384 || height > 16384 {
+                    bail!(
+                        "WebP frames are at most 16384x16384, not {}x{}",
+                        width,
+                        height
+                    );
+                }
+                // RIFF size is filled in by `finish`
+                file.write_all(b"RIFF\0\0\0\0WEBP")?;
+                let mut flags = 0x02; // animation
+                if alpha {
+                    flags |= 0x10;
+                }
+                if icc.is_some() {
+                    flags |= 0x20;
+                }
+                let mut vp8x = vec![flags, 0, 0, 0];
+                vp8x.extend(u24(width - 1));
+                vp8x.extend(u24(height - 1));
+                write_chunk(&mut file, b"VP8X", &vp8x)?;
+                if let Some(icc) = icc {
+                    write_chunk(&mut file, b"ICCP", icc)?;
+                }
+                // transparent black background, then the loop count
+                let mut anim = vec![0; 4];
+                anim.extend(settings.loops.to_le_bytes());
+                write_chunk(&mut file, b"ANIM", &anim)?;
+                Writer::Webp(file)
+            }
+        })
+    }
+
+    /// Add a `width`×`height` frame shown for `delay` ticks of the format.
+    fn write(&mut self, frame: Encoded, (width, height): (u32, u32), delay: u32) -> Result<()> {
+        match (self, frame) {
+            (
+                Writer::Gif(gif),
+                Encoded::Indexed {
+                    pixels,
+                    palette,
+                    transparent,
+                },
+            ) => {
+                let mut frame = gif::Frame::from_palette_pixels(
+                    width as u16,
+                    height as u16,
+                    pixels,
+                    palette,
+                    transparent,
+                );
+                frame.delay = delay.min(u16::MAX as u32) as u16;
+                // clear to the background so transparent pixels show no
+                // leftovers of the previous frame
+                frame.dispose = gif::DisposalMethod::Background;
+                gif.write_frame(&frame)?;
+            }
+            (Writer::Apng(png), Encoded::Raw(data)) => {
+                png.set_frame_delay(delay.min(u16::MAX as u32) as u16, 1000)?;
+                png.write_image_data(&data)?;
+            }
+            (Writer::Webp(file), Encoded::Vp8l(data)) => {
+                let mut anmf = Vec::with_capacity(data.len() + 25);
+                anmf.extend(u24(0));
+                anmf.extend(u24(0));
+                anmf.extend(u24(width - 1));
+                anmf.extend(u24(height - 1));
+                anmf.extend(u24(delay.min(0xFF_FFFF)));
+                // replace the canvas rather than blend onto it
+                anmf.push(0x02);
+                write_chunk(&mut anmf, b"VP8L", &data)?;
+                write_chunk(file, b"ANMF", &anmf)?;
+            }
+            _ => unreachable!("frame encoded for another format"),
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            Writer::Gif(gif) => gif.into_inner()?.flush()?,
+            Writer::Apng(png) => png.finish()?,
+            Writer::Webp(mut file) => {
+                let len = file.stream_position()?;
+                let Ok(riff) = u32::try_from(len - 8) else {
+                    bail!("WebP files are at most 4 GiB");
+                };
+                file.seek(SeekFrom::Start(4))?;
+                file.write_all(&riff.to_le_bytes())?;
+                file.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Little-endian 24-bit field of a WebP chunk.
+fn u24(v: u32) -> [u8; 3] {
+    let b = v.to_le_bytes();
+    [b[0], b[1], b[2]]
+}
+
+/// A RIFF chunk, padded to an even length.
+fn write_chunk(mut w: impl Write, name: &[u8; 4], data: &[u8]) -> std::io::Result<()> {
+    w.write_all(name)?;
+    w.write_all(&(data.len() as u32).to_le_bytes())?;
+    w.write_all(data)?;
+    if data.len() % 2 == 1 {
+        w.write_all(&[0])?;
+    }
+    Ok(())
+}
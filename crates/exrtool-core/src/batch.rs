@@ -0,0 +1,1252 @@
+//! Batch preview jobs.
+//!
+//! A job file is YAML (or JSON) with a `version`, run options and a list of
+//! jobs. Each job picks its inputs (a path/glob or a frame sequence), an
+//! output path template, an output format and a full colour pipeline:
+//!
+//! ```yaml
+//! version: 1
+//! continue_on_error: true
+//! log: run.csv
+//! jobs:
+//!   - name: beauty
+//!     sequence: renders/beauty.####.exr
+//!     frames: 1001-1100
+//!     output: previews/{job}/{stem}.{ext}
+//!     format: jpeg
+//!     colour:
+//!       exposure: 0.5
+//!       display: aces-srgb
+//!       input_space: acescg
+//!     mask: 2.39@0.8
+//!     slate:
+//!       project: Example
+//!       artist: Jane
+//! ```
+//!
+//! Outputs are `png`, `jpeg` or `tiff` at 8 bits, `png16`/`tiff16`,
+//! `tiff-float` or 10-bit `dpx` (see [`crate::stills`]), tagged with the
+//! colour encoding of the pipeline.
+//!
+//! A job's slate is written as an extra image one frame before its first
+//! frame (the output template sees `{frame}` and `{stem}` renumbered).
+//!
+//! Relative paths are resolved against the job file's directory. The older
+//! list form (`- input: a.exr` with `output`/`max_size`/`exposure`/`gamma`/
+//! `lut`) is still accepted; its paths stay relative to the working
+//! directory and it stops at the first error, as before.
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::Local;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::executor::{execute, exr_memory_estimate, ExecOptions, Outcome, Task};
+use crate::gamut::GamutMapping;
+use crate::rules::Template;
+use crate::slate::{AspectMask, Slate};
+use crate::stills::{self, icc_profile, DpxInfo};
+use crate::timecode::frame_number;
+use crate::video::ColorTags;
+use crate::{
+    generate_preview16, generate_preview_f32, generate_preview_with, load_exr_basic, parse_cube,
+    ClipMode, ColorProcessor, DisplayTransform, Lut, PreviewImage, PreviewImage16, PreviewImageF32,
+    PreviewPipeline, PreviewQuality, Primaries,
+};
+
+/// The job file version this build reads.
+pub const JOB_FILE_VERSION: u32 = 1;
+
+/// One entry of the legacy list form.
+#[derive(Debug, Deserialize)]
+pub struct ApplyRule {
+    pub input: PathBuf,
+    #[serde(default)]
+    pub output: Option<PathBuf>,
+    #[serde(default)]
+    pub max_size: Option<u32>,
+    #[serde(default)]
+    pub exposure: Option<f32>,
+    #[serde(default)]
+    pub gamma: Option<f32>,
+    #[serde(default)]
+    pub lut: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobFile {
+    pub version: u32,
+    /// Keep going after a failed item instead of stopping.
+    #[serde(default)]
+    pub continue_on_error: bool,
+    /// Run log (`.csv` or `.json`) to write after the run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log: Option<PathBuf>,
+    pub jobs: Vec<Job>,
+    /// Directory relative paths are resolved against.
+    #[serde(skip)]
+    pub base: PathBuf,
+    /// Converted from the legacy list form: outputs are literal paths (no
+    /// `#` frame placeholders) and items run one at a time, in list order.
+    #[serde(skip)]
+    pub legacy: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Job {
+    /// Shown in reports and available as `{job}` in the output template.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// A file or a glob pattern (`plates/*.exr`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<String>,
+    /// A frame sequence with `####` or `%04d` in place of the frame number.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<String>,
+    /// Frames of `sequence` (`1001-1100`); every frame on disk when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frames: Option<FrameRange>,
+    /// Output path template. Variables: `{job}`, `{dir}`, `{stem}`,
+    /// `{filename}`, `{frame}` (`{frame:04}`), `{index}`, `{ext}`,
+    /// `{attr:NAME}` and `{now:%Y%m%d}`. A run of `#` is the frame number
+    /// padded to its length (`beauty.####.dpx`).
+    #[serde(default = "default_output")]
+    pub output: String,
+    #[serde(default)]
+    pub format: OutputFormat,
+    /// JPEG quality, 1-100.
+    #[serde(default = "default_jpeg_quality")]
+    pub jpeg_quality: u8,
+    /// Colour tags embedded in the outputs (ICC profile, or the DPX transfer
+    /// and colorimetric fields): a preset or `primaries=`/`trc=` list as for
+    /// movies, or `none`. Derived from the display transform when absent;
+    /// outputs through a LUT or OCIO are untagged unless given here.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<String>,
+    #[serde(default = "default_max_size")]
+    pub max_size: u32,
+    #[serde(default = "default_quality")]
+    pub quality: PreviewQuality,
+    #[serde(default)]
+    pub colour: ColourSettings,
+    /// Letterbox/pillarbox bars (`2.39`, `16:9`, `1.85@0.5`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mask: Option<AspectMask>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slate: Option<Slate>,
+}
+
+fn default_output() -> String {
+    "{dir}/{stem}.{ext}".to_string()
+}
+
+fn default_jpeg_quality() -> u8 {
+    90
+}
+
+fn default_max_size() -> u32 {
+    2048
+}
+
+fn default_quality() -> PreviewQuality {
+    PreviewQuality::High
+}
+
+/// Colour pipeline of a job, in processing order: exposure → OCIO → gamut
+/// mapping → LUT → display transform.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColourSettings {
+    /// Exposure in stops.
+    pub exposure: f32,
+    /// Gamma of the `standard` display (0 disables it).
+    pub gamma: f32,
+    /// `.cube` LUT.
+    pub lut: Option<PathBuf>,
+    /// `standard`, `raw` or `aces-<odt>` (`aces-srgb`, `aces-rec709`,
+    /// `aces-p3d65`, `aces-rec2100-pq`).
+    pub display: String,
+    /// Primaries of the scene-linear input, for ACES displays and gamut
+    /// mapping.
+    pub input_space: String,
+    pub gamut: Option<GamutSettings>,
+    pub ocio: Option<OcioSettings>,
+}
+
+impl ColourSettings {
+    /// Short description of the pipeline, for slates.
+    pub fn label(&self) -> String {
+        let mut label = match &self.ocio {
+            Some(OcioSettings {
+                display: Some(d),
+                view: Some(v),
+                ..
+            }) => format!("OCIO {} / {}", d, v),
+            Some(OcioSettings { to: Some(to), .. }) => format!("OCIO -> {}", to),
+            _ => format!("{} ({})", self.display, self.input_space),
+        };
+        if let Some(name) = self.lut.as_ref().and_then(|l| l.file_stem()) {
+            label.push_str(&format!(" + {}", name.to_string_lossy()));
+        }
+        label
+    }
+}
+
+impl Default for ColourSettings {
+    fn default() -> Self {
+        Self {
+            exposure: 0.0,
+            gamma: 2.2,
+            lut: None,
+            display: "standard".to_string(),
+            input_space: "acescg".to_string(),
+            gamut: None,
+            ocio: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GamutSettings {
+    /// `clip`, `noclip` or `rgc`.
+    pub mode: String,
+    #[serde(default = "default_gamut_target")]
+    pub target: String,
+    /// Paint out-of-gamut pixels with magenta stripes.
+    #[serde(default)]
+    pub overlay: bool,
+}
+
+fn default_gamut_target() -> String {
+    "srgb".to_string()
+}
+
+/// An OCIO transform: `from` → `to`, or `from` → `display`/`view`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OcioSettings {
+    /// Config file (`aces1.3` for the built-in config with `use_ocio`).
+    pub config: String,
+    /// Source colour space; the `scene_linear` role when absent.
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub display: Option<String>,
+    #[serde(default)]
+    pub view: Option<String>,
+    #[serde(default)]
+    pub looks: Option<String>,
+    #[serde(default)]
+    pub vars: BTreeMap<String, String>,
+}
+
+/// Still image format of a job's outputs. `png`, `jpeg` and `tiff` are
+/// 8-bit; the others keep the pipeline's precision.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Png,
+    /// 16-bit RGBA PNG.
+    Png16,
+    #[serde(alias = "jpg")]
+    Jpeg,
+    #[serde(alias = "tif")]
+    Tiff,
+    /// 16-bit RGBA TIFF.
+    #[serde(alias = "tif16")]
+    Tiff16,
+    /// 32-bit float RGBA TIFF of the unquantized display values.
+    #[serde(rename = "tiff-float", alias = "tiff32")]
+    TiffFloat,
+    /// 10-bit RGB DPX.
+    Dpx,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 7] = [
+        OutputFormat::Png,
+        OutputFormat::Png16,
+        OutputFormat::Jpeg,
+        OutputFormat::Tiff,
+        OutputFormat::Tiff16,
+        OutputFormat::TiffFloat,
+        OutputFormat::Dpx,
+    ];
+
+    /// Name used in job files and on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Png16 => "png16",
+            OutputFormat::Jpeg => "jpeg",
+            OutputFormat::Tiff => "tiff",
+            OutputFormat::Tiff16 => "tiff16",
+            OutputFormat::TiffFloat => "tiff-float",
+            OutputFormat::Dpx => "dpx",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Png | OutputFormat::Png16 => "png",
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Tiff | OutputFormat::Tiff16 | OutputFormat::TiffFloat => "tif",
+            OutputFormat::Dpx => "dpx",
+        }
+    }
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.to_ascii_lowercase();
+        let alias = match s.as_str() {
+            "jpg" => "jpeg",
+            "tif" => "tiff",
+            "tif16" => "tiff16",
+            "tiff32" => "tiff-float",
+            other => other,
+        };
+        OutputFormat::ALL
+            .into_iter()
+            .find(|f| f.name() == alias)
+            .ok_or_else(|| {
+                let names: Vec<_> = OutputFormat::ALL.iter().map(|f| f.name()).collect();
+                anyhow!("unknown output format: {} ({})", s, names.join(", "))
+            })
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Inclusive frame range, written `1001-1100` (or a single frame).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct FrameRange {
+    pub first: u64,
+    pub last: u64,
+}
+
+impl std::str::FromStr for FrameRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let number = |t: &str| {
+            t.trim()
+                .parse::<u64>()
+                .map_err(|_| anyhow!("invalid frame range {:?} (use 1001-1100)", s))
+        };
+        let (first, last) = match s.split_once('-') {
+            Some((a, b)) => (number(a)?, number(b)?),
+            None => (number(s)?, number(s)?),
+        };
+        if last < first {
+            bail!("frame range {:?} ends before it starts", s);
+        }
+        Ok(FrameRange { first, last })
+    }
+}
+
+impl TryFrom<String> for FrameRange {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<FrameRange> for String {
+    fn from(r: FrameRange) -> String {
+        r.to_string()
+    }
+}
+
+impl fmt::Display for FrameRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.first, self.last)
+    }
+}
+
+/// A sequence pattern split around its frame number placeholder.
+struct SequencePattern {
+    prefix: String,
+    pad: usize,
+    suffix: String,
+}
+
+impl SequencePattern {
+    fn parse(pattern: &str) -> Result<Self> {
+        let re = Regex::new(r"#+|%0?(\d*)d").expect("valid regex");
+        let mut found = re.captures_iter(pattern);
+        let caps = found
+            .next()
+            .with_context(|| format!("sequence {:?} has no #### or %04d", pattern))?;
+        if found.next().is_some() {
+            bail!("sequence {:?} has more than one frame placeholder", pattern);
+        }
+        let whole = caps.get(0).expect("match");
+        let pad = match caps.get(1) {
+            Some(w) => w.as_str().parse().unwrap_or(1),
+            None => whole.len(),
+        };
+        Ok(SequencePattern {
+            prefix: pattern[..whole.start()].to_string(),
+            pad,
+            suffix: pattern[whole.end()..].to_string(),
+        })
+    }
+
+    fn path(&self, frame: u64) -> String {
+        format!(
+            "{}{:0pad$}{}",
+            self.prefix,
+            frame,
+            self.suffix,
+            pad = self.pad
+        )
+    }
+
+    /// Frames present on disk, in frame order.
+    fn scan(&self, base: &Path) -> Result<Vec<(u64, PathBuf)>> {
+        let full = base.join(&self.prefix);
+        let (dir, name_prefix) = if self.prefix.ends_with(['/', '\\']) || self.prefix.is_empty() {
+            (full.clone(), String::new())
+        } else {
+            let name = full
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            (
+                full.parent().map(Path::to_path_buf).unwrap_or_default(),
+                name,
+            )
+        };
+        let re = Regex::new(&format!(
+            "^{}(\\d{{{},}}){}$",
+            regex::escape(&name_prefix),
+            self.pad,
+            regex::escape(&self.suffix)
+        ))?;
+        let dir = if dir.as_os_str().is_empty() {
+            PathBuf::from(".")
+        } else {
+            dir
+        };
+        let mut frames = Vec::new();
+        for entry in fs::read_dir(&dir).with_context(|| format!("{}", dir.display()))? {
+            let path = entry?.path();
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if let Some(n) = re.captures(&name).and_then(|c| c[1].parse().ok()) {
+                frames.push((n, path));
+            }
+        }
+        frames.sort();
+        Ok(frames)
+    }
+}
+
+/// One input → output conversion of a job.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WorkItem {
+    /// Index into [`JobFile::jobs`].
+    pub job: usize,
+    pub input: PathBuf,
+    pub output: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame: Option<u64>,
+    /// Set for a job's slate, which is drawn instead of converting `input`
+    /// (the first frame, which only gives the size).
+    #[serde(skip)]
+    pub slate: Option<Slate>,
+}
+
+impl Job {
+    fn label(&self, index: usize) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("job{}", index + 1))
+    }
+
+    /// Input files with their frame numbers, in order.
+    fn inputs(&self, base: &Path) -> Result<Vec<(PathBuf, Option<u64>)>> {
+        match (&self.input, &self.sequence) {
+            (Some(input), None) => {
+                if self.frames.is_some() {
+                    bail!("'frames' needs 'sequence'");
+                }
+                let pattern = base.join(input);
+                let pattern = pattern.to_string_lossy();
+                let mut files: Vec<PathBuf> = glob::glob(&pattern)
+                    .with_context(|| format!("invalid input pattern {:?}", input))?
+                    .filter_map(|p| p.ok())
+                    .filter(|p| p.is_file())
+                    .collect();
+                if files.is_empty() {
+                    bail!("no files match {}", pattern);
+                }
+                files.sort();
+                Ok(files
+                    .into_iter()
+                    .map(|p| {
+                        let frame = frame_number(&p);
+                        (p, frame)
+                    })
+                    .collect())
+            }
+            (None, Some(sequence)) => {
+                let pattern = SequencePattern::parse(sequence)?;
+                match self.frames {
+                    // listed frames are processed even if missing so the
+                    // gap shows up as a failure
+                    Some(range) => Ok((range.first..=range.last)
+                        .map(|n| (base.join(pattern.path(n)), Some(n)))
+                        .collect()),
+                    None => {
+                        let frames = pattern.scan(base)?;
+                        if frames.is_empty() {
+                            bail!("no frames of {} found", sequence);
+                        }
+                        Ok(frames.into_iter().map(|(n, p)| (p, Some(n))).collect())
+                    }
+                }
+            }
+            (Some(_), Some(_)) => bail!("use either 'input' or 'sequence', not both"),
+            (None, None) => bail!("'input' or 'sequence' is required"),
+        }
+    }
+}
+
+impl JobFile {
+    /// Read a job file; the legacy list form is converted on the way.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).with_context(|| format!("{}", path.display()))?;
+        let base = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Self::from_str_with_base(&text, &base)
+    }
+
+    /// Parse a job file whose relative paths are relative to `base`.
+    pub fn from_str_with_base(text: &str, base: &Path) -> Result<Self> {
+        let value: serde_yaml::Value = serde_yaml::from_str(text)?;
+        if value.is_sequence() {
+            let rules: Vec<ApplyRule> = serde_yaml::from_value(value)?;
+            return Ok(Self::from_legacy(rules));
+        }
+        match value.get("version").and_then(|v| v.as_u64()) {
+            Some(v) if v == JOB_FILE_VERSION as u64 => {}
+            Some(v) => bail!(
+                "unsupported job file version {} (this build reads version {})",
+                v,
+                JOB_FILE_VERSION
+            ),
+            None => bail!("job file needs 'version: {}'", JOB_FILE_VERSION),
+        }
+        let mut file: JobFile = serde_yaml::from_value(value)?;
+        file.base = base.to_path_buf();
+        Ok(file)
+    }
+
+    fn from_legacy(rules: Vec<ApplyRule>) -> Self {
+        let literal = |p: &Path| p.to_string_lossy().replace('{', "{{").replace('}', "}}");
+        let jobs = rules
+            .into_iter()
+            .map(|r| {
+                let output = r.output.unwrap_or_else(|| r.input.with_extension("png"));
+                Job {
+                    name: None,
+                    input: Some(glob::Pattern::escape(&r.input.to_string_lossy())),
+                    sequence: None,
+                    frames: None,
+                    output: literal(&output),
+                    format: OutputFormat::Png,
+                    jpeg_quality: default_jpeg_quality(),
+                    tags: None,
+                    max_size: r.max_size.unwrap_or(2048),
+                    quality: PreviewQuality::High,
+                    colour: ColourSettings {
+                        exposure: r.exposure.unwrap_or(0.0),
+                        gamma: r.gamma.unwrap_or(2.2),
+                        lut: r.lut,
+                        ..ColourSettings::default()
+                    },
+                    mask: None,
+                    slate: None,
+                }
+            })
+            .collect();
+        JobFile {
+            version: JOB_FILE_VERSION,
+            continue_on_error: false,
+            log: None,
+            jobs,
+            base: PathBuf::new(),
+            legacy: true,
+        }
+    }
+
+    /// Job name for reports (`job<N>` for unnamed jobs).
+    pub fn job_label(&self, job: usize) -> String {
+        self.jobs[job].label(job)
+    }
+
+    /// Expand inputs and output templates of every job. Two items writing
+    /// the same output are refused.
+    pub fn plan(&self) -> Result<Vec<WorkItem>> {
+        let now = Local::now();
+        let mut items = Vec::new();
+        let mut outputs: HashMap<PathBuf, PathBuf> = HashMap::new();
+        for (j, job) in self.jobs.iter().enumerate() {
+            let label = job.label(j);
+            let ctx = |e: anyhow::Error| e.context(format!("job '{}'", label));
+            let output = if self.legacy {
+                job.output.clone()
+            } else {
+                frame_placeholders(&job.output)
+            };
+            let template = Template::parse(&output)
+                .context("invalid output template")
+                .map_err(ctx)?;
+            let inputs = job.inputs(&self.base).map_err(ctx)?;
+            let mut planned: Vec<(PathBuf, Option<u64>, String, Option<Slate>)> = Vec::new();
+            if let Some(slate) = &job.slate {
+                let (first, frame) = inputs[0].clone();
+                let slate_frame = match frame {
+                    Some(f) => Some(f.checked_sub(1).ok_or_else(|| {
+                        ctx(anyhow!("no frame number before {} for the slate", f))
+                    })?),
+                    None => None,
+                };
+                let files: Vec<PathBuf> = inputs.iter().map(|(p, _)| p.clone()).collect();
+                let slate = slate.with_defaults(&files, &job.colour.label());
+                planned.push((first, slate_frame, "slate".into(), Some(slate)));
+            }
+            planned.extend(
+                inputs
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, (input, frame))| (input, frame, i.to_string(), None)),
+            );
+            for (input, frame, index, slate) in planned {
+                let text =
+                    |s: Option<&std::ffi::OsStr>| s.map(|s| s.to_string_lossy().into_owned());
+                let mut stem = text(input.file_stem());
+                let mut filename = text(input.file_name());
+                if slate.is_some() {
+                    let renamed = stem.as_deref().map(|s| slate_stem(s, frame));
+                    filename = match (&renamed, input.extension()) {
+                        (Some(r), Some(ext)) => Some(format!("{}.{}", r, ext.to_string_lossy())),
+                        _ => renamed.clone(),
+                    };
+                    stem = renamed;
+                }
+                let var = |name: &str| -> Option<String> {
+                    match name {
+                        "job" => Some(label.clone()),
+                        "dir" => input.parent().map(|d| d.to_string_lossy().into_owned()),
+                        "stem" => stem.clone(),
+                        "filename" => filename.clone(),
+                        "frame" => frame.map(|f| f.to_string()),
+                        "index" => Some(index.clone()),
+                        "ext" => Some(job.format.extension().to_string()),
+                        _ => None,
+                    }
+                };
+                let attr = |key: &str| {
+                    let parts = crate::metadata::read_part_attributes(&input).ok()?;
+                    parts.first()?.attributes.get(key).map(|v| v.to_string())
+                };
+                let output = template
+                    .expand_with(&var, &attr, now)
+                    .with_context(|| format!("output for {}", input.display()))
+                    .map_err(ctx)?;
+                let output = self.base.join(output);
+                if let Some(other) = outputs.insert(output.clone(), input.clone()) {
+                    bail!(
+                        "job '{}': {} and {} both write {}",
+                        label,
+                        other.display(),
+                        input.display(),
+                        output.display()
+                    );
+                }
+                items.push(WorkItem {
+                    job: j,
+                    input,
+                    output,
+                    frame,
+                    slate,
+                });
+            }
+        }
+        Ok(items)
+    }
+
+    /// Load the LUTs and OCIO processors of every job.
+    pub fn prepare(&self) -> Result<Vec<PreparedJob>> {
+        self.jobs
+            .iter()
+            .enumerate()
+            .map(|(i, job)| {
+                PreparedJob::new(job, &self.base).with_context(|| format!("job '{}'", job.label(i)))
+            })
+            .collect()
+    }
+
+    /// Run every item on a worker pool (see [`crate::executor`]), calling
+    /// `progress` with each item's report and the finished/total counts as
+    /// items complete. The returned report lists items in plan order.
+    pub fn run(
+        &self,
+        opts: &RunOptions,
+        mut progress: impl FnMut(&ItemReport, usize, usize),
+    ) -> Result<RunReport> {
+        let items = self.plan()?;
+        let started = Local::now();
+        let clock = Instant::now();
+        let new_report = |item: &WorkItem, status| ItemReport {
+            job: self.job_label(item.job),
+            input: item.input.clone(),
+            output: item.output.clone(),
+            frame: item.frame,
+            status,
+            error: None,
+            millis: 0,
+        };
+        let mut reports: Vec<Option<ItemReport>> = vec![None; items.len()];
+        if opts.dry_run {
+            for (i, item) in items.iter().enumerate() {
+                let report = new_report(item, ItemStatus::DryRun);
+                progress(&report, i + 1, items.len());
+                reports[i] = Some(report);
+            }
+        } else {
+            let prepared = self.prepare()?;
+            let exec = ExecOptions {
+                threads: if self.legacy { 1 } else { opts.threads },
+                memory_limit: opts.memory_limit,
+                checkpoint: opts.checkpoint.clone(),
+                continue_on_error: opts.continue_on_error || self.continue_on_error,
+                ordered: self.legacy,
+                cancel: opts.cancel.clone(),
+            };
+            execute(
+                &items,
+                &exec,
+                |item| prepared[item.job].process(item, opts.backup),
+                |p| {
+                    let mut report = new_report(p.item, ItemStatus::Skipped);
+                    report.millis = p.millis;
+                    match p.outcome {
+                        Outcome::Done(()) => report.status = ItemStatus::Written,
+                        Outcome::Failed(e) => {
+                            report.status = ItemStatus::Failed;
+                            report.error = Some(e);
+                        }
+                        Outcome::Skipped => {}
+                        Outcome::Resumed => report.status = ItemStatus::Resumed,
+                    }
+                    progress(&report, p.completed, p.total);
+                    reports[p.index] = Some(report);
+                },
+            )?;
+        }
+        Ok(RunReport {
+            started: started.to_rfc3339(),
+            elapsed_ms: clock.elapsed().as_millis() as u64,
+            items: reports.into_iter().flatten().collect(),
+        })
+    }
+}
+
+/// `template` with every run of `#` outside `{}` replaced by a padded
+/// `{frame}`.
+fn frame_placeholders(template: &str) -> String {
+    let mut out = String::new();
+    let mut depth = 0usize;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            '#' if depth == 0 => {
+                let mut pad = 1;
+                while chars.next_if_eq(&'#').is_some() {
+                    pad += 1;
+                }
+                out.push_str(&format!("{{frame:0{}}}", pad));
+                continue;
+            }
+            _ => {}
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// The stem of a slate written at `frame`: the stem's frame number
+/// replaced (`beauty.1001` → `beauty.1000`), or `_slate` appended.
+fn slate_stem(stem: &str, frame: Option<u64>) -> String {
+    let digits = stem.rfind(|c: char| c.is_ascii_digit()).map(|end| {
+        let start = stem[..=end]
+            .rfind(|c: char| !c.is_ascii_digit())
+            .map_or(0, |i| i + 1);
+        (start, end + 1)
+    });
+    match (digits, frame) {
+        (Some((start, end)), Some(f)) => format!(
+            "{}{:0width$}{}",
+            &stem[..start],
+            f,
+            &stem[end..],
+            width = end - start
+        ),
+        _ => format!("{}_slate", stem),
+    }
+}
+
+impl Task for WorkItem {
+    fn key(&self) -> String {
+        let slate = if self.slate.is_some() { "slate " } else { "" };
+        format!(
+            "{}{} -> {}",
+            slate,
+            self.input.display(),
+            self.output.display()
+        )
+    }
+
+    fn memory(&self) -> u64 {
+        exr_memory_estimate(&self.input)
+    }
+}
+
+/// A job with its LUT and OCIO processor loaded.
+pub struct PreparedJob {
+    max_size: u32,
+    quality: PreviewQuality,
+    format: OutputFormat,
+    jpeg_quality: u8,
+    exposure: f32,
+    gamma: f32,
+    display: DisplayTransform,
+    gamut: Option<GamutMapping>,
+    overlay: bool,
+    lut: Option<Lut>,
+    ocio: Option<Box<dyn ColorProcessor + Send + Sync>>,
+    mask: Option<AspectMask>,
+    tags: ColorTags,
+    icc: Option<Vec<u8>>,
+}
+
+impl PreparedJob {
+    pub fn new(job: &Job, base: &Path) -> Result<Self> {
+        let c = &job.colour;
+        if !(1..=100).contains(&job.jpeg_quality) {
+            bail!("jpeg_quality must be 1-100");
+        }
+        let input: Primaries = c.input_space.parse()?;
+        let lut = match &c.lut {
+            Some(p) => {
+                let p = base.join(p);
+                let text = fs::read_to_string(&p).with_context(|| format!("{}", p.display()))?;
+                Some(parse_cube(&text)?)
+            }
+            None => None,
+        };
+        let (ocio, ocio_is_display) = match &c.ocio {
+            Some(o) => {
+                let (proc, is_display) = ocio_processor(o, base)?;
+                (Some(proc), is_display)
+            }
+            None => (None, false),
+        };
+        let mut display = DisplayTransform::parse(&c.display, input)?;
+        // OCIO display/view output (or a display colour space) is already display-referred
+        if ocio_is_display && display == DisplayTransform::Standard {
+            display = DisplayTransform::Raw;
+        }
+        let gamut = match &c.gamut {
+            Some(g) => Some(GamutMapping {
+                working: input,
+                target: g.target.parse()?,
+                mode: g.mode.parse::<ClipMode>()?,
+            }),
+            None => None,
+        };
+        // a LUT or OCIO transform leaves the output encoding unknown
+        let derived = if lut.is_some() || ocio.is_some() {
+            ColorTags::default()
+        } else {
+            ColorTags::for_display(display, input)
+        };
+        let tags = match job.tags.as_deref().map(str::trim) {
+            Some("none") => ColorTags::default(),
+            Some(t) => derived.with(t.parse().context("tags")?),
+            None => derived,
+        };
+        Ok(PreparedJob {
+            max_size: job.max_size,
+            quality: job.quality,
+            format: job.format,
+            jpeg_quality: job.jpeg_quality,
+            exposure: c.exposure,
+            gamma: c.gamma,
+            display,
+            gamut,
+            overlay: c.gamut.as_ref().is_some_and(|g| g.overlay),
+            lut,
+            ocio,
+            mask: job.mask,
+            tags,
+            icc: icc_profile(&tags),
+        })
+    }
+
+    /// Colour tags written into the outputs.
+    pub fn tags(&self) -> ColorTags {
+        self.tags
+    }
+
+    fn pipeline(&self) -> PreviewPipeline<'_> {
+        let mut p = PreviewPipeline::new(self.exposure, self.gamma, self.lut.as_ref());
+        p.display = self.display;
+        p.gamut = self.gamut;
+        p.oog_overlay = self.overlay;
+        p.ocio = self.ocio.as_deref().map(|o| o as &dyn ColorProcessor);
+        p
+    }
+
+    /// Convert one item, backing up an existing output to `.bak` first.
+    pub fn process(&self, item: &WorkItem, backup: bool) -> Result<()> {
+        let img =
+            load_exr_basic(&item.input).with_context(|| format!("{}", item.input.display()))?;
+        let pipeline = self.pipeline();
+        let mut still = match self.format {
+            OutputFormat::Png | OutputFormat::Jpeg | OutputFormat::Tiff => Still::Eight(
+                generate_preview_with(&img, self.max_size, &pipeline, self.quality),
+            ),
+            OutputFormat::Png16 | OutputFormat::Tiff16 | OutputFormat::Dpx => Still::Sixteen(
+                generate_preview16(&img, self.max_size, &pipeline, self.quality),
+            ),
+            OutputFormat::TiffFloat => Still::Float(generate_preview_f32(
+                &img,
+                self.max_size,
+                &pipeline,
+                self.quality,
+            )),
+        };
+        match (&item.slate, &self.mask) {
+            (Some(slate), _) => {
+                let (width, height) = still.size();
+                still = still.with_pixels(slate.render(width, height)?);
+            }
+            (None, Some(mask)) => match &mut still {
+                Still::Eight(p) => mask.apply(p),
+                Still::Sixteen(p) => mask.apply16(p),
+                Still::Float(p) => mask.apply_f32(p),
+            },
+            (None, None) => {}
+        }
+        if let Some(dir) = item.output.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        if backup && item.output.exists() {
+            fs::copy(&item.output, item.output.with_extension("bak"))?;
+        }
+        self.save(&item.output, &still, item.frame)
+            .with_context(|| format!("{}", item.output.display()))
+    }
+
+    fn save(&self, path: &Path, still: &Still, frame: Option<u64>) -> Result<()> {
+        let icc = self.icc.as_deref();
+        match (self.format, still) {
+            (OutputFormat::Png, Still::Eight(p)) => stills::write_png(path, p, icc),
+            (OutputFormat::Jpeg, Still::Eight(p)) => {
+                stills::write_jpeg(path, p, self.jpeg_quality, icc)
+            }
+            (OutputFormat::Tiff, Still::Eight(p)) => stills::write_tiff(path, p, icc),
+            (OutputFormat::Png16, Still::Sixteen(p)) => stills::write_png16(path, p, icc),
+            (OutputFormat::Tiff16, Still::Sixteen(p)) => stills::write_tiff16(path, p, icc),
+            (OutputFormat::Dpx, Still::Sixteen(p)) => {
+                stills::write_dpx(path, p, &DpxInfo::new(&self.tags, frame))
+            }
+            (OutputFormat::TiffFloat, Still::Float(p)) => stills::write_tiff_f32(path, p, icc),
+            (format, _) => unreachable!("{} rendered at the wrong depth", format),
+        }
+    }
+}
+
+/// A rendered item at the depth of its output format.
+enum Still {
+    Eight(PreviewImage),
+    Sixteen(PreviewImage16),
+    Float(PreviewImageF32),
+}
+
+impl Still {
+    fn size(&self) -> (u32, u32) {
+        match self {
+            Still::Eight(p) => (p.width, p.height),
+            Still::Sixteen(p) => (p.width, p.height),
+            Still::Float(p) => (p.width, p.height),
+        }
+    }
+
+    /// `img` (an 8-bit drawing such as a slate) at the depth of `self`.
+    fn with_pixels(&self, img: PreviewImage) -> Still {
+        let (width, height) = (img.width, img.height);
+        match self {
+            Still::Eight(_) => Still::Eight(img),
+            Still::Sixteen(_) => Still::Sixteen(PreviewImage16 {
+                width,
+                height,
+                rgba16: img.rgba8.iter().map(|&v| v as u16 * 257).collect(),
+            }),
+            Still::Float(_) => Still::Float(PreviewImageF32 {
+                width,
+                height,
+                rgba_f32: img.rgba8.iter().map(|&v| v as f32 / 255.0).collect(),
+            }),
+        }
+    }
+}
+
+/// The job's OCIO processor and whether its output is display-referred.
+#[cfg(feature = "use_ocio")]
+fn ocio_processor(
+    s: &OcioSettings,
+    base: &Path,
+) -> Result<(Box<dyn ColorProcessor + Send + Sync>, bool)> {
+    use crate::ocio::Config;
+    // "aces1.3" is the built-in CG config of OCIO 2.2+
+    let path = if s.config.eq_ignore_ascii_case("aces1.3") {
+        PathBuf::from("ocio://cg-config-v1.0.0_aces-v1.3_ocio-v2.1")
+    } else {
+        base.join(&s.config)
+    };
+    let cfg = Config::from_file(&path)?;
+    let mut ctx = cfg.context()?;
+    for (k, v) in &s.vars {
+        ctx.set_var(k, v)?;
+    }
+    let src = match &s.from {
+        Some(f) => f.clone(),
+        None => cfg
+            .role("scene_linear")
+            .context("'from' is required (no scene_linear role)")?,
+    };
+    let looks = s.looks.as_deref();
+    Ok(match (&s.to, &s.view) {
+        (Some(dst), None) => (
+            Box::new(cfg.processor_ex(Some(&ctx), &src, dst, looks)?),
+            cfg.is_display_colorspace(dst),
+        ),
+        (None, Some(view)) => {
+            let display = match &s.display {
+                Some(d) => d.clone(),
+                None => cfg
+                    .displays()
+                    .into_iter()
+                    .next()
+                    .context("config has no displays")?,
+            };
+            let proc =
+                cfg.processor_display_view_ex(Some(&ctx), Some(&src), &display, view, looks)?;
+            (Box::new(proc), true)
+        }
+        (None, None) => bail!("ocio needs 'to' or 'view'"),
+        (Some(_), Some(_)) => bail!("ocio 'to' and 'view' cannot be combined"),
+    })
+}
+
+#[cfg(not(feature = "use_ocio"))]
+fn ocio_processor(
+    s: &OcioSettings,
+    base: &Path,
+) -> Result<(Box<dyn ColorProcessor + Send + Sync>, bool)> {
+    let path = base.join(&s.config);
+    let mut cfg = crate::ocio_config::Config::from_file(&path)
+        .with_context(|| format!("OCIO config {}", path.display()))?;
+    for (k, v) in &s.vars {
+        cfg.set_context_var(k, v);
+    }
+    let src = match &s.from {
+        Some(f) => f.clone(),
+        None => cfg
+            .role("scene_linear")
+            .context("'from' is required (no scene_linear role)")?,
+    };
+    let looks = s.looks.as_deref().unwrap_or("");
+    Ok(match (&s.to, &s.view) {
+        (Some(dst), None) => (
+            Box::new(cfg.processor_with_looks(&src, dst, looks)?),
+            cfg.is_display_colorspace(dst),
+        ),
+        (None, Some(view)) => {
+            let display = match &s.display {
+                Some(d) => d.clone(),
+                None => cfg
+                    .displays()
+                    .into_iter()
+                    .next()
+                    .context("config has no displays")?,
+            };
+            let proc = cfg.processor_display_view_with_looks(&src, &display, view, looks)?;
+            (Box::new(proc), true)
+        }
+        (None, None) => bail!("ocio needs 'to' or 'view'"),
+        (Some(_), Some(_)) => bail!("ocio 'to' and 'view' cannot be combined"),
+    })
+}
+
+/// Options for [`JobFile::run`].
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Only plan and report the items.
+    pub dry_run: bool,
+    /// Copy an existing output to `.bak` before overwriting it.
+    pub backup: bool,
+    /// Keep going after a failure even if the job file does not ask for it.
+    pub continue_on_error: bool,
+    /// Worker threads; 0 for one per CPU.
+    pub threads: usize,
+    /// Cap on the estimated memory of the frames being processed, in bytes.
+    pub memory_limit: Option<u64>,
+    /// Checkpoint file for resuming an interrupted run.
+    pub checkpoint: Option<PathBuf>,
+    /// Set from another thread to stop starting new items.
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemStatus {
+    Written,
+    DryRun,
+    Failed,
+    /// Not attempted because an earlier item failed or the run was cancelled.
+    Skipped,
+    /// Written by an earlier, interrupted run.
+    Resumed,
+}
+
+impl fmt::Display for ItemStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ItemStatus::Written => "written",
+            ItemStatus::DryRun => "dry-run",
+            ItemStatus::Failed => "failed",
+            ItemStatus::Skipped => "skipped",
+            ItemStatus::Resumed => "resumed",
+        })
+    }
+}
+
+/// One line of the run log.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ItemReport {
+    pub job: String,
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub frame: Option<u64>,
+    pub status: ItemStatus,
+    pub error: Option<String>,
+    /// Processing time.
+    pub millis: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunReport {
+    /// Start time, RFC 3339.
+    pub started: String,
+    pub elapsed_ms: u64,
+    pub items: Vec<ItemReport>,
+}
+
+/// Item counts of a run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RunSummary {
+    pub written: usize,
+    pub dry_run: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub resumed: usize,
+}
+
+impl RunReport {
+    pub fn summary(&self) -> RunSummary {
+        let mut s = RunSummary::default();
+        for item in &self.items {
+            match item.status {
+                ItemStatus::Written => s.written += 1,
+                ItemStatus::DryRun => s.dry_run += 1,
+                ItemStatus::Failed => s.failed += 1,
+                ItemStatus::Skipped => s.skipped += 1,
+                ItemStatus::Resumed => s.resumed += 1,
+            }
+        }
+        s
+    }
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "written={} failed={} skipped={}",
+            self.written, self.failed, self.skipped
+        )?;
+        if self.dry_run > 0 {
+            write!(f, " dry-run={}", self.dry_run)?;
+        }
+        if self.resumed > 0 {
+            write!(f, " resumed={}", self.resumed)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run a job file (or a legacy rule list), printing one line per item.
+/// Fails if any item failed.
+pub fn apply_rules_file(path: &Path, dry_run: bool, backup: bool) -> Result<()> {
+    let file = JobFile::from_path(path)?;
+    let opts = RunOptions {
+        dry_run,
+        backup,
+        ..Default::default()
+    };
+    let report = file.run(&opts, |r, _, _| match r.status {
+        ItemStatus::DryRun => println!("process: {} -> {}", r.input.display(), r.output.display()),
+        ItemStatus::Written => println!("saved: {} -> {}", r.input.display(), r.output.display()),
+        _ => eprintln!(
+            "failed: {}: {}",
+            r.input.display(),
+            r.error.as_deref().unwrap_or_default()
+        ),
+    })?;
+    let summary = report.summary();
+    if summary.failed > 0 {
+        bail!("{} of {} items failed", summary.failed, report.items.len());
+    }
+    Ok(())
+}
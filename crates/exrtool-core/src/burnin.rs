@@ -0,0 +1,517 @@
+//! Burn-ins: text such as frame numbers, timecode and metadata drawn onto
+//! rendered frames with a built-in 5×7 bitmap font, so no system fonts are
+//! needed.
+//!
+//! Each [`BurnInText`] is a template anchored to a corner, an edge or the
+//! centre of the frame. Templates use the same `{name}` placeholders as
+//! metadata rules and batch output paths:
+//!
+//! * `{frame}` (`{frame:04}` pads), `{timecode}`, `{filename}`, `{stem}`
+//! * `{shot}`: the stem before the frame number, or a named group of
+//!   [`BurnInOptions::filename_regex`] (every group becomes a variable)
+//! * `{attr:NAME}`: any attribute of the first part
+//! * `{date}`, `{now:%H:%M}`: the export date and time
+//! * `{lut}`, `{width}`, `{height}`
+//!
+//! Characters outside printable ASCII are drawn as `?`.
+
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::header::{describe_value, ExrHeaders};
+use crate::metadata::MetadataValue;
+use crate::rules::Template;
+use crate::timecode::{frame_number, FrameRate, TimeCode};
+use crate::{PreviewImage, PreviewImage16};
+
+/// Where a block of text sits in the frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Center,
+    #[default]
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    pub const ALL: [Anchor; 7] = [
+        Anchor::TopLeft,
+        Anchor::Top,
+        Anchor::TopRight,
+        Anchor::Center,
+        Anchor::BottomLeft,
+        Anchor::Bottom,
+        Anchor::BottomRight,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Anchor::TopLeft => "top-left",
+            Anchor::Top => "top",
+            Anchor::TopRight => "top-right",
+            Anchor::Center => "center",
+            Anchor::BottomLeft => "bottom-left",
+            Anchor::Bottom => "bottom",
+            Anchor::BottomRight => "bottom-right",
+        }
+    }
+}
+
+impl FromStr for Anchor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_ascii_lowercase();
+        Anchor::ALL
+            .into_iter()
+            .find(|a| a.name() == s)
+            .with_context(|| {
+                let names: Vec<_> = Anchor::ALL.iter().map(|a| a.name()).collect();
+                format!("unknown burn-in position: {} ({})", s, names.join(", "))
+            })
+    }
+}
+
+impl fmt::Display for Anchor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// One templated line of text, `position=template` on the command line
+/// (e.g. `top-right={timecode}`); without a position it goes bottom-left.
+/// Lines sharing a position stack in the order given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BurnInText {
+    pub anchor: Anchor,
+    pub template: String,
+}
+
+impl FromStr for BurnInText {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some((anchor, template)) = s.split_once('=') {
+            if let Ok(anchor) = anchor.parse() {
+                return Ok(BurnInText {
+                    anchor,
+                    template: template.to_string(),
+                });
+            }
+        }
+        Ok(BurnInText {
+            anchor: Anchor::default(),
+            template: s.to_string(),
+        })
+    }
+}
+
+/// What to burn in and how it looks.
+#[derive(Debug, Clone)]
+pub struct BurnInOptions {
+    pub texts: Vec<BurnInText>,
+    /// Size of one font pixel in image pixels; 0 scales with the frame
+    /// height (4 at 1080 lines).
+    pub scale: u32,
+    /// Text colour, display-referred sRGB.
+    pub color: [u8; 3],
+    /// Opacity of the black box behind each block of text; 0 for none.
+    pub box_opacity: f32,
+    /// Regex matched against the file name; its named groups become
+    /// variables, like a `from_filename` metadata rule.
+    pub filename_regex: Option<String>,
+    /// Value of `{lut}`; `none` when unset.
+    pub lut: Option<String>,
+    /// Rate `{timecode}` is counted at; otherwise the file's
+    /// `framesPerSecond`, falling back to 24.
+    pub rate: Option<FrameRate>,
+}
+
+impl Default for BurnInOptions {
+    fn default() -> Self {
+        BurnInOptions {
+            texts: Vec::new(),
+            scale: 0,
+            color: [255, 255, 255],
+            box_opacity: 0.5,
+            filename_regex: None,
+            lut: None,
+            rate: None,
+        }
+    }
+}
+
+/// Burn-in templates compiled once and drawn onto every frame.
+#[derive(Debug, Clone)]
+pub struct BurnIn {
+    texts: Vec<(Anchor, Template)>,
+    options: BurnInOptions,
+    regex: Option<Regex>,
+}
+
+impl BurnIn {
+    pub fn new(options: &BurnInOptions) -> Result<Self> {
+        let texts = options
+            .texts
+            .iter()
+            .map(|t| {
+                let template = Template::parse(&t.template)
+                    .with_context(|| format!("burn-in {:?}", t.template))?;
+                Ok((t.anchor, template))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let regex = match &options.filename_regex {
+            Some(r) => Some(Regex::new(r).with_context(|| format!("invalid regex {:?}", r))?),
+            None => None,
+        };
+        if !(0.0..=1.0).contains(&options.box_opacity) {
+            bail!(
+                "box opacity must be between 0 and 1: {}",
+                options.box_opacity
+            );
+        }
+        Ok(BurnIn {
+            texts,
+            options: options.clone(),
+            regex,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.texts.is_empty()
+    }
+
+    /// The expanded text of every line for the frame read from `path`.
+    pub fn lines(&self, path: &Path, width: u32, height: u32) -> Result<Vec<(Anchor, String)>> {
+        if self.texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let headers = ExrHeaders::read_file(path)?;
+        let header = headers.headers.first();
+        let attrs: HashMap<&str, String> = header
+            .map(|h| {
+                h.attributes
+                    .iter()
+                    .map(|a| {
+                        let value = describe_value(&a.type_name, &a.data);
+                        (a.name.as_str(), value.to_string())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let stem = path
+            .file_stem()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let frame = frame_number(path);
+        let rate = self
+            .options
+            .rate
+            .or_else(|| attrs.get("framesPerSecond").and_then(|r| r.parse().ok()))
+            .unwrap_or(FrameRate::Fps24);
+        let timecode = match header.and_then(|h| h.value("timeCode")) {
+            Some(MetadataValue::TimeCode(tc)) => Some(tc),
+            _ => frame.and_then(|f| TimeCode::from_frames(f, rate, false).ok()),
+        };
+
+        let now = Local::now();
+        let mut vars: HashMap<String, String> = HashMap::new();
+        vars.insert("shot".into(), shot_name(&stem));
+        vars.insert("filename".into(), file_name.clone());
+        vars.insert("stem".into(), stem);
+        vars.insert("width".into(), width.to_string());
+        vars.insert("height".into(), height.to_string());
+        vars.insert("date".into(), now.format("%Y-%m-%d").to_string());
+        if let Some(f) = frame {
+            vars.insert("frame".into(), f.to_string());
+        }
+        if let Some(tc) = timecode {
+            vars.insert("timecode".into(), tc.display_at(rate));
+        }
+        let lut = self.options.lut.as_deref().unwrap_or("none");
+        vars.insert("lut".into(), lut.to_string());
+        if let Some(caps) = self.regex.as_ref().and_then(|r| r.captures(&file_name)) {
+            for name in self.regex.iter().flat_map(|r| r.capture_names().flatten()) {
+                if let Some(m) = caps.name(name) {
+                    vars.insert(name.to_string(), m.as_str().to_string());
+                }
+            }
+        }
+
+        self.texts
+            .iter()
+            .map(|(anchor, template)| {
+                let text = template.expand_with(
+                    &|name| vars.get(name).cloned(),
+                    &|key| attrs.get(key).cloned(),
+                    now,
+                )?;
+                Ok((*anchor, text))
+            })
+            .collect()
+    }
+
+    /// Draw the burn-ins for `path` onto an 8-bit frame.
+    pub fn draw(&self, img: &mut PreviewImage, path: &Path) -> Result<()> {
+        let lines = self.lines(path, img.width, img.height)?;
+        let color = self.options.color.map(|c| c as f32 / 255.0);
+        let (width, height) = (img.width, img.height);
+        paint(width, height, &lines, &self.options, |i, alpha, text| {
+            let px = &mut img.rgba8[i * 4..i * 4 + 3];
+            for (c, v) in px.iter_mut().enumerate() {
+                let target = if text { color[c] * 255.0 } else { 0.0 };
+                *v = (*v as f32 + (target - *v as f32) * alpha).round() as u8;
+            }
+        });
+        Ok(())
+    }
+
+    /// Draw the burn-ins for `path` onto a 16-bit frame.
+    pub fn draw16(&self, img: &mut PreviewImage16, path: &Path) -> Result<()> {
+        let lines = self.lines(path, img.width, img.height)?;
+        let color = self.options.color.map(|c| c as f32 / 255.0);
+        let (width, height) = (img.width, img.height);
+        paint(width, height, &lines, &self.options, |i, alpha, text| {
+            let px = &mut img.rgba16[i * 4..i * 4 + 3];
+            for (c, v) in px.iter_mut().enumerate() {
+                let target = if text { color[c] * 65535.0 } else { 0.0 };
+                *v = (*v as f32 + (target - *v as f32) * alpha).round() as u16;
+            }
+        });
+        Ok(())
+    }
+}
+
+/// The stem before its frame number: `sh010_comp.1001` → `sh010_comp`.
+pub(crate) fn shot_name(stem: &str) -> String {
+    let trimmed = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+    let trimmed = trimmed.trim_end_matches(['.', '_', '-']);
+    if trimmed.is_empty() { stem } else { trimmed }.to_string()
+}
+
+const GLYPH_W: u32 = 5;
+const GLYPH_H: u32 = 7;
+const ADVANCE: u32 = GLYPH_W + 1;
+const LINE_H: u32 = GLYPH_H + 3;
+
+/// Lay out `lines` and call `blend(pixel index, alpha, is_text)` for every
+/// covered pixel: first the boxes, then the glyphs.
+fn paint(
+    width: u32,
+    height: u32,
+    lines: &[(Anchor, String)],
+    opts: &BurnInOptions,
+    mut blend: impl FnMut(usize, f32, bool),
+) {
+    let scale = match opts.scale {
+        0 => (height / 270).max(1),
+        s => s,
+    };
+    let margin = 4 * scale;
+    let pad = 2 * scale;
+    for anchor in Anchor::ALL {
+        let block: Vec<&str> = lines
+            .iter()
+            .filter(|(a, _)| *a == anchor)
+            .flat_map(|(_, text)| text.lines())
+            .collect();
+        if block.is_empty() {
+            continue;
+        }
+        let line_w = |l: &str| text_width(l, scale);
+        let block_w = block.iter().map(|l| line_w(l)).max().unwrap_or(0);
+        let block_h = (block.len() as u32 * LINE_H - (LINE_H - GLYPH_H)) * scale;
+        let (w, h) = (width as i64, height as i64);
+        let (bw, bh) = (block_w as i64, block_h as i64);
+        let m = margin as i64;
+        let x0 = match anchor {
+            Anchor::TopLeft | Anchor::BottomLeft => m,
+            Anchor::Top | Anchor::Center | Anchor::Bottom => (w - bw) / 2,
+            Anchor::TopRight | Anchor::BottomRight => w - m - bw,
+        };
+        let y0 = match anchor {
+            Anchor::TopLeft | Anchor::Top | Anchor::TopRight => m,
+            Anchor::Center => (h - bh) / 2,
+            _ => h - m - bh,
+        };
+
+        let mut fill = |x: i64, y: i64, alpha: f32, text: bool| {
+            if (0..w).contains(&x) && (0..h).contains(&y) {
+                blend((y * w + x) as usize, alpha, text);
+            }
+        };
+        if opts.box_opacity > 0.0 {
+            let p = pad as i64;
+            for y in y0 - p..y0 + bh + p {
+                for x in x0 - p..x0 + bw + p {
+                    fill(x, y, opts.box_opacity, false);
+                }
+            }
+        }
+        for (row, line) in block.iter().enumerate() {
+            let lx = match anchor {
+                Anchor::TopLeft | Anchor::BottomLeft => x0,
+                Anchor::Top | Anchor::Center | Anchor::Bottom => {
+                    x0 + (bw - line_w(line) as i64) / 2
+                }
+                Anchor::TopRight | Anchor::BottomRight => x0 + bw - line_w(line) as i64,
+            };
+            let ly = y0 + (row as u32 * LINE_H * scale) as i64;
+            glyph_pixels(line, lx, ly, scale, |x, y| fill(x, y, 1.0, true));
+        }
+    }
+}
+
+/// Width in pixels of `text` drawn at `scale`.
+pub(crate) fn text_width(text: &str, scale: u32) -> u32 {
+    (text.chars().count() as u32 * ADVANCE).saturating_sub(1) * scale
+}
+
+/// Height in pixels of one line of text at `scale`.
+pub(crate) fn text_height(scale: u32) -> u32 {
+    GLYPH_H * scale
+}
+
+/// Call `set(x, y)` for every pixel of `text` with its top-left at `x`, `y`.
+pub(crate) fn glyph_pixels(text: &str, x: i64, y: i64, scale: u32, mut set: impl FnMut(i64, i64)) {
+    for (col, ch) in text.chars().enumerate() {
+        let gx = x + (col as u32 * ADVANCE * scale) as i64;
+        for (cx, bits) in glyph(ch).iter().enumerate() {
+            for cy in 0..GLYPH_H {
+                if bits >> cy & 1 == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        set(
+                            gx + (cx as u32 * scale + dx) as i64,
+                            y + (cy * scale + dy) as i64,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn glyph(c: char) -> &'static [u8; 5] {
+    let i = match c {
+        ' '..='~' => c as usize - ' ' as usize,
+        _ => '?' as usize - ' ' as usize,
+    };
+    &FONT[i]
+}
+
+/// Printable ASCII, five columns per glyph, bit 0 the top row.
+#[rustfmt::skip]
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5F, 0x00, 0x00], // '!'
+    [0x00, 0x07, 0x00, 0x07, 0x00], // '"'
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], // '#'
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], // '$'
+    [0x23, 0x13, 0x08, 0x64, 0x62], // '%'
+    [0x36, 0x49, 0x55, 0x22, 0x50], // '&'
+    [0x00, 0x05, 0x03, 0x00, 0x00], // "'"
+    [0x00, 0x1C, 0x22, 0x41, 0x00], // '('
+    [0x00, 0x41, 0x22, 0x1C, 0x00], // ')'
+    [0x14, 0x08, 0x3E, 0x08, 0x14], // '*'
+    [0x08, 0x08, 0x3E, 0x08, 0x08], // '+'
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ','
+    [0x08, 0x08, 0x08, 0x08, 0x08], // '-'
+    [0x00, 0x60, 0x60, 0x00, 0x00], // '.'
+    [0x20, 0x10, 0x08, 0x04, 0x02], // '/'
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], // '0'
+    [0x00, 0x42, 0x7F, 0x40, 0x00], // '1'
+    [0x42, 0x61, 0x51, 0x49, 0x46], // '2'
+    [0x21, 0x41, 0x45, 0x4B, 0x31], // '3'
+    [0x18, 0x14, 0x12, 0x7F, 0x10], // '4'
+    [0x27, 0x45, 0x45, 0x45, 0x39], // '5'
+    [0x3C, 0x4A, 0x49, 0x49, 0x30], // '6'
+    [0x01, 0x71, 0x09, 0x05, 0x03], // '7'
+    [0x36, 0x49, 0x49, 0x49, 0x36], // '8'
+    [0x06, 0x49, 0x49, 0x29, 0x1E], // '9'
+    [0x00, 0x36, 0x36, 0x00, 0x00], // ':'
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ';'
+    [0x08, 0x14, 0x22, 0x41, 0x00], // '<'
+    [0x14, 0x14, 0x14, 0x14, 0x14], // '='
+    [0x00, 0x41, 0x22, 0x14, 0x08], // '>'
+    [0x02, 0x01, 0x51, 0x09, 0x06], // '?'
+    [0x32, 0x49, 0x79, 0x41, 0x3E], // '@'
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], // 'A'
+    [0x7F, 0x49, 0x49, 0x49, 0x36], // 'B'
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // 'C'
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], // 'D'
+    [0x7F, 0x49, 0x49, 0x49, 0x41], // 'E'
+    [0x7F, 0x09, 0x09, 0x09, 0x01], // 'F'
+    [0x3E, 0x41, 0x49, 0x49, 0x7A], // 'G'
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], // 'H'
+    [0x00, 0x41, 0x7F, 0x41, 0x00], // 'I'
+    [0x20, 0x40, 0x41, 0x3F, 0x01], // 'J'
+    [0x7F, 0x08, 0x14, 0x22, 0x41], // 'K'
+    [0x7F, 0x40, 0x40, 0x40, 0x40], // 'L'
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F], // 'M'
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], // 'N'
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // 'O'
+    [0x7F, 0x09, 0x09, 0x09, 0x06], // 'P'
+    [0x3E, 0x41, 0x51, 0x21, 0x5E], // 'Q'
+    [0x7F, 0x09, 0x19, 0x29, 0x46], // 'R'
+    [0x46, 0x49, 0x49, 0x49, 0x31], // 'S'
+    [0x01, 0x01, 0x7F, 0x01, 0x01], // 'T'
+    [0x3F, 0x40, 0x40, 0x40, 0x3F], // 'U'
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], // 'V'
+    [0x3F, 0x40, 0x38, 0x40, 0x3F], // 'W'
+    [0x63, 0x14, 0x08, 0x14, 0x63], // 'X'
+    [0x07, 0x08, 0x70, 0x08, 0x07], // 'Y'
+    [0x61, 0x51, 0x49, 0x45, 0x43], // 'Z'
+    [0x00, 0x7F, 0x41, 0x41, 0x00], // '['
+    [0x02, 0x04, 0x08, 0x10, 0x20], // '\\'
+    [0x00, 0x41, 0x41, 0x7F, 0x00], // ']'
+    [0x04, 0x02, 0x01, 0x02, 0x04], // '^'
+    [0x40, 0x40, 0x40, 0x40, 0x40], // '_'
+    [0x00, 0x01, 0x02, 0x04, 0x00], // '`'
+    [0x20, 0x54, 0x54, 0x54, 0x78], // 'a'
+    [0x7F, 0x48, 0x44, 0x44, 0x38], // 'b'
+    [0x38, 0x44, 0x44, 0x44, 0x20], // 'c'
+    [0x38, 0x44, 0x44, 0x48, 0x7F], // 'd'
+    [0x38, 0x54, 0x54, 0x54, 0x18], // 'e'
+    [0x08, 0x7E, 0x09, 0x01, 0x02], // 'f'
+    [0x0C, 0x52, 0x52, 0x52, 0x3E], // 'g'
+    [0x7F, 0x08, 0x04, 0x04, 0x78], // 'h'
+    [0x00, 0x44, 0x7D, 0x40, 0x00], // 'i'
+    [0x20, 0x40, 0x44, 0x3D, 0x00], // 'j'
+    [0x7F, 0x10, 0x28, 0x44, 0x00], // 'k'
+    [0x00, 0x41, 0x7F, 0x40, 0x00], // 'l'
+    [0x7C, 0x04, 0x18, 0x04, 0x78], // 'm'
+    [0x7C, 0x08, 0x04, 0x04, 0x78], // 'n'
+    [0x38, 0x44, 0x44, 0x44, 0x38], // 'o'
+    [0x7C, 0x14, 0x14, 0x14, 0x08], // 'p'
+    [0x08, 0x14, 0x14, 0x18, 0x7C], // 'q'
+    [0x7C, 0x08, 0x04, 0x04, 0x08], // 'r'
+    [0x48, 0x54, 0x54, 0x54, 0x20], // 's'
+    [0x04, 0x3F, 0x44, 0x40, 0x20], // 't'
+    [0x3C, 0x40, 0x40, 0x20, 0x7C], // 'u'
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], // 'v'
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], // 'w'
+    [0x44, 0x28, 0x10, 0x28, 0x44], // 'x'
+    [0x0C, 0x50, 0x50, 0x50, 0x3C], // 'y'
+    [0x44, 0x64, 0x54, 0x4C, 0x44], // 'z'
+    [0x00, 0x08, 0x36, 0x41, 0x00], // '{'
+    [0x00, 0x00, 0x7F, 0x00, 0x00], // '|'
+    [0x00, 0x41, 0x36, 0x08, 0x00], // '}'
+    [0x08, 0x04, 0x08, 0x10, 0x08], // '~'
+];
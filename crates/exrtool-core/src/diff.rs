@@ -0,0 +1,254 @@
+//! Attribute-level comparison of EXR headers: two files part by part, or a
+//! whole sequence to find attributes that vary from frame to frame.
+
+use anyhow::{Context, Result};
+use glob::Pattern;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::metadata::{read_part_attributes, MetadataValue, PartAttributes};
+
+/// Options shared by [`diff_files`] and [`diff_sequence`].
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    /// Glob patterns of attribute names to leave out (`capDate`, `render*`).
+    pub ignore: Vec<String>,
+}
+
+impl DiffOptions {
+    fn matcher(&self) -> Result<impl Fn(&str) -> bool> {
+        let patterns = self
+            .ignore
+            .iter()
+            .map(|p| Pattern::new(p).with_context(|| format!("invalid ignore pattern {:?}", p)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(move |key: &str| patterns.iter().any(|p| p.matches(key)))
+    }
+}
+
+/// One attribute that differs between two files. `None` means absent.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AttributeDiff {
+    pub part: String,
+    pub key: String,
+    pub a: Option<MetadataValue>,
+    pub b: Option<MetadataValue>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileDiff {
+    pub a: PathBuf,
+    pub b: PathBuf,
+    pub differences: Vec<AttributeDiff>,
+}
+
+/// Compare two lists of parts. Parts are paired by name (unnamed parts by
+/// index); a part missing on one side shows all its attributes as absent.
+pub fn diff_parts(
+    a: &[PartAttributes],
+    b: &[PartAttributes],
+    opts: &DiffOptions,
+) -> Result<Vec<AttributeDiff>> {
+    let ignored = opts.matcher()?;
+    let empty = BTreeMap::new();
+    let mut names: Vec<&str> = a.iter().map(|p| p.name.as_str()).collect();
+    for p in b {
+        if !names.contains(&p.name.as_str()) {
+            names.push(&p.name);
+        }
+    }
+    let mut out = Vec::new();
+    for name in names {
+        let (pa, pb) = (
+            find_part(a, name).unwrap_or(&empty),
+            find_part(b, name).unwrap_or(&empty),
+        );
+        let mut keys: Vec<&String> = pa.keys().chain(pb.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys.into_iter().filter(|k| !ignored(k)) {
+            let (va, vb) = (pa.get(key), pb.get(key));
+            if va != vb {
+                out.push(AttributeDiff {
+                    part: name.to_string(),
+                    key: key.clone(),
+                    a: va.cloned(),
+                    b: vb.cloned(),
+                });
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn find_part<'a>(
+    parts: &'a [PartAttributes],
+    name: &str,
+) -> Option<&'a BTreeMap<String, MetadataValue>> {
+    parts.iter().find(|p| p.name == name).map(|p| &p.attributes)
+}
+
+/// Compare every attribute of every part of two files.
+pub fn diff_files(a: &Path, b: &Path, opts: &DiffOptions) -> Result<FileDiff> {
+    let pa = read_part_attributes(a).with_context(|| format!("{}", a.display()))?;
+    let pb = read_part_attributes(b).with_context(|| format!("{}", b.display()))?;
+    Ok(FileDiff {
+        a: a.to_path_buf(),
+        b: b.to_path_buf(),
+        differences: diff_parts(&pa, &pb, opts)?,
+    })
+}
+
+/// How many frames carry one value of a varying attribute.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValueCount {
+    /// `None` for frames that lack the attribute.
+    pub value: Option<MetadataValue>,
+    pub count: usize,
+    /// First frame with this value.
+    pub first: PathBuf,
+}
+
+/// An attribute that is not the same in every frame.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VaryingAttribute {
+    pub part: String,
+    pub key: String,
+    /// Most common value first.
+    pub values: Vec<ValueCount>,
+    /// Smallest and largest value, for numeric and timecode attributes.
+    pub min: Option<MetadataValue>,
+    pub max: Option<MetadataValue>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SequenceDiff {
+    /// Frames whose headers could be read.
+    pub frames: usize,
+    /// Number of attributes identical in every frame.
+    pub constant: usize,
+    pub varying: Vec<VaryingAttribute>,
+    /// Frames that could not be read, with the error.
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+#[derive(Default)]
+struct Tracker {
+    values: Vec<ValueCount>,
+    /// Debug form of a value → index into `values`.
+    index: HashMap<String, usize>,
+    last_frame: usize,
+}
+
+impl Tracker {
+    fn add(&mut self, value: Option<&MetadataValue>, count: usize, path: &Path) {
+        let key = format!("{:?}", value);
+        match self.index.get(&key) {
+            Some(&i) => self.values[i].count += count,
+            None => {
+                self.index.insert(key, self.values.len());
+                self.values.push(ValueCount {
+                    value: value.cloned(),
+                    count,
+                    first: path.to_path_buf(),
+                });
+            }
+        }
+    }
+}
+
+/// Sort key for value ranges; `None` for values without an order.
+fn order_key(v: &MetadataValue) -> Option<(u8, f64)> {
+    Some(match v {
+        MetadataValue::Int(x) => (0, *x as f64),
+        MetadataValue::Float(x) => (0, *x as f64),
+        MetadataValue::Double(x) => (0, *x),
+        MetadataValue::Rational(n, d) if *d != 0 => (0, *n as f64 / *d as f64),
+        MetadataValue::TimeCode(tc) => {
+            let seconds = (tc.hours as u32 * 60 + tc.minutes as u32) * 60 + tc.seconds as u32;
+            (
+                1,
+                (seconds * 100 + tc.frames as u32 * 2 + tc.field_phase as u32) as f64,
+            )
+        }
+        _ => return None,
+    })
+}
+
+/// Smallest and largest of `values` when they are all comparable.
+fn value_range(values: &[ValueCount]) -> Option<(MetadataValue, MetadataValue)> {
+    let mut keyed = Vec::new();
+    for v in values.iter().filter_map(|c| c.value.as_ref()) {
+        keyed.push((order_key(v)?, v));
+    }
+    if keyed.windows(2).any(|w| w[0].0 .0 != w[1].0 .0) {
+        return None;
+    }
+    let min = keyed.iter().min_by(|x, y| x.0 .1.total_cmp(&y.0 .1))?;
+    let max = keyed.iter().max_by(|x, y| x.0 .1.total_cmp(&y.0 .1))?;
+    Some((min.1.clone(), max.1.clone()))
+}
+
+/// Find the attributes that differ between frames of a sequence, with how
+/// many frames carry each value. Unreadable frames are listed in
+/// [`SequenceDiff::failed`] and otherwise skipped.
+pub fn diff_sequence(files: &[PathBuf], opts: &DiffOptions) -> Result<SequenceDiff> {
+    let ignored = opts.matcher()?;
+    let mut result = SequenceDiff::default();
+    let mut trackers: BTreeMap<(String, String), Tracker> = BTreeMap::new();
+    let mut first_frame: Option<&Path> = None;
+    for path in files {
+        let parts = match read_part_attributes(path) {
+            Ok(p) => p,
+            Err(e) => {
+                result.failed.push((path.clone(), format!("{:#}", e)));
+                continue;
+            }
+        };
+        let frame = result.frames + 1;
+        let first = *first_frame.get_or_insert(path);
+        for part in parts {
+            for (key, value) in part.attributes {
+                if ignored(&key) {
+                    continue;
+                }
+                let t = trackers.entry((part.name.clone(), key)).or_insert_with(|| {
+                    let mut t = Tracker::default();
+                    // earlier frames did not have it
+                    if frame > 1 {
+                        t.add(None, frame - 1, first);
+                    }
+                    t
+                });
+                if t.last_frame != frame {
+                    t.add(Some(&value), 1, path);
+                    t.last_frame = frame;
+                }
+            }
+        }
+        for t in trackers.values_mut().filter(|t| t.last_frame != frame) {
+            t.add(None, 1, path);
+            t.last_frame = frame;
+        }
+        result.frames = frame;
+    }
+    for ((part, key), t) in trackers {
+        if t.values.len() < 2 {
+            result.constant += 1;
+            continue;
+        }
+        let mut values = t.values;
+        // stable: ties keep the order of first appearance
+        values.sort_by_key(|v| std::cmp::Reverse(v.count));
+        let (min, max) = value_range(&values).unzip();
+        result.varying.push(VaryingAttribute {
+            part,
+            key,
+            values,
+            min,
+            max,
+        });
+    }
+    Ok(result)
+}
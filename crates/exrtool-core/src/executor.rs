@@ -0,0 +1,428 @@
+//! Parallel per-file batch execution: a rayon pool with a thread count and a
+//! memory budget, per-item progress through a callback, and a checkpoint file
+//! so an interrupted batch can resume where it stopped.
+//!
+//! Workers only run the per-item work. The callback, the checkpoint and the
+//! bookkeeping stay on the calling thread, so the callback need not be `Send`
+//! and can drive a progress bar or emit GUI events directly.
+
+use anyhow::{anyhow, bail, Context, Result};
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::time::Instant;
+
+use crate::metadata::{read_part_attributes, MetadataValue};
+
+/// One unit of batch work.
+pub trait Task: Sync {
+    /// Stable identity of the item, recorded in checkpoints. Must not contain
+    /// a line break.
+    fn key(&self) -> String;
+
+    /// Rough peak memory needed to process the item, in bytes. Only asked
+    /// for when [`ExecOptions::memory_limit`] is set.
+    fn memory(&self) -> u64 {
+        0
+    }
+}
+
+impl Task for PathBuf {
+    fn key(&self) -> String {
+        self.to_string_lossy().into_owned()
+    }
+
+    fn memory(&self) -> u64 {
+        exr_memory_estimate(self)
+    }
+}
+
+/// Memory for decoding an EXR to RGBA f32 and building a preview from it:
+/// the data windows of all parts, twice over for the working copies. 0 when
+/// the header cannot be read.
+pub fn exr_memory_estimate(path: &Path) -> u64 {
+    let Ok(parts) = read_part_attributes(path) else {
+        return 0;
+    };
+    let pixels: u64 = parts
+        .iter()
+        .filter_map(|p| match p.attributes.get("dataWindow") {
+            Some(MetadataValue::Box2i { min, max }) => {
+                let w = (max[0] as i64 - min[0] as i64 + 1).max(0) as u64;
+                let h = (max[1] as i64 - min[1] as i64 + 1).max(0) as u64;
+                Some(w * h)
+            }
+            _ => None,
+        })
+        .sum();
+    pixels * 16 * 2
+}
+
+/// Parse a byte size such as `512M`, `8G`, `1.5GB` or `1048576`. Units are
+/// binary (K = 1024).
+pub fn parse_size(s: &str) -> Result<u64> {
+    let t = s.trim();
+    let upper = t.to_ascii_uppercase();
+    let digits = upper
+        .trim_end_matches("IB")
+        .trim_end_matches('B')
+        .trim_end();
+    let (number, shift) = match digits.chars().last() {
+        Some('K') => (&digits[..digits.len() - 1], 10),
+        Some('M') => (&digits[..digits.len() - 1], 20),
+        Some('G') => (&digits[..digits.len() - 1], 30),
+        Some('T') => (&digits[..digits.len() - 1], 40),
+        _ => (digits, 0),
+    };
+    let value: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("invalid size {:?} (e.g. 512M, 8G)", s))?;
+    if !value.is_finite() || value < 0.0 {
+        bail!("invalid size {:?}", s);
+    }
+    Ok((value * (1u64 << shift) as f64) as u64)
+}
+
+/// Settings for [`execute`].
+#[derive(Debug, Clone, Default)]
+pub struct ExecOptions {
+    /// Worker threads; 0 for one per CPU.
+    pub threads: usize,
+    /// Cap on the summed [`Task::memory`] of the items in flight, in bytes.
+    /// An item larger than the cap still runs, alone.
+    pub memory_limit: Option<u64>,
+    /// File recording finished items. If it exists from an interrupted run of
+    /// the same batch, those items are reported as [`Outcome::Resumed`] and
+    /// not run again. It is removed once every item has succeeded.
+    pub checkpoint: Option<PathBuf>,
+    /// Keep starting items after a failure instead of skipping the rest.
+    pub continue_on_error: bool,
+    /// Report items in order rather than as they finish. Finished items wait
+    /// for their predecessors and count against the memory limit meanwhile.
+    pub ordered: bool,
+    /// Set from another thread to stop starting new items.
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+#[derive(Debug)]
+pub enum Outcome<R> {
+    Done(R),
+    Failed(String),
+    /// Not started because an earlier item failed or the batch was cancelled.
+    Skipped,
+    /// Finished by an earlier, interrupted run (see [`ExecOptions::checkpoint`]).
+    Resumed,
+}
+
+/// One finished item, as passed to the progress callback.
+#[derive(Debug)]
+pub struct Progress<'a, T, R> {
+    pub index: usize,
+    pub item: &'a T,
+    pub outcome: Outcome<R>,
+    /// Time spent on the item; 0 unless it ran.
+    pub millis: u64,
+    /// Items reported so far, this one included.
+    pub completed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExecSummary {
+    pub done: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub resumed: usize,
+    /// The cancel flag was set.
+    pub cancelled: bool,
+}
+
+/// Run `work` on every item on a rayon pool and report each item exactly
+/// once through `progress`, on the calling thread. Items start in order;
+/// without [`ExecOptions::ordered`] they are reported as they finish.
+pub fn execute<T, R, W, P>(
+    items: &[T],
+    opts: &ExecOptions,
+    work: W,
+    mut progress: P,
+) -> Result<ExecSummary>
+where
+    T: Task,
+    R: Send,
+    W: Fn(&T) -> Result<R> + Sync,
+    P: FnMut(Progress<'_, T, R>),
+{
+    let mut checkpoint = match &opts.checkpoint {
+        Some(path) => Some(Checkpoint::open(path, items)?),
+        None => None,
+    };
+    let done_before = checkpoint
+        .as_mut()
+        .map(|c| std::mem::take(&mut c.done))
+        .unwrap_or_default();
+    let mut summary = ExecSummary::default();
+    let total = items.len();
+    let mut completed = 0;
+    let mut report = |index: usize, outcome: Outcome<R>, millis: u64| -> Result<()> {
+        match &outcome {
+            Outcome::Done(_) => {
+                summary.done += 1;
+                if let Some(c) = checkpoint.as_mut() {
+                    c.record(&items[index])?;
+                }
+            }
+            Outcome::Failed(_) => summary.failed += 1,
+            Outcome::Skipped => summary.skipped += 1,
+            Outcome::Resumed => summary.resumed += 1,
+        }
+        completed += 1;
+        progress(Progress {
+            index,
+            item: &items[index],
+            outcome,
+            millis,
+            completed,
+            total,
+        });
+        Ok(())
+    };
+
+    let mut pending = Vec::with_capacity(total);
+    for (i, item) in items.iter().enumerate() {
+        if !done_before.is_empty() && done_before.contains(&item.key()) {
+            report(i, Outcome::Resumed, 0)?;
+        } else {
+            pending.push(i);
+        }
+    }
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(opts.threads)
+        .build()
+        .context("failed to start worker threads")?;
+    let costs: Vec<u64> = match opts.memory_limit {
+        Some(limit) => pool.install(|| {
+            pending
+                .par_iter()
+                .map(|&i| items[i].memory().min(limit))
+                .collect()
+        }),
+        None => vec![0; pending.len()],
+    };
+    let sched = Scheduler {
+        state: Mutex::new(SchedState::default()),
+        freed: Condvar::new(),
+        limit: opts.memory_limit,
+        cancel: opts.cancel.clone(),
+    };
+    let workers = pool.current_num_threads();
+    let (tx, rx) = mpsc::channel::<(usize, Result<R, String>, u64)>();
+
+    let outcome = std::thread::scope(|s| -> Result<()> {
+        let (sched, work, pending, costs) = (&sched, &work, &pending, &costs);
+        s.spawn(move || {
+            pool.scope(|ps| {
+                for _ in 0..workers {
+                    let tx = tx.clone();
+                    ps.spawn(move |_| {
+                        while let Some(slot) = sched.claim(costs) {
+                            let t = Instant::now();
+                            let result =
+                                work(&items[pending[slot]]).map_err(|e| format!("{:#}", e));
+                            let millis = t.elapsed().as_millis() as u64;
+                            if result.is_err() && !opts.continue_on_error {
+                                sched.stop();
+                            }
+                            if tx.send((slot, result, millis)).is_err() {
+                                break;
+                            }
+                        }
+                    });
+                }
+            });
+        });
+
+        let mut held = BTreeMap::new();
+        let mut next = 0;
+        for (slot, result, millis) in rx {
+            held.insert(slot, (result, millis));
+            // unordered: deliver whatever arrived
+            let ready: Vec<usize> = if opts.ordered {
+                let mut ready = Vec::new();
+                while held.contains_key(&next) {
+                    ready.push(next);
+                    next += 1;
+                }
+                ready
+            } else {
+                vec![slot]
+            };
+            for slot in ready {
+                let (result, millis) = held.remove(&slot).unwrap();
+                let outcome = match result {
+                    Ok(r) => Outcome::Done(r),
+                    Err(e) => Outcome::Failed(e),
+                };
+                let delivered = report(pending[slot], outcome, millis);
+                sched.release(costs[slot]);
+                if let Err(e) = delivered {
+                    sched.stop();
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    });
+    outcome?;
+
+    let claimed = sched.state.lock().unwrap().next;
+    for &i in &pending[claimed..] {
+        report(i, Outcome::Skipped, 0)?;
+    }
+    summary.cancelled = opts
+        .cancel
+        .as_ref()
+        .is_some_and(|c| c.load(Ordering::SeqCst));
+    if summary.failed == 0 && summary.skipped == 0 {
+        if let Some(path) = &opts.checkpoint {
+            fs::remove_file(path)
+                .with_context(|| format!("failed to remove checkpoint {}", path.display()))?;
+        }
+    }
+    Ok(summary)
+}
+
+#[derive(Default)]
+struct SchedState {
+    /// Next slot of `pending` to start.
+    next: usize,
+    /// Memory of the items started and not yet reported.
+    in_flight: u64,
+    stop: bool,
+}
+
+struct Scheduler {
+    state: Mutex<SchedState>,
+    freed: Condvar,
+    limit: Option<u64>,
+    cancel: Option<Arc<AtomicBool>>,
+}
+
+impl Scheduler {
+    /// Claim the next slot once its memory fits. Claiming in order keeps the
+    /// lowest unreported item always running, so ordered delivery cannot
+    /// starve on memory held by its successors.
+    fn claim(&self, costs: &[u64]) -> Option<usize> {
+        let mut st = self.state.lock().unwrap();
+        loop {
+            let cancelled = self
+                .cancel
+                .as_ref()
+                .is_some_and(|c| c.load(Ordering::SeqCst));
+            if st.stop || cancelled || st.next >= costs.len() {
+                return None;
+            }
+            let cost = costs[st.next];
+            let fits = match self.limit {
+                Some(limit) => st.in_flight == 0 || st.in_flight + cost <= limit,
+                None => true,
+            };
+            if fits {
+                st.in_flight += cost;
+                st.next += 1;
+                return Some(st.next - 1);
+            }
+            st = self.freed.wait(st).unwrap();
+        }
+    }
+
+    fn release(&self, cost: u64) {
+        self.state.lock().unwrap().in_flight -= cost;
+        self.freed.notify_all();
+    }
+
+    fn stop(&self) {
+        self.state.lock().unwrap().stop = true;
+        self.freed.notify_all();
+    }
+}
+
+const CHECKPOINT_MAGIC: &str = "exrtool-checkpoint 1";
+
+/// Append-only list of finished item keys, after a header line naming the
+/// batch by a hash of all its keys.
+struct Checkpoint {
+    file: fs::File,
+    /// Keys finished by earlier runs.
+    done: HashSet<String>,
+}
+
+impl Checkpoint {
+    fn open<T: Task>(path: &Path, items: &[T]) -> Result<Self> {
+        let keys: Vec<String> = items.iter().map(Task::key).collect();
+        if let Some(k) = keys.iter().find(|k| k.contains(['\n', '\r'])) {
+            bail!(
+                "item {:?} cannot be checkpointed (line break in its name)",
+                k
+            );
+        }
+        let header = format!("{} {:016x}", CHECKPOINT_MAGIC, fingerprint(&keys));
+        let mut done = HashSet::new();
+        let mut repair = false;
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                let mut lines = text.lines();
+                if lines.next() != Some(header.as_str()) {
+                    bail!(
+                        "checkpoint {} belongs to a different batch; delete it to start over",
+                        path.display()
+                    );
+                }
+                let known: HashSet<&str> = keys.iter().map(String::as_str).collect();
+                // a line cut short by the interruption matches no key
+                done.extend(lines.filter(|l| known.contains(l)).map(String::from));
+                repair = !text.ends_with('\n');
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                fs::write(path, format!("{}\n", header))
+                    .with_context(|| format!("failed to create checkpoint {}", path.display()))?;
+            }
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("failed to read checkpoint {}", path.display()))
+            }
+        }
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to open checkpoint {}", path.display()))?;
+        if repair {
+            file.write_all(b"\n")?;
+        }
+        Ok(Checkpoint { file, done })
+    }
+
+    fn record<T: Task>(&mut self, item: &T) -> Result<()> {
+        // one write per line, so an interruption leaves at most one partial line
+        self.file
+            .write_all(format!("{}\n", item.key()).as_bytes())
+            .context("failed to update checkpoint")
+    }
+}
+
+/// FNV-1a over the keys; stable across builds, unlike `DefaultHasher`.
+fn fingerprint(keys: &[String]) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for key in keys {
+        for b in key.bytes().chain(std::iter::once(b'\n')) {
+            h ^= b as u64;
+            h = h.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    h
+}
@@ -0,0 +1,152 @@
+//! Out-of-gamut handling: hard clip, pass-through and the ACES Reference
+//! Gamut Compression (RGC).
+//!
+//! The RGC follows the ACES 1.3 `LMT.Academy.ReferenceGamutCompress` CTL:
+//! each channel's distance from the achromatic axis (`max(r,g,b)`) is
+//! compressed above a threshold so that `limit` maps onto the gamut
+//! boundary.
+
+use crate::{rgb_to_rgb_matrix, ClipMode, Primaries};
+use nalgebra::{Matrix3, Vector3};
+use serde::{Deserialize, Serialize};
+
+/// Parameters of the ACES Reference Gamut Compression.
+///
+/// Per-channel arrays are ordered R, G, B, i.e. the cyan, magenta and
+/// yellow limits of the reference implementation.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RgcParams {
+    /// Distance below which values are left untouched.
+    pub threshold: [f64; 3],
+    /// Distance that is compressed onto the gamut boundary.
+    pub limit: [f64; 3],
+    /// Roll-off exponent of the compression curve.
+    pub power: f64,
+}
+
+impl Default for RgcParams {
+    fn default() -> Self {
+        Self {
+            threshold: [0.815, 0.803, 0.880],
+            limit: [1.147, 1.264, 1.312],
+            power: 1.2,
+        }
+    }
+}
+
+impl RgcParams {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.power.is_nan() || self.power <= 0.0 {
+            anyhow::bail!("rgc power must be > 0 (got {})", self.power);
+        }
+        for c in 0..3 {
+            let (thr, lim) = (self.threshold[c], self.limit[c]);
+            if !(0.0..1.0).contains(&thr) || lim <= 1.0 {
+                anyhow::bail!(
+                    "rgc threshold must be in [0,1) and limit > 1 (channel {}: threshold={}, limit={})",
+                    c,
+                    thr,
+                    lim
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compress a single distance value. Distances below `thr` are returned unchanged.
+pub fn compress_distance(dist: f64, thr: f64, lim: f64, pwr: f64) -> f64 {
+    if dist < thr {
+        return dist;
+    }
+    // scale so that `lim` maps to exactly 1.0
+    let scale = (lim - thr) / (((1.0 - thr) / (lim - thr)).powf(-pwr) - 1.0).powf(1.0 / pwr);
+    let nd = (dist - thr) / scale;
+    thr + scale * nd / (1.0 + nd.powf(pwr)).powf(1.0 / pwr)
+}
+
+fn distances(rgb: [f64; 3]) -> Option<(f64, [f64; 3])> {
+    let ach = rgb[0].max(rgb[1]).max(rgb[2]);
+    if ach == 0.0 {
+        return None;
+    }
+    Some((ach, rgb.map(|c| (ach - c) / ach.abs())))
+}
+
+/// Apply the reference gamut compression to linear RGB in the target gamut.
+pub fn rgc_compress(rgb: [f64; 3], p: &RgcParams) -> [f64; 3] {
+    let Some((ach, dist)) = distances(rgb) else {
+        return rgb;
+    };
+    let mut out = [0.0; 3];
+    for c in 0..3 {
+        let d = compress_distance(dist[c], p.threshold[c], p.limit[c], p.power);
+        out[c] = ach - d * ach.abs();
+    }
+    out
+}
+
+/// True when any channel lies in the compression zone (distance above threshold).
+pub fn in_compression_zone(rgb: [f64; 3], p: &RgcParams) -> bool {
+    match distances(rgb) {
+        Some((_, dist)) => (0..3).any(|c| dist[c] > p.threshold[c]),
+        None => false,
+    }
+}
+
+/// Gamut mapping stage of the preview pipeline.
+///
+/// Pixels are converted from `working` to `target` primaries, mapped
+/// according to `mode` and converted back, so later stages still receive
+/// `working` values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GamutMapping {
+    pub working: Primaries,
+    pub target: Primaries,
+    pub mode: ClipMode,
+}
+
+/// [`GamutMapping`] with its matrices computed once per image.
+pub struct PreparedGamut {
+    to_target: Matrix3<f64>,
+    from_target: Matrix3<f64>,
+    mode: ClipMode,
+}
+
+impl PreparedGamut {
+    pub fn new(g: &GamutMapping) -> Self {
+        Self {
+            to_target: rgb_to_rgb_matrix(g.working, g.target),
+            from_target: rgb_to_rgb_matrix(g.target, g.working),
+            mode: g.mode,
+        }
+    }
+
+    fn to_target(&self, rgb: [f32; 3]) -> [f64; 3] {
+        let v = self.to_target * Vector3::new(rgb[0] as f64, rgb[1] as f64, rgb[2] as f64);
+        [v.x, v.y, v.z]
+    }
+
+    /// Map linear `working` RGB. `Clip` only removes negative components in the
+    /// target gamut; highlights are kept for the later display stage.
+    pub fn apply(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let t = self.to_target(rgb);
+        let mapped = match &self.mode {
+            ClipMode::NoClip => return rgb,
+            ClipMode::Clip => t.map(|v| v.max(0.0)),
+            ClipMode::Compress(p) => rgc_compress(t, p),
+        };
+        let v = self.from_target * Vector3::new(mapped[0], mapped[1], mapped[2]);
+        [v.x as f32, v.y as f32, v.z as f32]
+    }
+
+    /// Whether the pixel is affected by the mapping: in the compression zone
+    /// for `Compress`, otherwise outside the target gamut.
+    pub fn is_flagged(&self, rgb: [f32; 3]) -> bool {
+        let t = self.to_target(rgb);
+        match &self.mode {
+            ClipMode::Compress(p) => in_compression_zone(t, p),
+            _ => t.iter().any(|&v| v < 0.0),
+        }
+    }
+}
@@ -0,0 +1,735 @@
+//! Raw EXR header access.
+//!
+//! Parses the attribute lists and chunk offset tables of a file without
+//! touching pixel data, so metadata can be changed by rewriting only the
+//! header: the compressed chunks are copied byte for byte and the offset
+//! tables are shifted by the change in header size.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::metadata::{MetadataValue, TimeCode, ValueKind};
+
+const MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+const TILED_FLAG: u32 = 0x200;
+const LONG_NAMES_FLAG: u32 = 0x400;
+const DEEP_FLAG: u32 = 0x800;
+const MULTIPART_FLAG: u32 = 0x1000;
+/// Attribute and type names longer than this need the long-names flag.
+const SHORT_NAME_MAX: usize = 31;
+
+/// Attributes that describe how the pixel chunks are laid out; a header-only
+/// rewrite must leave them untouched.
+pub(crate) const LAYOUT_ATTRIBUTES: [&str; 8] = [
+    "channels",
+    "compression",
+    "dataWindow",
+    "lineOrder",
+    "tiles",
+    "type",
+    "chunkCount",
+    "name",
+];
+
+/// One attribute exactly as stored in the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawAttribute {
+    pub name: String,
+    pub type_name: String,
+    pub data: Vec<u8>,
+}
+
+/// The attribute list of one part, in file order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RawHeader {
+    pub attributes: Vec<RawAttribute>,
+}
+
+impl RawHeader {
+    pub fn get(&self, name: &str) -> Option<&RawAttribute> {
+        self.attributes.iter().find(|a| a.name == name)
+    }
+
+    /// Decoded value of an attribute, if its type is one [`MetadataValue`]
+    /// can represent.
+    pub fn value(&self, name: &str) -> Option<MetadataValue> {
+        self.get(name)
+            .and_then(|a| decode_value(&a.type_name, &a.data))
+    }
+
+    /// Replace an attribute in place, or append it.
+    pub fn set(&mut self, name: &str, value: &MetadataValue) {
+        let (type_name, mut data) = encode_value(value);
+        match self.attributes.iter_mut().find(|a| a.name == name) {
+            Some(a) => {
+                // keep colour frame/binary group bits and user data
+                if type_name == "timecode" && a.type_name == "timecode" && a.data.len() == 8 {
+                    const TIME_BITS: u32 = 0x3f7f_ff7f;
+                    let old = u32::from_le_bytes(a.data[..4].try_into().unwrap());
+                    let new = u32::from_le_bytes(data[..4].try_into().unwrap());
+                    let merged = (new & TIME_BITS) | (old & !TIME_BITS);
+                    data[..4].copy_from_slice(&merged.to_le_bytes());
+                    data[4..].copy_from_slice(&a.data[4..]);
+                }
+                a.type_name = type_name.to_string();
+                a.data = data;
+            }
+            None => self.attributes.push(RawAttribute {
+                name: name.to_string(),
+                type_name: type_name.to_string(),
+                data,
+            }),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.attributes.len();
+        self.attributes.retain(|a| a.name != name);
+        self.attributes.len() != before
+    }
+
+    /// The part's `name` attribute, present in multi-part files.
+    pub fn part_name(&self) -> Option<String> {
+        self.text("name")
+    }
+
+    fn int(&self, name: &str) -> Option<i32> {
+        match self.value(name)? {
+            MetadataValue::Int(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn text(&self, name: &str) -> Option<String> {
+        match self.value(name)? {
+            MetadataValue::Text(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Number of entries in this part's offset table: `chunkCount` when
+    /// present, otherwise derived from the data window, compression and
+    /// tiling. `version` is the file's version field.
+    pub fn chunk_count(&self, version: u32) -> Result<usize> {
+        if let Some(n) = self.int("chunkCount") {
+            return usize::try_from(n).map_err(|_| anyhow!("negative chunkCount {}", n));
+        }
+        let kind = self.text("type");
+        if version & DEEP_FLAG != 0 || kind.as_deref().is_some_and(|t| t.starts_with("deep")) {
+            bail!("deep part without a chunkCount attribute");
+        }
+        let Some(MetadataValue::Box2i { min, max }) = self.value("dataWindow") else {
+            bail!("missing dataWindow attribute");
+        };
+        let width = (max[0] as i64 - min[0] as i64 + 1).max(0) as u64;
+        let height = (max[1] as i64 - min[1] as i64 + 1).max(0) as u64;
+        let tiled = match kind.as_deref() {
+            Some(t) => t == "tiledimage",
+            None => version & TILED_FLAG != 0,
+        };
+        if tiled {
+            let tiles = self
+                .get("tiles")
+                .filter(|a| a.data.len() >= 9)
+                .ok_or_else(|| anyhow!("tiled part without a tiles attribute"))?;
+            let tw = u32::from_le_bytes(tiles.data[0..4].try_into().unwrap()) as u64;
+            let th = u32::from_le_bytes(tiles.data[4..8].try_into().unwrap()) as u64;
+            return tile_count(width, height, tw, th, tiles.data[8]);
+        }
+        let compression = self
+            .get("compression")
+            .and_then(|a| a.data.first().copied())
+            .ok_or_else(|| anyhow!("missing compression attribute"))?;
+        let lines = match compression {
+            0..=2 => 1,
+            3 | 5 => 16,
+            4 | 6 | 7 | 8 => 32,
+            9 => 256,
+            c => bail!("unsupported compression type {}", c),
+        };
+        Ok(height.div_ceil(lines) as usize)
+    }
+}
+
+fn tile_count(width: u64, height: u64, tw: u64, th: u64, mode: u8) -> Result<usize> {
+    if tw == 0 || th == 0 {
+        bail!("invalid tile size {}x{}", tw, th);
+    }
+    let round_up = mode >> 4 == 1;
+    let levels = |size: u64| -> u32 {
+        let size = size.max(1);
+        let floor = 63 - size.leading_zeros();
+        if round_up && !size.is_power_of_two() {
+            floor + 2
+        } else {
+            floor + 1
+        }
+    };
+    let level_size = |size: u64, l: u32| -> u64 {
+        let s = if round_up {
+            (size + (1 << l) - 1) >> l
+        } else {
+            size >> l
+        };
+        s.max(1)
+    };
+    let tiles = |w: u64, h: u64| w.div_ceil(tw) * h.div_ceil(th);
+    let total = match mode & 0x0f {
+        0 => tiles(width, height),
+        1 => (0..levels(width.max(height)))
+            .map(|l| tiles(level_size(width, l), level_size(height, l)))
+            .sum(),
+        2 => {
+            let mut n = 0;
+            for ly in 0..levels(height) {
+                for lx in 0..levels(width) {
+                    n += tiles(level_size(width, lx), level_size(height, ly));
+                }
+            }
+            n
+        }
+        m => bail!("unsupported tile level mode {}", m),
+    };
+    Ok(total as usize)
+}
+
+/// Headers and offset tables of an EXR file: everything before the
+/// first chunk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExrHeaders {
+    /// The version field: format version in the low byte plus flags.
+    pub version: u32,
+    pub headers: Vec<RawHeader>,
+    /// One offset table per part; offsets are absolute file positions.
+    pub offsets: Vec<Vec<u64>>,
+    /// File position of the first chunk.
+    pub data_start: u64,
+}
+
+impl ExrHeaders {
+    pub fn read_file(path: &Path) -> Result<Self> {
+        let mut r = BufReader::new(
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?,
+        );
+        Self::read(&mut r).with_context(|| format!("invalid EXR header: {}", path.display()))
+    }
+
+    pub fn read<R: Read + Seek>(r: &mut R) -> Result<Self> {
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            bail!("not an OpenEXR file");
+        }
+        let version = read_u32(r)?;
+        if version & 0xff != 2 {
+            bail!("unsupported OpenEXR version {}", version & 0xff);
+        }
+        let mut headers = Vec::new();
+        if version & MULTIPART_FLAG != 0 {
+            loop {
+                let h = read_header(r)?;
+                if h.attributes.is_empty() {
+                    break;
+                }
+                headers.push(h);
+            }
+        } else {
+            headers.push(read_header(r)?);
+        }
+        let mut offsets = Vec::with_capacity(headers.len());
+        for h in &headers {
+            let n = h.chunk_count(version)?;
+            let mut table = vec![0u8; n * 8];
+            r.read_exact(&mut table)
+                .context("offset table is truncated")?;
+            offsets.push(
+                table
+                    .chunks_exact(8)
+                    .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+                    .collect(),
+            );
+        }
+        let data_start = r.stream_position()?;
+        Ok(Self {
+            version,
+            headers,
+            offsets,
+            data_start,
+        })
+    }
+
+    pub fn is_multipart(&self) -> bool {
+        self.version & MULTIPART_FLAG != 0
+    }
+
+    /// Serialized magic, version and headers, without the offset tables.
+    fn encode_headers(&self) -> Result<Vec<u8>> {
+        let long = self
+            .headers
+            .iter()
+            .flat_map(|h| &h.attributes)
+            .any(|a| a.name.len() > SHORT_NAME_MAX || a.type_name.len() > SHORT_NAME_MAX);
+        let mut version = self.version & !LONG_NAMES_FLAG;
+        if long {
+            version |= LONG_NAMES_FLAG;
+        }
+        let mut out = MAGIC.to_vec();
+        out.extend_from_slice(&version.to_le_bytes());
+        for h in &self.headers {
+            for a in &h.attributes {
+                for s in [&a.name, &a.type_name] {
+                    if s.is_empty() || s.len() > 255 || s.contains('\0') {
+                        bail!("invalid attribute name {:?}", s);
+                    }
+                    out.extend_from_slice(s.as_bytes());
+                    out.push(0);
+                }
+                let size = i32::try_from(a.data.len())
+                    .map_err(|_| anyhow!("attribute {} is too large", a.name))?;
+                out.extend_from_slice(&size.to_le_bytes());
+                out.extend_from_slice(&a.data);
+            }
+            out.push(0);
+        }
+        if self.is_multipart() {
+            out.push(0);
+        }
+        Ok(out)
+    }
+
+    fn table_len(&self) -> u64 {
+        self.offsets.iter().map(|t| t.len() as u64 * 8).sum()
+    }
+}
+
+fn read_u32(r: &mut impl Read) -> Result<u32> {
+    let mut b = [0u8; 4];
+    r.read_exact(&mut b)?;
+    Ok(u32::from_le_bytes(b))
+}
+
+fn read_name(r: &mut impl Read) -> Result<String> {
+    let mut bytes = Vec::new();
+    let mut b = [0u8; 1];
+    loop {
+        r.read_exact(&mut b)?;
+        if b[0] == 0 {
+            break;
+        }
+        bytes.push(b[0]);
+        if bytes.len() > 255 {
+            bail!("attribute name is too long");
+        }
+    }
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn read_header(r: &mut impl Read) -> Result<RawHeader> {
+    let mut header = RawHeader::default();
+    loop {
+        let name = read_name(r)?;
+        if name.is_empty() {
+            return Ok(header);
+        }
+        let type_name = read_name(r)?;
+        let size = read_u32(r)? as i32;
+        if !(0..=1 << 26).contains(&size) {
+            bail!("attribute {} has invalid size {}", name, size);
+        }
+        let mut data = vec![0u8; size as usize];
+        r.read_exact(&mut data)
+            .with_context(|| format!("attribute {} is truncated", name))?;
+        header.attributes.push(RawAttribute {
+            name,
+            type_name,
+            data,
+        });
+    }
+}
+
+/// EXR type name of values of the given kind.
+pub fn type_name(kind: ValueKind) -> &'static str {
+    match kind {
+        ValueKind::Text => "string",
+        ValueKind::Int => "int",
+        ValueKind::Float => "float",
+        ValueKind::Double => "double",
+        ValueKind::Rational => "rational",
+        ValueKind::Timecode => "timecode",
+        ValueKind::V2f => "v2f",
+        ValueKind::V2i => "v2i",
+        ValueKind::Box2i => "box2i",
+        ValueKind::Chromaticities => "chromaticities",
+    }
+}
+
+/// The [`ValueKind`] an EXR type name corresponds to, if any.
+pub fn kind_of_type(type_name: &str) -> Option<ValueKind> {
+    Some(match type_name {
+        "string" => ValueKind::Text,
+        "int" => ValueKind::Int,
+        "float" => ValueKind::Float,
+        "double" => ValueKind::Double,
+        "rational" => ValueKind::Rational,
+        "timecode" => ValueKind::Timecode,
+        "v2f" => ValueKind::V2f,
+        "v2i" => ValueKind::V2i,
+        "box2i" => ValueKind::Box2i,
+        "chromaticities" => ValueKind::Chromaticities,
+        _ => return None,
+    })
+}
+
+fn bcd(v: u8) -> u32 {
+    ((v / 10) << 4 | (v % 10)) as u32
+}
+
+fn from_bcd(v: u32) -> u8 {
+    ((v >> 4) * 10 + (v & 0x0f)) as u8
+}
+
+/// Encode a value as an EXR attribute payload.
+pub fn encode_value(value: &MetadataValue) -> (&'static str, Vec<u8>) {
+    fn le<const N: usize>(items: impl IntoIterator<Item = [u8; N]>) -> Vec<u8> {
+        items.into_iter().flatten().collect()
+    }
+    let data = match value {
+        MetadataValue::Text(s) => s.as_bytes().to_vec(),
+        MetadataValue::Int(v) => v.to_le_bytes().to_vec(),
+        MetadataValue::Float(v) => v.to_le_bytes().to_vec(),
+        MetadataValue::Double(v) => v.to_le_bytes().to_vec(),
+        MetadataValue::Rational(n, d) => le([n.to_le_bytes(), d.to_le_bytes()]),
+        MetadataValue::TimeCode(tc) => {
+            let time = bcd(tc.frames) & 0x3f
+                | (tc.drop_frame as u32) << 6
+                | (bcd(tc.seconds) & 0x7f) << 8
+                | (tc.field_phase as u32) << 15
+                | (bcd(tc.minutes) & 0x7f) << 16
+                | (bcd(tc.hours) & 0x3f) << 24;
+            le([time.to_le_bytes(), 0u32.to_le_bytes()])
+        }
+        MetadataValue::V2f(v) => le(v.map(f32::to_le_bytes)),
+        MetadataValue::V2i(v) => le(v.map(i32::to_le_bytes)),
+        MetadataValue::Box2i { min, max } => {
+            le([min[0], min[1], max[0], max[1]].map(i32::to_le_bytes))
+        }
+        MetadataValue::Chromaticities(c) => le(c.map(f32::to_le_bytes)),
+    };
+    (type_name(value.kind()), data)
+}
+
+/// Decode an EXR attribute payload of a type [`MetadataValue`] supports.
+pub fn decode_value(type_name: &str, data: &[u8]) -> Option<MetadataValue> {
+    let kind = kind_of_type(type_name)?;
+    let words = |n: usize| -> Option<Vec<[u8; 4]>> {
+        (data.len() == n * 4).then(|| {
+            data.chunks_exact(4)
+                .map(|c| c.try_into().unwrap())
+                .collect()
+        })
+    };
+    Some(match kind {
+        ValueKind::Text => MetadataValue::Text(String::from_utf8_lossy(data).into_owned()),
+        ValueKind::Int => MetadataValue::Int(i32::from_le_bytes(words(1)?[0])),
+        ValueKind::Float => MetadataValue::Float(f32::from_le_bytes(words(1)?[0])),
+        ValueKind::Double => MetadataValue::Double(f64::from_le_bytes(data.try_into().ok()?)),
+        ValueKind::Rational => {
+            let w = words(2)?;
+            MetadataValue::Rational(i32::from_le_bytes(w[0]), u32::from_le_bytes(w[1]))
+        }
+        ValueKind::Timecode => {
+            let t = u32::from_le_bytes(words(2)?[0]);
+            MetadataValue::TimeCode(TimeCode {
+                hours: from_bcd(t >> 24 & 0x3f),
+                minutes: from_bcd(t >> 16 & 0x7f),
+                seconds: from_bcd(t >> 8 & 0x7f),
+                frames: from_bcd(t & 0x3f),
+                drop_frame: t >> 6 & 1 == 1,
+                field_phase: t >> 15 & 1 == 1,
+            })
+        }
+        ValueKind::V2f => {
+            let w = words(2)?;
+            MetadataValue::V2f([f32::from_le_bytes(w[0]), f32::from_le_bytes(w[1])])
+        }
+        ValueKind::V2i => {
+            let w = words(2)?;
+            MetadataValue::V2i([i32::from_le_bytes(w[0]), i32::from_le_bytes(w[1])])
+        }
+        ValueKind::Box2i => {
+            let w: Vec<i32> = words(4)?.into_iter().map(i32::from_le_bytes).collect();
+            MetadataValue::Box2i {
+                min: [w[0], w[1]],
+                max: [w[2], w[3]],
+            }
+        }
+        ValueKind::Chromaticities => {
+            let w: Vec<f32> = words(8)?.into_iter().map(f32::from_le_bytes).collect();
+            MetadataValue::Chromaticities(w.try_into().ok()?)
+        }
+    })
+}
+
+/// Decode any attribute payload: supported types as typed values, the rest
+/// (channel lists, compression, matrices, ...) as readable text, and
+/// opaque data as its type, size and a checksum so differences still show.
+pub fn describe_value(type_name: &str, data: &[u8]) -> MetadataValue {
+    if let Some(v) = decode_value(type_name, data) {
+        return v;
+    }
+    let floats = |n: usize| {
+        (data.len() == n * 4).then(|| {
+            data.chunks_exact(4)
+                .map(|c| f32::from_le_bytes(c.try_into().unwrap()).to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+    };
+    let doubles = |n: usize| {
+        (data.len() == n * 8).then(|| {
+            data.chunks_exact(8)
+                .map(|c| f64::from_le_bytes(c.try_into().unwrap()).to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+    };
+    let ints = |n: usize| {
+        (data.len() == n * 4).then(|| {
+            data.chunks_exact(4)
+                .map(|c| i32::from_le_bytes(c.try_into().unwrap()).to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+    };
+    let text = match type_name {
+        "compression" => data.first().map(|c| {
+            const NAMES: [&str; 10] = [
+                "none", "rle", "zips", "zip", "piz", "pxr24", "b44", "b44a", "dwaa", "dwab",
+            ];
+            NAMES
+                .get(*c as usize)
+                .map_or_else(|| format!("unknown({})", c), |n| n.to_string())
+        }),
+        "lineOrder" => data.first().map(|o| {
+            match o {
+                0 => "increasing_y",
+                1 => "decreasing_y",
+                2 => "random_y",
+                _ => "unknown",
+            }
+            .to_string()
+        }),
+        "envmap" => data.first().map(|e| {
+            match e {
+                0 => "latlong",
+                1 => "cube",
+                _ => "unknown",
+            }
+            .to_string()
+        }),
+        "chlist" => describe_channels(data),
+        "tiledesc" if data.len() == 9 => {
+            let w = u32::from_le_bytes(data[0..4].try_into().unwrap());
+            let h = u32::from_le_bytes(data[4..8].try_into().unwrap());
+            let level = ["one_level", "mipmap", "ripmap"]
+                .get((data[8] & 0x0f) as usize)
+                .unwrap_or(&"unknown");
+            let round = if data[8] >> 4 == 1 { "up" } else { "down" };
+            Some(format!("{}x{} {} round_{}", w, h, level, round))
+        }
+        "stringvector" => {
+            let mut items = Vec::new();
+            let mut rest = data;
+            while rest.len() >= 4 {
+                let n = i32::from_le_bytes(rest[..4].try_into().unwrap()).max(0) as usize;
+                let end = (4 + n).min(rest.len());
+                items.push(String::from_utf8_lossy(&rest[4..end]).into_owned());
+                rest = &rest[end..];
+            }
+            Some(items.join(","))
+        }
+        "floatvector" if data.len().is_multiple_of(4) => floats(data.len() / 4),
+        "v3f" => floats(3),
+        "box2f" => floats(4),
+        "m33f" => floats(9),
+        "m44f" => floats(16),
+        "v3i" => ints(3),
+        "keycode" => ints(7),
+        "v2d" => doubles(2),
+        "v3d" => doubles(3),
+        "m33d" => doubles(9),
+        "m44d" => doubles(16),
+        "preview" if data.len() >= 8 => {
+            let w = u32::from_le_bytes(data[0..4].try_into().unwrap());
+            let h = u32::from_le_bytes(data[4..8].try_into().unwrap());
+            Some(format!("{}x{} preview", w, h))
+        }
+        _ => None,
+    };
+    MetadataValue::Text(text.unwrap_or_else(|| {
+        // FNV-1a: cheap and stable across runs
+        let hash = data.iter().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+            (h ^ *b as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+        format!("<{} {} bytes {:016x}>", type_name, data.len(), hash)
+    }))
+}
+
+/// `R:half,G:half,...` for a channel list, with subsampling when not 1x1.
+fn describe_channels(data: &[u8]) -> Option<String> {
+    let mut out = Vec::new();
+    let mut rest = data;
+    loop {
+        let end = rest.iter().position(|b| *b == 0)?;
+        if end == 0 {
+            return Some(out.join(","));
+        }
+        let name = String::from_utf8_lossy(&rest[..end]);
+        let info = rest.get(end + 1..end + 17)?;
+        let pixel = match i32::from_le_bytes(info[0..4].try_into().unwrap()) {
+            0 => "uint",
+            1 => "half",
+            2 => "float",
+            _ => "unknown",
+        };
+        let xs = i32::from_le_bytes(info[8..12].try_into().unwrap());
+        let ys = i32::from_le_bytes(info[12..16].try_into().unwrap());
+        if (xs, ys) == (1, 1) {
+            out.push(format!("{}:{}", name, pixel));
+        } else {
+            out.push(format!("{}:{}/{}x{}", name, pixel, xs, ys));
+        }
+        rest = &rest[end + 17..];
+    }
+}
+
+/// Rewrite the headers of `src` into `dst` (which may be `src` itself)
+/// without decoding pixels.
+///
+/// `edit` changes the parsed headers; the result is written to a temporary
+/// file next to `dst` with the offset tables shifted and every chunk copied
+/// verbatim, then moved over `dst`. With `verify`, the temporary file is
+/// read back first and its chunk bytes compared with the source.
+pub fn rewrite_headers(
+    src: &Path,
+    dst: &Path,
+    verify: bool,
+    edit: impl FnOnce(&mut [RawHeader]) -> Result<()>,
+) -> Result<()> {
+    let old = ExrHeaders::read_file(src)?;
+    let mut new = old.clone();
+    edit(&mut new.headers)?;
+    for (i, (h, before)) in new.headers.iter().zip(&old.headers).enumerate() {
+        if let Some(key) = LAYOUT_ATTRIBUTES.iter().find(|k| h.get(k) != before.get(k)) {
+            bail!(
+                "edit changed {} of part {}, which needs a re-encode",
+                key,
+                i
+            );
+        }
+        if h.chunk_count(new.version)? != old.offsets[i].len() {
+            bail!("edit changed the chunk layout of part {}", i);
+        }
+    }
+    let head = new.encode_headers()?;
+    let new_start = head.len() as u64 + new.table_len();
+    let delta = new_start as i64 - old.data_start as i64;
+    for table in &mut new.offsets {
+        for off in table.iter_mut().filter(|o| **o != 0) {
+            *off = off
+                .checked_add_signed(delta)
+                .ok_or_else(|| anyhow!("invalid chunk offset {}", off))?;
+        }
+    }
+    new.data_start = new_start;
+
+    let tmp = temp_path(dst)?;
+    let result = write_rewritten(src, &tmp, &old, &new, &head).and_then(|_| {
+        if verify {
+            verify_rewrite(src, &tmp, &old, &new)
+        } else {
+            Ok(())
+        }
+    });
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    replace(&tmp, dst)
+}
+
+fn temp_path(dst: &Path) -> Result<PathBuf> {
+    let parent = dst.parent().ok_or_else(|| anyhow!("invalid path"))?;
+    let name = dst
+        .file_name()
+        .ok_or_else(|| anyhow!("invalid file name"))?
+        .to_string_lossy();
+    Ok(parent.join(format!("._{}_hdr_{}", name, std::process::id())))
+}
+
+fn write_rewritten(
+    src: &Path,
+    tmp: &Path,
+    old: &ExrHeaders,
+    new: &ExrHeaders,
+    head: &[u8],
+) -> Result<()> {
+    let mut input = File::open(src)?;
+    input.seek(SeekFrom::Start(old.data_start))?;
+    let mut out = BufWriter::new(File::create(tmp)?);
+    out.write_all(head)?;
+    for table in &new.offsets {
+        for off in table {
+            out.write_all(&off.to_le_bytes())?;
+        }
+    }
+    io::copy(&mut input, &mut out)?;
+    out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    Ok(())
+}
+
+fn verify_rewrite(src: &Path, tmp: &Path, old: &ExrHeaders, new: &ExrHeaders) -> Result<()> {
+    let written = ExrHeaders::read_file(tmp).context("verify: rewritten header is unreadable")?;
+    if written.headers != new.headers || written.offsets != new.offsets {
+        bail!("verify: rewritten header differs from the edited one");
+    }
+    let (mut a, mut b) = (File::open(src)?, File::open(tmp)?);
+    a.seek(SeekFrom::Start(old.data_start))?;
+    b.seek(SeekFrom::Start(written.data_start))?;
+    let (mut a, mut b) = (BufReader::new(a), BufReader::new(b));
+    let (mut ba, mut bb) = (vec![0u8; 1 << 16], vec![0u8; 1 << 16]);
+    let mut pos = 0u64;
+    loop {
+        let n = read_full(&mut a, &mut ba)?;
+        let m = read_full(&mut b, &mut bb)?;
+        if n != m || ba[..n] != bb[..m] {
+            bail!("verify: chunk data differs after byte {}", pos);
+        }
+        if n == 0 {
+            return Ok(());
+        }
+        pos += n as u64;
+    }
+}
+
+fn read_full(r: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match r.read(&mut buf[n..])? {
+            0 => break,
+            k => n += k,
+        }
+    }
+    Ok(n)
+}
+
+fn replace(tmp: &Path, dst: &Path) -> Result<()> {
+    // Windowsでrename上書き不可の場合は削除→rename
+    if fs::rename(tmp, dst).is_err() {
+        let _ = fs::remove_file(dst);
+        fs::rename(tmp, dst)?;
+    }
+    Ok(())
+}
@@ -1,46 +1,1194 @@
-#[cfg(feature = "use_exr_crate")]
-use std::collections::HashMap;
-#[cfg(feature = "use_exr_crate")]
-use std::path::Path;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 #[cfg(feature = "use_exr_crate")]
-use anyhow::Result;
+use exr::meta::attribute::{AttributeValue, Chromaticities, IntegerBounds, Text};
 #[cfg(feature = "use_exr_crate")]
-use exr::meta::attribute::{AttributeValue, Text};
+use exr::meta::header::{ImageAttributes, LayerAttributes};
 #[cfg(feature = "use_exr_crate")]
 use exr::prelude::*;
 
+use crate::header::{kind_of_type, rewrite_headers, RawHeader};
 #[cfg(feature = "use_exr_crate")]
 use crate::save::save_any_image;
 
-/// Write the provided metadata back to the EXR file.
+/// Standard EXR attributes with a fixed type. The `exr` crate keeps them
+/// in dedicated `ImageAttributes`/`LayerAttributes` fields instead of the
+/// `other` map.
+pub const STANDARD_ATTRIBUTES: &[(&str, ValueKind)] = &[
+    ("owner", ValueKind::Text),
+    ("comments", ValueKind::Text),
+    ("capDate", ValueKind::Text),
+    ("software", ValueKind::Text),
+    ("view", ValueKind::Text),
+    ("renderingTransform", ValueKind::Text),
+    ("lookModTransform", ValueKind::Text),
+    ("wrapmodes", ValueKind::Text),
+    ("framesPerSecond", ValueKind::Rational),
+    ("timeCode", ValueKind::Timecode),
+    ("chromaticities", ValueKind::Chromaticities),
+    ("whiteLuminance", ValueKind::Float),
+    ("adoptedNeutral", ValueKind::V2f),
+    ("utcOffset", ValueKind::Float),
+    ("longitude", ValueKind::Float),
+    ("latitude", ValueKind::Float),
+    ("altitude", ValueKind::Float),
+    ("focus", ValueKind::Float),
+    ("expTime", ValueKind::Float),
+    ("aperture", ValueKind::Float),
+    ("isoSpeed", ValueKind::Float),
+    ("xDensity", ValueKind::Float),
+];
+
+/// Canonical spelling of an attribute name: standard names are matched
+/// case-insensitively (`FramesPerSecond` → `framesPerSecond`), everything
+/// else is kept as is.
+pub fn canonical_key(key: &str) -> String {
+    STANDARD_ATTRIBUTES
+        .iter()
+        .find(|(s, _)| s.eq_ignore_ascii_case(key))
+        .map(|(s, _)| s.to_string())
+        .unwrap_or_else(|| key.to_string())
+}
+
+/// The type a standard attribute must be written as.
+pub fn standard_kind(key: &str) -> Option<ValueKind> {
+    STANDARD_ATTRIBUTES
+        .iter()
+        .find(|(s, _)| s.eq_ignore_ascii_case(key))
+        .map(|(_, k)| *k)
+}
+
+/// `value` converted to the type `key` requires, if it is a standard
+/// attribute (`"24"` → `24/1` for `framesPerSecond`).
+pub fn coerce_for_key(key: &str, value: &MetadataValue) -> Result<MetadataValue> {
+    match standard_kind(key) {
+        Some(kind) => value
+            .coerce(kind)
+            .with_context(|| format!("{} must be {}", canonical_key(key), kind)),
+        None => Ok(value.clone()),
+    }
+}
+
+/// Attribute types a metadata value can be written as.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueKind {
+    #[default]
+    Text,
+    Int,
+    Float,
+    Double,
+    Rational,
+    Timecode,
+    V2f,
+    V2i,
+    Box2i,
+    Chromaticities,
+}
+
+impl ValueKind {
+    pub fn is_text(&self) -> bool {
+        *self == ValueKind::Text
+    }
+}
+
+impl fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ValueKind::Text => "text",
+            ValueKind::Int => "int",
+            ValueKind::Float => "float",
+            ValueKind::Double => "double",
+            ValueKind::Rational => "rational",
+            ValueKind::Timecode => "timecode",
+            ValueKind::V2f => "v2f",
+            ValueKind::V2i => "v2i",
+            ValueKind::Box2i => "box2i",
+            ValueKind::Chromaticities => "chromaticities",
+        })
+    }
+}
+
+impl std::str::FromStr for ValueKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        [
+            ValueKind::Text,
+            ValueKind::Int,
+            ValueKind::Float,
+            ValueKind::Double,
+            ValueKind::Rational,
+            ValueKind::Timecode,
+            ValueKind::V2f,
+            ValueKind::V2i,
+            ValueKind::Box2i,
+            ValueKind::Chromaticities,
+        ]
+        .into_iter()
+        .find(|k| k.to_string().eq_ignore_ascii_case(s.trim()))
+        .ok_or_else(|| anyhow!("unknown value type {:?}", s))
+    }
+}
+
+/// SMPTE timecode as stored in an EXR `timeCode` attribute.
+///
+/// Above 30 fps the frame field counts frame pairs and `field_phase` marks
+/// the second frame of a pair (see [`crate::timecode`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeCode {
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+    pub frames: u8,
+    pub drop_frame: bool,
+    pub field_phase: bool,
+}
+
+impl std::str::FromStr for TimeCode {
+    type Err = anyhow::Error;
+
+    /// `HH:MM:SS:FF`, or `HH:MM:SS;FF` for drop-frame; a `.1` suffix sets
+    /// the field phase bit.
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (s, field_phase) = match s.rsplit_once('.') {
+            Some((tc, "0")) => (tc, false),
+            Some((tc, "1")) => (tc, true),
+            _ => (s, false),
+        };
+        let drop_frame = s.contains(';');
+        let parts: Vec<&str> = s.split([':', ';']).collect();
+        if parts.len() != 4 {
+            bail!("invalid timecode {:?} (expected HH:MM:SS:FF)", s);
+        }
+        let mut n = [0u8; 4];
+        for (slot, p) in n.iter_mut().zip(&parts) {
+            *slot = p
+                .parse()
+                .with_context(|| format!("invalid timecode {:?}", s))?;
+        }
+        if n[0] > 23 || n[1] > 59 || n[2] > 59 || n[3] > 29 {
+            bail!("timecode out of range: {:?}", s);
+        }
+        Ok(TimeCode {
+            hours: n[0],
+            minutes: n[1],
+            seconds: n[2],
+            frames: n[3],
+            drop_frame,
+            field_phase,
+        })
+    }
+}
+
+impl fmt::Display for TimeCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sep = if self.drop_frame { ';' } else { ':' };
+        write!(
+            f,
+            "{:02}:{:02}:{:02}{}{:02}",
+            self.hours, self.minutes, self.seconds, sep, self.frames
+        )?;
+        if self.field_phase {
+            f.write_str(".1")?;
+        }
+        Ok(())
+    }
+}
+
+/// A typed attribute value.
 ///
+/// Vectors are written `x,y` and boxes `xmin,ymin,xmax,ymax` (inclusive,
+/// like the EXR data window); rationals are `num/den`, and decimal frame
+/// rates parse to their exact form (`23.976` → `24000/1001`).
+/// Chromaticities are `rx,ry,gx,gy,bx,by,wx,wy`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataValue {
+    Text(String),
+    Int(i32),
+    Float(f32),
+    Double(f64),
+    Rational(i32, u32),
+    TimeCode(TimeCode),
+    V2f([f32; 2]),
+    V2i([i32; 2]),
+    Box2i { min: [i32; 2], max: [i32; 2] },
+    Chromaticities([f32; 8]),
+}
+
+impl MetadataValue {
+    /// Parse `text` as a value of the given kind.
+    pub fn parse(kind: ValueKind, text: &str) -> Result<Self> {
+        fn numbers<T: std::str::FromStr>(text: &str, n: usize, kind: ValueKind) -> Result<Vec<T>> {
+            let v: Vec<T> = text
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|p| !p.is_empty())
+                .map(|p| p.parse::<T>())
+                .collect::<std::result::Result<_, _>>()
+                .map_err(|_| anyhow!("invalid {} value {:?}", kind, text))?;
+            if v.len() != n {
+                bail!("{} needs {} numbers, got {:?}", kind, n, text);
+            }
+            Ok(v)
+        }
+        let t = text.trim();
+        let invalid = || anyhow!("invalid {} value {:?}", kind, text);
+        Ok(match kind {
+            ValueKind::Text => MetadataValue::Text(text.to_string()),
+            ValueKind::Int => MetadataValue::Int(t.parse().map_err(|_| invalid())?),
+            ValueKind::Float => MetadataValue::Float(t.parse().map_err(|_| invalid())?),
+            ValueKind::Double => MetadataValue::Double(t.parse().map_err(|_| invalid())?),
+            ValueKind::Rational => match t.split_once('/') {
+                Some((n, d)) => {
+                    let n = n.trim().parse().map_err(|_| invalid())?;
+                    let d: u32 = d.trim().parse().map_err(|_| invalid())?;
+                    if d == 0 {
+                        bail!("rational with zero denominator: {:?}", text);
+                    }
+                    MetadataValue::Rational(n, d)
+                }
+                None => {
+                    let v: f64 = t.parse().map_err(|_| invalid())?;
+                    let (n, d) = rational_from_f64(v).ok_or_else(invalid)?;
+                    MetadataValue::Rational(n, d)
+                }
+            },
+            ValueKind::Timecode => MetadataValue::TimeCode(t.parse()?),
+            ValueKind::V2f => {
+                let v = numbers::<f32>(t, 2, kind)?;
+                MetadataValue::V2f([v[0], v[1]])
+            }
+            ValueKind::V2i => {
+                let v = numbers::<i32>(t, 2, kind)?;
+                MetadataValue::V2i([v[0], v[1]])
+            }
+            ValueKind::Box2i => {
+                let v = numbers::<i32>(t, 4, kind)?;
+                if v[2] < v[0] || v[3] < v[1] {
+                    bail!("box2i max is smaller than min: {:?}", text);
+                }
+                MetadataValue::Box2i {
+                    min: [v[0], v[1]],
+                    max: [v[2], v[3]],
+                }
+            }
+            ValueKind::Chromaticities => {
+                let v = numbers::<f32>(t, 8, kind)?;
+                MetadataValue::Chromaticities(v.try_into().expect("8 numbers"))
+            }
+        })
+    }
+
+    /// The same value as another type, going through its text form
+    /// (`Int(24)` → `Rational(24, 1)`).
+    pub fn coerce(&self, kind: ValueKind) -> Result<MetadataValue> {
+        if self.kind() == kind {
+            Ok(self.clone())
+        } else {
+            Self::parse(kind, &self.to_string())
+        }
+    }
+
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            MetadataValue::Text(_) => ValueKind::Text,
+            MetadataValue::Int(_) => ValueKind::Int,
+            MetadataValue::Float(_) => ValueKind::Float,
+            MetadataValue::Double(_) => ValueKind::Double,
+            MetadataValue::Rational(..) => ValueKind::Rational,
+            MetadataValue::TimeCode(_) => ValueKind::Timecode,
+            MetadataValue::V2f(_) => ValueKind::V2f,
+            MetadataValue::V2i(_) => ValueKind::V2i,
+            MetadataValue::Box2i { .. } => ValueKind::Box2i,
+            MetadataValue::Chromaticities(_) => ValueKind::Chromaticities,
+        }
+    }
+}
+
+/// Exact rational for a decimal rate: integers, NTSC `N*1000/1001` rates,
+/// otherwise thousandths.
+fn rational_from_f64(v: f64) -> Option<(i32, u32)> {
+    if !v.is_finite() || v.abs() > i32::MAX as f64 / 1000.0 {
+        return None;
+    }
+    if (v - v.round()).abs() < 1e-6 {
+        return Some((v.round() as i32, 1));
+    }
+    let ntsc = (v * 1.001).round();
+    if ntsc != 0.0 && (ntsc / 1.001 - v).abs() < 0.005 {
+        return Some((ntsc as i32 * 1000, 1001));
+    }
+    let (mut n, mut d) = ((v * 1000.0).round() as i64, 1000i64);
+    let (mut a, mut b) = (n.abs(), d);
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    if a > 1 {
+        n /= a;
+        d /= a;
+    }
+    Some((n as i32, d as u32))
+}
+
+impl From<&str> for MetadataValue {
+    fn from(s: &str) -> Self {
+        MetadataValue::Text(s.to_string())
+    }
+}
+
+impl From<String> for MetadataValue {
+    fn from(s: String) -> Self {
+        MetadataValue::Text(s)
+    }
+}
+
+impl fmt::Display for MetadataValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataValue::Text(s) => f.write_str(s),
+            MetadataValue::Int(v) => write!(f, "{}", v),
+            MetadataValue::Float(v) => write!(f, "{}", v),
+            MetadataValue::Double(v) => write!(f, "{}", v),
+            MetadataValue::Rational(n, d) => write!(f, "{}/{}", n, d),
+            MetadataValue::TimeCode(tc) => write!(f, "{}", tc),
+            MetadataValue::V2f([x, y]) => write!(f, "{},{}", x, y),
+            MetadataValue::V2i([x, y]) => write!(f, "{},{}", x, y),
+            MetadataValue::Box2i { min, max } => {
+                write!(f, "{},{},{},{}", min[0], min[1], max[0], max[1])
+            }
+            MetadataValue::Chromaticities(c) => {
+                let parts: Vec<String> = c.iter().map(|v| v.to_string()).collect();
+                f.write_str(&parts.join(","))
+            }
+        }
+    }
+}
+
+impl Serialize for MetadataValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "use_exr_crate")]
+impl MetadataValue {
+    pub fn from_attribute(value: &AttributeValue) -> Self {
+        match value {
+            AttributeValue::Text(t) => MetadataValue::Text(t.to_string()),
+            AttributeValue::F32(v) => MetadataValue::Float(*v),
+            AttributeValue::F64(v) => MetadataValue::Double(*v),
+            AttributeValue::I32(v) => MetadataValue::Int(*v),
+            AttributeValue::Rational((n, d)) => MetadataValue::Rational(*n, *d),
+            AttributeValue::TimeCode(tc) => MetadataValue::TimeCode(TimeCode {
+                hours: tc.hours,
+                minutes: tc.minutes,
+                seconds: tc.seconds,
+                frames: tc.frame,
+                drop_frame: tc.drop_frame,
+                field_phase: tc.field_phase,
+            }),
+            AttributeValue::FloatVec2(v) => MetadataValue::V2f([v.0, v.1]),
+            AttributeValue::IntVec2(v) => MetadataValue::V2i([v.0, v.1]),
+            AttributeValue::IntegerBounds(b) => MetadataValue::Box2i {
+                min: [b.position.0, b.position.1],
+                max: [
+                    b.position.0 + b.size.0 as i32 - 1,
+                    b.position.1 + b.size.1 as i32 - 1,
+                ],
+            },
+            AttributeValue::Chromaticities(c) => MetadataValue::Chromaticities([
+                c.red.0, c.red.1, c.green.0, c.green.1, c.blue.0, c.blue.1, c.white.0, c.white.1,
+            ]),
+            AttributeValue::TextVector(v) => MetadataValue::Text(
+                v.iter()
+                    .map(|t| t.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            other => MetadataValue::Text(format!("{:?}", other)),
+        }
+    }
+
+    pub fn to_attribute(&self) -> AttributeValue {
+        match self {
+            MetadataValue::Text(s) => AttributeValue::Text(Text::from(s.as_str())),
+            MetadataValue::Int(v) => AttributeValue::I32(*v),
+            MetadataValue::Float(v) => AttributeValue::F32(*v),
+            MetadataValue::Double(v) => AttributeValue::F64(*v),
+            MetadataValue::Rational(n, d) => AttributeValue::Rational((*n, *d)),
+            MetadataValue::TimeCode(tc) => {
+                AttributeValue::TimeCode(exr::meta::attribute::TimeCode {
+                    hours: tc.hours,
+                    minutes: tc.minutes,
+                    seconds: tc.seconds,
+                    frame: tc.frames,
+                    drop_frame: tc.drop_frame,
+                    color_frame: false,
+                    field_phase: tc.field_phase,
+                    binary_group_flags: [false; 3],
+                    binary_groups: [0; 8],
+                })
+            }
+            MetadataValue::V2f([x, y]) => AttributeValue::FloatVec2(Vec2(*x, *y)),
+            MetadataValue::V2i([x, y]) => AttributeValue::IntVec2(Vec2(*x, *y)),
+            MetadataValue::Box2i { min, max } => AttributeValue::IntegerBounds(IntegerBounds {
+                position: Vec2(min[0], min[1]),
+                size: Vec2(
+                    (max[0] - min[0] + 1) as usize,
+                    (max[1] - min[1] + 1) as usize,
+                ),
+            }),
+            MetadataValue::Chromaticities(c) => AttributeValue::Chromaticities(chromaticities(c)),
+        }
+    }
+}
+
+#[cfg(feature = "use_exr_crate")]
+fn chromaticities(c: &[f32; 8]) -> Chromaticities {
+    Chromaticities {
+        red: Vec2(c[0], c[1]),
+        green: Vec2(c[2], c[3]),
+        blue: Vec2(c[4], c[5]),
+        white: Vec2(c[6], c[7]),
+    }
+}
+
+/// Type of an attribute as far as [`MetadataValue`] can represent it.
+#[cfg(feature = "use_exr_crate")]
+fn attribute_kind(value: &AttributeValue) -> Option<ValueKind> {
+    Some(match value {
+        AttributeValue::Text(_) => ValueKind::Text,
+        AttributeValue::I32(_) => ValueKind::Int,
+        AttributeValue::F32(_) => ValueKind::Float,
+        AttributeValue::F64(_) => ValueKind::Double,
+        AttributeValue::Rational(_) => ValueKind::Rational,
+        AttributeValue::TimeCode(_) => ValueKind::Timecode,
+        AttributeValue::FloatVec2(_) => ValueKind::V2f,
+        AttributeValue::IntVec2(_) => ValueKind::V2i,
+        AttributeValue::IntegerBounds(_) => ValueKind::Box2i,
+        AttributeValue::Chromaticities(_) => ValueKind::Chromaticities,
+        _ => return None,
+    })
+}
+
+/// What rules and diffs need to know about a file: its typed attributes,
+/// data window size and layer/part names.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderInfo {
+    pub attributes: BTreeMap<String, MetadataValue>,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    /// Part names plus channel-name prefixes (`diffuse` for `diffuse.R`).
+    pub layers: Vec<String>,
+}
+
+/// Dedicated `exr` header field backing a standard attribute.
+#[cfg(feature = "use_exr_crate")]
+enum StandardSlot<'a> {
+    Text(&'a mut Option<Text>),
+    Float(&'a mut Option<f32>),
+    Rational(&'a mut Option<(i32, u32)>),
+    TimeCode(&'a mut Option<exr::meta::attribute::TimeCode>),
+    Chromaticities(&'a mut Option<Chromaticities>),
+    V2f(&'a mut Option<Vec2<f32>>),
+}
+
+#[cfg(feature = "use_exr_crate")]
+impl StandardSlot<'_> {
+    fn new<'a>(
+        image: &'a mut ImageAttributes,
+        layer: &'a mut LayerAttributes,
+        key: &str,
+    ) -> Option<StandardSlot<'a>> {
+        use StandardSlot as S;
+        Some(match key {
+            "owner" => S::Text(&mut layer.owner),
+            "comments" => S::Text(&mut layer.comments),
+            "capDate" => S::Text(&mut layer.capture_date),
+            "software" => S::Text(&mut layer.software_name),
+            "view" => S::Text(&mut layer.view_name),
+            "renderingTransform" => S::Text(&mut layer.rendering_transform_name),
+            "lookModTransform" => S::Text(&mut layer.look_modification_transform_name),
+            "wrapmodes" => S::Text(&mut layer.wrap_mode_name),
+            "framesPerSecond" => S::Rational(&mut layer.frames_per_second),
+            "timeCode" => S::TimeCode(&mut image.time_code),
+            "chromaticities" => S::Chromaticities(&mut image.chromaticities),
+            "whiteLuminance" => S::Float(&mut layer.white_luminance),
+            "adoptedNeutral" => S::V2f(&mut layer.adopted_neutral),
+            "utcOffset" => S::Float(&mut layer.utc_offset),
+            "longitude" => S::Float(&mut layer.longitude),
+            "latitude" => S::Float(&mut layer.latitude),
+            "altitude" => S::Float(&mut layer.altitude),
+            "focus" => S::Float(&mut layer.focus),
+            "expTime" => S::Float(&mut layer.exposure),
+            "aperture" => S::Float(&mut layer.aperture),
+            "isoSpeed" => S::Float(&mut layer.iso_speed),
+            "xDensity" => S::Float(&mut layer.horizontal_density),
+            _ => return None,
+        })
+    }
+
+    fn get(&self) -> Option<MetadataValue> {
+        match self {
+            StandardSlot::Text(f) => f.as_ref().map(|t| MetadataValue::Text(t.to_string())),
+            StandardSlot::Float(f) => f.map(MetadataValue::Float),
+            StandardSlot::Rational(f) => f.map(|(n, d)| MetadataValue::Rational(n, d)),
+            StandardSlot::TimeCode(f) => {
+                f.map(|tc| MetadataValue::from_attribute(&AttributeValue::TimeCode(tc)))
+            }
+            StandardSlot::Chromaticities(f) => {
+                f.map(|c| MetadataValue::from_attribute(&AttributeValue::Chromaticities(c)))
+            }
+            StandardSlot::V2f(f) => f.map(|v| MetadataValue::V2f([v.0, v.1])),
+        }
+    }
+
+    /// Store `value`, which must already have the slot's type, or clear it.
+    fn set(self, value: Option<&MetadataValue>) {
+        match (self, value.map(MetadataValue::to_attribute)) {
+            (StandardSlot::Text(f), v) => {
+                *f = v.and_then(|v| match v {
+                    AttributeValue::Text(t) => Some(t),
+                    _ => None,
+                })
+            }
+            (StandardSlot::Float(f), v) => {
+                *f = v.and_then(|v| match v {
+                    AttributeValue::F32(x) => Some(x),
+                    _ => None,
+                })
+            }
+            (StandardSlot::Rational(f), v) => {
+                *f = v.and_then(|v| match v {
+                    AttributeValue::Rational(r) => Some(r),
+                    _ => None,
+                })
+            }
+            (StandardSlot::TimeCode(f), v) => {
+                *f = v.and_then(|v| match v {
+                    AttributeValue::TimeCode(tc) => Some(tc),
+                    _ => None,
+                })
+            }
+            (StandardSlot::Chromaticities(f), v) => {
+                *f = v.and_then(|v| match v {
+                    AttributeValue::Chromaticities(c) => Some(c),
+                    _ => None,
+                })
+            }
+            (StandardSlot::V2f(f), v) => {
+                *f = v.and_then(|v| match v {
+                    AttributeValue::FloatVec2(x) => Some(x),
+                    _ => None,
+                })
+            }
+        }
+    }
+}
+
+/// Render an attribute value as a string for display, diffs and rule input.
+#[cfg(feature = "use_exr_crate")]
+pub fn attribute_to_string(value: &AttributeValue) -> String {
+    MetadataValue::from_attribute(value).to_string()
+}
+
+/// Read the custom and standard text attributes of the first header
+/// (shared attributes included), keyed by canonical name.
+#[cfg(feature = "use_exr_crate")]
+pub fn read_attributes(path: &Path) -> Result<BTreeMap<String, MetadataValue>> {
+    Ok(read_header_info(path)?.attributes)
+}
+
+#[cfg(not(feature = "use_exr_crate"))]
+pub fn read_attributes(_path: &Path) -> Result<BTreeMap<String, MetadataValue>> {
+    Err(anyhow!("feature `use_exr_crate` is not enabled"))
+}
+
+/// Attributes of the first header together with its size and the layer
+/// names of every part.
+#[cfg(feature = "use_exr_crate")]
+pub fn read_header_info(path: &Path) -> Result<HeaderInfo> {
+    use exr::meta::MetaData;
+    let meta = MetaData::read_from_file(path, false)?;
+    let mut info = HeaderInfo::default();
+    for header in &meta.headers {
+        if let Some(name) = &header.own_attributes.layer_name {
+            info.layers.push(name.to_string());
+        }
+        for ch in header.channels.list.iter() {
+            let name = ch.name.to_string();
+            if let Some((layer, _)) = name.rsplit_once('.') {
+                if !info.layers.iter().any(|l| l == layer) {
+                    info.layers.push(layer.to_string());
+                }
+            }
+        }
+    }
+    let Some(header) = meta.headers.into_iter().next() else {
+        return Ok(info);
+    };
+    info.width = Some(header.layer_size.0);
+    info.height = Some(header.layer_size.1);
+    let out = &mut info.attributes;
+    for (k, v) in &header.shared_attributes.other {
+        out.insert(k.to_string(), MetadataValue::from_attribute(v));
+    }
+    for (k, v) in &header.own_attributes.other {
+        out.insert(k.to_string(), MetadataValue::from_attribute(v));
+    }
+    let (mut shared, mut own) = (header.shared_attributes, header.own_attributes);
+    for (key, _) in STANDARD_ATTRIBUTES {
+        if let Some(v) = StandardSlot::new(&mut shared, &mut own, key).and_then(|s| s.get()) {
+            out.insert(key.to_string(), v);
+        }
+    }
+    Ok(info)
+}
+
+#[cfg(not(feature = "use_exr_crate"))]
+pub fn read_header_info(_path: &Path) -> Result<HeaderInfo> {
+    Err(anyhow!("feature `use_exr_crate` is not enabled"))
+}
+
+/// Every attribute of one part, including the structural ones
+/// (`channels`, `compression`, `dataWindow`, ...).
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PartAttributes {
+    /// The part's `name`, or `#<index>` for unnamed parts.
+    pub name: String,
+    pub attributes: BTreeMap<String, MetadataValue>,
+}
+
+/// All attributes of every part, read from the headers only (no feature
+/// needed). Types without a typed value are described as text, see
+/// [`crate::header::describe_value`].
+pub fn read_part_attributes(path: &Path) -> Result<Vec<PartAttributes>> {
+    let exr = crate::header::ExrHeaders::read_file(path)?;
+    Ok(exr
+        .headers
+        .iter()
+        .enumerate()
+        .map(|(i, h)| PartAttributes {
+            name: part_label(h.part_name(), i),
+            attributes: h
+                .attributes
+                .iter()
+                .map(|a| {
+                    (
+                        a.name.clone(),
+                        crate::header::describe_value(&a.type_name, &a.data),
+                    )
+                })
+                .collect(),
+        })
+        .collect())
+}
+
+/// Options for [`edit_metadata`].
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// Write to this path instead of overwriting the source file.
+    pub out: Option<PathBuf>,
+    /// Allow replacing an existing custom attribute with a value of a
+    /// different type.
+    pub force_type: bool,
+    /// Read the rewritten file back and compare its chunk bytes with the
+    /// source before replacing anything.
+    pub verify: bool,
+    /// Decode and re-encode the whole image instead of rewriting only the
+    /// header (needs `use_exr_crate`).
+    pub reencode: bool,
+    /// Which part(s) of a multi-part file receive the attributes.
+    pub part: PartTarget,
+}
+
+/// Standard attributes every part of a multi-part file must agree on.
+const SHARED_ATTRIBUTES: &[&str] = &["timeCode", "chromaticities"];
+
+/// Where [`edit_metadata`] puts attributes in a multi-part file.
+///
+/// `timeCode` and `chromaticities` always go to every part, whatever the
+/// target, because the parts must agree on them.
+///
+/// Serialized in its command-line form: `first`, `all`, `shared` or a part
+/// name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum PartTarget {
+    /// The first part (the only one in single-part files).
+    #[default]
+    First,
+    /// Every part, as a per-part attribute.
+    All,
+    /// The shared header all parts have in common. Per-part standard
+    /// attributes such as `framesPerSecond` cannot go there.
+    Shared,
+    /// The part with this `name` attribute.
+    Name(String),
+}
+
+impl std::str::FromStr for PartTarget {
+    type Err = anyhow::Error;
+
+    /// `first`, `all`, `shared`, or a part name (`name:first` for a part
+    /// literally called `first`).
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s.trim() {
+            "" => bail!("empty part name"),
+            "first" => PartTarget::First,
+            "all" => PartTarget::All,
+            "shared" => PartTarget::Shared,
+            other => PartTarget::Name(other.strip_prefix("name:").unwrap_or(other).to_string()),
+        })
+    }
+}
+
+impl TryFrom<String> for PartTarget {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<PartTarget> for String {
+    fn from(t: PartTarget) -> Self {
+        t.to_string()
+    }
+}
+
+impl fmt::Display for PartTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartTarget::First => f.write_str("first"),
+            PartTarget::All => f.write_str("all"),
+            PartTarget::Shared => f.write_str("shared"),
+            PartTarget::Name(n) => write!(f, "name:{}", n),
+        }
+    }
+}
+
+/// Where an attribute ended up after [`edit_metadata`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Placement {
+    pub key: String,
+    /// The attribute was removed rather than written.
+    pub removed: bool,
+    /// It lives in the shared header, i.e. identically in every part.
+    pub shared: bool,
+    /// Names of the parts that were changed (`#<index>` for unnamed parts).
+    pub parts: Vec<String>,
+}
+
+impl fmt::Display for Placement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (sign, arrow) = if self.removed {
+            ('-', "<-")
+        } else {
+            ('+', "->")
+        };
+        write!(f, "{} {} {} ", sign, self.key, arrow)?;
+        if self.shared {
+            f.write_str("shared ")?;
+        }
+        write!(f, "[{}]", self.parts.join(", "))
+    }
+}
+
+/// Label of part `index` in reports.
+fn part_label(name: Option<String>, index: usize) -> String {
+    name.unwrap_or_else(|| format!("#{}", index))
+}
+
+/// Indices of the parts `target` selects, given each part's name.
+pub(crate) fn target_parts(target: &PartTarget, names: &[Option<String>]) -> Result<Vec<usize>> {
+    Ok(match target {
+        PartTarget::First => vec![0],
+        PartTarget::All | PartTarget::Shared => (0..names.len()).collect(),
+        PartTarget::Name(name) => match names.iter().position(|n| n.as_deref() == Some(name)) {
+            Some(i) => vec![i],
+            None => {
+                let known: Vec<_> = names.iter().flatten().map(String::as_str).collect();
+                bail!(
+                    "no part named '{}' (parts: {})",
+                    name,
+                    if known.is_empty() {
+                        "unnamed".to_string()
+                    } else {
+                        known.join(", ")
+                    }
+                );
+            }
+        },
+    })
+}
+
+/// Parts that receive `key` and whether it is stored as shared.
+fn key_parts(
+    key: &str,
+    target: &PartTarget,
+    selected: &[usize],
+    parts: usize,
+) -> Result<(Vec<usize>, bool)> {
+    if SHARED_ATTRIBUTES.contains(&key) {
+        return Ok(((0..parts).collect(), true));
+    }
+    if *target == PartTarget::Shared {
+        if standard_kind(key).is_some() {
+            bail!(
+                "{} is a per-part attribute and cannot go in the shared header; use part 'all'",
+                key
+            );
+        }
+        return Ok((selected.to_vec(), true));
+    }
+    Ok((selected.to_vec(), false))
+}
+
+fn check_type_change(
+    key: &str,
+    old: Option<ValueKind>,
+    new: ValueKind,
+    opts: &WriteOptions,
+) -> Result<()> {
+    if old != Some(new) && !opts.force_type {
+        bail!(
+            "{} is stored as {}; refusing to write it as {} without force",
+            key,
+            old.map_or("an unsupported type".to_string(), |k| k.to_string()),
+            new
+        );
+    }
+    Ok(())
+}
+
+/// Write the provided metadata back to the EXR file, rewriting only the
+/// header.
+///
+/// Standard attributes are written with their proper type (see
+/// [`STANDARD_ATTRIBUTES`]); changing the type of an existing custom
+/// attribute is refused, use [`edit_metadata`] with
+/// [`WriteOptions::force_type`] for that.
 /// If `out` is `Some`, the image is written to that path
 /// instead of overwriting the source file.
-#[cfg(feature = "use_exr_crate")]
 pub fn write_metadata(
     src: &Path,
-    metadata: &HashMap<String, String>,
+    metadata: &HashMap<String, MetadataValue>,
     out: Option<&Path>,
 ) -> Result<()> {
+    let opts = WriteOptions {
+        out: out.map(Path::to_path_buf),
+        ..WriteOptions::default()
+    };
+    edit_metadata(src, metadata, &[], &opts).map(|_| ())
+}
+
+/// Set and remove attributes in one rewrite and report where each one
+/// ended up.
+///
+/// Standard attributes are coerced to their type and stale custom copies
+/// with another spelling (e.g. a text `FramesPerSecond`) are dropped.
+/// Attributes go to the part(s) chosen by [`WriteOptions::part`], except
+/// `timeCode` and `chromaticities`, which every part carries.
+///
+/// Only the header is rewritten: compressed chunks are copied verbatim
+/// (see [`crate::header::rewrite_headers`]) unless
+/// [`WriteOptions::reencode`] is set.
+pub fn edit_metadata(
+    src: &Path,
+    set: &HashMap<String, MetadataValue>,
+    unset: &[String],
+    opts: &WriteOptions,
+) -> Result<Vec<Placement>> {
+    if opts.reencode {
+        #[cfg(feature = "use_exr_crate")]
+        return reencode_metadata(src, set, unset, opts);
+        #[cfg(not(feature = "use_exr_crate"))]
+        bail!("re-encoding needs feature `use_exr_crate`");
+    }
+    let dst = opts.out.as_deref().unwrap_or(src);
+    let mut placements = Vec::new();
+    rewrite_headers(src, dst, opts.verify, |headers| {
+        apply_edit(headers, &opts.part, set, unset, opts, &mut placements)
+    })?;
+    Ok(placements)
+}
+
+/// Attributes to set and remove in the part(s) `part` selects, for
+/// [`edit_metadata_parts`].
+#[derive(Debug, Clone, Default)]
+pub struct PartEdit {
+    pub part: PartTarget,
+    pub set: HashMap<String, MetadataValue>,
+    pub unset: Vec<String>,
+}
+
+/// Like [`edit_metadata`], with a separate edit per part target, all in a
+/// single header rewrite. [`WriteOptions::part`] is ignored and
+/// [`WriteOptions::reencode`] is not supported.
+pub fn edit_metadata_parts(
+    src: &Path,
+    edits: &[PartEdit],
+    opts: &WriteOptions,
+) -> Result<Vec<Placement>> {
+    if opts.reencode {
+        bail!("per-part edits only rewrite the header; re-encoding is not supported");
+    }
+    let dst = opts.out.as_deref().unwrap_or(src);
+    let mut placements = Vec::new();
+    rewrite_headers(src, dst, opts.verify, |headers| {
+        for e in edits {
+            apply_edit(headers, &e.part, &e.set, &e.unset, opts, &mut placements)?;
+        }
+        Ok(())
+    })?;
+    Ok(placements)
+}
+
+/// Copy `path` to `<name>.exr.bak` when `backup` is set, then run `write`.
+///
+/// Header rewrites only replace `path` once they succeed, so when `write`
+/// fails the original is still in place and the copy is removed again.
+/// After a successful write the copy is kept and its path returned.
+pub fn write_with_backup<T>(
+    path: &Path,
+    backup: bool,
+    write: impl FnOnce() -> Result<T>,
+) -> Result<(T, Option<PathBuf>)> {
+    let bak = if backup {
+        let bak = path.with_extension("exr.bak");
+        fs::copy(path, &bak).with_context(|| format!("backup failed: {}", bak.display()))?;
+        Some(bak)
+    } else {
+        None
+    };
+    match write() {
+        Ok(v) => Ok((v, bak)),
+        Err(e) => {
+            if let Some(bak) = &bak {
+                let _ = fs::remove_file(bak);
+            }
+            Err(e)
+        }
+    }
+}
+
+/// One [`edit_metadata`] edit applied to parsed headers.
+fn apply_edit(
+    headers: &mut [RawHeader],
+    target: &PartTarget,
+    set: &HashMap<String, MetadataValue>,
+    unset: &[String],
+    opts: &WriteOptions,
+    placements: &mut Vec<Placement>,
+) -> Result<()> {
+    if headers.is_empty() {
+        bail!("file has no headers");
+    }
+    let names: Vec<_> = headers.iter().map(|h| h.part_name()).collect();
+    let selected = target_parts(target, &names)?;
+    let label = |i: usize| part_label(names[i].clone(), i);
+    let mut unset: Vec<_> = unset.iter().map(|k| canonical_key(k)).collect();
+    unset.sort();
+    unset.dedup();
+    for k in unset {
+        let (parts, shared) = key_parts(&k, target, &selected, headers.len())?;
+        let removed: Vec<_> = parts
+            .into_iter()
+            .filter(|&i| headers[i].remove(&k))
+            .map(label)
+            .collect();
+        if !removed.is_empty() {
+            placements.push(Placement {
+                key: k,
+                removed: true,
+                shared,
+                parts: removed,
+            });
+        }
+    }
+    let mut set: Vec<_> = set.iter().map(|(k, v)| (canonical_key(k), v)).collect();
+    set.sort_by(|a, b| a.0.cmp(&b.0));
+    for (k, v) in set {
+        let (parts, shared) = key_parts(&k, target, &selected, headers.len())?;
+        let v = if standard_kind(&k).is_some() {
+            let v = coerce_for_key(&k, v)?;
+            for &i in &parts {
+                headers[i]
+                    .attributes
+                    .retain(|a| a.name == k || !a.name.eq_ignore_ascii_case(&k));
+            }
+            v
+        } else {
+            for &i in &parts {
+                if let Some(old) = headers[i].get(&k) {
+                    check_type_change(&k, kind_of_type(&old.type_name), v.kind(), opts)?;
+                }
+            }
+            v.clone()
+        };
+        for &i in &parts {
+            headers[i].set(&k, &v);
+        }
+        placements.push(Placement {
+            key: k,
+            removed: false,
+            shared,
+            parts: parts.into_iter().map(label).collect(),
+        });
+    }
+    Ok(())
+}
+
+/// [`edit_metadata`] through a full decode and re-encode of the image.
+#[cfg(feature = "use_exr_crate")]
+fn reencode_metadata(
+    src: &Path,
+    set: &HashMap<String, MetadataValue>,
+    unset: &[String],
+    opts: &WriteOptions,
+) -> Result<Vec<Placement>> {
     let mut image = read_all_data_from_file(src)?;
+    let names: Vec<_> = image
+        .layer_data
+        .iter()
+        .map(|l| l.attributes.layer_name.as_ref().map(|n| n.to_string()))
+        .collect();
+    let label = |i: usize| part_label(names[i].clone(), i);
+    let mut placements = Vec::new();
+
+    // no layer to hold layer-level fields: everything is top level
+    if image.layer_data.is_empty() {
+        for k in unset {
+            let k = canonical_key(k);
+            if image
+                .attributes
+                .other
+                .remove(&Text::from(k.as_str()))
+                .is_some()
+            {
+                placements.push(Placement {
+                    key: k,
+                    removed: true,
+                    shared: true,
+                    parts: Vec::new(),
+                });
+            }
+        }
+        for (k, v) in set {
+            let k = canonical_key(k);
+            let v = coerce_for_key(&k, v)?;
+            image
+                .attributes
+                .other
+                .insert(Text::from(k.as_str()), v.to_attribute());
+            placements.push(Placement {
+                key: k,
+                removed: false,
+                shared: true,
+                parts: Vec::new(),
+            });
+        }
+        let target = opts.out.as_deref().unwrap_or(src);
+        save_any_image(&image, target)?;
+        return Ok(placements);
+    }
+    let selected = target_parts(&opts.part, &names)?;
 
-    for (k, v) in metadata {
+    for k in unset {
+        let k = canonical_key(k);
         let key = Text::from(k.as_str());
-        let val = Text::from(v.as_str());
-        let attr = AttributeValue::Text(val.clone());
+        let (parts, shared) = key_parts(&k, &opts.part, &selected, names.len())?;
+        let mut removed = Vec::new();
+        if shared && image.attributes.other.remove(&key).is_some() {
+            removed = parts.clone();
+        }
+        for &i in &parts {
+            let layer = &mut image.layer_data[i];
+            let mut hit = layer.attributes.other.remove(&key).is_some();
+            if let Some(slot) = StandardSlot::new(&mut image.attributes, &mut layer.attributes, &k)
+            {
+                hit |= slot.get().is_some();
+                slot.set(None);
+            }
+            if hit && !removed.contains(&i) {
+                removed.push(i);
+            }
+        }
+        if !removed.is_empty() {
+            removed.sort();
+            placements.push(Placement {
+                key: k,
+                removed: true,
+                shared,
+                parts: removed.into_iter().map(label).collect(),
+            });
+        }
+    }
 
-        // 同名属性の重複を避けるため、まずトップレベルから削除
-        image.attributes.other.remove(&key);
+    let mut set: Vec<_> = set.iter().map(|(k, v)| (canonical_key(k), v)).collect();
+    set.sort_by(|a, b| a.0.cmp(&b.0));
+    for (k, v) in set {
+        let key = Text::from(k.as_str());
+        let (parts, shared) = key_parts(&k, &opts.part, &selected, names.len())?;
 
-        // レイヤー0があればそちらに集約。なければトップレベルに設定。
-        if let Some(layer) = image.layer_data.get_mut(0) {
-            layer.attributes.other.insert(key, attr);
+        if standard_kind(&k).is_some() {
+            let v = coerce_for_key(&k, v)?;
+            let stale = |name: &Text| name.to_string().eq_ignore_ascii_case(&k);
+            image.attributes.other.retain(|name, _| !stale(name));
+            for &i in &parts {
+                let layer = &mut image.layer_data[i];
+                layer.attributes.other.retain(|name, _| !stale(name));
+                if let Some(slot) =
+                    StandardSlot::new(&mut image.attributes, &mut layer.attributes, &k)
+                {
+                    slot.set(Some(&v));
+                }
+            }
         } else {
-            image.attributes.other.insert(key, AttributeValue::Text(val));
+            for &i in &parts {
+                let existing = image.layer_data[i]
+                    .attributes
+                    .other
+                    .get(&key)
+                    .or_else(|| image.attributes.other.get(&key));
+                if let Some(old) = existing {
+                    check_type_change(&k, attribute_kind(old), v.kind(), opts)?;
+                }
+            }
+            let attr = v.to_attribute();
+            if shared {
+                // the shared header is written into every part; drop per-part copies
+                for layer in &mut image.layer_data {
+                    layer.attributes.other.remove(&key);
+                }
+                image.attributes.other.insert(key, attr);
+            } else {
+                // 同名属性の重複を避けるため、まずトップレベルから削除
+                image.attributes.other.remove(&key);
+                for &i in &parts {
+                    image.layer_data[i]
+                        .attributes
+                        .other
+                        .insert(key.clone(), attr.clone());
+                }
+            }
         }
+        placements.push(Placement {
+            key: k,
+            removed: false,
+            shared,
+            parts: parts.into_iter().map(label).collect(),
+        });
     }
 
-    let target = out.unwrap_or(src);
-    save_any_image(&image, target)
+    let target = opts.out.as_deref().unwrap_or(src);
+    save_any_image(&image, target)?;
+    Ok(placements)
 }
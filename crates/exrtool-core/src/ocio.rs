@@ -1,17 +1,48 @@
-use std::ffi::CString;
-use std::path::Path;
 use anyhow::{anyhow, Result};
+use rayon::prelude::*;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::path::Path;
+use std::ptr;
 
 mod ffi {
-    #![allow(non_camel_case_types, non_snake_case, non_upper_case_globals, dead_code)]
+    #![allow(
+        non_camel_case_types,
+        non_snake_case,
+        non_upper_case_globals,
+        dead_code
+    )]
     include!(concat!(env!("OUT_DIR"), "/ocio_bindings.rs"));
 }
 
+/// Pixels per packed-image call; chunks are processed in parallel.
+const APPLY_CHUNK_PIXELS: usize = 64 * 1024;
+
+unsafe fn owned_str(p: *const c_char) -> Option<String> {
+    if p.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(p).to_string_lossy().into_owned())
+    }
+}
+
+fn opt_cstring(s: Option<&str>) -> Result<Option<CString>> {
+    Ok(match s {
+        Some(s) => Some(CString::new(s)?),
+        None => None,
+    })
+}
+
+fn opt_ptr(s: &Option<CString>) -> *const c_char {
+    s.as_ref().map_or(ptr::null(), |c| c.as_ptr())
+}
+
 pub struct Config {
     ptr: ffi::OcioConfig,
 }
 
 impl Config {
+    /// Load a config file, or a built-in config URI such as `ocio://default`.
     pub fn from_file(path: &Path) -> Result<Self> {
         let cpath = CString::new(path.to_string_lossy().as_bytes())?;
         let ptr = unsafe { ffi::ocio_config_from_file(cpath.as_ptr()) };
@@ -23,11 +54,37 @@ impl Config {
     }
 
     pub fn processor(&self, src: &str, dst: &str) -> Result<Processor> {
+        self.processor_ex(None, src, dst, None)
+    }
+
+    /// Processor from `src` to `dst` with an optional context and look list
+    /// (e.g. `"+grade, -neutral"`, applied through a LookTransform).
+    pub fn processor_ex(
+        &self,
+        ctx: Option<&Context>,
+        src: &str,
+        dst: &str,
+        looks: Option<&str>,
+    ) -> Result<Processor> {
         let csrc = CString::new(src)?;
         let cdst = CString::new(dst)?;
-        let p = unsafe { ffi::ocio_config_get_processor(self.ptr, csrc.as_ptr(), cdst.as_ptr()) };
+        let clooks = opt_cstring(looks)?;
+        let cctx = ctx.map_or(ptr::null_mut(), |c| c.ptr);
+        let p = unsafe {
+            ffi::ocio_config_get_processor_ex(
+                self.ptr,
+                cctx,
+                csrc.as_ptr(),
+                cdst.as_ptr(),
+                opt_ptr(&clooks),
+            )
+        };
         if p.is_null() {
-            Err(anyhow!("failed to create OCIO processor"))
+            Err(anyhow!(
+                "failed to create OCIO processor ({} -> {})",
+                src,
+                dst
+            ))
         } else {
             Ok(Processor { ptr: p })
         }
@@ -37,12 +94,7 @@ impl Config {
         let n = unsafe { ffi::ocio_config_num_displays(self.ptr) } as usize;
         (0..n)
             .filter_map(|i| unsafe {
-                let p = ffi::ocio_config_get_display_name(self.ptr, i as i32);
-                if p.is_null() {
-                    None
-                } else {
-                    Some(std::ffi::CStr::from_ptr(p).to_string_lossy().into_owned())
-                }
+                owned_str(ffi::ocio_config_get_display_name(self.ptr, i as i32))
             })
             .collect()
     }
@@ -52,22 +104,100 @@ impl Config {
         let n = unsafe { ffi::ocio_config_num_views(self.ptr, d.as_ptr()) } as usize;
         (0..n)
             .filter_map(|i| unsafe {
-                let p = ffi::ocio_config_get_view_name(self.ptr, d.as_ptr(), i as i32);
-                if p.is_null() {
-                    None
-                } else {
-                    Some(std::ffi::CStr::from_ptr(p).to_string_lossy().into_owned())
-                }
+                owned_str(ffi::ocio_config_get_view_name(
+                    self.ptr,
+                    d.as_ptr(),
+                    i as i32,
+                ))
+            })
+            .collect()
+    }
+
+    pub fn colorspaces(&self) -> Vec<String> {
+        let n = unsafe { ffi::ocio_config_num_colorspaces(self.ptr) } as usize;
+        (0..n)
+            .filter_map(|i| unsafe {
+                owned_str(ffi::ocio_config_get_colorspace_name(self.ptr, i as i32))
+            })
+            .collect()
+    }
+
+    /// Family of a colour space ("" when unset).
+    pub fn family(&self, colorspace: &str) -> Option<String> {
+        let c = CString::new(colorspace).ok()?;
+        unsafe { owned_str(ffi::ocio_config_get_colorspace_family(self.ptr, c.as_ptr())) }
+    }
+
+    /// Colour space name a role points to.
+    pub fn role(&self, role: &str) -> Option<String> {
+        let c = CString::new(role).ok()?;
+        unsafe { owned_str(ffi::ocio_config_get_role_colorspace(self.ptr, c.as_ptr())) }
+            .filter(|s| !s.is_empty())
+    }
+
+    /// Whether a colour space holds display-referred values (a display colour
+    /// space, or the colour space of a display's view).
+    pub fn is_display_colorspace(&self, colorspace: &str) -> bool {
+        let Ok(c) = CString::new(colorspace) else {
+            return false;
+        };
+        unsafe { ffi::ocio_config_is_display_colorspace(self.ptr, c.as_ptr()) != 0 }
+    }
+
+    pub fn looks(&self) -> Vec<String> {
+        let n = unsafe { ffi::ocio_config_num_looks(self.ptr) } as usize;
+        (0..n)
+            .filter_map(|i| unsafe {
+                owned_str(ffi::ocio_config_get_look_name(self.ptr, i as i32))
             })
             .collect()
     }
 
+    /// Editable copy of the config's current context, for `SHOT`/`SEQ` style overrides.
+    pub fn context(&self) -> Result<Context> {
+        let ptr = unsafe { ffi::ocio_config_create_context(self.ptr) };
+        if ptr.is_null() {
+            Err(anyhow!("failed to create OCIO context"))
+        } else {
+            Ok(Context { ptr })
+        }
+    }
+
     pub fn processor_display_view(&self, display: &str, view: &str) -> Result<Processor> {
+        self.processor_display_view_ex(None, None, display, view, None)
+    }
+
+    /// Display/view processor. `src` defaults to the `scene_linear` role;
+    /// `looks` are applied in `src` before the view.
+    pub fn processor_display_view_ex(
+        &self,
+        ctx: Option<&Context>,
+        src: Option<&str>,
+        display: &str,
+        view: &str,
+        looks: Option<&str>,
+    ) -> Result<Processor> {
+        let csrc = opt_cstring(src)?;
         let d = CString::new(display)?;
         let v = CString::new(view)?;
-        let p = unsafe { ffi::ocio_config_get_processor_display_view(self.ptr, d.as_ptr(), v.as_ptr()) };
+        let clooks = opt_cstring(looks)?;
+        let cctx = ctx.map_or(ptr::null_mut(), |c| c.ptr);
+        let p = unsafe {
+            ffi::ocio_config_get_processor_display_view_ex(
+                self.ptr,
+                cctx,
+                opt_ptr(&csrc),
+                d.as_ptr(),
+                v.as_ptr(),
+                opt_ptr(&clooks),
+            )
+        };
         if p.is_null() {
-            Err(anyhow!("failed to create OCIO display/view processor"))
+            Err(anyhow!(
+                "failed to create OCIO display/view processor ({} / {})",
+                display,
+                view
+            ))
         } else {
             Ok(Processor { ptr: p })
         }
@@ -80,14 +210,71 @@ impl Drop for Config {
     }
 }
 
+/// OCIO context with string variable overrides.
+pub struct Context {
+    ptr: ffi::OcioContext,
+}
+
+impl Context {
+    pub fn set_var(&mut self, name: &str, value: &str) -> Result<()> {
+        let n = CString::new(name)?;
+        let v = CString::new(value)?;
+        if unsafe { ffi::ocio_context_set_var(self.ptr, n.as_ptr(), v.as_ptr()) } != 0 {
+            return Err(anyhow!("failed to set OCIO context variable {}", name));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        unsafe { ffi::ocio_context_release(self.ptr) }
+    }
+}
+
 pub struct Processor {
     ptr: ffi::OcioProcessor,
 }
 
+// SAFETY: `ptr` owns a heap-allocated `ConstCPUProcessorRcPtr` (a
+// `std::shared_ptr<const CPUProcessor>`) that nothing else aliases; it is only
+// released in `Drop`, so moving it to another thread is sound (`Send`).
+// OCIO documents processors as immutable once created: `CPUProcessor::apply`
+// and `applyRGB` are `const` and safe to call concurrently from several
+// threads, and those are the only calls made through a shared reference
+// (`Sync`). The shared_ptr's reference count is atomic.
+unsafe impl Send for Processor {}
+// SAFETY: see above; `&Processor` only reaches the thread-safe const apply calls.
+unsafe impl Sync for Processor {}
+
 impl Processor {
-    pub fn apply_rgb(&self, rgb: &mut [f32;3]) {
+    pub fn apply_rgb(&self, rgb: &mut [f32; 3]) {
         unsafe { ffi::ocio_processor_apply_rgb(self.ptr, rgb.as_mut_ptr()) }
     }
+
+    /// Apply to a packed RGBA buffer, one packed-image call per chunk.
+    /// A chunk the packed path rejects falls back to per-pixel application.
+    pub fn apply_rgba(&self, data: &mut [f32]) {
+        data.par_chunks_mut(APPLY_CHUNK_PIXELS * 4)
+            .for_each(|chunk| {
+                let w = (chunk.len() / 4) as std::os::raw::c_long;
+                let rc =
+                    unsafe { ffi::ocio_processor_apply_rgba(self.ptr, chunk.as_mut_ptr(), w, 1) };
+                if rc != 0 {
+                    for px in chunk.chunks_exact_mut(4) {
+                        let mut rgb = [px[0], px[1], px[2]];
+                        self.apply_rgb(&mut rgb);
+                        px[..3].copy_from_slice(&rgb);
+                    }
+                }
+            });
+    }
+}
+
+impl crate::ColorProcessor for Processor {
+    fn apply_rgba(&self, data: &mut [f32]) {
+        Processor::apply_rgba(self, data)
+    }
 }
 
 impl Drop for Processor {
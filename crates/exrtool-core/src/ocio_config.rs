@@ -0,0 +1,1233 @@
+//! Pure-Rust reader for OpenColorIO v1/v2 YAML configs.
+//!
+//! This is a fallback for builds without the `use_ocio` FFI. It covers the
+//! transforms our configs use — Matrix, Exponent, ExponentWithLinear, Log,
+//! LogAffine, LogCamera, FileTransform (.cube/.spi1d/.spi3d), ColorSpace and
+//! Group — and the colour space / display / view / look structure of both
+//! config versions. Unsupported transforms are reported as errors when a
+//! processor is built, never silently skipped.
+
+use anyhow::{anyhow, bail, Context, Result};
+use nalgebra::{Matrix3, Vector3};
+use rayon::prelude::*;
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::{parse_cube, ColorProcessor, Lut};
+
+/// Maximum nesting of ColorSpaceTransforms, to catch reference cycles.
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReferenceSpace {
+    Scene,
+    Display,
+}
+
+#[derive(Debug, Clone)]
+enum Transform {
+    Matrix {
+        m: Matrix3<f64>,
+        offset: [f64; 3],
+    },
+    Exponent([f64; 3]),
+    ExponentWithLinear {
+        gamma: [f64; 3],
+        offset: [f64; 3],
+    },
+    LogAffine(LogParams),
+    File {
+        src: String,
+    },
+    ColorSpace {
+        src: String,
+        dst: String,
+    },
+    Group(Vec<Transform>),
+    Inverse(Box<Transform>),
+    /// Parsed but not implemented; reported when a processor needs it.
+    Unsupported(String),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LogParams {
+    base: f64,
+    log_slope: [f64; 3],
+    log_offset: [f64; 3],
+    lin_slope: [f64; 3],
+    lin_offset: [f64; 3],
+    /// LogCamera: linear segment below this value
+    lin_break: Option<[f64; 3]>,
+    linear_slope: Option<[f64; 3]>,
+}
+
+#[derive(Debug, Clone)]
+struct ColorSpaceDef {
+    name: String,
+    aliases: Vec<String>,
+    family: String,
+    reference: ReferenceSpace,
+    is_data: bool,
+    to_ref: Option<Transform>,
+    from_ref: Option<Transform>,
+}
+
+#[derive(Debug, Clone)]
+struct ViewDef {
+    name: String,
+    colorspace: Option<String>,
+    view_transform: Option<String>,
+    display_colorspace: Option<String>,
+    looks: String,
+}
+
+#[derive(Debug, Clone)]
+struct ViewTransformDef {
+    name: String,
+    from_scene: Option<Transform>,
+    to_scene: Option<Transform>,
+}
+
+#[derive(Debug, Clone)]
+struct LookDef {
+    name: String,
+    process_space: String,
+    transform: Option<Transform>,
+    inverse_transform: Option<Transform>,
+}
+
+/// A parsed OCIO config.
+pub struct Config {
+    version: u32,
+    dir: PathBuf,
+    search_paths: Vec<String>,
+    environment: HashMap<String, String>,
+    /// Context variable overrides (`SHOT`, `SEQ`, ...), checked before the environment.
+    context: HashMap<String, String>,
+    roles: HashMap<String, String>,
+    colorspaces: Vec<ColorSpaceDef>,
+    displays: Vec<(String, Vec<ViewDef>)>,
+    active_displays: Vec<String>,
+    active_views: Vec<String>,
+    view_transforms: Vec<ViewTransformDef>,
+    default_view_transform: Option<String>,
+    looks: Vec<LookDef>,
+}
+
+fn tag_name(t: &serde_yaml::value::TaggedValue) -> String {
+    t.tag
+        .to_string()
+        .trim_start_matches('!')
+        .trim_matches(|c| c == '<' || c == '>')
+        .to_string()
+}
+
+/// serde_yaml drops verbatim tags, so rewrite OCIO's `!<Name>` as `!Name`.
+fn shorthand_tags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find("!<") {
+        out.push_str(&rest[..i]);
+        let after = &rest[i + 2..];
+        match after.find('>') {
+            Some(end)
+                if after[..end]
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_') =>
+            {
+                out.push('!');
+                out.push_str(&after[..end]);
+                rest = &after[end + 1..];
+            }
+            _ => {
+                out.push_str("!<");
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn untag(v: &Value) -> &Value {
+    match v {
+        Value::Tagged(t) => &t.value,
+        _ => v,
+    }
+}
+
+fn get<'a>(v: &'a Value, key: &str) -> Option<&'a Value> {
+    untag(v).as_mapping()?.get(key)
+}
+
+fn get_str(v: &Value, key: &str) -> Option<String> {
+    get(v, key).and_then(scalar_string)
+}
+
+fn scalar_string(v: &Value) -> Option<String> {
+    match untag(v) {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn get_f64(v: &Value, key: &str) -> Option<f64> {
+    get(v, key).and_then(|x| untag(x).as_f64())
+}
+
+fn f64_list(v: &Value) -> Result<Vec<f64>> {
+    untag(v)
+        .as_sequence()
+        .ok_or_else(|| anyhow!("expected a list of numbers"))?
+        .iter()
+        .map(|x| {
+            untag(x)
+                .as_f64()
+                .ok_or_else(|| anyhow!("expected a number"))
+        })
+        .collect()
+}
+
+/// Scalar or 3/4-element list → per-channel RGB values.
+fn get_rgb(v: &Value, key: &str, default: f64) -> Result<[f64; 3]> {
+    match get(v, key) {
+        None => Ok([default; 3]),
+        Some(x) => match untag(x) {
+            Value::Sequence(_) => {
+                let l = f64_list(x)?;
+                if l.len() < 3 {
+                    bail!("'{}' needs at least 3 values", key);
+                }
+                Ok([l[0], l[1], l[2]])
+            }
+            other => {
+                let s = other
+                    .as_f64()
+                    .ok_or_else(|| anyhow!("'{}' must be a number", key))?;
+                Ok([s; 3])
+            }
+        },
+    }
+}
+
+fn string_list(v: Option<&Value>) -> Vec<String> {
+    match v.map(untag) {
+        Some(Value::Sequence(s)) => s.iter().filter_map(scalar_string).collect(),
+        Some(Value::String(s)) => s
+            .split(',')
+            .map(|x| x.trim().to_string())
+            .filter(|x| !x.is_empty())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn is_inverse(v: &Value) -> bool {
+    get_str(v, "direction").is_some_and(|d| d.eq_ignore_ascii_case("inverse"))
+}
+
+fn parse_transform(v: &Value) -> Result<Transform> {
+    let t = match v {
+        Value::Sequence(items) => {
+            return Ok(Transform::Group(
+                items.iter().map(parse_transform).collect::<Result<_>>()?,
+            ));
+        }
+        Value::Tagged(t) => t,
+        _ => bail!("expected a tagged transform"),
+    };
+    let kind = tag_name(t);
+    let parsed = match kind.as_str() {
+        "GroupTransform" => {
+            let children = get(v, "children")
+                .and_then(|c| untag(c).as_sequence())
+                .ok_or_else(|| anyhow!("GroupTransform without children"))?;
+            Transform::Group(
+                children
+                    .iter()
+                    .map(parse_transform)
+                    .collect::<Result<_>>()?,
+            )
+        }
+        "MatrixTransform" => {
+            let m = match get(v, "matrix") {
+                Some(x) => f64_list(x)?,
+                None => vec![
+                    1., 0., 0., 0., 0., 1., 0., 0., 0., 0., 1., 0., 0., 0., 0., 1.,
+                ],
+            };
+            if m.len() != 16 {
+                bail!("MatrixTransform matrix must have 16 values");
+            }
+            let offset = match get(v, "offset") {
+                Some(x) => f64_list(x)?,
+                None => vec![0.0; 4],
+            };
+            if offset.len() < 3 {
+                bail!("MatrixTransform offset must have 4 values");
+            }
+            Transform::Matrix {
+                m: Matrix3::new(m[0], m[1], m[2], m[4], m[5], m[6], m[8], m[9], m[10]),
+                offset: [offset[0], offset[1], offset[2]],
+            }
+        }
+        "ExponentTransform" => Transform::Exponent(get_rgb(v, "value", 1.0)?),
+        "ExponentWithLinearTransform" => Transform::ExponentWithLinear {
+            gamma: get_rgb(v, "gamma", 1.0)?,
+            offset: get_rgb(v, "offset", 0.0)?,
+        },
+        "LogTransform" => Transform::LogAffine(LogParams {
+            base: get_f64(v, "base").unwrap_or(2.0),
+            log_slope: [1.0; 3],
+            log_offset: [0.0; 3],
+            lin_slope: [1.0; 3],
+            lin_offset: [0.0; 3],
+            lin_break: None,
+            linear_slope: None,
+        }),
+        "LogAffineTransform" | "LogCameraTransform" => {
+            let camera = kind == "LogCameraTransform";
+            let lin_break = if camera {
+                Some(
+                    get(v, "lin_side_break")
+                        .map(|_| get_rgb(v, "lin_side_break", 0.0))
+                        .transpose()?
+                        .ok_or_else(|| anyhow!("LogCameraTransform requires lin_side_break"))?,
+                )
+            } else {
+                None
+            };
+            Transform::LogAffine(LogParams {
+                base: get_f64(v, "base").unwrap_or(2.0),
+                log_slope: get_rgb(v, "log_side_slope", 1.0)?,
+                log_offset: get_rgb(v, "log_side_offset", 0.0)?,
+                lin_slope: get_rgb(v, "lin_side_slope", 1.0)?,
+                lin_offset: get_rgb(v, "lin_side_offset", 0.0)?,
+                lin_break,
+                linear_slope: get(v, "linear_slope")
+                    .map(|_| get_rgb(v, "linear_slope", 1.0))
+                    .transpose()?,
+            })
+        }
+        "FileTransform" => Transform::File {
+            src: get_str(v, "src").ok_or_else(|| anyhow!("FileTransform without src"))?,
+        },
+        "ColorSpaceTransform" => Transform::ColorSpace {
+            src: get_str(v, "src").ok_or_else(|| anyhow!("ColorSpaceTransform without src"))?,
+            dst: get_str(v, "dst").ok_or_else(|| anyhow!("ColorSpaceTransform without dst"))?,
+        },
+        other => Transform::Unsupported(other.to_string()),
+    };
+    Ok(if is_inverse(v) {
+        Transform::Inverse(Box::new(parsed))
+    } else {
+        parsed
+    })
+}
+
+/// Parse an optional transform field.
+fn parse_opt_transform(v: &Value, key: &str) -> Result<Option<Transform>> {
+    get(v, key).map(parse_transform).transpose()
+}
+
+impl Config {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read OCIO config {}", path.display()))?;
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Self::from_yaml_str(&text, &dir)
+    }
+
+    /// Parse config text; relative search paths resolve against `dir`.
+    pub fn from_yaml_str(text: &str, dir: &Path) -> Result<Self> {
+        let root: Value =
+            serde_yaml::from_str(&shorthand_tags(text)).context("invalid OCIO config YAML")?;
+        let version = get(&root, "ocio_profile_version")
+            .and_then(scalar_string)
+            .and_then(|s| s.split('.').next().and_then(|m| m.parse().ok()))
+            .unwrap_or(1);
+        let search_paths = match get(&root, "search_path").map(untag) {
+            Some(Value::Sequence(s)) => s.iter().filter_map(scalar_string).collect(),
+            Some(v) => scalar_string(v)
+                .map(|s| s.split(':').map(str::to_string).collect())
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
+        let mut environment = HashMap::new();
+        if let Some(env) = get(&root, "environment").and_then(|e| untag(e).as_mapping()) {
+            for (k, v) in env {
+                if let (Some(k), Some(v)) = (scalar_string(k), scalar_string(v)) {
+                    environment.insert(k, v);
+                }
+            }
+        }
+        let mut roles = HashMap::new();
+        if let Some(r) = get(&root, "roles").and_then(|r| untag(r).as_mapping()) {
+            for (k, v) in r {
+                if let (Some(k), Some(v)) = (scalar_string(k), scalar_string(v)) {
+                    roles.insert(k.to_ascii_lowercase(), v);
+                }
+            }
+        }
+
+        let mut colorspaces = Vec::new();
+        for (key, reference) in [
+            ("colorspaces", ReferenceSpace::Scene),
+            ("display_colorspaces", ReferenceSpace::Display),
+        ] {
+            let Some(list) = get(&root, key).and_then(|l| untag(l).as_sequence()) else {
+                continue;
+            };
+            for cs in list {
+                let name = get_str(cs, "name").ok_or_else(|| anyhow!("colorspace without name"))?;
+                let (to_key, from_key) = match (version, reference) {
+                    (1, _) => ("to_reference", "from_reference"),
+                    (_, ReferenceSpace::Scene) => ("to_scene_reference", "from_scene_reference"),
+                    (_, ReferenceSpace::Display) => {
+                        ("to_display_reference", "from_display_reference")
+                    }
+                };
+                let with_name = |e: anyhow::Error| e.context(format!("colorspace '{}'", name));
+                colorspaces.push(ColorSpaceDef {
+                    aliases: string_list(get(cs, "aliases")),
+                    family: get_str(cs, "family").unwrap_or_default(),
+                    reference,
+                    is_data: get(cs, "isdata")
+                        .and_then(|b| untag(b).as_bool())
+                        .unwrap_or(false),
+                    // v1 configs also accept the v2 key names and vice versa
+                    to_ref: parse_opt_transform(cs, to_key)
+                        .and_then(|t| match t {
+                            Some(t) => Ok(Some(t)),
+                            None => parse_opt_transform(cs, "to_reference"),
+                        })
+                        .map_err(with_name)?,
+                    from_ref: parse_opt_transform(cs, from_key)
+                        .and_then(|t| match t {
+                            Some(t) => Ok(Some(t)),
+                            None => parse_opt_transform(cs, "from_reference"),
+                        })
+                        .map_err(with_name)?,
+                    name,
+                });
+            }
+        }
+
+        let parse_view = |v: &Value| -> Option<ViewDef> {
+            Some(ViewDef {
+                name: get_str(v, "name")?,
+                colorspace: get_str(v, "colorspace"),
+                view_transform: get_str(v, "view_transform"),
+                display_colorspace: get_str(v, "display_colorspace"),
+                looks: get_str(v, "looks").unwrap_or_default(),
+            })
+        };
+        let shared_views: Vec<ViewDef> = get(&root, "shared_views")
+            .and_then(|s| untag(s).as_sequence())
+            .map(|s| s.iter().filter_map(parse_view).collect())
+            .unwrap_or_default();
+        let mut displays = Vec::new();
+        if let Some(d) = get(&root, "displays").and_then(|d| untag(d).as_mapping()) {
+            for (name, views) in d {
+                let Some(name) = scalar_string(name) else {
+                    continue;
+                };
+                let mut list = Vec::new();
+                for v in untag(views).as_sequence().into_iter().flatten() {
+                    match v {
+                        Value::Tagged(t) if tag_name(t) == "Views" => {
+                            for shared in string_list(Some(&t.value)) {
+                                if let Some(sv) = shared_views.iter().find(|s| s.name == shared) {
+                                    list.push(sv.clone());
+                                }
+                            }
+                        }
+                        _ => list.extend(parse_view(v)),
+                    }
+                }
+                displays.push((name, list));
+            }
+        }
+
+        let mut view_transforms = Vec::new();
+        for vt in get(&root, "view_transforms")
+            .and_then(|l| untag(l).as_sequence())
+            .into_iter()
+            .flatten()
+        {
+            let name = get_str(vt, "name").ok_or_else(|| anyhow!("view_transform without name"))?;
+            view_transforms.push(ViewTransformDef {
+                from_scene: parse_opt_transform(vt, "from_scene_reference")?,
+                to_scene: parse_opt_transform(vt, "to_scene_reference")?,
+                name,
+            });
+        }
+
+        let mut looks = Vec::new();
+        for l in get(&root, "looks")
+            .and_then(|l| untag(l).as_sequence())
+            .into_iter()
+            .flatten()
+        {
+            let name = get_str(l, "name").ok_or_else(|| anyhow!("look without name"))?;
+            looks.push(LookDef {
+                process_space: get_str(l, "process_space").unwrap_or_default(),
+                transform: parse_opt_transform(l, "transform")?,
+                inverse_transform: parse_opt_transform(l, "inverse_transform")?,
+                name,
+            });
+        }
+
+        Ok(Config {
+            version,
+            dir: dir.to_path_buf(),
+            search_paths,
+            environment,
+            context: HashMap::new(),
+            roles,
+            colorspaces,
+            displays,
+            active_displays: string_list(get(&root, "active_displays")),
+            active_views: string_list(get(&root, "active_views")),
+            view_transforms,
+            default_view_transform: get_str(&root, "default_view_transform"),
+            looks,
+        })
+    }
+
+    /// Major profile version (1 or 2).
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn colorspaces(&self) -> Vec<String> {
+        self.colorspaces.iter().map(|c| c.name.clone()).collect()
+    }
+
+    /// Family of a colour space ("" when unset).
+    pub fn family(&self, colorspace: &str) -> Option<String> {
+        self.colorspace(colorspace).ok().map(|c| c.family.clone())
+    }
+
+    /// Colour space name a role points to.
+    pub fn role(&self, role: &str) -> Option<String> {
+        self.roles.get(&role.to_ascii_lowercase()).cloned()
+    }
+
+    pub fn displays(&self) -> Vec<String> {
+        let all = self.displays.iter().map(|(n, _)| n.clone());
+        if self.active_displays.is_empty() {
+            all.collect()
+        } else {
+            self.active_displays
+                .iter()
+                .filter(|a| self.displays.iter().any(|(n, _)| n == *a))
+                .cloned()
+                .collect()
+        }
+    }
+
+    pub fn views(&self, display: &str) -> Vec<String> {
+        let Some((_, views)) = self.displays.iter().find(|(n, _)| n == display) else {
+            return Vec::new();
+        };
+        let names = views.iter().map(|v| v.name.clone());
+        if self.active_views.is_empty() {
+            names.collect()
+        } else {
+            let mut active: Vec<String> = self
+                .active_views
+                .iter()
+                .filter(|a| views.iter().any(|v| v.name == **a))
+                .cloned()
+                .collect();
+            if active.is_empty() {
+                active = names.collect();
+            }
+            active
+        }
+    }
+
+    pub fn looks(&self) -> Vec<String> {
+        self.looks.iter().map(|l| l.name.clone()).collect()
+    }
+
+    /// Whether a colour space holds display-referred values: a v2
+    /// `display_colorspaces` entry, or the colour space of a display's view.
+    pub fn is_display_colorspace(&self, colorspace: &str) -> bool {
+        let Ok(cs) = self.colorspace(colorspace) else {
+            return false;
+        };
+        cs.reference == ReferenceSpace::Display
+            || self.displays.iter().any(|(display, views)| {
+                views.iter().any(|v| {
+                    let name = v.display_colorspace.as_ref().or(v.colorspace.as_ref());
+                    name.is_some_and(|n| {
+                        let n = if n == "<USE_DISPLAY_NAME>" {
+                            display
+                        } else {
+                            n
+                        };
+                        self.colorspace(n).is_ok_and(|c| c.name == cs.name)
+                    })
+                })
+            })
+    }
+
+    fn colorspace(&self, name: &str) -> Result<&ColorSpaceDef> {
+        let find = |n: &str| {
+            self.colorspaces.iter().find(|c| {
+                c.name.eq_ignore_ascii_case(n)
+                    || c.aliases.iter().any(|a| a.eq_ignore_ascii_case(n))
+            })
+        };
+        find(name)
+            .or_else(|| self.role(name).and_then(|r| find(&r)))
+            .ok_or_else(|| anyhow!("unknown OCIO colorspace: {}", name))
+    }
+
+    /// Override a context variable used in search paths and file names.
+    pub fn set_context_var(&mut self, name: &str, value: &str) {
+        self.context.insert(name.to_string(), value.to_string());
+    }
+
+    /// Processor converting `src` to `dst` (colour space or role names).
+    pub fn processor(&self, src: &str, dst: &str) -> Result<Processor> {
+        self.processor_with_looks(src, dst, "")
+    }
+
+    /// Like [`Config::processor`], applying a look list (e.g. `"+grade, -neutral"`)
+    /// on the way, as OCIO's LookTransform does.
+    pub fn processor_with_looks(&self, src: &str, dst: &str, looks: &str) -> Result<Processor> {
+        let mut b = Builder::new(self);
+        let mut current = self.colorspace(src)?.name.clone();
+        for (name, inverse) in parse_look_list(looks) {
+            current = b.look(&current, &name, inverse)?;
+        }
+        b.convert(&current, dst, 0)?;
+        Ok(b.finish())
+    }
+
+    /// Display/view processor from the `scene_linear` role, like the FFI wrapper.
+    pub fn processor_display_view(&self, display: &str, view: &str) -> Result<Processor> {
+        let src = self
+            .role("scene_linear")
+            .ok_or_else(|| anyhow!("config has no scene_linear role"))?;
+        self.processor_display_view_from(&src, display, view)
+    }
+
+    /// Display/view processor from an explicit source colour space.
+    pub fn processor_display_view_from(
+        &self,
+        src: &str,
+        display: &str,
+        view: &str,
+    ) -> Result<Processor> {
+        self.processor_display_view_with_looks(src, display, view, "")
+    }
+
+    /// Display/view processor with extra `looks` applied in `src` before the
+    /// view's own looks.
+    pub fn processor_display_view_with_looks(
+        &self,
+        src: &str,
+        display: &str,
+        view: &str,
+        looks: &str,
+    ) -> Result<Processor> {
+        let (_, views) = self
+            .displays
+            .iter()
+            .find(|(n, _)| n == display)
+            .ok_or_else(|| anyhow!("unknown OCIO display: {}", display))?;
+        let v = views
+            .iter()
+            .find(|v| v.name == view)
+            .ok_or_else(|| anyhow!("unknown OCIO view '{}' for display '{}'", view, display))?;
+        let mut b = Builder::new(self);
+        let mut current = self.colorspace(src)?.name.clone();
+        let mut look_list = parse_look_list(looks);
+        look_list.extend(parse_look_list(&v.looks));
+        for (name, inverse) in look_list {
+            current = b.look(&current, &name, inverse)?;
+        }
+        match (&v.view_transform, &v.display_colorspace, &v.colorspace) {
+            (Some(vt), Some(dcs), _) => {
+                let dcs = if dcs == "<USE_DISPLAY_NAME>" {
+                    display
+                } else {
+                    dcs.as_str()
+                };
+                let cs = self.colorspace(&current)?;
+                if cs.reference != ReferenceSpace::Scene {
+                    bail!(
+                        "view transform needs a scene-referred source, got '{}'",
+                        current
+                    );
+                }
+                b.push_to_reference(cs, 0)?;
+                b.view_transform(Some(vt))?;
+                let dst = self.colorspace(dcs)?;
+                b.push_from_reference(dst, 0)?;
+            }
+            (_, _, Some(cs)) => {
+                let cs = if cs == "<USE_DISPLAY_NAME>" {
+                    display
+                } else {
+                    cs.as_str()
+                };
+                b.convert(&current, cs, 0)?;
+            }
+            _ => bail!("view '{}' has no colorspace", view),
+        }
+        Ok(b.finish())
+    }
+
+    fn resolve_file(&self, src: &str) -> Result<PathBuf> {
+        let src = self.expand_vars(src);
+        let p = Path::new(&src);
+        if p.is_absolute() {
+            return Ok(p.to_path_buf());
+        }
+        let mut dirs: Vec<PathBuf> = self
+            .search_paths
+            .iter()
+            .map(|s| self.dir.join(self.expand_vars(s)))
+            .collect();
+        dirs.push(self.dir.clone());
+        dirs.iter()
+            .map(|d| d.join(&src))
+            .find(|c| c.is_file())
+            .ok_or_else(|| anyhow!("LUT file not found in search path: {}", src))
+    }
+
+    /// Expand `$VAR` / `${VAR}` from context overrides, then the process
+    /// environment, falling back to the config's `environment` defaults.
+    fn expand_vars(&self, s: &str) -> String {
+        let lookup = |name: &str| {
+            self.context
+                .get(name)
+                .cloned()
+                .or_else(|| std::env::var(name).ok())
+                .or_else(|| self.environment.get(name).cloned())
+                .unwrap_or_default()
+        };
+        let mut out = String::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '$' {
+                out.push(c);
+                continue;
+            }
+            let braced = chars.peek() == Some(&'{');
+            if braced {
+                chars.next();
+            }
+            let mut name = String::new();
+            while let Some(&n) = chars.peek() {
+                if n.is_ascii_alphanumeric() || n == '_' {
+                    name.push(n);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            if braced && chars.peek() == Some(&'}') {
+                chars.next();
+            }
+            out.push_str(&lookup(&name));
+        }
+        out
+    }
+}
+
+/// Parse a view/look string such as `"+grade, -neutral"`.
+fn parse_look_list(s: &str) -> Vec<(String, bool)> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(|x| match x.strip_prefix('-') {
+            Some(n) => (n.trim().to_string(), true),
+            None => (x.trim_start_matches('+').trim().to_string(), false),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+enum Op {
+    Matrix(Matrix3<f64>, Vector3<f64>),
+    Exponent([f64; 3]),
+    MonCurveFwd { gamma: [f64; 3], offset: [f64; 3] },
+    MonCurveRev { gamma: [f64; 3], offset: [f64; 3] },
+    LogFwd(LogParams),
+    LogRev(LogParams),
+    Lut1d(Arc<Lut1d>),
+    Lut1dInv(Arc<Lut1d>),
+    Lut(Arc<Lut>),
+}
+
+/// `.spi1d` table.
+#[derive(Debug)]
+struct Lut1d {
+    from: (f64, f64),
+    table: Vec<[f64; 3]>,
+}
+
+impl Lut1d {
+    fn eval(&self, c: usize, x: f64) -> f64 {
+        let n = self.table.len();
+        let t = ((x - self.from.0) / (self.from.1 - self.from.0)).clamp(0.0, 1.0) * (n - 1) as f64;
+        let i0 = (t.floor() as usize).min(n - 1);
+        let i1 = (i0 + 1).min(n - 1);
+        let f = t - i0 as f64;
+        self.table[i0][c] + (self.table[i1][c] - self.table[i0][c]) * f
+    }
+
+    /// Inverse by bisection; assumes a monotonically increasing table.
+    fn eval_inv(&self, c: usize, y: f64) -> f64 {
+        let (mut lo, mut hi) = self.from;
+        for _ in 0..40 {
+            let mid = 0.5 * (lo + hi);
+            if self.eval(c, mid) < y {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        0.5 * (lo + hi)
+    }
+}
+
+fn log_fwd(p: &LogParams, c: usize, x: f64) -> f64 {
+    let log = |x: f64| {
+        p.log_slope[c]
+            * (p.lin_slope[c] * x + p.lin_offset[c])
+                .max(f64::MIN_POSITIVE)
+                .log(p.base)
+            + p.log_offset[c]
+    };
+    match p.lin_break {
+        Some(brk) if x <= brk[c] => {
+            let (slope, off) = camera_linear_segment(p, c, brk[c]);
+            slope * x + off
+        }
+        _ => log(x),
+    }
+}
+
+fn log_rev(p: &LogParams, c: usize, y: f64) -> f64 {
+    if let Some(brk) = p.lin_break {
+        let (slope, off) = camera_linear_segment(p, c, brk[c]);
+        if y <= slope * brk[c] + off {
+            return (y - off) / slope;
+        }
+    }
+    (p.base.powf((y - p.log_offset[c]) / p.log_slope[c]) - p.lin_offset[c]) / p.lin_slope[c]
+}
+
+/// LogCamera linear segment (slope, offset), continuous with the log curve at `brk`.
+fn camera_linear_segment(p: &LogParams, c: usize, brk: f64) -> (f64, f64) {
+    let inner = p.lin_slope[c] * brk + p.lin_offset[c];
+    let log_at_break = p.log_slope[c] * inner.log(p.base) + p.log_offset[c];
+    let slope = match p.linear_slope {
+        Some(s) => s[c],
+        None => p.log_slope[c] * p.lin_slope[c] / (inner * p.base.ln()),
+    };
+    (slope, log_at_break - slope * brk)
+}
+
+fn moncurve_fwd(x: f64, gamma: f64, offset: f64) -> f64 {
+    if offset == 0.0 {
+        return x.max(0.0).powf(gamma);
+    }
+    let brk = offset / (gamma - 1.0);
+    if x >= brk {
+        ((x + offset) / (1.0 + offset)).powf(gamma)
+    } else {
+        let fs = ((gamma - 1.0) / offset)
+            * (offset * gamma / ((gamma - 1.0) * (1.0 + offset))).powf(gamma);
+        x * fs
+    }
+}
+
+fn moncurve_rev(y: f64, gamma: f64, offset: f64) -> f64 {
+    if offset == 0.0 {
+        return y.max(0.0).powf(1.0 / gamma);
+    }
+    let brk = (offset * gamma / ((gamma - 1.0) * (1.0 + offset))).powf(gamma);
+    if y >= brk {
+        (1.0 + offset) * y.powf(1.0 / gamma) - offset
+    } else {
+        let rs = ((gamma - 1.0) / offset).powf(gamma - 1.0) * ((1.0 + offset) / gamma).powf(gamma);
+        y * rs
+    }
+}
+
+impl Op {
+    fn apply(&self, v: [f64; 3]) -> [f64; 3] {
+        match self {
+            Op::Matrix(m, off) => {
+                let r = m * Vector3::new(v[0], v[1], v[2]) + off;
+                [r.x, r.y, r.z]
+            }
+            Op::Exponent(e) => [0, 1, 2].map(|c| v[c].max(0.0).powf(e[c])),
+            Op::MonCurveFwd { gamma, offset } => {
+                [0, 1, 2].map(|c| moncurve_fwd(v[c], gamma[c], offset[c]))
+            }
+            Op::MonCurveRev { gamma, offset } => {
+                [0, 1, 2].map(|c| moncurve_rev(v[c], gamma[c], offset[c]))
+            }
+            Op::LogFwd(p) => [0, 1, 2].map(|c| log_fwd(p, c, v[c])),
+            Op::LogRev(p) => [0, 1, 2].map(|c| log_rev(p, c, v[c])),
+            Op::Lut1d(l) => [0, 1, 2].map(|c| l.eval(c, v[c])),
+            Op::Lut1dInv(l) => [0, 1, 2].map(|c| l.eval_inv(c, v[c])),
+            Op::Lut(l) => l
+                .apply([v[0] as f32, v[1] as f32, v[2] as f32])
+                .map(|x| x as f64),
+        }
+    }
+}
+
+/// Compiled chain of operations from a pure-Rust [`Config`].
+#[derive(Debug, Clone, Default)]
+pub struct Processor {
+    ops: Vec<Op>,
+}
+
+impl Processor {
+    pub fn is_noop(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    pub fn apply_rgb(&self, rgb: &mut [f32; 3]) {
+        let mut v = rgb.map(|x| x as f64);
+        for op in &self.ops {
+            v = op.apply(v);
+        }
+        *rgb = v.map(|x| x as f32);
+    }
+
+    /// Apply to an interleaved RGBA buffer in parallel; alpha is untouched.
+    pub fn apply_rgba(&self, data: &mut [f32]) {
+        if self.is_noop() {
+            return;
+        }
+        data.par_chunks_mut(4).for_each(|px| {
+            let mut rgb = [px[0], px[1], px[2]];
+            self.apply_rgb(&mut rgb);
+            px[..3].copy_from_slice(&rgb);
+        });
+    }
+}
+
+impl ColorProcessor for Processor {
+    fn apply_rgba(&self, data: &mut [f32]) {
+        Processor::apply_rgba(self, data)
+    }
+}
+
+struct Builder<'a> {
+    cfg: &'a Config,
+    ops: Vec<Op>,
+    files: HashMap<PathBuf, FileLut>,
+}
+
+#[derive(Clone)]
+enum FileLut {
+    OneD(Arc<Lut1d>),
+    ThreeD(Arc<Lut>),
+}
+
+impl<'a> Builder<'a> {
+    fn new(cfg: &'a Config) -> Self {
+        Self {
+            cfg,
+            ops: Vec::new(),
+            files: HashMap::new(),
+        }
+    }
+
+    fn finish(self) -> Processor {
+        Processor { ops: self.ops }
+    }
+
+    fn convert(&mut self, src: &str, dst: &str, depth: usize) -> Result<()> {
+        if depth > MAX_DEPTH {
+            bail!("OCIO colorspace references are nested too deeply");
+        }
+        let s = self.cfg.colorspace(src)?;
+        let d = self.cfg.colorspace(dst)?;
+        if s.name == d.name || s.is_data || d.is_data {
+            return Ok(());
+        }
+        self.push_to_reference(s, depth)?;
+        match (s.reference, d.reference) {
+            (ReferenceSpace::Scene, ReferenceSpace::Display) => self.view_transform(None)?,
+            (ReferenceSpace::Display, ReferenceSpace::Scene) => self.inverse_view_transform()?,
+            _ => {}
+        }
+        self.push_from_reference(d, depth)
+    }
+
+    fn push_to_reference(&mut self, cs: &ColorSpaceDef, depth: usize) -> Result<()> {
+        match (&cs.to_ref, &cs.from_ref) {
+            (Some(t), _) => self.push(t, false, depth),
+            (None, Some(t)) => self.push(t, true, depth),
+            (None, None) => Ok(()),
+        }
+        .with_context(|| format!("colorspace '{}'", cs.name))
+    }
+
+    fn push_from_reference(&mut self, cs: &ColorSpaceDef, depth: usize) -> Result<()> {
+        match (&cs.from_ref, &cs.to_ref) {
+            (Some(t), _) => self.push(t, false, depth),
+            (None, Some(t)) => self.push(t, true, depth),
+            (None, None) => Ok(()),
+        }
+        .with_context(|| format!("colorspace '{}'", cs.name))
+    }
+
+    fn find_view_transform(&self, name: Option<&str>) -> Result<&'a ViewTransformDef> {
+        let cfg = self.cfg;
+        let name = name.or(cfg.default_view_transform.as_deref());
+        match name {
+            Some(n) => cfg.view_transforms.iter().find(|v| v.name == n),
+            None => cfg.view_transforms.first(),
+        }
+        .ok_or_else(|| anyhow!("no view transform available for scene → display conversion"))
+    }
+
+    /// Scene reference → display reference.
+    fn view_transform(&mut self, name: Option<&str>) -> Result<()> {
+        let vt = self.find_view_transform(name)?;
+        match (&vt.from_scene, &vt.to_scene) {
+            (Some(t), _) => self.push(t, false, 0),
+            (None, Some(t)) => self.push(t, true, 0),
+            _ => Ok(()),
+        }
+    }
+
+    fn inverse_view_transform(&mut self) -> Result<()> {
+        let vt = self.find_view_transform(None)?;
+        match (&vt.to_scene, &vt.from_scene) {
+            (Some(t), _) => self.push(t, false, 0),
+            (None, Some(t)) => self.push(t, true, 0),
+            _ => Ok(()),
+        }
+    }
+
+    /// Apply a look from `current`, returning the new current colour space.
+    fn look(&mut self, current: &str, name: &str, inverse: bool) -> Result<String> {
+        let cfg = self.cfg;
+        let look = cfg
+            .looks
+            .iter()
+            .find(|l| l.name == name)
+            .ok_or_else(|| anyhow!("unknown OCIO look: {}", name))?;
+        let space = if look.process_space.is_empty() {
+            current
+        } else {
+            look.process_space.as_str()
+        };
+        self.convert(current, space, 0)?;
+        match (inverse, &look.transform, &look.inverse_transform) {
+            (false, Some(t), _) => self.push(t, false, 0)?,
+            (false, None, Some(t)) => self.push(t, true, 0)?,
+            (true, _, Some(t)) => self.push(t, false, 0)?,
+            (true, Some(t), None) => self.push(t, true, 0)?,
+            _ => {}
+        }
+        Ok(cfg.colorspace(space)?.name.clone())
+    }
+
+    fn push(&mut self, t: &Transform, inverse: bool, depth: usize) -> Result<()> {
+        match t {
+            Transform::Inverse(inner) => self.push(inner, !inverse, depth),
+            Transform::Group(children) => {
+                if inverse {
+                    children
+                        .iter()
+                        .rev()
+                        .try_for_each(|c| self.push(c, true, depth))
+                } else {
+                    children.iter().try_for_each(|c| self.push(c, false, depth))
+                }
+            }
+            Transform::Matrix { m, offset } => {
+                let off = Vector3::new(offset[0], offset[1], offset[2]);
+                if inverse {
+                    let inv = m
+                        .try_inverse()
+                        .ok_or_else(|| anyhow!("MatrixTransform is not invertible"))?;
+                    self.ops.push(Op::Matrix(inv, -(inv * off)));
+                } else {
+                    self.ops.push(Op::Matrix(*m, off));
+                }
+                Ok(())
+            }
+            Transform::Exponent(e) => {
+                self.ops
+                    .push(Op::Exponent(if inverse { e.map(|x| 1.0 / x) } else { *e }));
+                Ok(())
+            }
+            Transform::ExponentWithLinear { gamma, offset } => {
+                let (gamma, offset) = (*gamma, *offset);
+                self.ops.push(if inverse {
+                    Op::MonCurveRev { gamma, offset }
+                } else {
+                    Op::MonCurveFwd { gamma, offset }
+                });
+                Ok(())
+            }
+            Transform::LogAffine(p) => {
+                // OCIO's forward log direction is linear → log
+                self.ops.push(if inverse {
+                    Op::LogRev(*p)
+                } else {
+                    Op::LogFwd(*p)
+                });
+                Ok(())
+            }
+            Transform::File { src } => {
+                let lut = self.load_file(src)?;
+                match (lut, inverse) {
+                    (FileLut::OneD(l), false) => self.ops.push(Op::Lut1d(l)),
+                    (FileLut::OneD(l), true) => self.ops.push(Op::Lut1dInv(l)),
+                    (FileLut::ThreeD(l), false) => self.ops.push(Op::Lut(l)),
+                    (FileLut::ThreeD(_), true) => bail!("inverse 3D LUT is not supported: {}", src),
+                }
+                Ok(())
+            }
+            Transform::ColorSpace { src, dst } => {
+                let (s, d) = if inverse { (dst, src) } else { (src, dst) };
+                self.convert(s, d, depth + 1)
+            }
+            Transform::Unsupported(kind) => bail!("unsupported OCIO transform: {}", kind),
+        }
+    }
+
+    fn load_file(&mut self, src: &str) -> Result<FileLut> {
+        let path = self.cfg.resolve_file(src)?;
+        if let Some(l) = self.files.get(&path) {
+            return Ok(l.clone());
+        }
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        let lut = match ext.as_str() {
+            "cube" => FileLut::ThreeD(Arc::new(parse_cube(&text)?)),
+            "spi1d" => FileLut::OneD(Arc::new(parse_spi1d(&text)?)),
+            "spi3d" => FileLut::ThreeD(Arc::new(parse_spi3d(&text)?)),
+            other => bail!("unsupported LUT format '.{}': {}", other, path.display()),
+        };
+        self.files.insert(path, lut.clone());
+        Ok(lut)
+    }
+}
+
+fn parse_spi1d(text: &str) -> Result<Lut1d> {
+    let mut from = (0.0, 1.0);
+    let mut components = 1usize;
+    let mut table = Vec::new();
+    let mut in_body = false;
+    for line in text.lines() {
+        let l = line.trim();
+        if l.is_empty() || l.starts_with('#') {
+            continue;
+        }
+        if in_body {
+            if l.starts_with('}') {
+                break;
+            }
+            let vals: Vec<f64> = l
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<_, _>>()?;
+            match (components, vals.as_slice()) {
+                (1, [v, ..]) => table.push([*v; 3]),
+                (3, [r, g, b, ..]) => table.push([*r, *g, *b]),
+                _ => bail!(".spi1d: invalid row '{}'", l),
+            }
+            continue;
+        }
+        let mut parts = l.split_whitespace();
+        match parts.next() {
+            Some("From") => {
+                let a: f64 = parts
+                    .next()
+                    .ok_or_else(|| anyhow!(".spi1d: bad From"))?
+                    .parse()?;
+                let b: f64 = parts
+                    .next()
+                    .ok_or_else(|| anyhow!(".spi1d: bad From"))?
+                    .parse()?;
+                from = (a, b);
+            }
+            Some("Components") => {
+                components = parts
+                    .next()
+                    .ok_or_else(|| anyhow!(".spi1d: bad Components"))?
+                    .parse()?
+            }
+            Some("{") => in_body = true,
+            _ => {}
+        }
+    }
+    if table.len() < 2 {
+        bail!(".spi1d: table is empty");
+    }
+    Ok(Lut1d { from, table })
+}
+
+fn parse_spi3d(text: &str) -> Result<Lut> {
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'));
+    let header = lines.next().unwrap_or_default();
+    if !header.starts_with("SPILUT") {
+        bail!(".spi3d: missing SPILUT header");
+    }
+    lines.next(); // "3 3"
+    let dims: Vec<usize> = lines
+        .next()
+        .ok_or_else(|| anyhow!(".spi3d: missing size"))?
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<_, _>>()?;
+    if dims.len() != 3 || dims[0] != dims[1] || dims[1] != dims[2] || dims[0] < 2 {
+        bail!(".spi3d: only cubic tables are supported");
+    }
+    let n = dims[0];
+    let mut table = vec![[0.0f32; 3]; n * n * n];
+    let mut count = 0;
+    for l in lines {
+        let v: Vec<&str> = l.split_whitespace().collect();
+        if v.len() < 6 {
+            bail!(".spi3d: invalid row '{}'", l);
+        }
+        let (i, j, k): (usize, usize, usize) = (v[0].parse()?, v[1].parse()?, v[2].parse()?);
+        if i >= n || j >= n || k >= n {
+            bail!(".spi3d: index out of range '{}'", l);
+        }
+        table[k * n * n + j * n + i] = [v[3].parse()?, v[4].parse()?, v[5].parse()?];
+        count += 1;
+    }
+    if count != n * n * n {
+        bail!(".spi3d: expected {} entries, got {}", n * n * n, count);
+    }
+    Ok(Lut {
+        shaper_size: 0,
+        shaper_table: Vec::new(),
+        cube_size: n,
+        cube_table: table,
+    })
+}
@@ -1,10 +1,29 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+use glob::Pattern;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs::File, io::BufReader, path::Path};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use crate::metadata::{
+    canonical_key, coerce_for_key, edit_metadata_parts, read_part_attributes, target_parts,
+    write_with_backup, HeaderInfo, MetadataValue, PartAttributes, PartEdit, PartTarget, Placement,
+    ValueKind, WriteOptions,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct RuleFile {
+    /// Part(s) of multi-part files the rules write to (`first` when absent).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub part: Option<PartTarget>,
     pub rules: Vec<Rule>,
 }
 
@@ -13,21 +32,66 @@ pub struct RuleFile {
 pub enum Rule {
     Set {
         key: String,
+        /// Template such as `{shot}_{frame:04}`, `{attr:owner}`,
+        /// `{now:%Y-%m-%d}` or `{width}x{height}`; `{{`/`}}` are literal braces.
         value: String,
+        /// Type the expanded value is written as.
+        #[serde(default, skip_serializing_if = "ValueKind::is_text")]
+        value_type: ValueKind,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        when: Vec<Condition>,
     },
     Unset {
         key: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        when: Vec<Condition>,
     },
     Copy {
         from: String,
         to: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        when: Vec<Condition>,
     },
     FromFilename {
         pattern: String,
         mapping: HashMap<String, String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        when: Vec<Condition>,
     },
 }
 
+/// Guard for a rule; a rule runs only when all of its `when` entries hold.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    /// The attribute is present.
+    Exists(String),
+    /// The attribute's value, as text, equals `value`.
+    Equals {
+        key: String,
+        value: String,
+    },
+    /// The attribute's value, as text, matches a regex.
+    Matches {
+        key: String,
+        pattern: String,
+    },
+    /// The file name matches a glob (`sh0*_*.exr`).
+    Filename(String),
+    /// Data window size; an omitted side matches anything.
+    Resolution {
+        #[serde(default)]
+        width: Option<usize>,
+        #[serde(default)]
+        height: Option<usize>,
+    },
+    /// Some part or channel layer name matches a glob.
+    Layer(String),
+    All(Vec<Condition>),
+    Any(Vec<Condition>),
+    Not(Box<Condition>),
+}
+
 impl RuleFile {
     pub fn from_path(path: &Path) -> Result<Self> {
         let file = File::open(path)?;
@@ -35,3 +99,724 @@ impl RuleFile {
         Ok(serde_yaml::from_reader(reader)?)
     }
 }
+
+/// One attribute difference between the current header and the rule result.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetadataChange {
+    pub key: String,
+    pub before: Option<MetadataValue>,
+    pub after: Option<MetadataValue>,
+    /// Parts of a multi-part file the change applies to (empty for
+    /// single-part files).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub parts: Vec<String>,
+}
+
+/// Text values are quoted; typed ones carry their type: `24/1 (rational)`.
+struct Shown<'a>(&'a MetadataValue);
+
+impl fmt::Display for Shown<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            MetadataValue::Text(s) => write!(f, "{:?}", s),
+            v => write!(f, "{} ({})", v, v.kind()),
+        }
+    }
+}
+
+impl fmt::Display for MetadataChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.before, &self.after) {
+            (None, Some(a)) => write!(f, "+ {}: {}", self.key, Shown(a)),
+            (Some(b), None) => write!(f, "- {}: {}", self.key, Shown(b)),
+            (Some(b), Some(a)) => write!(f, "~ {}: {} -> {}", self.key, Shown(b), Shown(a)),
+            (None, None) => write!(f, "  {}", self.key),
+        }?;
+        if !self.parts.is_empty() {
+            write!(f, " [{}]", self.parts.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of evaluating the rules for one file, before anything is written.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RulePlan {
+    pub changes: Vec<MetadataChange>,
+    /// Rules that did not apply (missing copy source, filename mismatch, ...).
+    pub notes: Vec<String>,
+    /// Values that could not be produced (failed template expansion, bad
+    /// value for the type, ...). [`RuleEngine::apply`] writes nothing when
+    /// there are any.
+    pub errors: Vec<String>,
+}
+
+impl RulePlan {
+    /// Attributes to set and remove, in the form `edit_metadata` expects.
+    pub fn edits(&self) -> (HashMap<String, MetadataValue>, Vec<String>) {
+        let mut set = HashMap::new();
+        let mut unset = Vec::new();
+        for c in &self.changes {
+            match &c.after {
+                Some(v) => {
+                    set.insert(c.key.clone(), v.clone());
+                }
+                None => unset.push(c.key.clone()),
+            }
+        }
+        (set, unset)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RuleRunOptions {
+    /// Only compute the diff, do not touch the file.
+    pub dry_run: bool,
+    /// Copy the file to `<name>.exr.bak` before rewriting it.
+    pub backup: bool,
+    /// Allow a rule to change the type of an existing custom attribute.
+    pub force_type: bool,
+    /// Compare the rewritten chunk data with the original before replacing
+    /// the file.
+    pub verify: bool,
+    /// Overrides the rule file's `part`.
+    pub part: Option<PartTarget>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum FileStatus {
+    Unchanged,
+    DryRun,
+    Written { backup: Option<PathBuf> },
+    Failed { error: String },
+}
+
+/// Per-file outcome of [`RuleEngine::apply`].
+#[derive(Debug, Clone, Serialize)]
+pub struct FileReport {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub status: FileStatus,
+    pub changes: Vec<MetadataChange>,
+    pub notes: Vec<String>,
+    /// Where each written or removed attribute ended up.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub placements: Vec<Placement>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Var { name: String, pad: usize },
+    Attr(String),
+    Now(String),
+}
+
+/// A parsed `Rule::Set` value (also used for batch output paths and burn-ins).
+#[derive(Debug, Clone)]
+pub(crate) struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    pub(crate) fn parse(text: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut lit = String::new();
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    lit.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    lit.push('}');
+                }
+                '}' => return Err(anyhow!("unmatched '}}' in {:?}", text)),
+                '{' => {
+                    let mut inner = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => inner.push(c),
+                            None => return Err(anyhow!("unclosed '{{' in {:?}", text)),
+                        }
+                    }
+                    if !lit.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut lit)));
+                    }
+                    segments.push(Self::placeholder(&inner)?);
+                }
+                c => lit.push(c),
+            }
+        }
+        if !lit.is_empty() {
+            segments.push(Segment::Literal(lit));
+        }
+        Ok(Self { segments })
+    }
+
+    fn placeholder(inner: &str) -> Result<Segment> {
+        let (name, spec) = match inner.split_once(':') {
+            Some((n, s)) => (n.trim(), Some(s)),
+            None => (inner.trim(), None),
+        };
+        if name.is_empty() {
+            return Err(anyhow!("empty placeholder '{{{}}}'", inner));
+        }
+        Ok(match (name, spec) {
+            ("attr", Some(key)) if !key.trim().is_empty() => {
+                Segment::Attr(canonical_key(key.trim()))
+            }
+            ("attr", _) => return Err(anyhow!("'{{attr:NAME}}' needs an attribute name")),
+            ("now", spec) => {
+                let fmt = spec.unwrap_or("%Y-%m-%d").to_string();
+                if StrftimeItems::new(&fmt).any(|i| matches!(i, Item::Error)) {
+                    return Err(anyhow!("invalid date format {:?}", fmt));
+                }
+                Segment::Now(fmt)
+            }
+            (name, None) => Segment::Var {
+                name: name.to_string(),
+                pad: 0,
+            },
+            (name, Some(spec)) => {
+                let pad = spec
+                    .strip_prefix('0')
+                    .and_then(|w| w.parse().ok())
+                    .ok_or_else(|| {
+                        anyhow!("unsupported format '{{{}}}' (use '{{{}:0N}}')", inner, name)
+                    })?;
+                Segment::Var {
+                    name: name.to_string(),
+                    pad,
+                }
+            }
+        })
+    }
+
+    fn is_literal(&self) -> bool {
+        self.segments
+            .iter()
+            .all(|s| matches!(s, Segment::Literal(_)))
+    }
+
+    fn expand(&self, ctx: &EvalContext, attrs: &BTreeMap<String, MetadataValue>) -> Result<String> {
+        self.expand_with(
+            &|name| ctx.var(name),
+            &|key| attrs.get(key).map(|v| v.to_string()),
+            ctx.now,
+        )
+    }
+
+    /// Expand with caller-supplied variables and attribute lookup.
+    pub(crate) fn expand_with(
+        &self,
+        var: &dyn Fn(&str) -> Option<String>,
+        attr: &dyn Fn(&str) -> Option<String>,
+        now: DateTime<Local>,
+    ) -> Result<String> {
+        let mut out = String::new();
+        for seg in &self.segments {
+            match seg {
+                Segment::Literal(s) => out.push_str(s),
+                Segment::Attr(key) => match attr(key) {
+                    Some(v) => out.push_str(&v),
+                    None => return Err(anyhow!("attribute '{}' not found", key)),
+                },
+                Segment::Now(fmt) => out.push_str(&now.format(fmt).to_string()),
+                Segment::Var { name, pad } => {
+                    let v = var(name).ok_or_else(|| anyhow!("unknown variable '{{{}}}'", name))?;
+                    if *pad == 0 {
+                        out.push_str(&v);
+                    } else {
+                        let n: i64 = v
+                            .parse()
+                            .map_err(|_| anyhow!("'{{{}}}' is not a number: {:?}", name, v))?;
+                        out.push_str(&format!("{:0width$}", n, width = pad));
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Per-file values templates and conditions can refer to.
+struct EvalContext<'a> {
+    file_name: &'a str,
+    info: &'a HeaderInfo,
+    now: DateTime<Local>,
+    /// Named groups captured by `from_filename` rules so far.
+    captures: HashMap<String, String>,
+}
+
+impl EvalContext<'_> {
+    fn var(&self, name: &str) -> Option<String> {
+        if let Some(v) = self.captures.get(name) {
+            return Some(v.clone());
+        }
+        match name {
+            "filename" => Some(self.file_name.to_string()),
+            "stem" => Some(
+                Path::new(self.file_name)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            ),
+            "width" => self.info.width.map(|w| w.to_string()),
+            "height" => self.info.height.map(|h| h.to_string()),
+            _ => None,
+        }
+    }
+}
+
+enum CompiledCondition {
+    Exists(String),
+    Equals(String, String),
+    Matches(String, Regex),
+    Filename(Pattern),
+    Resolution(Option<usize>, Option<usize>),
+    Layer(Pattern),
+    All(Vec<CompiledCondition>),
+    Any(Vec<CompiledCondition>),
+    Not(Box<CompiledCondition>),
+}
+
+impl CompiledCondition {
+    fn new(c: &Condition) -> Result<Self> {
+        let glob = |g: &str| Pattern::new(g).with_context(|| format!("invalid glob {:?}", g));
+        Ok(match c {
+            Condition::Exists(key) => Self::Exists(canonical_key(key)),
+            Condition::Equals { key, value } => Self::Equals(canonical_key(key), value.clone()),
+            Condition::Matches { key, pattern } => Self::Matches(
+                canonical_key(key),
+                Regex::new(pattern).with_context(|| format!("invalid pattern {:?}", pattern))?,
+            ),
+            Condition::Filename(g) => Self::Filename(glob(g)?),
+            Condition::Resolution { width, height } => Self::Resolution(*width, *height),
+            Condition::Layer(g) => Self::Layer(glob(g)?),
+            Condition::All(list) => Self::All(list.iter().map(Self::new).collect::<Result<_>>()?),
+            Condition::Any(list) => Self::Any(list.iter().map(Self::new).collect::<Result<_>>()?),
+            Condition::Not(c) => Self::Not(Box::new(Self::new(c)?)),
+        })
+    }
+
+    fn eval(&self, ctx: &EvalContext, attrs: &BTreeMap<String, MetadataValue>) -> bool {
+        match self {
+            Self::Exists(key) => attrs.contains_key(key),
+            Self::Equals(key, value) => attrs.get(key).is_some_and(|v| v.to_string() == *value),
+            Self::Matches(key, re) => attrs.get(key).is_some_and(|v| re.is_match(&v.to_string())),
+            Self::Filename(p) => p.matches(ctx.file_name),
+            Self::Resolution(w, h) => {
+                let side = |want: &Option<usize>, got: Option<usize>| {
+                    want.is_none() || (got.is_some() && got == *want)
+                };
+                side(w, ctx.info.width) && side(h, ctx.info.height)
+            }
+            Self::Layer(p) => ctx.info.layers.iter().any(|l| p.matches(l)),
+            Self::All(list) => list.iter().all(|c| c.eval(ctx, attrs)),
+            Self::Any(list) => list.iter().any(|c| c.eval(ctx, attrs)),
+            Self::Not(c) => !c.eval(ctx, attrs),
+        }
+    }
+}
+
+enum Action {
+    Set {
+        key: String,
+        value: Template,
+        kind: ValueKind,
+    },
+    Unset {
+        key: String,
+    },
+    Copy {
+        from: String,
+        to: String,
+    },
+    FromFilename {
+        regex: Regex,
+        mapping: Vec<(String, String)>,
+    },
+}
+
+struct CompiledRule {
+    action: Action,
+    when: Vec<CompiledCondition>,
+}
+
+/// Metadata rule engine: a validated [`RuleFile`] with its regexes,
+/// globs and templates compiled.
+pub struct RuleEngine {
+    rules: Vec<CompiledRule>,
+    part: PartTarget,
+}
+
+impl RuleEngine {
+    pub fn new(file: &RuleFile) -> Result<Self> {
+        let mut rules = Vec::with_capacity(file.rules.len());
+        for (i, r) in file.rules.iter().enumerate() {
+            let check = |key: &str| {
+                if key.trim().is_empty() {
+                    Err(anyhow!("rule {}: attribute name must not be empty", i + 1))
+                } else {
+                    Ok(canonical_key(key))
+                }
+            };
+            let (action, when) = match r {
+                Rule::Set {
+                    key,
+                    value,
+                    value_type,
+                    when,
+                } => {
+                    let template = Template::parse(value)
+                        .with_context(|| format!("rule {}: invalid value template", i + 1))?;
+                    // catch typos in constant typed values up front
+                    if template.is_literal() {
+                        MetadataValue::parse(
+                            *value_type,
+                            &value.replace("{{", "{").replace("}}", "}"),
+                        )
+                        .with_context(|| format!("rule {}", i + 1))?;
+                    }
+                    (
+                        Action::Set {
+                            key: check(key)?,
+                            value: template,
+                            kind: *value_type,
+                        },
+                        when,
+                    )
+                }
+                Rule::Unset { key, when } => (Action::Unset { key: check(key)? }, when),
+                Rule::Copy { from, to, when } => (
+                    Action::Copy {
+                        from: check(from)?,
+                        to: check(to)?,
+                    },
+                    when,
+                ),
+                Rule::FromFilename {
+                    pattern,
+                    mapping,
+                    when,
+                } => {
+                    let regex = Regex::new(pattern).with_context(|| {
+                        format!("rule {}: invalid pattern {:?}", i + 1, pattern)
+                    })?;
+                    let mut pairs = Vec::new();
+                    for (group, key) in mapping {
+                        if !regex.capture_names().flatten().any(|n| n == group) {
+                            return Err(anyhow!(
+                                "rule {}: pattern has no named group '{}'",
+                                i + 1,
+                                group
+                            ));
+                        }
+                        pairs.push((group.clone(), check(key)?));
+                    }
+                    // HashMap order is random; keep reports stable
+                    pairs.sort();
+                    (
+                        Action::FromFilename {
+                            regex,
+                            mapping: pairs,
+                        },
+                        when,
+                    )
+                }
+            };
+            let when = when
+                .iter()
+                .map(CompiledCondition::new)
+                .collect::<Result<_>>()
+                .with_context(|| format!("rule {}: invalid condition", i + 1))?;
+            rules.push(CompiledRule { action, when });
+        }
+        Ok(Self {
+            rules,
+            part: file.part.clone().unwrap_or_default(),
+        })
+    }
+
+    pub fn from_path(path: &Path) -> Result<Self> {
+        Self::new(&RuleFile::from_path(path)?)
+    }
+
+    /// Evaluate the rules in order against `current` attributes of a file
+    /// named `file_name`. Later rules see the result of earlier ones.
+    /// Resolution and layer conditions never match here; use
+    /// [`RuleEngine::plan_for`] when the header is at hand.
+    pub fn plan(&self, file_name: &str, current: &BTreeMap<String, MetadataValue>) -> RulePlan {
+        let info = HeaderInfo {
+            attributes: current.clone(),
+            ..HeaderInfo::default()
+        };
+        self.plan_for(file_name, &info)
+    }
+
+    /// Like [`RuleEngine::plan`], with the file's size and layers available
+    /// to conditions and templates.
+    pub fn plan_for(&self, file_name: &str, info: &HeaderInfo) -> RulePlan {
+        let current = &info.attributes;
+        let mut ctx = EvalContext {
+            file_name,
+            info,
+            now: Local::now(),
+            captures: HashMap::new(),
+        };
+        let mut attrs = current.clone();
+        let mut notes = Vec::new();
+        let mut errors = Vec::new();
+        for (i, rule) in self.rules.iter().enumerate() {
+            if !rule.when.iter().all(|c| c.eval(&ctx, &attrs)) {
+                continue;
+            }
+            match &rule.action {
+                Action::Set { key, value, kind } => {
+                    match value
+                        .expand(&ctx, &attrs)
+                        .and_then(|text| MetadataValue::parse(*kind, &text))
+                        .and_then(|v| coerce_for_key(key, &v))
+                    {
+                        Ok(v) => {
+                            attrs.insert(key.clone(), v);
+                        }
+                        Err(e) => errors.push(format!("set {} (rule {}): {}", key, i + 1, e)),
+                    }
+                }
+                Action::Unset { key } => {
+                    attrs.remove(key);
+                }
+                Action::Copy { from, to } => match attrs.get(from).map(|v| coerce_for_key(to, v)) {
+                    Some(Ok(v)) => {
+                        attrs.insert(to.clone(), v);
+                    }
+                    Some(Err(e)) => errors.push(format!("copy: {:#}", e)),
+                    None => notes.push(format!("copy: '{}' not found", from)),
+                },
+                Action::FromFilename { regex, mapping } => match regex.captures(file_name) {
+                    Some(caps) => {
+                        for name in regex.capture_names().flatten() {
+                            if let Some(m) = caps.name(name) {
+                                ctx.captures
+                                    .insert(name.to_string(), m.as_str().to_string());
+                            }
+                        }
+                        for (group, key) in mapping {
+                            let Some(m) = caps.name(group) else { continue };
+                            match coerce_for_key(key, &m.as_str().into()) {
+                                Ok(v) => {
+                                    attrs.insert(key.clone(), v);
+                                }
+                                Err(e) => errors.push(format!("from_filename: {:#}", e)),
+                            }
+                        }
+                    }
+                    None => notes.push(format!(
+                        "from_filename: '{}' does not match {}",
+                        file_name, regex
+                    )),
+                },
+            }
+        }
+
+        let mut changes = Vec::new();
+        for (k, v) in &attrs {
+            match current.get(k) {
+                Some(old) if old == v => {}
+                old => changes.push(MetadataChange {
+                    key: k.clone(),
+                    before: old.cloned(),
+                    after: Some(v.clone()),
+                    parts: Vec::new(),
+                }),
+            }
+        }
+        for (k, v) in current {
+            if !attrs.contains_key(k) {
+                changes.push(MetadataChange {
+                    key: k.clone(),
+                    before: Some(v.clone()),
+                    after: None,
+                    parts: Vec::new(),
+                });
+            }
+        }
+        changes.sort_by(|a, b| a.key.cmp(&b.key));
+        RulePlan {
+            changes,
+            notes,
+            errors,
+        }
+    }
+
+    /// Evaluate the rules for one EXR and, unless `dry_run`, write the result.
+    ///
+    /// Every part the rules target is planned from its own header. Parts
+    /// that end up with different edits still get them in one rewrite.
+    pub fn apply(&self, path: &Path, opts: &RuleRunOptions) -> FileReport {
+        let mut report = FileReport {
+            path: path.to_path_buf(),
+            status: FileStatus::Unchanged,
+            changes: Vec::new(),
+            notes: Vec::new(),
+            placements: Vec::new(),
+        };
+        report.status = self
+            .run(path, opts, &mut report)
+            .unwrap_or_else(|e| FileStatus::Failed {
+                error: format!("{:#}", e),
+            });
+        report
+    }
+
+    fn run(
+        &self,
+        path: &Path,
+        opts: &RuleRunOptions,
+        report: &mut FileReport,
+    ) -> Result<FileStatus> {
+        let target = opts.part.clone().unwrap_or_else(|| self.part.clone());
+        let parts = read_part_attributes(path)?;
+        let names: Vec<Option<String>> = parts
+            .iter()
+            .map(|p| p.attributes.get("name").map(|n| n.to_string()))
+            .collect();
+        let selected = target_parts(&target, &names)?;
+        let layers = part_layers(&parts);
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        // parts with the same result are reported and written together
+        let mut groups: Vec<(Vec<usize>, RulePlan)> = Vec::new();
+        let mut errors = Vec::new();
+        for i in selected {
+            let plan = self.plan_for(&file_name, &part_info(&parts[i], &layers));
+            for e in &plan.errors {
+                if !errors.contains(e) {
+                    errors.push(e.clone());
+                }
+            }
+            match groups.iter_mut().find(|(_, g)| g.changes == plan.changes) {
+                Some((members, _)) => members.push(i),
+                None => groups.push((vec![i], plan)),
+            }
+        }
+        for (members, plan) in &groups {
+            let labels: Vec<String> = if parts.len() > 1 {
+                members.iter().map(|&i| parts[i].name.clone()).collect()
+            } else {
+                Vec::new()
+            };
+            report
+                .changes
+                .extend(plan.changes.iter().map(|c| MetadataChange {
+                    parts: labels.clone(),
+                    ..c.clone()
+                }));
+            for n in &plan.notes {
+                if !report.notes.contains(n) {
+                    report.notes.push(n.clone());
+                }
+            }
+        }
+        if !errors.is_empty() {
+            bail!("{}", errors.join("; "));
+        }
+        if report.changes.is_empty() {
+            return Ok(FileStatus::Unchanged);
+        }
+        if opts.dry_run {
+            return Ok(FileStatus::DryRun);
+        }
+
+        let edits = if groups.len() == 1 {
+            let (set, unset) = groups[0].1.edits();
+            vec![PartEdit {
+                part: target,
+                set,
+                unset,
+            }]
+        } else {
+            if target == PartTarget::Shared {
+                bail!("the rules give the parts different results, so they cannot go in the shared header; use part 'all'");
+            }
+            let mut edits = Vec::new();
+            for (members, plan) in &groups {
+                let (set, unset) = plan.edits();
+                for &i in members {
+                    let name = names[i]
+                        .clone()
+                        .ok_or_else(|| anyhow!("part {} has no name", parts[i].name))?;
+                    edits.push(PartEdit {
+                        part: PartTarget::Name(name),
+                        set: set.clone(),
+                        unset: unset.clone(),
+                    });
+                }
+            }
+            edits
+        };
+        let write = WriteOptions {
+            force_type: opts.force_type,
+            verify: opts.verify,
+            ..Default::default()
+        };
+        let (placements, backup) = write_with_backup(path, opts.backup, || {
+            edit_metadata_parts(path, &edits, &write)
+        })?;
+        report.placements = placements;
+        Ok(FileStatus::Written { backup })
+    }
+}
+
+/// Part names plus channel-name prefixes (`diffuse` for `diffuse.R`) of
+/// every part, in file order.
+fn part_layers(parts: &[PartAttributes]) -> Vec<String> {
+    let mut layers: Vec<String> = Vec::new();
+    let mut add = |l: &str| {
+        if !layers.iter().any(|x| x == l) {
+            layers.push(l.to_string());
+        }
+    };
+    for p in parts {
+        if let Some(name) = p.attributes.get("name") {
+            add(&name.to_string());
+        }
+        // described as `R:half,diffuse.G:half,...`
+        if let Some(channels) = p.attributes.get("channels") {
+            for ch in channels.to_string().split(',') {
+                let name = ch.rsplit_once(':').map_or(ch, |(n, _)| n);
+                if let Some((layer, _)) = name.rsplit_once('.') {
+                    add(layer);
+                }
+            }
+        }
+    }
+    layers
+}
+
+/// The header of one part as rule conditions see it: its attributes, its
+/// data window size and the layers of the whole file.
+fn part_info(part: &PartAttributes, layers: &[String]) -> HeaderInfo {
+    let size = match part.attributes.get("dataWindow") {
+        Some(MetadataValue::Box2i { min, max }) => Some((
+            (max[0] - min[0] + 1).max(0) as usize,
+            (max[1] - min[1] + 1).max(0) as usize,
+        )),
+        _ => None,
+    };
+    HeaderInfo {
+        attributes: part.attributes.clone(),
+        width: size.map(|s| s.0),
+        height: size.map(|s| s.1),
+        layers: layers.to_vec(),
+    }
+}
@@ -0,0 +1,493 @@
+//! Metadata sidecars: per-file attribute records that can be exported to
+//! JSON or CSV, edited (e.g. in a spreadsheet) and imported back, plus XMP
+//! packets for tools that read `.xmp` sidecars.
+//!
+//! The JSON/CSV encoding itself is left to the caller; this module works on
+//! [`SidecarRecord`]s and on plain string tables ([`MetadataTable`]).
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::header::LAYOUT_ATTRIBUTES;
+use crate::metadata::{
+    canonical_key, coerce_for_key, edit_metadata, edit_metadata_parts, read_part_attributes,
+    standard_kind, MetadataValue, PartAttributes, PartEdit, PartTarget, Placement, ValueKind,
+    WriteOptions,
+};
+use crate::timecode::FrameRate;
+
+/// Attributes every header must have; an import never removes them.
+const REQUIRED_ATTRIBUTES: [&str; 4] = [
+    "displayWindow",
+    "pixelAspectRatio",
+    "screenWindowCenter",
+    "screenWindowWidth",
+];
+
+/// One attribute value in a sidecar.
+///
+/// Text is stored as a plain string; other types carry their type so they
+/// survive the round trip (`{"type": "rational", "value": "24/1"}`). A plain
+/// string for a standard attribute is read as that attribute's type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SidecarValue {
+    Text(String),
+    Typed {
+        #[serde(rename = "type")]
+        kind: ValueKind,
+        value: String,
+    },
+}
+
+impl SidecarValue {
+    pub fn from_value(value: &MetadataValue) -> Self {
+        match value {
+            MetadataValue::Text(s) => SidecarValue::Text(s.clone()),
+            v => SidecarValue::Typed {
+                kind: v.kind(),
+                value: v.to_string(),
+            },
+        }
+    }
+
+    /// The value to write for `key`.
+    pub fn to_value(&self, key: &str) -> Result<MetadataValue> {
+        match self {
+            SidecarValue::Text(s) => match standard_kind(key) {
+                Some(kind) => MetadataValue::parse(kind, s),
+                None => Ok(MetadataValue::Text(s.clone())),
+            },
+            SidecarValue::Typed { kind, value } => MetadataValue::parse(*kind, value),
+        }
+    }
+}
+
+/// The attributes of one part of one file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SidecarRecord {
+    pub file: PathBuf,
+    /// Part name, or `#<index>` for unnamed parts.
+    pub part: String,
+    pub attributes: BTreeMap<String, SidecarValue>,
+}
+
+/// Records for every part of `path`, leaving out the layout attributes
+/// (`channels`, `compression`, ...) that a header edit cannot change.
+pub fn export_file(path: &Path) -> Result<Vec<SidecarRecord>> {
+    let parts = read_part_attributes(path).with_context(|| format!("{}", path.display()))?;
+    Ok(parts
+        .into_iter()
+        .map(|p| SidecarRecord {
+            file: path.to_path_buf(),
+            part: p.name,
+            attributes: p
+                .attributes
+                .iter()
+                .filter(|(k, _)| !LAYOUT_ATTRIBUTES.contains(&k.as_str()))
+                .map(|(k, v)| (k.clone(), SidecarValue::from_value(v)))
+                .collect(),
+        })
+        .collect())
+}
+
+/// Records as a table with one row per record: `file`, `part`, then one
+/// column per attribute. Non-text columns are named `key:type`
+/// (`framesPerSecond:rational`); empty cells mean the attribute is absent.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetadataTable {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+fn column_name(key: &str, value: &SidecarValue) -> String {
+    match value {
+        SidecarValue::Text(_) => key.to_string(),
+        SidecarValue::Typed { kind, .. } => format!("{}:{}", key, kind),
+    }
+}
+
+/// Attribute name and type of a column; names without a known type suffix
+/// are plain text columns.
+fn parse_column(column: &str) -> (&str, Option<ValueKind>) {
+    match column.rsplit_once(':') {
+        Some((key, kind)) => match kind.parse() {
+            Ok(kind) if !key.is_empty() => (key, Some(kind)),
+            _ => (column, None),
+        },
+        None => (column, None),
+    }
+}
+
+impl MetadataTable {
+    pub fn from_records(records: &[SidecarRecord]) -> Self {
+        let mut columns: Vec<String> = records
+            .iter()
+            .flat_map(|r| r.attributes.iter().map(|(k, v)| column_name(k, v)))
+            .collect();
+        columns.sort();
+        columns.dedup();
+        let rows = records
+            .iter()
+            .map(|r| {
+                let cells: HashMap<String, String> = r
+                    .attributes
+                    .iter()
+                    .map(|(k, v)| {
+                        let text = match v {
+                            SidecarValue::Text(s) => s.clone(),
+                            SidecarValue::Typed { value, .. } => value.clone(),
+                        };
+                        (column_name(k, v), text)
+                    })
+                    .collect();
+                [r.file.to_string_lossy().into_owned(), r.part.clone()]
+                    .into_iter()
+                    .chain(
+                        columns
+                            .iter()
+                            .map(|c| cells.get(c).cloned().unwrap_or_default()),
+                    )
+                    .collect()
+            })
+            .collect();
+        let columns = ["file", "part"]
+            .into_iter()
+            .map(String::from)
+            .chain(columns)
+            .collect();
+        MetadataTable { columns, rows }
+    }
+
+    pub fn to_records(&self) -> Result<Vec<SidecarRecord>> {
+        let position = |name: &str| {
+            self.columns
+                .iter()
+                .position(|c| c == name)
+                .with_context(|| format!("table has no '{}' column", name))
+        };
+        let (file, part) = (position("file")?, position("part")?);
+        self.rows
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                if row.len() != self.columns.len() {
+                    bail!(
+                        "row {} has {} cells, expected {}",
+                        i + 1,
+                        row.len(),
+                        self.columns.len()
+                    );
+                }
+                let mut attributes = BTreeMap::new();
+                for (c, (column, cell)) in self.columns.iter().zip(row).enumerate() {
+                    if c == file || c == part || cell.is_empty() {
+                        continue;
+                    }
+                    let (key, kind) = parse_column(column);
+                    let value = match kind {
+                        Some(kind) => SidecarValue::Typed {
+                            kind,
+                            value: cell.clone(),
+                        },
+                        None => SidecarValue::Text(cell.clone()),
+                    };
+                    attributes.insert(key.to_string(), value);
+                }
+                Ok(SidecarRecord {
+                    file: PathBuf::from(&row[file]),
+                    part: row[part].clone(),
+                    attributes,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Options for [`plan_import`].
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    /// Remove attributes the record does not list (required and layout
+    /// attributes are always kept).
+    pub remove_missing: bool,
+}
+
+/// The edits that bring one part of a file in line with its record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportChange {
+    pub file: PathBuf,
+    pub part: PartTarget,
+    pub set: BTreeMap<String, MetadataValue>,
+    pub unset: Vec<String>,
+}
+
+impl ImportChange {
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty() && self.unset.is_empty()
+    }
+
+    /// Write the change with [`edit_metadata`]; `opts.part` is replaced by
+    /// the record's part.
+    pub fn apply(&self, opts: &WriteOptions) -> Result<Vec<Placement>> {
+        let set: HashMap<String, MetadataValue> = self.set.clone().into_iter().collect();
+        let opts = WriteOptions {
+            part: self.part.clone(),
+            ..opts.clone()
+        };
+        edit_metadata(&self.file, &set, &self.unset, &opts)
+    }
+}
+
+/// Group changes by file, in the order each file first appears, so that
+/// records for several parts of one file can be written together with
+/// [`apply_changes`].
+pub fn group_by_file(changes: &[ImportChange]) -> Vec<(&Path, Vec<&ImportChange>)> {
+    let mut groups: Vec<(&Path, Vec<&ImportChange>)> = Vec::new();
+    for c in changes {
+        match groups.iter_mut().find(|(f, _)| *f == c.file) {
+            Some((_, group)) => group.push(c),
+            None => groups.push((&c.file, vec![c])),
+        }
+    }
+    groups
+}
+
+/// Write the changes for one file in a single header rewrite, each to its
+/// own part. `opts.part` is ignored and re-encoding is not supported.
+pub fn apply_changes(
+    file: &Path,
+    changes: &[&ImportChange],
+    opts: &WriteOptions,
+) -> Result<Vec<Placement>> {
+    let edits: Vec<PartEdit> = changes
+        .iter()
+        .map(|c| {
+            if c.file != file {
+                bail!("{}: change belongs to {}", file.display(), c.file.display());
+            }
+            Ok(PartEdit {
+                part: c.part.clone(),
+                set: c.set.clone().into_iter().collect(),
+                unset: c.unset.clone(),
+            })
+        })
+        .collect::<Result<_>>()?;
+    edit_metadata_parts(file, &edits, opts)
+}
+
+impl fmt::Display for ImportChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if self.part != PartTarget::First {
+            write!(f, " [{}]", self.part)?;
+        }
+        for (k, v) in &self.set {
+            write!(f, " {}={}", k, v)?;
+        }
+        for k in &self.unset {
+            write!(f, " -{}", k)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare a record with the file it names and list the attributes that
+/// differ. Relative record paths are resolved against `base` (usually the
+/// directory of the imported table). Unchanged attributes are left out, so
+/// attributes of types that are only exported as text can be kept in the
+/// table as long as they are not edited.
+pub fn plan_import(
+    record: &SidecarRecord,
+    base: &Path,
+    opts: &ImportOptions,
+) -> Result<ImportChange> {
+    let file = base.join(&record.file);
+    let parts = read_part_attributes(&file).with_context(|| format!("{}", file.display()))?;
+    let Some(current) = parts.iter().find(|p| p.name == record.part) else {
+        let names: Vec<&str> = parts.iter().map(|p| p.name.as_str()).collect();
+        bail!(
+            "{}: no part '{}' (parts: {})",
+            file.display(),
+            record.part,
+            names.join(", ")
+        );
+    };
+    let current = &current.attributes;
+    let mut set = BTreeMap::new();
+    for (key, value) in &record.attributes {
+        let key = canonical_key(key);
+        let value = value
+            .to_value(&key)
+            .and_then(|v| coerce_for_key(&key, &v))
+            .with_context(|| format!("{}: {}", file.display(), key))?;
+        if current.get(&key) == Some(&value) {
+            continue;
+        }
+        if LAYOUT_ATTRIBUTES.contains(&key.as_str()) {
+            bail!(
+                "{}: {} describes the pixel layout and cannot be imported",
+                file.display(),
+                key
+            );
+        }
+        set.insert(key, value);
+    }
+    let mut unset = Vec::new();
+    if opts.remove_missing {
+        let listed: Vec<String> = record.attributes.keys().map(|k| canonical_key(k)).collect();
+        unset = current
+            .keys()
+            .filter(|k| {
+                !listed.contains(k)
+                    && !LAYOUT_ATTRIBUTES.contains(&k.as_str())
+                    && !REQUIRED_ATTRIBUTES.contains(&k.as_str())
+            })
+            .cloned()
+            .collect();
+    }
+    Ok(ImportChange {
+        file,
+        part: if parts.len() == 1 {
+            PartTarget::First
+        } else {
+            PartTarget::Name(record.part.clone())
+        },
+        set,
+        unset,
+    })
+}
+
+const XMP_NAMESPACES: [(&str, &str); 5] = [
+    ("rdf", "http://www.w3.org/1999/02/22-rdf-syntax-ns#"),
+    ("dc", "http://purl.org/dc/elements/1.1/"),
+    ("xmp", "http://ns.adobe.com/xap/1.0/"),
+    ("xmpDM", "http://ns.adobe.com/xmp/1.0/DynamicMedia/"),
+    ("exr", "urn:exrtool:exr:1.0/"),
+];
+
+fn xml_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// XMP `timeFormat` of a timecode counted at `rate`.
+fn xmp_time_format(rate: FrameRate, drop_frame: bool) -> String {
+    let digits: String = rate.to_string().chars().filter(|c| *c != '.').collect();
+    if rate.supports_drop_frame() {
+        let drop = if drop_frame { "Drop" } else { "NonDrop" };
+        format!("{}{}Timecode", digits, drop)
+    } else {
+        format!("{}Timecode", digits)
+    }
+}
+
+/// An XMP sidecar packet for a file.
+///
+/// Well-known attributes of the first part are mapped to Dublin Core and
+/// XMP properties (`owner` → `dc:creator`, `comments` → `dc:description`,
+/// `capDate` → `xmp:CreateDate`, `software` → `xmp:CreatorTool`,
+/// `framesPerSecond`/`timeCode` → `xmpDM:videoFrameRate`/`startTimecode`);
+/// every attribute of every part is also listed under `exr:attributes`.
+pub fn xmp_packet(parts: &[PartAttributes]) -> String {
+    let empty = BTreeMap::new();
+    let first = parts.first().map_or(&empty, |p| &p.attributes);
+    let text = |key: &str| match first.get(key) {
+        Some(MetadataValue::Text(s)) => Some(xml_escape(s)),
+        _ => None,
+    };
+    let rate = match first.get("framesPerSecond") {
+        Some(MetadataValue::Rational(n, d)) => FrameRate::ALL
+            .into_iter()
+            .find(|r| r.rational() == (*n, *d)),
+        _ => None,
+    };
+
+    let mut body = String::new();
+    if let Some(owner) = text("owner") {
+        body += &format!(
+            "   <dc:creator><rdf:Seq><rdf:li>{}</rdf:li></rdf:Seq></dc:creator>\n",
+            owner
+        );
+    }
+    if let Some(comments) = text("comments") {
+        body += &format!(
+            "   <dc:description><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt></dc:description>\n",
+            comments
+        );
+    }
+    // capDate is "YYYY:MM:DD HH:MM:SS"; XMP dates are ISO 8601
+    let created = match first.get("capDate") {
+        Some(MetadataValue::Text(s)) => {
+            chrono::NaiveDateTime::parse_from_str(s.trim(), "%Y:%m:%d %H:%M:%S").ok()
+        }
+        _ => None,
+    };
+    if let Some(created) = created {
+        body += &format!(
+            "   <xmp:CreateDate>{}</xmp:CreateDate>\n",
+            created.format("%Y-%m-%dT%H:%M:%S")
+        );
+    }
+    if let Some(software) = text("software") {
+        body += &format!("   <xmp:CreatorTool>{}</xmp:CreatorTool>\n", software);
+    }
+    if let Some(rate) = rate {
+        body += &format!("   <xmpDM:videoFrameRate>{}</xmpDM:videoFrameRate>\n", rate);
+    }
+    if let (Some(MetadataValue::TimeCode(tc)), Some(rate)) = (first.get("timeCode"), rate) {
+        body += &format!(
+            "   <xmpDM:startTimecode rdf:parseType=\"Resource\">\n    <xmpDM:timeValue>{}</xmpDM:timeValue>\n    <xmpDM:timeFormat>{}</xmpDM:timeFormat>\n   </xmpDM:startTimecode>\n",
+            tc.display_at(rate),
+            xmp_time_format(rate, tc.drop_frame)
+        );
+    }
+    body += "   <exr:attributes>\n    <rdf:Bag>\n";
+    for part in parts {
+        for (key, value) in &part.attributes {
+            body += &format!(
+                "     <rdf:li rdf:parseType=\"Resource\"><exr:part>{}</exr:part><exr:name>{}</exr:name><exr:type>{}</exr:type><exr:value>{}</exr:value></rdf:li>\n",
+                xml_escape(&part.name),
+                xml_escape(key),
+                value.kind(),
+                xml_escape(&value.to_string())
+            );
+        }
+    }
+    body += "    </rdf:Bag>\n   </exr:attributes>\n";
+
+    let namespaces: Vec<String> = XMP_NAMESPACES[1..]
+        .iter()
+        .map(|(prefix, uri)| format!("xmlns:{}=\"{}\"", prefix, uri))
+        .collect();
+    format!(
+        "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+         <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n \
+         <rdf:RDF xmlns:rdf=\"{}\">\n  \
+         <rdf:Description rdf:about=\"\"\n    {}>\n\
+         {}  \
+         </rdf:Description>\n \
+         </rdf:RDF>\n\
+         </x:xmpmeta>\n\
+         <?xpacket end=\"w\"?>\n",
+        XMP_NAMESPACES[0].1,
+        namespaces.join("\n    "),
+        body
+    )
+}
+
+/// Where the XMP sidecar of `path` goes: the same name with `.xmp`.
+pub fn xmp_path(path: &Path) -> PathBuf {
+    path.with_extension("xmp")
+}
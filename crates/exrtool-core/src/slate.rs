@@ -0,0 +1,279 @@
+//! Delivery extras: a slate frame in front of the shot and letterbox /
+//! pillarbox masks for the common projection aspects.
+
+use anyhow::{anyhow, bail, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::burnin::{glyph_pixels, text_height, text_width};
+use crate::timecode::frame_number;
+use crate::{PreviewImage, PreviewImage16, PreviewImageF32};
+
+/// Bars masking the frame down to `aspect` (letterbox when the frame is
+/// taller, pillarbox when it is wider). Written `2.39`, `16:9` or
+/// `1.85@0.5` for half-opaque bars.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct AspectMask {
+    pub aspect: f32,
+    /// 1 paints the bars black, 0 leaves the frame alone.
+    pub opacity: f32,
+}
+
+impl FromStr for AspectMask {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (aspect, opacity) = match s.split_once('@') {
+            Some((a, o)) => (a, Some(o)),
+            None => (s, None),
+        };
+        let number = |t: &str| {
+            t.trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|v| v.is_finite() && *v > 0.0)
+                .ok_or_else(|| anyhow!("invalid aspect mask {:?} (e.g. 2.39, 16:9, 1.85@0.5)", s))
+        };
+        let aspect = match aspect.split_once(':') {
+            Some((w, h)) => number(w)? / number(h)?,
+            None => number(aspect)?,
+        };
+        let opacity = match opacity {
+            Some(o) => o
+                .trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|o| (0.0..=1.0).contains(o))
+                .ok_or_else(|| anyhow!("mask opacity must be between 0 and 1: {:?}", o))?,
+            None => 1.0,
+        };
+        Ok(AspectMask { aspect, opacity })
+    }
+}
+
+impl TryFrom<String> for AspectMask {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<AspectMask> for String {
+    fn from(m: AspectMask) -> String {
+        m.to_string()
+    }
+}
+
+impl fmt::Display for AspectMask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.aspect)?;
+        if self.opacity < 1.0 {
+            write!(f, "@{}", self.opacity)?;
+        }
+        Ok(())
+    }
+}
+
+impl AspectMask {
+    /// The unmasked part of a `width`×`height` frame as `(x0, y0, x1, y1)`,
+    /// end exclusive.
+    pub fn visible(&self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let frame = width as f32 / height.max(1) as f32;
+        if frame > self.aspect {
+            let w = ((height as f32 * self.aspect).round() as u32).min(width);
+            let x0 = (width - w) / 2;
+            (x0, 0, x0 + w, height)
+        } else {
+            let h = ((width as f32 / self.aspect).round() as u32).min(height);
+            let y0 = (height - h) / 2;
+            (0, y0, width, y0 + h)
+        }
+    }
+
+    /// Darken the bars of an 8-bit frame.
+    pub fn apply(&self, img: &mut PreviewImage) {
+        let keep = 1.0 - self.opacity;
+        self.each_masked(img.width, img.height, |i| {
+            for v in &mut img.rgba8[i * 4..i * 4 + 3] {
+                *v = (*v as f32 * keep).round() as u8;
+            }
+        });
+    }
+
+    /// Darken the bars of a 16-bit frame.
+    pub fn apply16(&self, img: &mut PreviewImage16) {
+        let keep = 1.0 - self.opacity;
+        self.each_masked(img.width, img.height, |i| {
+            for v in &mut img.rgba16[i * 4..i * 4 + 3] {
+                *v = (*v as f32 * keep).round() as u16;
+            }
+        });
+    }
+
+    /// Darken the bars of a float frame.
+    pub fn apply_f32(&self, img: &mut PreviewImageF32) {
+        let keep = 1.0 - self.opacity;
+        self.each_masked(img.width, img.height, |i| {
+            for v in &mut img.rgba_f32[i * 4..i * 4 + 3] {
+                *v *= keep;
+            }
+        });
+    }
+
+    fn each_masked(&self, width: u32, height: u32, mut f: impl FnMut(usize)) {
+        let (x0, y0, x1, y1) = self.visible(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                if !(x0..x1).contains(&x) || !(y0..y1).contains(&y) {
+                    f((y * width + x) as usize);
+                }
+            }
+        }
+    }
+}
+
+/// The slate shown before the shot. Unset rows are left out, except the
+/// date (today), and the shot, colour and frames, which exports fill in
+/// with [`Slate::with_defaults`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Slate {
+    pub project: Option<String>,
+    pub shot: Option<String>,
+    pub version: Option<String>,
+    pub artist: Option<String>,
+    pub date: Option<String>,
+    /// Colour pipeline, e.g. `ACEScg -> ACES 1.3 Rec.709`.
+    pub colour: Option<String>,
+    /// Frame range, e.g. `1001-1100 (100 frames)`.
+    pub frames: Option<String>,
+    pub notes: Option<String>,
+    /// Frames the slate is held for in front of a movie.
+    pub hold: u32,
+}
+
+impl Default for Slate {
+    fn default() -> Self {
+        Slate {
+            project: None,
+            shot: None,
+            version: None,
+            artist: None,
+            date: None,
+            colour: None,
+            frames: None,
+            notes: None,
+            hold: 1,
+        }
+    }
+}
+
+impl Slate {
+    /// `self` with the shot, colour and frame range filled in from the
+    /// shot's `files` where not given.
+    pub fn with_defaults(&self, files: &[PathBuf], colour: &str) -> Slate {
+        let shot = files
+            .first()
+            .and_then(|f| f.file_stem())
+            .map(|s| crate::burnin::shot_name(&s.to_string_lossy()));
+        Slate {
+            shot: self.shot.clone().or(shot),
+            colour: self.colour.clone().or_else(|| Some(colour.to_string())),
+            frames: self
+                .frames
+                .clone()
+                .or_else(|| Some(frame_range_label(files))),
+            ..self.clone()
+        }
+    }
+
+    /// Label and value of every row, in slate order.
+    pub fn rows(&self) -> Vec<(&'static str, String)> {
+        let date = self
+            .date
+            .clone()
+            .unwrap_or_else(|| Local::now().format("%Y-%m-%d").to_string());
+        [
+            ("PROJECT", self.project.clone()),
+            ("SHOT", self.shot.clone()),
+            ("VERSION", self.version.clone()),
+            ("ARTIST", self.artist.clone()),
+            ("DATE", Some(date)),
+            ("COLOUR", self.colour.clone()),
+            ("FRAMES", self.frames.clone()),
+            ("NOTES", self.notes.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(label, value)| value.filter(|v| !v.is_empty()).map(|v| (label, v)))
+        .collect()
+    }
+
+    /// Draw the slate: labels right-aligned against values on black, as
+    /// large as fits the frame.
+    pub fn render(&self, width: u32, height: u32) -> Result<PreviewImage> {
+        if width == 0 || height == 0 {
+            bail!("slate size must not be zero");
+        }
+        let rows = self.rows();
+        let gap = |scale: u32| 4 * scale;
+        let label_w = |scale: u32| {
+            rows.iter()
+                .map(|(l, _)| text_width(l, scale))
+                .max()
+                .unwrap_or(0)
+        };
+        let value_w = |scale: u32| {
+            rows.iter()
+                .map(|(_, v)| text_width(v, scale))
+                .max()
+                .unwrap_or(0)
+        };
+        let line_h = |scale: u32| text_height(scale) * 2;
+        // largest scale that fits with a 5% margin, at most 1/200 of the height
+        let mut scale = (height / 200).max(1);
+        while scale > 1
+            && (2 * label_w(scale).max(value_w(scale)) + gap(scale) > width * 9 / 10
+                || rows.len() as u32 * line_h(scale) > height * 9 / 10)
+        {
+            scale -= 1;
+        }
+
+        let mut img = PreviewImage {
+            width,
+            height,
+            rgba8: [0, 0, 0, 255].repeat((width * height) as usize),
+        };
+        let centre = width as i64 / 2;
+        let block_h = rows.len() as i64 * line_h(scale) as i64;
+        let top = (height as i64 - block_h) / 2;
+        let mut put = |x: i64, y: i64, v: u8| {
+            if (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+                let i = (y as usize * width as usize + x as usize) * 4;
+                img.rgba8[i..i + 3].fill(v);
+            }
+        };
+        for (row, (label, value)) in rows.iter().enumerate() {
+            let y = top + row as i64 * line_h(scale) as i64;
+            let half_gap = gap(scale) as i64 / 2;
+            let lx = centre - half_gap - text_width(label, scale) as i64;
+            glyph_pixels(label, lx, y, scale, |x, y| put(x, y, 150));
+            glyph_pixels(value, centre + half_gap, y, scale, |x, y| put(x, y, 255));
+        }
+        Ok(img)
+    }
+}
+
+/// `1001-1100 (100 frames)` for a numbered sequence, the count otherwise.
+pub fn frame_range_label(files: &[PathBuf]) -> String {
+    let first = files.first().and_then(|f| frame_number(f));
+    let last = files.last().and_then(|f| frame_number(f));
+    match (first, last) {
+        (Some(a), Some(b)) => format!("{}-{} ({} frames)", a, b, files.len()),
+        _ => format!("{} frames", files.len()),
+    }
+}
@@ -0,0 +1,418 @@
+//! Still image writers for sequence exports: 8/16-bit PNG, 8/16-bit and
+//! float TIFF, JPEG and 10-bit DPX.
+//!
+//! Colour tags (the same [`ColorTags`] movies carry) go into an embedded
+//! ICC profile for PNG, TIFF and JPEG, and into the transfer and
+//! colorimetric fields of the DPX image element. Stills are full range RGB,
+//! so the matrix and range tags do not apply.
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Local, Timelike, Utc};
+use nalgebra::{Matrix3, Vector3};
+use std::borrow::Cow;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use tiff::encoder::colortype::{self, ColorType};
+use tiff::encoder::compression::Deflate;
+use tiff::encoder::{TiffEncoder, TiffValue};
+use tiff::tags::{Tag, Type};
+
+use crate::video::ColorTags;
+use crate::{
+    chromatic_adaptation_matrix, rgb_to_xyz, white_point_xyz, ChromaticAdaptation, PreviewImage,
+    PreviewImage16, PreviewImageF32, Primaries,
+};
+
+/// TIFF tag holding an ICC profile.
+const TIFF_ICC_PROFILE: u16 = 34675;
+/// D50, the ICC profile connection space white.
+const D50: [f64; 3] = [0.9642, 1.0, 0.8249];
+
+/// Tone curve of an ICC profile.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Curve {
+    Srgb,
+    Gamma(f64),
+}
+
+/// A matrix/TRC display profile (ICC v4) for `tags`, or `None` when the
+/// primaries or transfer are unknown or have no such curve (PQ, HLG).
+pub fn icc_profile(tags: &ColorTags) -> Option<Vec<u8>> {
+    let primaries = match tags.primaries? {
+        "bt709" => Primaries::SrgbD65,
+        "bt2020" => Primaries::Rec2020D65,
+        "smpte432" => Primaries::P3D65,
+        _ => return None,
+    };
+    let curve = match tags.transfer? {
+        "iec61966-2-1" => Curve::Srgb,
+        // display-referred video is decoded with BT.1886 (gamma 2.4)
+        "bt709" | "smpte170m" | "bt2020-10" | "bt2020-12" => Curve::Gamma(2.4),
+        "gamma22" => Curve::Gamma(2.2),
+        "gamma28" => Curve::Gamma(2.8),
+        // ST 428-1 is a 2.6 power curve (on DCDM X'Y'Z')
+        "smpte428" => Curve::Gamma(2.6),
+        "linear" => Curve::Gamma(1.0),
+        _ => return None,
+    };
+    let description = format!("exrtool {} {}", tags.primaries?, tags.transfer?);
+    Some(build_icc(primaries, curve, &description))
+}
+
+fn build_icc(primaries: Primaries, curve: Curve, description: &str) -> Vec<u8> {
+    let d50 = Vector3::new(D50[0], D50[1], D50[2]);
+    let chad = chromatic_adaptation_matrix(
+        white_point_xyz(primaries),
+        d50,
+        ChromaticAdaptation::Bradford,
+    );
+    let colorants: Matrix3<f64> = chad * rgb_to_xyz(primaries);
+    let xyz = |v: [f64; 3]| {
+        let mut t = tag_type(b"XYZ ");
+        v.iter().for_each(|&c| t.extend(s15f16(c)));
+        t
+    };
+    let column = |i: usize| {
+        let c = colorants.column(i);
+        xyz([c[0], c[1], c[2]])
+    };
+    let mut sf32 = tag_type(b"sf32");
+    for r in 0..3 {
+        for c in 0..3 {
+            sf32.extend(s15f16(chad[(r, c)]));
+        }
+    }
+    let mut trc = tag_type(b"para");
+    match curve {
+        Curve::Srgb => {
+            trc.extend([0, 3, 0, 0]);
+            for p in [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045] {
+                trc.extend(s15f16(p));
+            }
+        }
+        Curve::Gamma(g) => {
+            trc.extend([0, 0, 0, 0]);
+            trc.extend(s15f16(g));
+        }
+    }
+    let tags: Vec<(&[u8; 4], Vec<u8>)> = vec![
+        (b"desc", mluc(description)),
+        (b"cprt", mluc("No copyright, use freely")),
+        (b"wtpt", xyz(D50)),
+        (b"chad", sf32),
+        (b"rXYZ", column(0)),
+        (b"gXYZ", column(1)),
+        (b"bXYZ", column(2)),
+    ];
+    let trc_sigs = [b"rTRC", b"gTRC", b"bTRC"];
+
+    let table_len = 4 + 12 * (tags.len() + trc_sigs.len());
+    let mut data = Vec::new();
+    let mut table = Vec::new();
+    for (sig, body) in &tags {
+        table.push((**sig, 128 + table_len + data.len(), body.len()));
+        data.extend(body);
+        data.resize(data.len().next_multiple_of(4), 0);
+    }
+    // the three channels share one curve
+    let trc_offset = 128 + table_len + data.len();
+    for sig in trc_sigs {
+        table.push((*sig, trc_offset, trc.len()));
+    }
+    data.extend(&trc);
+    data.resize(data.len().next_multiple_of(4), 0);
+    let size = 128 + table_len + data.len();
+
+    let mut icc = Vec::with_capacity(size);
+    icc.extend(be32(size as u32));
+    icc.extend([0; 4]); // preferred CMM
+    icc.extend([4, 0x30, 0, 0]); // version 4.3
+    icc.extend(b"mntrRGB XYZ ");
+    let now = Utc::now();
+    let date = [
+        now.year() as u32,
+        now.month(),
+        now.day(),
+        now.hour(),
+        now.minute(),
+        now.second(),
+    ];
+    date.iter()
+        .for_each(|&v| icc.extend((v as u16).to_be_bytes()));
+    icc.extend(b"acsp");
+    icc.extend([0; 24]); // platform, flags, manufacturer, model, attributes
+    icc.extend([0; 4]); // perceptual intent
+    D50.iter().for_each(|&c| icc.extend(s15f16(c)));
+    icc.extend([0; 4]); // creator
+    icc.extend([0; 16]); // profile ID (not computed)
+    icc.extend([0; 28]);
+    debug_assert_eq!(icc.len(), 128);
+    icc.extend(be32(table.len() as u32));
+    for (sig, offset, len) in table {
+        icc.extend(sig);
+        icc.extend(be32(offset as u32));
+        icc.extend(be32(len as u32));
+    }
+    icc.extend(data);
+    icc
+}
+
+fn be32(v: u32) -> [u8; 4] {
+    v.to_be_bytes()
+}
+
+fn s15f16(v: f64) -> [u8; 4] {
+    ((v * 65536.0).round() as i32).to_be_bytes()
+}
+
+/// Type signature followed by the reserved word.
+fn tag_type(sig: &[u8; 4]) -> Vec<u8> {
+    let mut t = sig.to_vec();
+    t.extend([0; 4]);
+    t
+}
+
+/// A single `en-US` record of a multiLocalizedUnicodeType.
+fn mluc(text: &str) -> Vec<u8> {
+    let utf16: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+    let mut t = tag_type(b"mluc");
+    t.extend(be32(1));
+    t.extend(be32(12));
+    t.extend(b"enUS");
+    t.extend(be32(utf16.len() as u32));
+    t.extend(be32(28));
+    t.extend(utf16);
+    t
+}
+
+/// Colour fields and frame number of a DPX file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DpxInfo {
+    /// Transfer characteristic (SMPTE 268 table), 0 for user-defined.
+    pub transfer: u8,
+    /// Colorimetric specification (same table), 0 for user-defined.
+    pub colorimetric: u8,
+    /// Written as the film header's frame position.
+    pub frame: Option<u64>,
+}
+
+impl DpxInfo {
+    /// Codes for `tags`. DPX has no sRGB, P3, PQ or HLG codes; those are
+    /// written as user-defined.
+    pub fn new(tags: &ColorTags, frame: Option<u64>) -> Self {
+        let transfer = match tags.transfer {
+            Some("linear") => 2,
+            Some("bt709") => 6,
+            Some("smpte170m") => 8,
+            Some("bt2020-10") | Some("bt2020-12") => 14,
+            _ => 0,
+        };
+        let colorimetric = match tags.primaries {
+            Some("bt709") => 6,
+            Some("bt470bg") => 7,
+            Some("smpte170m") => 8,
+            Some("bt2020") => 14,
+            _ => 0,
+        };
+        DpxInfo {
+            transfer,
+            colorimetric,
+            frame,
+        }
+    }
+}
+
+/// Write an 8-bit RGBA PNG.
+pub fn write_png(path: &Path, img: &PreviewImage, icc: Option<&[u8]>) -> Result<()> {
+    write_png_data(
+        path,
+        img.width,
+        img.height,
+        png::BitDepth::Eight,
+        &img.rgba8,
+        icc,
+    )
+}
+
+/// Write a 16-bit RGBA PNG.
+pub fn write_png16(path: &Path, img: &PreviewImage16, icc: Option<&[u8]>) -> Result<()> {
+    let data: Vec<u8> = img.rgba16.iter().flat_map(|v| v.to_be_bytes()).collect();
+    write_png_data(
+        path,
+        img.width,
+        img.height,
+        png::BitDepth::Sixteen,
+        &data,
+        icc,
+    )
+}
+
+fn write_png_data(
+    path: &Path,
+    width: u32,
+    height: u32,
+    depth: png::BitDepth,
+    data: &[u8],
+    icc: Option<&[u8]>,
+) -> Result<()> {
+    let mut info = png::Info::with_size(width, height);
+    info.color_type = png::ColorType::Rgba;
+    info.bit_depth = depth;
+    info.icc_profile = icc.map(Cow::Borrowed);
+    let file = BufWriter::new(fs::File::create(path)?);
+    let mut writer = png::Encoder::with_info(file, info)?.write_header()?;
+    writer.write_image_data(data)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Write an 8-bit RGBA TIFF.
+pub fn write_tiff(path: &Path, img: &PreviewImage, icc: Option<&[u8]>) -> Result<()> {
+    write_tiff_data::<colortype::RGBA8>(path, img.width, img.height, &img.rgba8, icc)
+}
+
+/// Write a 16-bit RGBA TIFF.
+pub fn write_tiff16(path: &Path, img: &PreviewImage16, icc: Option<&[u8]>) -> Result<()> {
+    write_tiff_data::<colortype::RGBA16>(path, img.width, img.height, &img.rgba16, icc)
+}
+
+/// Write a 32-bit float RGBA TIFF.
+pub fn write_tiff_f32(path: &Path, img: &PreviewImageF32, icc: Option<&[u8]>) -> Result<()> {
+    write_tiff_data::<colortype::RGBA32Float>(path, img.width, img.height, &img.rgba_f32, icc)
+}
+
+fn write_tiff_data<C: ColorType>(
+    path: &Path,
+    width: u32,
+    height: u32,
+    data: &[C::Inner],
+    icc: Option<&[u8]>,
+) -> Result<()>
+where
+    [C::Inner]: TiffValue,
+{
+    let file = BufWriter::new(fs::File::create(path)?);
+    let mut tiff = TiffEncoder::new(file)?;
+    let mut image = tiff.new_image_with_compression::<C, _>(width, height, Deflate::default())?;
+    // the fourth sample is unassociated alpha
+    image.encoder().write_tag(Tag::ExtraSamples, 2u16)?;
+    if let Some(icc) = icc {
+        image
+            .encoder()
+            .write_tag(Tag::Unknown(TIFF_ICC_PROFILE), Undefined(icc))?;
+    }
+    image.write_data(data)?;
+    Ok(())
+}
+
+/// Opaque bytes, written with the UNDEFINED field type the ICC profile tag
+/// calls for (`[u8]` is written as BYTE).
+struct Undefined<'a>(&'a [u8]);
+
+impl TiffValue for Undefined<'_> {
+    const BYTE_LEN: u8 = 1;
+    const FIELD_TYPE: Type = Type::UNDEFINED;
+
+    fn count(&self) -> usize {
+        self.0.len()
+    }
+
+    fn data(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self.0)
+    }
+}
+
+/// Write a baseline JPEG (alpha is dropped) at `quality` (1-100).
+pub fn write_jpeg(path: &Path, img: &PreviewImage, quality: u8, icc: Option<&[u8]>) -> Result<()> {
+    let rgb: Vec<u8> = img
+        .rgba8
+        .chunks_exact(4)
+        .flat_map(|p| [p[0], p[1], p[2]])
+        .collect();
+    let mut jpeg = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, quality).encode(
+        &rgb,
+        img.width,
+        img.height,
+        image::ColorType::Rgb8,
+    )?;
+    if let Some(icc) = icc {
+        jpeg = with_icc_segment(jpeg, icc)?;
+    }
+    fs::write(path, jpeg)?;
+    Ok(())
+}
+
+/// `jpeg` with an APP2 `ICC_PROFILE` segment after SOI and the JFIF header.
+fn with_icc_segment(jpeg: Vec<u8>, icc: &[u8]) -> Result<Vec<u8>> {
+    const HEADER: &[u8] = b"ICC_PROFILE\0";
+    let len = 2 + HEADER.len() + 2 + icc.len();
+    let len = u16::try_from(len).map_err(|_| anyhow!("ICC profile too large for JPEG"))?;
+    let mut at = 2;
+    if jpeg.get(2..4) == Some(&[0xFF, 0xE0]) {
+        at += 2 + u16::from_be_bytes([jpeg[4], jpeg[5]]) as usize;
+    }
+    let mut out = Vec::with_capacity(jpeg.len() + len as usize + 2);
+    out.extend(&jpeg[..at]);
+    out.extend([0xFF, 0xE2]);
+    out.extend(len.to_be_bytes());
+    out.extend(HEADER);
+    out.extend([1, 1]); // chunk 1 of 1
+    out.extend(icc);
+    out.extend(&jpeg[at..]);
+    Ok(out)
+}
+
+/// DPX file, image and orientation headers, and the industry headers.
+const DPX_HEADER: usize = 2048;
+
+/// Write a 10-bit RGB DPX (filled to 32-bit words, method A, big endian).
+/// Alpha is dropped.
+pub fn write_dpx(path: &Path, img: &PreviewImage16, info: &DpxInfo) -> Result<()> {
+    let pixels = img.width as usize * img.height as usize;
+    let size = DPX_HEADER + pixels * 4;
+    let mut h = vec![0u8; DPX_HEADER];
+    let mut put = |at: usize, bytes: &[u8]| h[at..at + bytes.len()].copy_from_slice(bytes);
+    // file header
+    put(0, b"SDPX");
+    put(4, &be32(DPX_HEADER as u32));
+    put(8, b"V2.0");
+    put(16, &be32(size as u32));
+    put(20, &be32(1)); // new frame, not a ditto
+    put(24, &be32(1664)); // generic header
+    put(28, &be32(384)); // film + television headers
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    put(36, &name.as_bytes()[..name.len().min(99)]);
+    let created = Local::now().format("%Y:%m:%d:%H:%M:%S%z").to_string();
+    put(136, &created.as_bytes()[..created.len().min(23)]);
+    put(160, b"exrtool");
+    put(660, &be32(u32::MAX)); // not encrypted
+                               // image header
+    put(770, &1u16.to_be_bytes()); // one element
+    put(772, &be32(img.width));
+    put(776, &be32(img.height));
+    // image element 1
+    put(792, &be32(1023)); // reference high code (full range)
+    put(800, &[50, info.transfer, info.colorimetric, 10]); // RGB, 10 bits
+    put(804, &1u16.to_be_bytes()); // filled, method A
+    put(808, &be32(DPX_HEADER as u32));
+    put(820, b"RGB");
+    // film header
+    put(1712, &be32(info.frame.map_or(u32::MAX, |f| f as u32)));
+
+    let mut data = h;
+    data.reserve(pixels * 4);
+    let ten = |v: u16| (v as u32 * 1023 + 32767) / 65535;
+    for p in img.rgba16.chunks_exact(4) {
+        let word = ten(p[0]) << 22 | ten(p[1]) << 12 | ten(p[2]) << 2;
+        data.extend(be32(word));
+    }
+    let mut file = BufWriter::new(fs::File::create(path)?);
+    file.write_all(&data)?;
+    file.flush()?;
+    Ok(())
+}
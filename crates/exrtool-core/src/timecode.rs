@@ -0,0 +1,427 @@
+//! SMPTE timecode arithmetic and per-frame timecode stamping for sequences.
+//!
+//! Timecode labels are converted to and from absolute frame counts at one
+//! of the standard rates, with drop-frame counting at 29.97 and 59.94.
+//! EXR's `timeCode` attribute only has room for frame numbers up to 39, so
+//! at 50, 59.94 and 60 fps the frame field counts frame pairs and the
+//! field phase bit marks the second frame of each pair (SMPTE ST 12-1).
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::header::ExrHeaders;
+pub use crate::metadata::TimeCode;
+use crate::metadata::{MetadataValue, ValueKind};
+
+/// Frame rates timecode can be counted at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FrameRate {
+    Fps23_976,
+    Fps24,
+    Fps25,
+    Fps29_97,
+    Fps30,
+    Fps50,
+    Fps59_94,
+    Fps60,
+}
+
+impl FrameRate {
+    pub const ALL: [FrameRate; 8] = [
+        FrameRate::Fps23_976,
+        FrameRate::Fps24,
+        FrameRate::Fps25,
+        FrameRate::Fps29_97,
+        FrameRate::Fps30,
+        FrameRate::Fps50,
+        FrameRate::Fps59_94,
+        FrameRate::Fps60,
+    ];
+
+    /// Frames per timecode second (30 for 29.97).
+    pub fn nominal(self) -> u32 {
+        match self {
+            FrameRate::Fps23_976 | FrameRate::Fps24 => 24,
+            FrameRate::Fps25 => 25,
+            FrameRate::Fps29_97 | FrameRate::Fps30 => 30,
+            FrameRate::Fps50 => 50,
+            FrameRate::Fps59_94 | FrameRate::Fps60 => 60,
+        }
+    }
+
+    /// Exact rate, as written to `framesPerSecond`.
+    pub fn rational(self) -> (i32, u32) {
+        match self {
+            FrameRate::Fps23_976 => (24000, 1001),
+            FrameRate::Fps29_97 => (30000, 1001),
+            FrameRate::Fps59_94 => (60000, 1001),
+            r => (r.nominal() as i32, 1),
+        }
+    }
+
+    /// Whether drop-frame counting is defined for this rate.
+    pub fn supports_drop_frame(self) -> bool {
+        matches!(self, FrameRate::Fps29_97 | FrameRate::Fps59_94)
+    }
+
+    /// Labels skipped at the start of every minute not divisible by ten.
+    fn dropped_per_minute(self) -> u64 {
+        self.nominal() as u64 / 15
+    }
+
+    /// Frames in 24 hours of timecode.
+    pub fn frames_per_day(self, drop_frame: bool) -> u64 {
+        let nominal = self.nominal() as u64 * 86_400;
+        if drop_frame {
+            nominal - self.dropped_per_minute() * 9 * 6 * 24
+        } else {
+            nominal
+        }
+    }
+
+    /// Frame field values above 30 fps count pairs of frames.
+    fn uses_frame_pairs(self) -> bool {
+        self.nominal() > 30
+    }
+}
+
+impl std::str::FromStr for FrameRate {
+    type Err = anyhow::Error;
+
+    /// `24`, `23.976`, `29.97`, `30000/1001`, ...
+    fn from_str(s: &str) -> Result<Self> {
+        let parsed = match MetadataValue::parse(ValueKind::Rational, s) {
+            Ok(MetadataValue::Rational(n, d)) => Some((n, d)),
+            _ => None,
+        };
+        // 23.98 is a common editorial rounding of 23.976
+        let parsed = match s.trim() {
+            "23.98" => Some((24000, 1001)),
+            _ => parsed,
+        };
+        FrameRate::ALL
+            .into_iter()
+            .find(|r| Some(r.rational()) == parsed)
+            .with_context(|| {
+                format!(
+                    "unsupported timecode rate {:?} (23.976, 24, 25, 29.97, 30, 50, 59.94 or 60)",
+                    s
+                )
+            })
+    }
+}
+
+impl fmt::Display for FrameRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FrameRate::Fps23_976 => "23.976",
+            FrameRate::Fps24 => "24",
+            FrameRate::Fps25 => "25",
+            FrameRate::Fps29_97 => "29.97",
+            FrameRate::Fps30 => "30",
+            FrameRate::Fps50 => "50",
+            FrameRate::Fps59_94 => "59.94",
+            FrameRate::Fps60 => "60",
+        })
+    }
+}
+
+impl TimeCode {
+    /// Timecode of absolute frame `frame` (counted from 00:00:00:00,
+    /// wrapping at 24 hours).
+    pub fn from_frames(frame: u64, rate: FrameRate, drop_frame: bool) -> Result<TimeCode> {
+        if drop_frame && !rate.supports_drop_frame() {
+            bail!("drop-frame timecode is only defined for 29.97 and 59.94");
+        }
+        let nominal = rate.nominal() as u64;
+        let mut frame = frame % rate.frames_per_day(drop_frame);
+        if drop_frame {
+            // add back the labels skipped so far
+            let drop = rate.dropped_per_minute();
+            let per_ten = nominal * 600 - drop * 9;
+            let per_minute = nominal * 60 - drop;
+            let (tens, rem) = (frame / per_ten, frame % per_ten);
+            frame += drop * 9 * tens;
+            if rem > drop {
+                frame += drop * ((rem - drop) / per_minute);
+            }
+        }
+        let label = (frame % nominal) as u8;
+        let seconds = frame / nominal;
+        let (frames, field_phase) = if rate.uses_frame_pairs() {
+            (label / 2, label % 2 == 1)
+        } else {
+            (label, false)
+        };
+        Ok(TimeCode {
+            hours: (seconds / 3600) as u8,
+            minutes: (seconds / 60 % 60) as u8,
+            seconds: (seconds % 60) as u8,
+            frames,
+            drop_frame,
+            field_phase,
+        })
+    }
+
+    /// Frame number within the second as shown to editors: the pair index
+    /// and field phase combined above 30 fps.
+    pub fn label_frame(&self, rate: FrameRate) -> u32 {
+        if rate.uses_frame_pairs() {
+            self.frames as u32 * 2 + self.field_phase as u32
+        } else {
+            self.frames as u32
+        }
+    }
+
+    /// Check that this label exists at `rate`.
+    pub fn validate(&self, rate: FrameRate) -> Result<()> {
+        let label = self.label_frame(rate);
+        if self.hours > 23 || self.minutes > 59 || self.seconds > 59 {
+            bail!("timecode {} is out of range", self);
+        }
+        if label >= rate.nominal() {
+            bail!("timecode {} has too many frames for {} fps", self, rate);
+        }
+        if self.drop_frame {
+            if !rate.supports_drop_frame() {
+                bail!("drop-frame timecode {} at {} fps", self, rate);
+            }
+            if self.seconds == 0
+                && !self.minutes.is_multiple_of(10)
+                && (label as u64) < rate.dropped_per_minute()
+            {
+                bail!("{} is skipped in drop-frame counting", self);
+            }
+        }
+        Ok(())
+    }
+
+    /// Absolute frame number of this label at `rate`.
+    pub fn to_frames(&self, rate: FrameRate) -> Result<u64> {
+        self.validate(rate)?;
+        let nominal = rate.nominal() as u64;
+        let minutes = self.hours as u64 * 60 + self.minutes as u64;
+        let mut frame =
+            (minutes * 60 + self.seconds as u64) * nominal + self.label_frame(rate) as u64;
+        if self.drop_frame {
+            frame -= rate.dropped_per_minute() * (minutes - minutes / 10);
+        }
+        Ok(frame)
+    }
+
+    /// Parse a timecode typed by a user at `rate`: above 30 fps the frame
+    /// field runs to 49/59 and is converted to the stored pair form.
+    pub fn parse_at(text: &str, rate: FrameRate) -> Result<TimeCode> {
+        let (head, label) = text
+            .trim()
+            .rsplit_once([':', ';'])
+            .with_context(|| format!("invalid timecode {:?} (expected HH:MM:SS:FF)", text))?;
+        let label: u8 = label
+            .parse()
+            .with_context(|| format!("invalid timecode {:?}", text))?;
+        let sep = &text.trim()[head.len()..head.len() + 1];
+        let mut tc: TimeCode = format!("{}{}00", head, sep).parse()?;
+        if rate.uses_frame_pairs() {
+            tc.frames = label / 2;
+            tc.field_phase = label % 2 == 1;
+        } else {
+            tc.frames = label;
+        }
+        tc.validate(rate)?;
+        Ok(tc)
+    }
+
+    /// The label as editors write it at `rate` (`HH:MM:SS:FF`, frames up
+    /// to 59 at 60 fps).
+    pub fn display_at(&self, rate: FrameRate) -> String {
+        let sep = if self.drop_frame { ';' } else { ':' };
+        format!(
+            "{:02}:{:02}:{:02}{}{:02}",
+            self.hours,
+            self.minutes,
+            self.seconds,
+            sep,
+            self.label_frame(rate)
+        )
+    }
+}
+
+/// Frame number of a sequence file: the last run of digits in its stem
+/// (`sh010_v2.1001.exr` → 1001).
+pub fn frame_number(path: &Path) -> Option<u64> {
+    let stem = path.file_stem()?.to_str()?;
+    let end = stem.rfind(|c: char| c.is_ascii_digit())? + 1;
+    let start = stem[..end]
+        .rfind(|c: char| !c.is_ascii_digit())
+        .map_or(0, |i| i + 1);
+    stem[start..end].parse().ok()
+}
+
+/// Where stamped timecode starts counting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StampStart {
+    /// The first file gets this timecode.
+    Timecode(TimeCode),
+    /// Each file gets the timecode of its frame number (frame 86400 at
+    /// 24 fps is 01:00:00:00).
+    FrameNumber,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StampOptions {
+    pub rate: FrameRate,
+    pub drop_frame: bool,
+    pub start: StampStart,
+}
+
+/// Timecode for every file of a sorted sequence.
+///
+/// From a start timecode, files advance by the difference of their frame
+/// numbers, so gaps in the sequence stay gaps in the timecode; when some
+/// file has no frame number, they advance by one per file instead.
+pub fn plan_stamps(files: &[PathBuf], opts: &StampOptions) -> Result<Vec<(PathBuf, TimeCode)>> {
+    // stops at the first file without a frame number
+    let numbers: std::result::Result<Vec<u64>, &PathBuf> =
+        files.iter().map(|f| frame_number(f).ok_or(f)).collect();
+    let frames: Vec<u64> = match opts.start {
+        StampStart::FrameNumber => {
+            numbers.map_err(|f| anyhow!("no frame number in {}", f.display()))?
+        }
+        StampStart::Timecode(start) => {
+            if start.drop_frame != opts.drop_frame {
+                bail!(
+                    "start timecode {} does not match the {} counting",
+                    start,
+                    if opts.drop_frame {
+                        "drop-frame"
+                    } else {
+                        "non-drop"
+                    }
+                );
+            }
+            let base = start.to_frames(opts.rate)?;
+            match numbers {
+                Ok(n) if !n.is_empty() => n.iter().map(|x| base + x.saturating_sub(n[0])).collect(),
+                _ => (0..files.len() as u64).map(|i| base + i).collect(),
+            }
+        }
+    };
+    files
+        .iter()
+        .zip(frames)
+        .map(|(f, n)| {
+            Ok((
+                f.clone(),
+                TimeCode::from_frames(n, opts.rate, opts.drop_frame)?,
+            ))
+        })
+        .collect()
+}
+
+/// The attributes that stamp `tc` on a frame.
+pub fn stamp_attributes(tc: TimeCode, rate: FrameRate) -> HashMap<String, MetadataValue> {
+    let (n, d) = rate.rational();
+    HashMap::from([
+        ("timeCode".to_string(), MetadataValue::TimeCode(tc)),
+        ("framesPerSecond".to_string(), MetadataValue::Rational(n, d)),
+    ])
+}
+
+/// The `timeCode` of a file's first part, read from the header only.
+pub fn read_timecode(path: &Path) -> Result<Option<TimeCode>> {
+    let headers = ExrHeaders::read_file(path)?;
+    Ok(
+        match headers.headers.first().and_then(|h| h.value("timeCode")) {
+            Some(MetadataValue::TimeCode(tc)) => Some(tc),
+            _ => None,
+        },
+    )
+}
+
+/// A break in the timecode of a sequence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContinuityIssue {
+    /// The file has no `timeCode`.
+    Missing { path: PathBuf },
+    /// The label does not exist at the checked rate.
+    Invalid { path: PathBuf, error: String },
+    /// The timecode does not follow from the previous file.
+    Jump {
+        path: PathBuf,
+        expected: TimeCode,
+        found: TimeCode,
+    },
+}
+
+impl fmt::Display for ContinuityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContinuityIssue::Missing { path } => write!(f, "{}: no timeCode", path.display()),
+            ContinuityIssue::Invalid { path, error } => write!(f, "{}: {}", path.display(), error),
+            ContinuityIssue::Jump {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{}: expected {}, found {}",
+                path.display(),
+                expected,
+                found
+            ),
+        }
+    }
+}
+
+/// Check that every timecode follows from the previous one.
+///
+/// Consecutive files are expected to advance by the difference of their
+/// frame numbers (one when a name has no number). After a jump, checking
+/// continues from the timecode that was found, so each break is reported
+/// once.
+pub fn check_continuity(
+    frames: &[(PathBuf, Option<TimeCode>)],
+    rate: FrameRate,
+) -> Vec<ContinuityIssue> {
+    let mut issues = Vec::new();
+    // (absolute frame, frame number, drop-frame) of the last good timecode
+    let mut prev: Option<(u64, Option<u64>, bool)> = None;
+    for (path, tc) in frames {
+        let number = frame_number(path);
+        let Some(tc) = tc else {
+            issues.push(ContinuityIssue::Missing { path: path.clone() });
+            continue;
+        };
+        let abs = match tc.to_frames(rate) {
+            Ok(a) => a,
+            Err(e) => {
+                issues.push(ContinuityIssue::Invalid {
+                    path: path.clone(),
+                    error: e.to_string(),
+                });
+                continue;
+            }
+        };
+        if let Some((last, last_number, drop)) = prev {
+            let step = match (last_number, number) {
+                (Some(a), Some(b)) if b > a => b - a,
+                _ => 1,
+            };
+            let expected = (last + step) % rate.frames_per_day(drop);
+            if expected != abs || drop != tc.drop_frame {
+                // the previous label validated, so its counting exists at this rate
+                if let Ok(expected) = TimeCode::from_frames(expected, rate, drop) {
+                    issues.push(ContinuityIssue::Jump {
+                        path: path.clone(),
+                        expected,
+                        found: *tc,
+                    });
+                }
+            }
+        }
+        prev = Some((abs, number, tc.drop_frame));
+    }
+    issues
+}
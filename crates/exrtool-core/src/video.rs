@@ -0,0 +1,1686 @@
+//! Video export: render an EXR sequence through the preview pipeline and pipe
+//! the frames to ffmpeg as raw 8- or 16-bit RGB(A).
+//!
+//! [`ffmpeg_args`] builds the command line without running anything, so the
+//! mapping from settings to ffmpeg options can be tested on its own. Audio
+//! files are muxed in lined up with the frames, and stream durations are
+//! checked with ffprobe afterwards when it is installed next to ffmpeg.
+
+use anyhow::{anyhow, bail, Context, Result};
+use image::imageops::FilterType;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::aces::AcesOdt;
+use crate::burnin::BurnIn;
+use crate::executor::{execute, ExecOptions, Outcome};
+use crate::gamut::GamutMapping;
+use crate::header::ExrHeaders;
+use crate::metadata::MetadataValue;
+use crate::slate::{AspectMask, Slate};
+use crate::timecode::{frame_number, read_timecode, FrameRate, TimeCode};
+use crate::{
+    generate_preview16, generate_preview_with, load_exr_basic, make_3d_lut_cube, parse_cube,
+    ColorProcessor, DisplayTransform, LoadedExr, Lut, PreviewImage, PreviewImage16,
+    PreviewPipeline, PreviewQuality, Primaries, TransferFn,
+};
+
+/// Primaries conversion baked into the frames when the display transform is
+/// `standard`: `src:dst`, e.g. `acescg:srgb`. `linear` stands for linear
+/// sRGB/Rec.709.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VideoColorspace {
+    pub src: Primaries,
+    pub dst: Primaries,
+}
+
+impl Default for VideoColorspace {
+    fn default() -> Self {
+        VideoColorspace {
+            src: Primaries::SrgbD65,
+            dst: Primaries::SrgbD65,
+        }
+    }
+}
+
+impl FromStr for VideoColorspace {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (src, dst) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow!("colorspace must be src:dst (e.g. acescg:srgb): {}", s))?;
+        let space = |p: &str| -> Result<Primaries> {
+            if p.eq_ignore_ascii_case("linear") {
+                Ok(Primaries::SrgbD65)
+            } else {
+                p.parse()
+            }
+        };
+        Ok(VideoColorspace {
+            src: space(src)?,
+            dst: space(dst)?,
+        })
+    }
+}
+
+impl fmt::Display for VideoColorspace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = |p: Primaries| match p {
+            Primaries::SrgbD65 => "srgb",
+            Primaries::Rec2020D65 => "rec2020",
+            Primaries::P3D65 => "p3d65",
+            Primaries::ACEScgD60 => "acescg",
+            Primaries::ACES2065_1D60 => "aces2065",
+        };
+        write!(f, "{}:{}", name(self.src), name(self.dst))
+    }
+}
+
+impl VideoColorspace {
+    /// The 3D LUT for the conversion, or `None` when the primaries match.
+    pub fn lut(&self) -> Result<Option<Lut>> {
+        if self.src == self.dst {
+            return Ok(None);
+        }
+        let text = make_3d_lut_cube(
+            self.src,
+            TransferFn::Linear,
+            self.dst,
+            TransferFn::Srgb,
+            33,
+            1024,
+        );
+        Ok(Some(parse_cube(&text)?))
+    }
+}
+
+/// One codec profile from [`CODEC_PROFILES`]: its `-profile:v` value and the
+/// pixel formats it is made for.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CodecProfile {
+    /// Codec family, as given to `--codec`: `prores`, `dnxhr`, `h264`, `h265`.
+    pub codec: &'static str,
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub label: &'static str,
+    /// The `-profile:v` value.
+    pub profile: &'static str,
+    /// Pixel formats without alpha; the first is the default.
+    pub pix_fmts: &'static [&'static str],
+    /// Pixel format used for alpha, when the profile can carry it.
+    pub alpha_pix_fmt: Option<&'static str>,
+}
+
+const fn entry(
+    codec: &'static str,
+    name: &'static str,
+    aliases: &'static [&'static str],
+    label: &'static str,
+    profile: &'static str,
+    pix_fmts: &'static [&'static str],
+    alpha_pix_fmt: Option<&'static str>,
+) -> CodecProfile {
+    CodecProfile {
+        codec,
+        name,
+        aliases,
+        label,
+        profile,
+        pix_fmts,
+        alpha_pix_fmt,
+    }
+}
+
+/// Every codec and profile video export can write.
+pub static CODEC_PROFILES: &[CodecProfile] = &[
+    entry(
+        "prores",
+        "proxy",
+        &[],
+        "ProRes 422 Proxy",
+        "0",
+        &["yuv422p10le"],
+        None,
+    ),
+    entry(
+        "prores",
+        "lt",
+        &[],
+        "ProRes 422 LT",
+        "1",
+        &["yuv422p10le"],
+        None,
+    ),
+    entry(
+        "prores",
+        "422",
+        &["standard"],
+        "ProRes 422",
+        "2",
+        &["yuv422p10le"],
+        None,
+    ),
+    entry(
+        "prores",
+        "422hq",
+        &["hq"],
+        "ProRes 422 HQ",
+        "3",
+        &["yuv422p10le"],
+        None,
+    ),
+    entry(
+        "prores",
+        "4444",
+        &[],
+        "ProRes 4444",
+        "4",
+        &["yuv444p10le"],
+        Some("yuva444p10le"),
+    ),
+    entry(
+        "prores",
+        "4444xq",
+        &["xq"],
+        "ProRes 4444 XQ",
+        "5",
+        &["yuv444p10le"],
+        Some("yuva444p10le"),
+    ),
+    entry(
+        "dnxhr",
+        "lb",
+        &[],
+        "DNxHR LB",
+        "dnxhr_lb",
+        &["yuv422p"],
+        None,
+    ),
+    entry(
+        "dnxhr",
+        "sq",
+        &[],
+        "DNxHR SQ",
+        "dnxhr_sq",
+        &["yuv422p"],
+        None,
+    ),
+    entry(
+        "dnxhr",
+        "hq",
+        &[],
+        "DNxHR HQ",
+        "dnxhr_hq",
+        &["yuv422p"],
+        None,
+    ),
+    entry(
+        "dnxhr",
+        "hqx",
+        &[],
+        "DNxHR HQX",
+        "dnxhr_hqx",
+        &["yuv422p10le"],
+        None,
+    ),
+    entry(
+        "dnxhr",
+        "444",
+        &[],
+        "DNxHR 444",
+        "dnxhr_444",
+        &["yuv444p10le", "gbrp10le"],
+        None,
+    ),
+    entry(
+        "h264",
+        "high",
+        &["8bit"],
+        "H.264 High 8-bit",
+        "high",
+        &["yuv420p"],
+        None,
+    ),
+    entry(
+        "h264",
+        "high10",
+        &["10bit"],
+        "H.264 High 10",
+        "high10",
+        &["yuv420p10le"],
+        None,
+    ),
+    entry(
+        "h265",
+        "main",
+        &["8bit"],
+        "H.265 Main 8-bit",
+        "main",
+        &["yuv420p"],
+        None,
+    ),
+    entry(
+        "h265",
+        "main10",
+        &["10bit"],
+        "H.265 Main 10",
+        "main10",
+        &["yuv420p10le"],
+        None,
+    ),
+];
+
+/// A codec family: its ffmpeg encoder, other names accepted for it and the
+/// profile used when none is given.
+struct Codec {
+    name: &'static str,
+    encoder: &'static str,
+    aliases: &'static [&'static str],
+    default_profile: &'static str,
+}
+
+const CODECS: &[Codec] = &[
+    Codec {
+        name: "prores",
+        encoder: "prores_ks",
+        aliases: &[],
+        default_profile: "422hq",
+    },
+    Codec {
+        name: "dnxhr",
+        encoder: "dnxhd",
+        aliases: &[],
+        default_profile: "hq",
+    },
+    Codec {
+        name: "h264",
+        encoder: "libx264",
+        aliases: &["avc"],
+        default_profile: "high",
+    },
+    Codec {
+        name: "h265",
+        encoder: "libx265",
+        aliases: &["hevc"],
+        default_profile: "main",
+    },
+];
+
+impl CodecProfile {
+    /// Look up `profile` of `codec`, or the codec's default profile. Unknown
+    /// codecs and profiles are errors listing the valid names.
+    pub fn find(codec: &str, profile: Option<&str>) -> Result<&'static CodecProfile> {
+        let codec_l = codec.trim().to_ascii_lowercase();
+        let family = CODECS
+            .iter()
+            .find(|c| c.name == codec_l || c.encoder == codec_l || c.aliases.contains(&&*codec_l))
+            .ok_or_else(|| {
+                let names: Vec<_> = CODECS.iter().map(|c| c.name).collect();
+                anyhow!("unknown codec: {} ({})", codec, names.join(", "))
+            })?;
+        let wanted = profile
+            .map(|p| p.trim().to_ascii_lowercase())
+            .unwrap_or_else(|| family.default_profile.to_string());
+        let profiles = || CODEC_PROFILES.iter().filter(|p| p.codec == family.name);
+        profiles()
+            .find(|p| p.name == wanted || p.aliases.contains(&&*wanted))
+            .ok_or_else(|| {
+                let names: Vec<_> = profiles().map(|p| p.name).collect();
+                anyhow!(
+                    "unknown {} profile: {} ({})",
+                    family.name,
+                    wanted,
+                    names.join(", ")
+                )
+            })
+    }
+
+    /// The ffmpeg encoder.
+    pub fn encoder(&self) -> &'static str {
+        CODECS
+            .iter()
+            .find(|c| c.name == self.codec)
+            .expect("catalogue codec")
+            .encoder
+    }
+
+    /// Whether a CRF or bitrate can be given ([`RateControl`]); the
+    /// intermediate codecs have fixed rates per profile.
+    pub fn rate_control(&self) -> bool {
+        matches!(self.codec, "h264" | "h265")
+    }
+
+    /// The encoder's pixel format: `requested` if given and valid for the
+    /// profile, otherwise the default one. Alpha needs a profile that
+    /// carries it.
+    pub fn pix_fmt(&self, alpha: bool, requested: Option<&str>) -> Result<&'static str> {
+        let valid: Vec<&'static str> = if alpha {
+            let Some(fmt) = self.alpha_pix_fmt else {
+                let with_alpha: Vec<_> = CODEC_PROFILES
+                    .iter()
+                    .filter(|p| p.alpha_pix_fmt.is_some())
+                    .map(|p| p.label)
+                    .collect();
+                bail!(
+                    "{} cannot carry alpha (use {})",
+                    self.label,
+                    with_alpha.join(" or ")
+                );
+            };
+            vec![fmt]
+        } else {
+            self.pix_fmts.to_vec()
+        };
+        match requested {
+            None => Ok(valid[0]),
+            Some(r) => valid.iter().copied().find(|f| *f == r).ok_or_else(|| {
+                anyhow!(
+                    "{} does not take pixel format {} ({})",
+                    self.label,
+                    r,
+                    valid.join(", ")
+                )
+            }),
+        }
+    }
+}
+
+/// `codec profile`, e.g. `prores 422hq`.
+impl fmt::Display for CodecProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.codec, self.name)
+    }
+}
+
+/// Quality target for the encoders with rate control: a constant rate
+/// factor (`crf=18` or just `18`) or a bitrate (`bitrate=20M` or `20M`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RateControl {
+    Crf(u8),
+    Bitrate(String),
+}
+
+impl FromStr for RateControl {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (key, value) = match s.split_once('=') {
+            Some((k, v)) => (Some(k.trim().to_ascii_lowercase()), v.trim()),
+            None => (None, s),
+        };
+        let is_crf = match key.as_deref() {
+            Some("crf") => true,
+            Some("bitrate") | Some("b") => false,
+            Some(other) => bail!("unknown rate control: {} (crf, bitrate)", other),
+            None => value.bytes().all(|b| b.is_ascii_digit()),
+        };
+        if is_crf {
+            return match value.parse::<u8>() {
+                Ok(crf) if crf <= 51 => Ok(RateControl::Crf(crf)),
+                _ => bail!("CRF must be 0-51: {}", value),
+            };
+        }
+        let digits = value.trim_end_matches(['k', 'K', 'm', 'M']);
+        if digits.is_empty()
+            || value.len() - digits.len() > 1
+            || !digits.bytes().all(|b| b.is_ascii_digit())
+            || digits.bytes().all(|b| b == b'0')
+        {
+            bail!("invalid bitrate: {} (e.g. 20M, 8000k)", value);
+        }
+        Ok(RateControl::Bitrate(value.to_string()))
+    }
+}
+
+impl fmt::Display for RateControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RateControl::Crf(crf) => write!(f, "crf={}", crf),
+            RateControl::Bitrate(rate) => write!(f, "bitrate={}", rate),
+        }
+    }
+}
+
+/// Precision of the frames piped to ffmpeg.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitDepth {
+    Eight,
+    /// Enough for 10/12-bit codecs such as ProRes 422 HQ and 4444.
+    #[default]
+    Sixteen,
+}
+
+impl BitDepth {
+    /// ffmpeg's name for the raw frame layout.
+    pub fn pix_fmt(self, alpha: bool) -> &'static str {
+        match (self, alpha) {
+            (BitDepth::Eight, false) => "rgb24",
+            (BitDepth::Eight, true) => "rgba",
+            (BitDepth::Sixteen, false) => "rgb48le",
+            (BitDepth::Sixteen, true) => "rgba64le",
+        }
+    }
+}
+
+impl FromStr for BitDepth {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "8" => Ok(BitDepth::Eight),
+            "16" => Ok(BitDepth::Sixteen),
+            _ => bail!("bit depth must be 8 or 16: {}", s),
+        }
+    }
+}
+
+const PRIMARIES: &[&str] = &[
+    "bt709",
+    "bt470m",
+    "bt470bg",
+    "smpte170m",
+    "smpte240m",
+    "film",
+    "bt2020",
+    "smpte428",
+    "smpte431",
+    "smpte432",
+    "jedec-p22",
+];
+const TRANSFERS: &[&str] = &[
+    "bt709",
+    "gamma22",
+    "gamma28",
+    "smpte170m",
+    "smpte240m",
+    "linear",
+    "iec61966-2-1",
+    "bt2020-10",
+    "bt2020-12",
+    "smpte2084",
+    "smpte428",
+    "arib-std-b67",
+];
+const MATRICES: &[&str] = &[
+    "rgb",
+    "bt709",
+    "fcc",
+    "bt470bg",
+    "smpte170m",
+    "smpte240m",
+    "ycgco",
+    "bt2020nc",
+    "bt2020c",
+];
+const RANGES: &[&str] = &["tv", "pc"];
+
+/// Colour description of the movie, in ffmpeg's names for the
+/// `-color_primaries`, `-color_trc`, `-colorspace` and `-color_range` values
+/// that end up in the `colr`/`nclc` atom. Unset fields are left to ffmpeg.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ColorTags {
+    pub primaries: Option<&'static str>,
+    pub transfer: Option<&'static str>,
+    pub matrix: Option<&'static str>,
+    pub range: Option<&'static str>,
+}
+
+impl ColorTags {
+    /// Presets: `srgb`, `rec709` (BT.1886), `rec2020`, `p3d65`, `pq`
+    /// (Rec.2100 PQ) and `hlg` (Rec.2100 HLG).
+    ///
+    /// `p3d65` leaves the transfer untagged: CICP has no plain 2.6 power
+    /// curve, and ST 428-1 describes DCDM X'Y'Z' rather than P3 RGB.
+    pub fn preset(name: &str) -> Result<Self> {
+        let (primaries, transfer, matrix) = match name.to_ascii_lowercase().as_str() {
+            "srgb" => ("bt709", Some("iec61966-2-1"), "bt709"),
+            "rec709" | "bt1886" => ("bt709", Some("bt709"), "bt709"),
+            "rec2020" => ("bt2020", Some("bt2020-10"), "bt2020nc"),
+            "p3d65" | "p3" => ("smpte432", None, "bt709"),
+            "pq" | "rec2100-pq" => ("bt2020", Some("smpte2084"), "bt2020nc"),
+            "hlg" | "rec2100-hlg" => ("bt2020", Some("arib-std-b67"), "bt2020nc"),
+            _ => bail!(
+                "unknown colour tag preset: {} (srgb, rec709, rec2020, p3d65, pq, hlg)",
+                name
+            ),
+        };
+        Ok(ColorTags {
+            primaries: Some(primaries),
+            transfer,
+            matrix: Some(matrix),
+            range: Some("tv"),
+        })
+    }
+
+    /// The tags matching what `display` writes into the frames. `dst` is the
+    /// output primaries of the `standard` transform. Only the matrix and range
+    /// are known for `raw` output, whose encoding came from elsewhere.
+    pub fn for_display(display: DisplayTransform, dst: Primaries) -> Self {
+        let preset = match display {
+            DisplayTransform::Standard => match dst {
+                Primaries::SrgbD65 => Some(("bt709", "bt709")),
+                Primaries::Rec2020D65 => Some(("bt2020", "bt2020nc")),
+                Primaries::P3D65 => Some(("smpte432", "bt709")),
+                Primaries::ACEScgD60 | Primaries::ACES2065_1D60 => None,
+            }
+            .map(|(primaries, matrix)| ColorTags {
+                primaries: Some(primaries),
+                // gamma on top of the sRGB encode; tagged as the encode
+                transfer: Some("iec61966-2-1"),
+                matrix: Some(matrix),
+                range: Some("tv"),
+            }),
+            DisplayTransform::Aces { odt, .. } => Some(
+                ColorTags::preset(match odt {
+                    AcesOdt::Srgb => "srgb",
+                    AcesOdt::Rec709 => "rec709",
+                    AcesOdt::P3D65 => "p3d65",
+                    AcesOdt::Rec2100Pq1000 => "pq",
+                })
+                .expect("known preset"),
+            ),
+            DisplayTransform::Raw => None,
+        };
+        preset.unwrap_or(ColorTags {
+            primaries: None,
+            transfer: None,
+            matrix: Some("bt709"),
+            range: Some("tv"),
+        })
+    }
+
+    /// `self` with every field set in `overrides` replaced.
+    pub fn with(self, overrides: ColorTags) -> Self {
+        ColorTags {
+            primaries: overrides.primaries.or(self.primaries),
+            transfer: overrides.transfer.or(self.transfer),
+            matrix: overrides.matrix.or(self.matrix),
+            range: overrides.range.or(self.range),
+        }
+    }
+
+    /// ffmpeg output options writing the tags, plus a `scale` filter that
+    /// converts to the tagged matrix and range (swscale defaults to BT.601).
+    fn ffmpeg_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for (flag, value) in [
+            ("-color_primaries", self.primaries),
+            ("-color_trc", self.transfer),
+            ("-colorspace", self.matrix),
+            ("-color_range", self.range),
+        ] {
+            if let Some(v) = value {
+                args.extend([flag.to_string(), v.to_string()]);
+            }
+        }
+        let matrix = match self.matrix {
+            Some("bt709") => Some("bt709"),
+            Some("bt2020nc") | Some("bt2020c") => Some("bt2020"),
+            Some("smpte170m") => Some("smpte170m"),
+            Some("bt470bg") => Some("bt470"),
+            Some("smpte240m") => Some("smpte240m"),
+            Some("fcc") => Some("fcc"),
+            _ => None,
+        };
+        let mut scale: Vec<String> = Vec::new();
+        if let Some(m) = matrix {
+            scale.push(format!("out_color_matrix={}", m));
+        }
+        if let Some(r) = self.range {
+            scale.push(format!("out_range={}", r));
+        }
+        if !scale.is_empty() {
+            args.extend(["-vf".to_string(), format!("scale={}", scale.join(":"))]);
+        }
+        args
+    }
+}
+
+/// Comma separated presets and `key=value` pairs (`primaries`, `trc`,
+/// `matrix`, `range`); later entries win, e.g. `hlg` or `rec709,range=pc`.
+impl FromStr for ColorTags {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut tags = ColorTags::default();
+        for item in s.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let Some((key, value)) = item.split_once('=') else {
+                tags = tags.with(ColorTags::preset(item)?);
+                continue;
+            };
+            let (names, field) = match key.trim().to_ascii_lowercase().as_str() {
+                "primaries" => (PRIMARIES, &mut tags.primaries),
+                "trc" | "transfer" => (TRANSFERS, &mut tags.transfer),
+                "matrix" | "colorspace" => (MATRICES, &mut tags.matrix),
+                "range" => (RANGES, &mut tags.range),
+                other => bail!(
+                    "unknown colour tag: {} (primaries, trc, matrix, range)",
+                    other
+                ),
+            };
+            let value = value.trim().to_ascii_lowercase();
+            *field = Some(names.iter().copied().find(|n| *n == value).ok_or_else(|| {
+                anyhow!("unknown {} value: {} ({})", key, value, names.join(", "))
+            })?);
+        }
+        Ok(tags)
+    }
+}
+
+impl fmt::Display for ColorTags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "primaries={} trc={} matrix={} range={}",
+            self.primaries.unwrap_or("-"),
+            self.transfer.unwrap_or("-"),
+            self.matrix.unwrap_or("-"),
+            self.range.unwrap_or("-")
+        )
+    }
+}
+
+/// Which files of the sequence are encoded: `first`..=`last` by frame
+/// number, widened by `handles` on both sides, then every `step`-th file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameRange {
+    pub first: Option<u64>,
+    pub last: Option<u64>,
+    pub handles: u64,
+    pub step: usize,
+}
+
+impl Default for FrameRange {
+    fn default() -> Self {
+        FrameRange {
+            first: None,
+            last: None,
+            handles: 0,
+            step: 1,
+        }
+    }
+}
+
+impl FrameRange {
+    /// The files of `files` in the range. Files without a frame number are
+    /// only accepted when no in/out frame is given.
+    pub fn select(&self, files: &[PathBuf]) -> Result<Vec<PathBuf>> {
+        if self.step == 0 {
+            bail!("frame step must be at least 1");
+        }
+        if let (Some(first), Some(last)) = (self.first, self.last) {
+            if first > last {
+                bail!("first frame {} is after last frame {}", first, last);
+            }
+        }
+        let bounded = self.first.is_some() || self.last.is_some();
+        let lo = self.first.map(|f| f.saturating_sub(self.handles));
+        let hi = self.last.map(|l| l.saturating_add(self.handles));
+        let mut selected = Vec::new();
+        for f in files {
+            if bounded {
+                let n = frame_number(f).ok_or_else(|| {
+                    anyhow!("{} has no frame number for the frame range", f.display())
+                })?;
+                if lo.is_some_and(|lo| n < lo) || hi.is_some_and(|hi| n > hi) {
+                    continue;
+                }
+            }
+            selected.push(f.clone());
+        }
+        let selected: Vec<_> = selected.into_iter().step_by(self.step).collect();
+        if selected.is_empty() && !files.is_empty() {
+            bail!("no frames in {}", self);
+        }
+        Ok(selected)
+    }
+}
+
+/// `1001-1100+8/2`: in-out, handles and step, `-` for an open end.
+impl fmt::Display for FrameRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let end = |v: Option<u64>| v.map_or("-".to_string(), |v| v.to_string());
+        write!(f, "{}-{}", end(self.first), end(self.last))?;
+        if self.handles > 0 {
+            write!(f, "+{}", self.handles)?;
+        }
+        if self.step > 1 {
+            write!(f, "/{}", self.step)?;
+        }
+        Ok(())
+    }
+}
+
+/// How the image is brought to an exact [`Resize`] resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FitMode {
+    /// Scale to fit inside; the frame takes the scaled size.
+    Fit,
+    /// Scale to cover and crop the overhang.
+    Fill,
+    /// No scaling; crop (or pad) around the centre.
+    Crop,
+    /// Scale to fit inside and pad with black.
+    #[default]
+    Pad,
+}
+
+impl FromStr for FitMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "fit" => FitMode::Fit,
+            "fill" => FitMode::Fill,
+            "crop" => FitMode::Crop,
+            "pad" => FitMode::Pad,
+            _ => bail!("unknown fit mode: {} (fit, fill, crop, pad)", s),
+        })
+    }
+}
+
+/// Output resolution, written `1920x1080`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resize {
+    pub width: u32,
+    pub height: u32,
+    pub mode: FitMode,
+}
+
+impl FromStr for Resize {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let size = s
+            .split_once(['x', 'X'])
+            .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)))
+            .filter(|&(w, h): &(u32, u32)| w > 0 && h > 0);
+        let Some((width, height)) = size else {
+            bail!("resolution must be WIDTHxHEIGHT (e.g. 1920x1080): {}", s);
+        };
+        Ok(Resize {
+            width,
+            height,
+            mode: FitMode::default(),
+        })
+    }
+}
+
+/// Where a source image ends up in the output frame: resampled to
+/// `scaled`, cut down to `crop` (x, y, width, height) and placed at
+/// `offset` on a black `canvas`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameLayout {
+    pub scaled: (u32, u32),
+    pub crop: (u32, u32, u32, u32),
+    pub canvas: (u32, u32),
+    pub offset: (u32, u32),
+}
+
+impl FrameLayout {
+    /// The kept part of `img`, resampled and cropped.
+    fn apply(&self, img: LoadedExr, quality: PreviewQuality) -> LoadedExr {
+        let (sw, sh) = self.scaled;
+        let img = if (img.width, img.height) == (sw as usize, sh as usize) {
+            img
+        } else {
+            let src =
+                image::Rgba32FImage::from_raw(img.width as u32, img.height as u32, img.rgba_f32)
+                    .expect("rgba buffer");
+            let filter = match quality {
+                PreviewQuality::Fast => FilterType::Triangle,
+                PreviewQuality::High => FilterType::Lanczos3,
+            };
+            LoadedExr {
+                width: sw as usize,
+                height: sh as usize,
+                rgba_f32: image::imageops::resize(&src, sw, sh, filter).into_raw(),
+            }
+        };
+        let (x, y, w, h) = self.crop;
+        if (w, h) == (sw, sh) {
+            return img;
+        }
+        let mut rgba_f32 = Vec::with_capacity((w * h * 4) as usize);
+        for row in y..y + h {
+            let start = (row * sw + x) as usize * 4;
+            rgba_f32.extend_from_slice(&img.rgba_f32[start..start + w as usize * 4]);
+        }
+        LoadedExr {
+            width: w as usize,
+            height: h as usize,
+            rgba_f32,
+        }
+    }
+
+    /// Put the rendered crop (RGBA) on the canvas.
+    fn place<T: Copy>(&self, data: Vec<T>, black: [T; 4]) -> Vec<T> {
+        let (cw, ch) = self.canvas;
+        let (w, h) = (self.crop.2, self.crop.3);
+        if (w, h) == (cw, ch) {
+            return data;
+        }
+        let mut out = black.repeat((cw * ch) as usize);
+        let (ox, oy) = self.offset;
+        for (row, src) in data.chunks_exact(w as usize * 4).enumerate() {
+            let start = ((oy + row as u32) * cw + ox) as usize * 4;
+            out[start..start + src.len()].copy_from_slice(src);
+        }
+        out
+    }
+}
+
+/// The `pixelAspectRatio` of `path`, 1 when missing or unusable.
+fn pixel_aspect(path: &Path) -> f32 {
+    ExrHeaders::read_file(path)
+        .ok()
+        .and_then(|h| h.headers.first()?.value("pixelAspectRatio"))
+        .and_then(|v| match v {
+            MetadataValue::Float(pa) if pa.is_finite() && pa > 0.0 => Some(pa),
+            _ => None,
+        })
+        .unwrap_or(1.0)
+}
+
+/// Where the first sample of an audio file lines up with the picture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioStart {
+    /// A frame number of the sequence, e.g. `1001`.
+    Frame(i64),
+    /// A picture timecode, e.g. the `01:00:00:00` time reference of a BWF.
+    Timecode(TimeCode),
+}
+
+impl FromStr for AudioStart {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s.contains([':', ';']) {
+            return Ok(AudioStart::Timecode(s.parse()?));
+        }
+        s.parse()
+            .map(AudioStart::Frame)
+            .map_err(|_| anyhow!("audio start must be a frame or a timecode: {}", s))
+    }
+}
+
+/// An audio file muxed into the movie as a track of its own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioInput {
+    pub path: PathBuf,
+    /// Defaults to the first frame of the sequence.
+    pub start: Option<AudioStart>,
+    /// Input channels (from 0) making up the track, in order; all if empty.
+    pub channels: Vec<u32>,
+}
+
+/// `PATH[,start=1001|01:00:00:00][,channels=1+0]`.
+impl FromStr for AudioInput {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut path = Vec::new();
+        let mut input = AudioInput {
+            path: PathBuf::new(),
+            start: None,
+            channels: Vec::new(),
+        };
+        for part in s.split(',') {
+            match part.split_once('=') {
+                Some(("start", v)) => input.start = Some(v.parse()?),
+                Some(("channels", v)) => {
+                    input.channels = v
+                        .split('+')
+                        .map(|c| c.trim().parse())
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| anyhow!("channels must be like 0+1: {}", v))?;
+                }
+                // commas inside the path are kept
+                _ => path.push(part),
+            }
+        }
+        if path.is_empty() || path.concat().trim().is_empty() {
+            bail!("no audio file in {:?}", s);
+        }
+        input.path = PathBuf::from(path.join(","));
+        Ok(input)
+    }
+}
+
+/// An [`AudioInput`] lined up with the exported frames.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioTrack {
+    pub path: PathBuf,
+    /// Seconds into the file at the first frame of the movie; negative
+    /// when the audio starts later and is delayed.
+    pub seek: f64,
+    pub channels: Vec<u32>,
+}
+
+impl AudioTrack {
+    /// The audio filter chain: channel mapping, delay, then padding with
+    /// silence so `-shortest` ends the track with the picture.
+    fn filter(&self) -> String {
+        let mut chain = Vec::new();
+        if !self.channels.is_empty() {
+            let layout = match self.channels.len() {
+                1 => "mono".to_string(),
+                2 => "stereo".to_string(),
+                n => format!("{}c", n),
+            };
+            let map: Vec<String> = self
+                .channels
+                .iter()
+                .enumerate()
+                .map(|(i, c)| format!("c{}=c{}", i, c))
+                .collect();
+            chain.push(format!("pan={}|{}", layout, map.join("|")));
+        }
+        if self.seek < 0.0 {
+            chain.push(format!("adelay={:.0}:all=1", -self.seek * 1000.0));
+        }
+        chain.push("apad".to_string());
+        chain.join(",")
+    }
+}
+
+/// Everything that decides how a sequence becomes a movie.
+#[derive(Debug, Clone)]
+pub struct VideoExportSettings {
+    /// The ffmpeg executable.
+    pub ffmpeg: PathBuf,
+    pub fps: f32,
+    /// Codec and profile, from [`CODEC_PROFILES`].
+    pub codec: &'static CodecProfile,
+    /// CRF or bitrate, for the codecs with rate control.
+    pub rate: Option<RateControl>,
+    /// Encoder pixel format instead of the profile's default.
+    pub pix_fmt: Option<String>,
+    pub bit_depth: BitDepth,
+    /// Pass alpha through; needs a profile that carries it (ProRes 4444).
+    pub alpha: bool,
+    pub colorspace: VideoColorspace,
+    /// Overrides for the colour tags derived from the display transform.
+    pub color_tags: ColorTags,
+    /// Frames of the sequence to encode.
+    pub range: FrameRange,
+    /// Exact output resolution; frames are otherwise only scaled down to
+    /// `max_size`.
+    pub resize: Option<Resize>,
+    /// Longest edge of the frames.
+    pub max_size: u32,
+    pub exposure: f32,
+    pub gamma: f32,
+    pub quality: PreviewQuality,
+    pub display: DisplayTransform,
+    pub gamut: Option<GamutMapping>,
+    pub oog_overlay: bool,
+    /// Letterbox or pillarbox bars, drawn under the burn-ins.
+    pub mask: Option<AspectMask>,
+    /// Text drawn onto every frame.
+    pub burnin: Option<BurnIn>,
+    /// Slate held in front of the frames; rows left unset are filled in by
+    /// [`Slate::with_defaults`].
+    pub slate: Option<Slate>,
+    /// Audio tracks muxed in, trimmed to the exported frames.
+    pub audio: Vec<AudioInput>,
+    /// Frames decoded in parallel; 0 for one per CPU.
+    pub threads: usize,
+    /// Cap on the estimated memory of the frames in flight, in bytes.
+    pub memory_limit: Option<u64>,
+}
+
+impl Default for VideoExportSettings {
+    fn default() -> Self {
+        VideoExportSettings {
+            ffmpeg: PathBuf::from("ffmpeg"),
+            fps: 24.0,
+            codec: CodecProfile::find("prores", None).expect("default codec"),
+            rate: None,
+            pix_fmt: None,
+            bit_depth: BitDepth::default(),
+            alpha: false,
+            colorspace: VideoColorspace::default(),
+            color_tags: ColorTags::default(),
+            range: FrameRange::default(),
+            resize: None,
+            max_size: 2048,
+            exposure: 0.0,
+            gamma: 2.2,
+            quality: PreviewQuality::High,
+            display: DisplayTransform::Standard,
+            gamut: None,
+            oog_overlay: false,
+            mask: None,
+            burnin: None,
+            slate: None,
+            audio: Vec::new(),
+            threads: 0,
+            memory_limit: None,
+        }
+    }
+}
+
+impl VideoExportSettings {
+    /// The timecode rate `fps` stands for (23.976 → 24000/1001), if any.
+    pub fn frame_rate(&self) -> Option<FrameRate> {
+        self.fps.to_string().parse().ok()
+    }
+
+    /// `fps` as ffmpeg should read it: the exact rational for timecode
+    /// rates (`24000/1001`), otherwise the number as given.
+    pub fn fps_arg(&self) -> String {
+        match self.frame_rate().map(FrameRate::rational) {
+            Some((n, 1)) => n.to_string(),
+            Some((n, d)) => format!("{}/{}", n, d),
+            None => self.fps.to_string(),
+        }
+    }
+
+    /// Frames per second, exact for timecode rates.
+    pub fn exact_fps(&self) -> f64 {
+        self.frame_rate().map_or(self.fps as f64, |r| {
+            let (n, d) = r.rational();
+            n as f64 / d as f64
+        })
+    }
+
+    /// Check the settings that do not depend on the frames, so mistakes
+    /// show up before anything is rendered or ffmpeg is started.
+    pub fn validate(&self) -> Result<()> {
+        if !(self.fps.is_finite() && self.fps > 0.0) {
+            bail!("invalid fps: {}", self.fps);
+        }
+        self.codec.pix_fmt(self.alpha, self.pix_fmt.as_deref())?;
+        if self.range.step == 0 {
+            bail!("frame step must be at least 1");
+        }
+        if self.range.step > 1 && !self.audio.is_empty() {
+            bail!("audio cannot follow every {}th frame", self.range.step);
+        }
+        if let Some(r) = self.resize {
+            let (even_w, even_h) = self.even_dims();
+            if r.mode != FitMode::Fit
+                && ((even_w && r.width % 2 == 1) || (even_h && r.height % 2 == 1))
+            {
+                bail!(
+                    "{} needs even dimensions, {}x{} is not",
+                    self.codec.label,
+                    r.width,
+                    r.height
+                );
+            }
+        }
+        if let Some(rate) = &self.rate {
+            if !self.codec.rate_control() {
+                bail!(
+                    "{} has a fixed rate per profile; {} is only for H.264/H.265",
+                    self.codec.label,
+                    rate
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// The colour tags written into the movie: those of the display
+    /// transform, with [`VideoExportSettings::color_tags`] applied on top.
+    pub fn resolved_color_tags(&self) -> ColorTags {
+        ColorTags::for_display(self.display, self.colorspace.dst).with(self.color_tags)
+    }
+
+    /// Whether the encoder's chroma subsampling needs an even width and
+    /// height.
+    fn even_dims(&self) -> (bool, bool) {
+        match self.codec.pix_fmt(self.alpha, self.pix_fmt.as_deref()) {
+            Ok(f) if f.starts_with("yuv420") => (true, true),
+            Ok(f) if f.starts_with("yuv422") => (true, false),
+            _ => (false, false),
+        }
+    }
+
+    /// Where a `width`×`height` source with `pixel_aspect` goes in the
+    /// output frame. Anamorphic plates are stretched to square pixels, and
+    /// sizes left to the scaling are rounded down to what the codec's
+    /// chroma subsampling needs.
+    pub fn frame_layout(&self, width: u32, height: u32, pixel_aspect: f32) -> FrameLayout {
+        let (even_w, even_h) = self.even_dims();
+        let even = |v: u32, on: bool| if on { (v & !1).max(2) } else { v.max(1) };
+        let dw = width as f64 * pixel_aspect as f64;
+        let dh = height as f64;
+        let size = |s: f64| {
+            (
+                (dw * s).round().max(1.0) as u32,
+                (dh * s).round().max(1.0) as u32,
+            )
+        };
+        let whole = |(w, h): (u32, u32)| {
+            let (w, h) = (even(w, even_w), even(h, even_h));
+            FrameLayout {
+                scaled: (w, h),
+                crop: (0, 0, w, h),
+                canvas: (w, h),
+                offset: (0, 0),
+            }
+        };
+        let Some(r) = self.resize else {
+            return whole(size((self.max_size as f64 / dw.max(dh)).min(1.0)));
+        };
+        let (tw, th) = (r.width as f64, r.height as f64);
+        let (sw, sh) = match r.mode {
+            FitMode::Fit => return whole(size((tw / dw).min(th / dh))),
+            FitMode::Pad => {
+                let (w, h) = size((tw / dw).min(th / dh));
+                (w.min(r.width), h.min(r.height))
+            }
+            FitMode::Fill => {
+                let (w, h) = size((tw / dw).max(th / dh));
+                (w.max(r.width), h.max(r.height))
+            }
+            FitMode::Crop => size(1.0),
+        };
+        let (cw, ch) = (sw.min(r.width), sh.min(r.height));
+        FrameLayout {
+            scaled: (sw, sh),
+            crop: ((sw - cw) / 2, (sh - ch) / 2, cw, ch),
+            canvas: (r.width, r.height),
+            offset: ((r.width - cw) / 2, (r.height - ch) / 2),
+        }
+    }
+
+    /// Short description of the colour pipeline, for slates.
+    pub fn colour_label(&self) -> String {
+        match self.display {
+            DisplayTransform::Standard => self.colorspace.to_string(),
+            DisplayTransform::Aces { odt, .. } => odt.label().to_string(),
+            DisplayTransform::Raw => "raw".to_string(),
+        }
+    }
+}
+
+/// The ffmpeg arguments for encoding raw `width`×`height` frames from stdin
+/// into `out`, with `audio` muxed in as one track each.
+pub fn ffmpeg_args(
+    settings: &VideoExportSettings,
+    width: u32,
+    height: u32,
+    audio: &[AudioTrack],
+    out: &Path,
+) -> Result<Vec<OsString>> {
+    settings.validate()?;
+    let pix_fmt = settings
+        .codec
+        .pix_fmt(settings.alpha, settings.pix_fmt.as_deref())?;
+    // x264/x265 refuse 4:2:0 frames with odd dimensions
+    if pix_fmt.starts_with("yuv420") && (width % 2 == 1 || height % 2 == 1) {
+        bail!(
+            "{} ({}) needs even frame dimensions, got {}x{}",
+            settings.codec.label,
+            pix_fmt,
+            width,
+            height
+        );
+    }
+    let mut args: Vec<OsString> = [
+        "-y",
+        "-f",
+        "rawvideo",
+        "-pix_fmt",
+        settings.bit_depth.pix_fmt(settings.alpha),
+        "-s",
+        &format!("{}x{}", width, height),
+        "-r",
+        &settings.fps_arg(),
+        "-i",
+        "-",
+    ]
+    .iter()
+    .map(OsString::from)
+    .collect();
+    for track in audio {
+        if track.seek > 0.0 {
+            args.extend(["-ss".into(), format!("{:.6}", track.seek).into()]);
+        }
+        args.extend(["-i".into(), track.path.clone().into_os_string()]);
+    }
+    args.extend(
+        [
+            "-c:v",
+            settings.codec.encoder(),
+            "-profile:v",
+            settings.codec.profile,
+            "-pix_fmt",
+            pix_fmt,
+        ]
+        .map(OsString::from),
+    );
+    match &settings.rate {
+        Some(RateControl::Crf(crf)) => args.extend(["-crf".into(), crf.to_string().into()]),
+        Some(RateControl::Bitrate(rate)) => args.extend(["-b:v", rate].map(OsString::from)),
+        None => {}
+    }
+    args.extend(
+        settings
+            .resolved_color_tags()
+            .ffmpeg_args()
+            .into_iter()
+            .map(OsString::from),
+    );
+    let ext = out
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    if !audio.is_empty() {
+        args.extend(["-map", "0:v"].map(OsString::from));
+        for (i, track) in audio.iter().enumerate() {
+            args.extend([
+                "-map".into(),
+                format!("{}:a:0", i + 1).into(),
+                format!("-filter:a:{}", i).into(),
+                track.filter().into(),
+            ]);
+        }
+        // MP4 players expect AAC; MOV and MXF take PCM
+        let codec = match ext.as_str() {
+            "mp4" | "m4v" => "aac",
+            _ => "pcm_s24le",
+        };
+        args.extend(["-c:a", codec, "-shortest"].map(OsString::from));
+    }
+    if matches!(ext.as_str(), "mov" | "mp4" | "m4v") {
+        args.extend(["-movflags", "+write_colr"].map(OsString::from));
+        // QuickTime only plays HEVC tagged hvc1
+        if settings.codec.codec == "h265" {
+            args.extend(["-tag:v", "hvc1"].map(OsString::from));
+        }
+    }
+    args.push(out.as_os_str().to_owned());
+    Ok(args)
+}
+
+/// Line `settings.audio` up with `exported`, the frames of `sequence`
+/// going into the movie (after the slate, if any).
+pub fn audio_tracks(
+    settings: &VideoExportSettings,
+    sequence: &[PathBuf],
+    exported: &[PathBuf],
+) -> Result<Vec<AudioTrack>> {
+    let (Some(seq_first), Some(first)) = (sequence.first(), exported.first()) else {
+        return Ok(Vec::new());
+    };
+    // frame numbers, or positions for unnumbered files
+    let position = |f: &PathBuf| -> i64 {
+        frame_number(f)
+            .map(|n| n as i64)
+            .or_else(|| sequence.iter().position(|s| s == f).map(|i| i as i64))
+            .unwrap_or(0)
+    };
+    let rate = settings.frame_rate();
+    let fps = settings.exact_fps();
+    let lead_in = settings.slate.as_ref().map_or(0, |s| s.hold) as f64 / fps;
+    settings
+        .audio
+        .iter()
+        .map(|a| {
+            if !a.path.is_file() {
+                bail!("audio file not found: {}", a.path.display());
+            }
+            let frames = match a.start {
+                None => position(first) - position(seq_first),
+                Some(AudioStart::Frame(n)) => position(first) - n,
+                Some(AudioStart::Timecode(tc)) => {
+                    let rate = rate.ok_or_else(|| {
+                        anyhow!(
+                            "audio start by timecode needs a timecode rate, not {} fps",
+                            settings.fps
+                        )
+                    })?;
+                    let picture = match read_timecode(first)? {
+                        Some(tc) => tc,
+                        None => TimeCode::from_frames(position(first).max(0) as u64, rate, false)?,
+                    };
+                    picture.to_frames(rate)? as i64 - tc.to_frames(rate)? as i64
+                }
+            };
+            Ok(AudioTrack {
+                path: a.path.clone(),
+                seek: frames as f64 / fps - lead_in,
+                channels: a.channels.clone(),
+            })
+        })
+        .collect()
+}
+
+/// ffprobe next to `ffmpeg` (`ffprobe` on the PATH for a bare `ffmpeg`).
+fn ffprobe_for(ffmpeg: &Path) -> Option<PathBuf> {
+    let name = ffmpeg.file_name()?.to_str()?;
+    name.contains("ffmpeg")
+        .then(|| ffmpeg.with_file_name(name.replace("ffmpeg", "ffprobe")))
+}
+
+/// Check with ffprobe, when there is one, that every stream of `out` lasts
+/// `frames` at the export rate, give or take a frame.
+fn verify_durations(settings: &VideoExportSettings, out: &Path, frames: usize) -> Result<()> {
+    let Some(ffprobe) = ffprobe_for(&settings.ffmpeg) else {
+        return Ok(());
+    };
+    let Ok(output) = Command::new(&ffprobe)
+        .args(["-v", "error", "-show_entries", "stream=codec_type,duration"])
+        .args(["-of", "csv=p=0"])
+        .arg(out)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+    else {
+        return Ok(());
+    };
+    if !output.status.success() {
+        bail!("ffprobe could not read {}", out.display());
+    }
+    let fps = settings.exact_fps();
+    let expected = frames as f64 / fps;
+    let tolerance = 1.5 / fps;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Some((kind, duration)) = line.trim().split_once(',') else {
+            continue;
+        };
+        // containers without per-stream durations print N/A
+        let Ok(duration) = duration.parse::<f64>() else {
+            continue;
+        };
+        if (duration - expected).abs() > tolerance {
+            bail!(
+                "{} stream of {} lasts {:.3}s, expected {:.3}s ({} frames)",
+                kind,
+                out.display(),
+                duration,
+                expected,
+                frames
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Whether `ffmpeg -version` runs.
+pub fn ffmpeg_available(ffmpeg: &Path) -> bool {
+    Command::new(ffmpeg)
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success())
+}
+
+/// The `.exr` files directly inside `dir`, sorted by name.
+pub fn sequence_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("{}", dir.display()))? {
+        let path = entry?.path();
+        let is_exr = path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("exr"));
+        if is_exr && path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    Ok(files)
+}
+
+/// Passed to the progress callback of [`export_video`] after each frame is
+/// handed to ffmpeg.
+#[derive(Debug)]
+pub struct VideoProgress<'a> {
+    pub completed: usize,
+    pub total: usize,
+    pub frame: &'a Path,
+}
+
+/// Encode `files` into `out`. Frames are decoded in parallel and written to
+/// ffmpeg in order as raw RGB(A); all must come out the same size. `progress`
+/// returns `false` to cancel, which stops ffmpeg and fails with "cancelled".
+pub fn export_video(
+    files: &[PathBuf],
+    out: &Path,
+    settings: &VideoExportSettings,
+    ocio: Option<&(dyn ColorProcessor + Send + Sync)>,
+    mut progress: impl FnMut(&VideoProgress) -> bool,
+) -> Result<()> {
+    settings.validate()?;
+    let sequence = files;
+    let files = &settings.range.select(files)?[..];
+    let audio = audio_tracks(settings, sequence, files)?;
+    let Some((first_path, rest)) = files.split_first() else {
+        bail!("no EXR frames to encode");
+    };
+    // the display transforms include their own gamut conversion
+    let lut = match settings.display {
+        DisplayTransform::Standard => settings.colorspace.lut()?,
+        _ => None,
+    };
+    // the first frame fixes the size given to ffmpeg
+    let first = render_frame(first_path, settings, lut.as_ref(), ocio)
+        .with_context(|| format!("{}", first_path.display()))?;
+    let args = ffmpeg_args(settings, first.width, first.height, &audio, out)?;
+    let mut child = Command::new(&settings.ffmpeg)
+        .args(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to start {}", settings.ffmpeg.display()))?;
+    // drain stderr so a chatty ffmpeg never blocks on a full pipe
+    let mut stderr = child.stderr.take().expect("piped stderr");
+    let log = std::thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text);
+        text
+    });
+    let mut stdin = child.stdin.take().expect("piped stdin");
+
+    let size = (first.width, first.height);
+    let mut failure: Option<anyhow::Error> = match &settings.slate {
+        Some(slate) => slate
+            .with_defaults(files, &settings.colour_label())
+            .render(size.0, size.1)
+            .and_then(|img| {
+                let frame = Frame::new(&img, settings);
+                for _ in 0..slate.hold {
+                    stdin
+                        .write_all(&frame.data)
+                        .context("failed to write to ffmpeg")?;
+                }
+                Ok(())
+            })
+            .context("slate")
+            .err(),
+        None => None,
+    };
+    let cancel = Arc::new(AtomicBool::new(false));
+    let mut deliver = |frame: Result<Frame, String>, path: &Path, completed: usize| {
+        if failure.is_none() {
+            let written = frame.map_err(|e| anyhow!(e)).and_then(|f| {
+                if (f.width, f.height) != size {
+                    bail!(
+                        "frame is {}x{}, expected {}x{} like the first frame",
+                        f.width,
+                        f.height,
+                        size.0,
+                        size.1
+                    );
+                }
+                stdin
+                    .write_all(&f.data)
+                    .context("failed to write to ffmpeg")
+            });
+            failure = written
+                .err()
+                .map(|e| e.context(format!("{}", path.display())));
+        }
+        let keep_going = failure.is_none()
+            && progress(&VideoProgress {
+                completed,
+                total: files.len(),
+                frame: path,
+            });
+        if !keep_going {
+            cancel.store(true, Ordering::SeqCst);
+        }
+    };
+    deliver(Ok(first), first_path, 1);
+
+    let exec = ExecOptions {
+        threads: settings.threads,
+        memory_limit: settings.memory_limit,
+        ordered: true,
+        cancel: Some(cancel.clone()),
+        ..Default::default()
+    };
+    let run = if cancel.load(Ordering::SeqCst) {
+        Ok(Default::default())
+    } else {
+        execute(
+            rest,
+            &exec,
+            |f| render_frame(f, settings, lut.as_ref(), ocio),
+            |p| {
+                let frame = match p.outcome {
+                    Outcome::Done(f) => Ok(f),
+                    Outcome::Failed(e) => Err(e),
+                    Outcome::Skipped | Outcome::Resumed => return,
+                };
+                deliver(frame, p.item, p.completed + 1);
+            },
+        )
+    };
+    drop(stdin);
+    let stop = run
+        .err()
+        .or(failure)
+        .or_else(|| cancel.load(Ordering::SeqCst).then(|| anyhow!("cancelled")));
+    if let Some(e) = stop {
+        let _ = child.kill();
+        let _ = child.wait();
+        let _ = log.join();
+        return Err(e);
+    }
+    let status = child.wait()?;
+    let log = log.join().unwrap_or_default();
+    if !status.success() {
+        let tail: Vec<&str> = log.lines().rev().take(5).collect();
+        let tail: Vec<&str> = tail.into_iter().rev().collect();
+        bail!("ffmpeg exited with {}: {}", status, tail.join("\n"));
+    }
+    let hold = settings.slate.as_ref().map_or(0, |s| s.hold as usize);
+    verify_durations(settings, out, hold + files.len())
+}
+
+/// One rendered frame in the layout of [`BitDepth::pix_fmt`].
+pub(crate) struct Frame {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) data: Vec<u8>,
+}
+
+/// Render one frame through the preview pipeline at the export bit depth.
+pub(crate) fn render_frame(
+    path: &Path,
+    settings: &VideoExportSettings,
+    lut: Option<&Lut>,
+    ocio: Option<&(dyn ColorProcessor + Send + Sync)>,
+) -> Result<Frame> {
+    let img = load_exr_basic(path)?;
+    let layout = settings.frame_layout(img.width as u32, img.height as u32, pixel_aspect(path));
+    // already at the output size, so the preview does no resampling
+    let img = layout.apply(img, settings.quality);
+    let mut pipeline = PreviewPipeline::new(settings.exposure, settings.gamma, lut);
+    pipeline.display = settings.display;
+    pipeline.gamut = settings.gamut;
+    pipeline.oog_overlay = settings.oog_overlay;
+    pipeline.ocio = ocio.map(|p| p as &dyn ColorProcessor);
+    match settings.bit_depth {
+        BitDepth::Eight => {
+            let p = generate_preview_with(&img, u32::MAX, &pipeline, PreviewQuality::Fast);
+            let mut p = PreviewImage {
+                width: layout.canvas.0,
+                height: layout.canvas.1,
+                rgba8: layout.place(p.rgba8, [0, 0, 0, 255]),
+            };
+            if let Some(m) = &settings.mask {
+                m.apply(&mut p);
+            }
+            if let Some(b) = &settings.burnin {
+                b.draw(&mut p, path)?;
+            }
+            Ok(Frame::new(&p, settings))
+        }
+        BitDepth::Sixteen => {
+            let p = generate_preview16(&img, u32::MAX, &pipeline, PreviewQuality::Fast);
+            let mut p = PreviewImage16 {
+                width: layout.canvas.0,
+                height: layout.canvas.1,
+                rgba16: layout.place(p.rgba16, [0, 0, 0, u16::MAX]),
+            };
+            if let Some(m) = &settings.mask {
+                m.apply16(&mut p);
+            }
+            if let Some(b) = &settings.burnin {
+                b.draw16(&mut p, path)?;
+            }
+            Ok(Frame::new16(&p, settings))
+        }
+    }
+}
+
+impl Frame {
+    /// Pack an 8-bit image at the export bit depth (scaled up for 16-bit).
+    pub(crate) fn new(p: &PreviewImage, settings: &VideoExportSettings) -> Frame {
+        let channels = if settings.alpha { 4 } else { 3 };
+        let data = match settings.bit_depth {
+            BitDepth::Eight => p
+                .rgba8
+                .chunks_exact(4)
+                .flat_map(|px| px[..channels].to_vec())
+                .collect(),
+            BitDepth::Sixteen => p
+                .rgba8
+                .chunks_exact(4)
+                .flat_map(|px| {
+                    px[..channels]
+                        .iter()
+                        .flat_map(|&v| (v as u16 * 257).to_le_bytes())
+                })
+                .collect(),
+        };
+        Frame {
+            width: p.width,
+            height: p.height,
+            data,
+        }
+    }
+
+    fn new16(p: &PreviewImage16, settings: &VideoExportSettings) -> Frame {
+        let channels = if settings.alpha { 4 } else { 3 };
+        Frame {
+            width: p.width,
+            height: p.height,
+            data: p
+                .rgba16
+                .chunks_exact(4)
+                .flat_map(|px| px[..channels].iter().flat_map(|v| v.to_le_bytes()))
+                .collect(),
+        }
+    }
+}
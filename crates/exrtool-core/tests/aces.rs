@@ -0,0 +1,163 @@
+use exrtool_core::aces::{acescct_decode, acescct_encode, pq_encode, AcesOdt, OutputTransform};
+use exrtool_core::{make_aces_lut_cube, parse_cube, Primaries, TransferFn};
+
+// Reference values derived from the aces-dev 1.3 CTL for an 18% grey input.
+const GREY: [f64; 3] = [0.18, 0.18, 0.18];
+
+fn assert_grey(odt: AcesOdt, expected: f64) {
+    let out = OutputTransform::new(odt).apply_f64(GREY);
+    for c in out {
+        assert!(
+            (c - expected).abs() < 1e-4,
+            "{:?}: {} != {}",
+            odt,
+            c,
+            expected
+        );
+    }
+}
+
+#[test]
+fn rrt_maps_mid_grey_to_4_8_nits() {
+    let oces = OutputTransform::new(AcesOdt::Srgb).rrt(GREY);
+    for c in oces {
+        assert!((c - 4.8).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn odt_mid_grey_reference_values() {
+    assert_grey(AcesOdt::Srgb, 0.355954);
+    assert_grey(AcesOdt::Rec709, 0.389530);
+    assert_grey(AcesOdt::P3D65, 0.411867);
+    // 15 nits on a 1000 nit PQ display
+    assert_grey(AcesOdt::Rec2100Pq1000, pq_encode(15.0));
+    assert!((pq_encode(15.0) - 0.332590).abs() < 1e-5);
+}
+
+// aces-dev 1.3 CTL outputs for saturated, off-neutral and high-luminance
+// ACES2065-1 inputs: the AP0 primaries, a bright red, a warm tone and 16.0 grey.
+const INPUTS: [[f64; 3]; 6] = [
+    [1.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0],
+    [0.0, 0.0, 1.0],
+    [4.0, 0.5, 0.25],
+    [0.3, 0.2, 0.1],
+    [16.0, 16.0, 16.0],
+];
+
+fn assert_references(odt: AcesOdt, expected: [[f64; 3]; 6]) {
+    let ot = OutputTransform::new(odt);
+    for (aces, want) in INPUTS.iter().zip(expected) {
+        let out = ot.apply_f64(*aces);
+        for i in 0..3 {
+            assert!(
+                (out[i] - want[i]).abs() < 1e-4,
+                "{:?} {:?}: {:?} != {:?}",
+                odt,
+                aces,
+                out,
+                want
+            );
+        }
+    }
+}
+
+#[test]
+fn odt_srgb_reference_values() {
+    assert_references(
+        AcesOdt::Srgb,
+        [
+            [1.0, 0.0, 0.009581],
+            [0.0, 0.878298, 0.0],
+            [0.0, 0.0, 0.851978],
+            [1.0, 0.471071, 0.511164],
+            [0.616042, 0.369920, 0.218166],
+            [0.999741, 0.999741, 0.999741],
+        ],
+    );
+}
+
+#[test]
+fn odt_rec709_reference_values() {
+    assert_references(
+        AcesOdt::Rec709,
+        [
+            [1.0, 0.0, 0.049642],
+            [0.0, 0.884642, 0.0],
+            [0.0, 0.0, 0.859695],
+            [1.0, 0.498646, 0.536648],
+            [0.636058, 0.402768, 0.258925],
+            [0.999754, 0.999754, 0.999754],
+        ],
+    );
+}
+
+#[test]
+fn odt_p3d65_reference_values() {
+    assert_references(
+        AcesOdt::P3D65,
+        [
+            [0.966196, 0.0, 0.080126],
+            [0.0, 0.879886, 0.0],
+            [0.0, 0.0, 0.839677],
+            [1.0, 0.545775, 0.561006],
+            [0.627324, 0.432881, 0.289964],
+            [0.999769, 0.999769, 0.999769],
+        ],
+    );
+}
+
+#[test]
+fn odt_rec2100_pq_reference_values() {
+    // 0 cd/m² encodes to a PQ code value of ~7.3e-7 rather than 0
+    assert_references(
+        AcesOdt::Rec2100Pq1000,
+        [
+            [0.531965, 0.000001, 0.055663],
+            [0.000001, 0.529657, 0.000001],
+            [0.065045, 0.000001, 0.514418],
+            [0.638335, 0.406885, 0.393400],
+            [0.407147, 0.346994, 0.269007],
+            [0.699920, 0.699920, 0.699920],
+        ],
+    );
+}
+
+#[test]
+fn output_is_monotonic_and_bounded() {
+    for odt in AcesOdt::ALL {
+        let ot = OutputTransform::new(odt);
+        let mut prev = -1.0;
+        for i in 0..=64 {
+            let x = 2f64.powf(i as f64 / 4.0 - 12.0);
+            let y = ot.apply_f64([x, x, x])[1];
+            assert!(y >= prev, "{:?} not monotonic at {}", odt, x);
+            assert!((0.0..=1.0).contains(&y));
+            prev = y;
+        }
+    }
+}
+
+#[test]
+fn acescct_round_trip() {
+    for &x in &[-0.01, 0.0, 0.0078125, 0.18, 1.0, 100.0] {
+        let y = acescct_decode(acescct_encode(x));
+        assert!((x - y).abs() < 1e-9 * x.abs().max(1.0));
+    }
+    assert!((acescct_encode(0.18) - 0.4135884).abs() < 1e-6);
+}
+
+#[test]
+fn baked_lut_matches_direct_evaluation() {
+    let text = make_aces_lut_cube(Primaries::ACEScgD60, TransferFn::AcesCct, AcesOdt::Srgb, 65);
+    let lut = parse_cube(&text).unwrap();
+    let ot = OutputTransform::new(AcesOdt::Srgb);
+    let cct = acescct_encode(0.18) as f32;
+    let baked = lut.apply([cct, cct, cct]);
+    // ACEScg grey is also grey in AP0
+    let direct = ot.apply_f64(GREY);
+    for i in 0..3 {
+        assert!((baked[i] as f64 - direct[i]).abs() < 5e-3);
+    }
+}
@@ -0,0 +1,241 @@
+mod common;
+
+use common::scratch;
+use exrtool_core::anim::{export_animation, AnimationFormat, AnimationSettings, Dither};
+use exrtool_core::slate::Slate;
+use exrtool_core::video::{FrameRange, VideoExportSettings};
+use exrtool_core::DisplayTransform;
+use std::path::{Path, PathBuf};
+
+/// `count` 16x8 frames: red ramps across, green down, blue with the frame.
+fn gradients(dir: &Path, count: u32) -> Vec<PathBuf> {
+    (1..=count)
+        .map(|n| {
+            let mut img = image::Rgba32FImage::new(16, 8);
+            for (x, y, p) in img.enumerate_pixels_mut() {
+                *p = image::Rgba([
+                    x as f32 / 15.0,
+                    y as f32 / 7.0,
+                    n as f32 / count as f32,
+                    1.0,
+                ]);
+            }
+            let path = dir.join(format!("grad.{}.exr", 1000 + n));
+            img.save(&path).unwrap();
+            path
+        })
+        .collect()
+}
+
+fn settings(format: AnimationFormat, frames: VideoExportSettings) -> AnimationSettings {
+    AnimationSettings {
+        format,
+        frames: VideoExportSettings {
+            display: DisplayTransform::Raw,
+            ..frames
+        },
+        ..Default::default()
+    }
+}
+
+fn export(files: &[PathBuf], out: &Path, settings: &AnimationSettings) {
+    export_animation(files, out, settings, None, |_| true).unwrap();
+}
+
+#[test]
+fn formats_and_dithers_parse() {
+    assert_eq!(
+        AnimationFormat::from_path(Path::new("clip.WEBP")),
+        Some(AnimationFormat::Webp)
+    );
+    assert_eq!(
+        AnimationFormat::from_path(Path::new("clip.png")),
+        Some(AnimationFormat::Apng)
+    );
+    assert_eq!(AnimationFormat::from_path(Path::new("clip.mov")), None);
+    assert_eq!(
+        "APNG".parse::<AnimationFormat>().unwrap().extension(),
+        "png"
+    );
+    assert!("mp4"
+        .parse::<AnimationFormat>()
+        .unwrap_err()
+        .to_string()
+        .contains("gif, apng, webp"));
+    assert_eq!("bayer".parse::<Dither>().unwrap(), Dither::Ordered);
+    assert_eq!(Dither::default().to_string(), "floyd-steinberg");
+
+    let bad = AnimationSettings {
+        colors: 1,
+        ..Default::default()
+    };
+    assert!(bad.validate().is_err());
+}
+
+#[test]
+fn gifs_keep_the_frame_clock_and_palettes() {
+    let dir = scratch("anim", "gif");
+    let files = gradients(&dir, 4);
+    let out = dir.join("clip.gif");
+    let mut s = settings(
+        AnimationFormat::Gif,
+        VideoExportSettings {
+            slate: Some(Slate {
+                hold: 2,
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+    );
+    s.colors = 16;
+    s.loops = 3;
+    let mut seen = Vec::new();
+    export_animation(&files, &out, &s, None, |p| {
+        seen.push((p.completed, p.total));
+        true
+    })
+    .unwrap();
+    assert_eq!(seen, [(1, 4), (2, 4), (3, 4), (4, 4)]);
+
+    let mut opts = gif::DecodeOptions::new();
+    opts.set_color_output(gif::ColorOutput::Indexed);
+    let mut gif = opts
+        .clone()
+        .read_info(std::fs::File::open(&out).unwrap())
+        .unwrap();
+    let mut delays = Vec::new();
+    while let Some(frame) = gif.read_next_frame().unwrap() {
+        assert_eq!((frame.width, frame.height), (16, 8));
+        let palette = frame.palette.as_ref().unwrap();
+        assert!(palette.len() <= 16 * 3);
+        delays.push(frame.delay);
+    }
+    // slate held for two frames, then 1/24 s rounded without drifting
+    assert_eq!(delays, [8, 5, 4, 4, 4]);
+    assert_eq!(gif.repeat(), gif::Repeat::Finite(2));
+
+    s.frames.slate = None;
+    for dither in [Dither::None, Dither::Ordered] {
+        s.dither = dither;
+        export(&files, &out, &s);
+        let mut gif = opts
+            .clone()
+            .read_info(std::fs::File::open(&out).unwrap())
+            .unwrap();
+        let mut frames = 0;
+        while let Some(frame) = gif.read_next_frame().unwrap() {
+            assert!(frame.buffer.iter().all(|&i| (i as usize) < 16));
+            frames += 1;
+        }
+        assert_eq!(frames, 4);
+    }
+}
+
+#[test]
+fn flat_frames_keep_their_colours_and_transparency() {
+    let dir = scratch("anim", "flat");
+    let mut img = image::Rgba32FImage::new(4, 2);
+    for (x, _, p) in img.enumerate_pixels_mut() {
+        let a = if x < 2 { 1.0 } else { 0.0 };
+        *p = image::Rgba([0.25, 0.5, 0.75, a]);
+    }
+    let file = dir.join("flat.1001.exr");
+    img.save(&file).unwrap();
+    let mut s = settings(AnimationFormat::Gif, Default::default());
+    s.frames.alpha = true;
+    let out = dir.join("flat.gif");
+    export(std::slice::from_ref(&file), &out, &s);
+
+    let mut opts = gif::DecodeOptions::new();
+    opts.set_color_output(gif::ColorOutput::RGBA);
+    let mut gif = opts.read_info(std::fs::File::open(&out).unwrap()).unwrap();
+    let frame = gif.read_next_frame().unwrap().unwrap();
+    let gif_px: Vec<&[u8]> = frame.buffer.chunks_exact(4).collect();
+
+    s.format = AnimationFormat::Apng;
+    let out = dir.join("flat.png");
+    export(&[file], &out, &s);
+    let png = image::open(&out).unwrap().to_rgba8();
+    assert_eq!(gif_px[0], png.get_pixel(0, 0).0);
+    assert_eq!(gif_px[2][3], 0);
+    assert_eq!(png.get_pixel(2, 0).0[3], 0);
+}
+
+#[test]
+fn apngs_step_through_the_range() {
+    let dir = scratch("anim", "apng");
+    let files = gradients(&dir, 5);
+    let out = dir.join("clip.png");
+    let s = settings(
+        AnimationFormat::Apng,
+        VideoExportSettings {
+            fps: 25.0,
+            range: FrameRange {
+                first: Some(1002),
+                step: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    );
+    export(&files, &out, &s);
+
+    let decoder = png::Decoder::new(std::fs::File::open(&out).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let info = reader.info();
+    let actl = info.animation_control.unwrap();
+    assert_eq!((actl.num_frames, actl.num_plays), (2, 0));
+    assert_eq!(info.color_type, png::ColorType::Rgb);
+    let mut buf = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut buf).unwrap();
+    let fctl = reader.info().frame_control.unwrap();
+    assert_eq!((fctl.delay_num, fctl.delay_den), (40, 1000));
+    // frame 1002 of 5: blue 2/5
+    assert_eq!(buf[2], 102);
+}
+
+#[test]
+fn webps_are_animated_riff_files() {
+    let dir = scratch("anim", "webp");
+    let files = gradients(&dir, 3);
+    let out = dir.join("clip.webp");
+    let mut s = settings(AnimationFormat::Webp, Default::default());
+    s.frames.display = DisplayTransform::Standard;
+    s.loops = 2;
+    export(&files, &out, &s);
+
+    let data = std::fs::read(&out).unwrap();
+    assert_eq!(&data[..4], b"RIFF");
+    let riff = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize;
+    assert_eq!(riff, data.len() - 8);
+    let mut webp = image_webp::WebPDecoder::new(std::io::Cursor::new(&data)).unwrap();
+    assert!(webp.is_animated());
+    assert_eq!(webp.num_frames(), 3);
+    assert_eq!(webp.dimensions(), (16, 8));
+    assert_eq!(
+        webp.loop_count(),
+        image_webp::LoopCount::Times(2.try_into().unwrap())
+    );
+    // sRGB colour tags from the standard display transform
+    assert!(webp.icc_profile().unwrap().is_some());
+    let mut buf = vec![0; webp.output_buffer_size().unwrap()];
+    let delays: Vec<u32> = (0..3).map(|_| webp.read_frame(&mut buf).unwrap()).collect();
+    assert_eq!(delays, [42, 41, 42]);
+}
+
+#[test]
+fn failures_leave_nothing_behind() {
+    let dir = scratch("anim", "cancel");
+    let files = gradients(&dir, 3);
+    let out = dir.join("clip.gif");
+    let s = settings(AnimationFormat::Gif, Default::default());
+    let err = export_animation(&files, &out, &s, None, |_| false).unwrap_err();
+    assert_eq!(err.to_string(), "cancelled");
+    assert!(!out.exists());
+
+    let mut broken = files.clone();
+    broken.push(dir.join("grad.1009.exr"));
+    std::fs::write(&broken[3], b"not an exr").unwrap();
+    assert!(export_animation(&broken, &out, &s, None, |_| true).is_err());
+    assert!(!out.exists());
+}
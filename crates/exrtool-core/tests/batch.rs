@@ -0,0 +1,248 @@
+mod common;
+
+use common::{frames, scratch, GREY};
+use exrtool_core::batch::{FrameRange, ItemStatus, JobFile, OutputFormat, RunOptions};
+use std::path::Path;
+
+fn job_file(dir: &Path, yaml: &str) -> JobFile {
+    JobFile::from_str_with_base(yaml, dir).unwrap()
+}
+
+#[test]
+fn frame_ranges_parse() {
+    assert_eq!(
+        "1001-1100".parse::<FrameRange>().unwrap(),
+        FrameRange {
+            first: 1001,
+            last: 1100
+        }
+    );
+    assert_eq!("7".parse::<FrameRange>().unwrap().last, 7);
+    assert!("1100-1001".parse::<FrameRange>().is_err());
+    assert!("a-b".parse::<FrameRange>().is_err());
+}
+
+#[test]
+fn versions_and_fields_are_checked() {
+    let base = Path::new("");
+    let err = |yaml: &str| {
+        JobFile::from_str_with_base(yaml, base)
+            .unwrap_err()
+            .to_string()
+    };
+    assert!(err("jobs: []").contains("version"));
+    assert!(err("version: 2\njobs: []").contains("unsupported job file version 2"));
+    assert!(
+        JobFile::from_str_with_base("version: 1\njobs: [{input: a.exr, colr: {}}]", base).is_err()
+    );
+
+    let file = JobFile::from_str_with_base(
+        "version: 1\njobs: [{input: a.exr, format: jpg, quality: fast}]",
+        base,
+    )
+    .unwrap();
+    assert_eq!(file.jobs[0].format, OutputFormat::Jpeg);
+    assert_eq!(file.jobs[0].max_size, 2048);
+    assert_eq!(file.jobs[0].colour.gamma, 2.2);
+}
+
+#[test]
+fn legacy_lists_still_load() {
+    let dir = scratch("batch", "legacy");
+    frames(&dir, "shot[a]", &[1], (8, 4), GREY);
+    let input = dir.join("renders").join("shot[a].1.exr");
+    let yaml = format!(
+        "- input: {}\n  exposure: 1.5\n- input: {}\n  output: {}\n",
+        input.display(),
+        input.display(),
+        dir.join("out{1}#2.png").display()
+    );
+    let file = JobFile::from_str_with_base(&yaml, Path::new("ignored")).unwrap();
+    assert!(!file.continue_on_error);
+    assert!(file.legacy);
+    assert_eq!(file.jobs[0].colour.exposure, 1.5);
+    let items = file.plan().unwrap();
+    assert_eq!(items[0].input, input);
+    assert_eq!(items[0].output, input.with_extension("png"));
+    // legacy outputs are literal paths, '#' included
+    assert_eq!(items[1].output, dir.join("out{1}#2.png"));
+}
+
+#[test]
+fn plans_expand_sequences_globs_and_templates() {
+    let dir = scratch("batch", "plan");
+    frames(&dir, "beauty", &[1001, 1002, 1004], (8, 4), GREY);
+    frames(&dir, "diffuse", &[1001], (8, 4), GREY);
+
+    let file = job_file(
+        &dir,
+        "version: 1\njobs:\n\
+         - name: beauty\n  sequence: renders/beauty.####.exr\n  output: out/{job}_{frame:05}.{ext}\n\
+         - sequence: renders/beauty.%04d.exr\n  frames: 1002-1003\n  format: tiff\n  output: out/{stem}.{ext}\n\
+         - input: renders/diffuse.*.exr\n  output: '{dir}/../out/{index}_{filename}.jpg'\n",
+    );
+    let items = file.plan().unwrap();
+    let outputs: Vec<String> = items
+        .iter()
+        .map(|i| {
+            i.output
+                .strip_prefix(&dir)
+                .unwrap()
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .collect();
+    assert_eq!(
+        outputs,
+        [
+            "out/beauty_01001.png",
+            "out/beauty_01002.png",
+            "out/beauty_01004.png",
+            "out/beauty.1002.tif",
+            "out/beauty.1003.tif",
+            "renders/../out/0_diffuse.1001.exr.jpg",
+        ]
+    );
+    assert_eq!(items[3].frame, Some(1002));
+    assert_eq!(file.job_label(1), "job2");
+
+    // every frame writing the same file is refused
+    let clash = job_file(
+        &dir,
+        "version: 1\njobs: [{sequence: renders/beauty.####.exr, output: out/preview.png}]",
+    );
+    assert!(clash.plan().unwrap_err().to_string().contains("both write"));
+    for bad in [
+        "version: 1\njobs: [{input: renders/none.*.exr}]",
+        "version: 1\njobs: [{input: a.exr, sequence: b.####.exr}]",
+        "version: 1\njobs: [{sequence: renders/beauty.exr}]",
+        "version: 1\njobs: [{input: renders/diffuse.1001.exr, output: '{frame}/{nope}'}]",
+    ] {
+        assert!(job_file(&dir, bad).plan().is_err(), "{}", bad);
+    }
+}
+
+#[test]
+fn runs_stop_or_continue_on_errors() {
+    let dir = scratch("batch", "run");
+    frames(&dir, "beauty", &[1001, 1003], (8, 4), GREY);
+    let yaml = |continue_on_error: bool| {
+        format!(
+            "version: 1\ncontinue_on_error: {}\njobs:\n\
+             - sequence: renders/beauty.####.exr\n  frames: 1001-1003\n  format: jpeg\n  max_size: 4\n  output: out/{{frame}}.{{ext}}\n  colour: {{display: aces-srgb, gamut: {{mode: rgc}}}}\n",
+            continue_on_error
+        )
+    };
+
+    // one worker, so nothing after the failure has started yet
+    let serial = RunOptions {
+        threads: 1,
+        ..Default::default()
+    };
+    let report = job_file(&dir, &yaml(false))
+        .run(&serial, |_, _, _| {})
+        .unwrap();
+    let statuses: Vec<ItemStatus> = report.items.iter().map(|i| i.status).collect();
+    assert_eq!(
+        statuses,
+        [ItemStatus::Written, ItemStatus::Failed, ItemStatus::Skipped]
+    );
+    assert!(report.items[1].error.is_some());
+
+    let mut seen = 0;
+    let report = job_file(&dir, &yaml(true))
+        .run(&RunOptions::default(), |_, _, total| {
+            assert_eq!(total, 3);
+            seen += 1
+        })
+        .unwrap();
+    let summary = report.summary();
+    assert_eq!(
+        (summary.written, summary.failed, summary.skipped),
+        (2, 1, 0)
+    );
+    assert_eq!(seen, 3);
+    let out = image::open(dir.join("out/1003.jpg")).unwrap();
+    assert_eq!((out.width(), out.height()), (4, 2));
+
+    // dry runs touch nothing
+    let file = job_file(
+        &dir,
+        "version: 1\njobs: [{input: renders/beauty.1001.exr, output: 'dry/{stem}.png'}]",
+    );
+    let report = file
+        .run(
+            &RunOptions {
+                dry_run: true,
+                ..Default::default()
+            },
+            |_, _, _| {},
+        )
+        .unwrap();
+    assert_eq!(report.summary().dry_run, 1);
+    assert!(!dir.join("dry").exists());
+}
+
+#[test]
+fn slates_and_masks_in_jobs() {
+    let dir = scratch("batch", "slate");
+    frames(&dir, "sh010_beauty", &[1001, 1002], (8, 4), GREY);
+    let file = job_file(
+        &dir,
+        "version: 1\njobs:\n\
+         - sequence: renders/sh010_beauty.####.exr\n  output: out/{stem}.{ext}\n  max_size: 8\n  mask: '2:1'\n  slate: {project: Demo, artist: Jane}\n",
+    );
+    let items = file.plan().unwrap();
+    let names: Vec<_> = items
+        .iter()
+        .map(|i| i.output.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "sh010_beauty.1000.png",
+            "sh010_beauty.1001.png",
+            "sh010_beauty.1002.png"
+        ]
+    );
+    let slate = items[0].slate.as_ref().unwrap();
+    assert_eq!(items[0].frame, Some(1000));
+    assert_eq!(slate.shot.as_deref(), Some("sh010_beauty"));
+    assert_eq!(slate.frames.as_deref(), Some("1001-1002 (2 frames)"));
+    assert_eq!(slate.colour.as_deref(), Some("standard (acescg)"));
+    assert!(items[1..].iter().all(|i| i.slate.is_none()));
+
+    let report = file.run(&RunOptions::default(), |_, _, _| {}).unwrap();
+    assert_eq!(report.summary().written, 3);
+    // 8x4 frames masked to 2:1 are untouched; the slate is drawn on black
+    let frame = image::open(dir.join("out/sh010_beauty.1001.png"))
+        .unwrap()
+        .to_rgba8();
+    assert!(frame.pixels().all(|p| p[0] > 0));
+    let slate = image::open(dir.join("out/sh010_beauty.1000.png"))
+        .unwrap()
+        .to_rgba8();
+    assert_eq!((slate.width(), slate.height()), (8, 4));
+    assert_eq!(slate.get_pixel(0, 0).0, [0, 0, 0, 255]);
+
+    let masked = job_file(
+        &dir,
+        "version: 1\njobs: [{input: renders/sh010_beauty.1001.exr, output: 'masked.png', mask: '4:1'}]",
+    );
+    masked.run(&RunOptions::default(), |_, _, _| {}).unwrap();
+    let img = image::open(dir.join("masked.png")).unwrap().to_rgba8();
+    assert_eq!(img.get_pixel(0, 0).0[..3], [0, 0, 0]);
+    assert!(img.get_pixel(0, 1).0[0] > 0);
+
+    let bad = "version: 1\njobs: [{input: renders/sh010_beauty.1001.exr, mask: wide}]";
+    assert!(JobFile::from_str_with_base(bad, &dir).is_err());
+}
+
+#[test]
+fn sample_job_file_loads() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../docs/jobs.yml");
+    let file = JobFile::from_path(&path).unwrap();
+    assert!(file.continue_on_error);
+    assert_eq!(file.jobs.len(), 3);
+    assert_eq!(file.jobs[2].format, OutputFormat::Tiff);
+}
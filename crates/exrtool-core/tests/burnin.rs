@@ -0,0 +1,120 @@
+mod common;
+
+use common::{frame, scratch, GREY};
+use exrtool_core::burnin::{Anchor, BurnIn, BurnInOptions, BurnInText};
+use exrtool_core::{generate_preview, load_exr_basic, PreviewQuality};
+
+fn burnin(texts: &[&str], opts: BurnInOptions) -> BurnIn {
+    BurnIn::new(&BurnInOptions {
+        texts: texts.iter().map(|t| t.parse().unwrap()).collect(),
+        ..opts
+    })
+    .unwrap()
+}
+
+#[test]
+fn texts_parse_with_positions() {
+    let t: BurnInText = "top-right={timecode}".parse().unwrap();
+    assert_eq!(
+        (t.anchor, t.template.as_str()),
+        (Anchor::TopRight, "{timecode}")
+    );
+    // no position, or something that is not one, keeps the whole text
+    let t: BurnInText = "{shot} {frame}".parse().unwrap();
+    assert_eq!(
+        (t.anchor, t.template.as_str()),
+        (Anchor::BottomLeft, "{shot} {frame}")
+    );
+    let t: BurnInText = "a=b".parse().unwrap();
+    assert_eq!(t.template, "a=b");
+    assert!("middle".parse::<Anchor>().is_err());
+
+    let bad = BurnInOptions {
+        texts: vec!["{attr:}".parse().unwrap()],
+        ..Default::default()
+    };
+    assert!(BurnIn::new(&bad).is_err());
+    let bad = BurnInOptions {
+        box_opacity: 1.5,
+        ..Default::default()
+    };
+    assert!(BurnIn::new(&bad).is_err());
+}
+
+#[test]
+fn fields_expand_per_frame() {
+    let dir = scratch("burnin", "fields");
+    let path = dir.join("sh010_comp_v002.1001.exr");
+    frame(&path, 64, 36, GREY);
+    let b = burnin(
+        &[
+            "top-left={shot} {frame:06}",
+            "top-right={timecode}",
+            "bottom={task} {lut} {width}x{height}",
+            "{attr:compression}",
+        ],
+        BurnInOptions {
+            filename_regex: Some(r"^(?P<seq>sh\d+)_(?P<task>[a-z]+)".into()),
+            lut: Some("show_lut".into()),
+            ..Default::default()
+        },
+    );
+    let lines = b.lines(&path, 64, 36).unwrap();
+    let text: Vec<_> = lines.iter().map(|(_, t)| t.as_str()).collect();
+    assert_eq!(text[0], "sh010_comp_v002 001001");
+    // no timeCode attribute: counted from the frame number at 24 fps
+    assert_eq!(text[1], "00:00:41:17");
+    assert_eq!(text[2], "comp show_lut 64x36");
+    assert!(!text[3].is_empty());
+
+    let missing = burnin(&["{attr:owner}"], BurnInOptions::default());
+    assert!(missing.lines(&path, 64, 36).is_err());
+}
+
+#[test]
+fn text_and_box_are_drawn_at_the_anchor() {
+    let dir = scratch("burnin", "draw");
+    let path = dir.join("plate.0007.exr");
+    frame(&path, 320, 180, GREY);
+    let img = load_exr_basic(&path).unwrap();
+    let plain = generate_preview(&img, 320, 0.0, 2.2, None, PreviewQuality::Fast);
+
+    let mut drawn = plain.clone();
+    burnin(&["top-left=A"], BurnInOptions::default())
+        .draw(&mut drawn, &path)
+        .unwrap();
+    let px = |img: &exrtool_core::PreviewImage, x: u32, y: u32| {
+        let i = ((y * img.width + x) * 4) as usize;
+        img.rgba8[i]
+    };
+    // scale 1 below 270 lines: 4 px margin, 2 px box padding, 5x7 glyph
+    assert_eq!(px(&drawn, 6, 4), 255, "top of the A");
+    assert!(px(&drawn, 2, 2) < px(&plain, 2, 2), "box darkens");
+    assert_eq!(
+        px(&drawn, 4, 4),
+        px(&drawn, 2, 2),
+        "inside the box, off the glyph"
+    );
+    assert_eq!(px(&drawn, 20, 20), px(&plain, 20, 20), "outside the box");
+
+    let mut no_box = plain.clone();
+    burnin(
+        &["bottom-right=A"],
+        BurnInOptions {
+            box_opacity: 0.0,
+            scale: 2,
+            ..Default::default()
+        },
+    )
+    .draw(&mut no_box, &path)
+    .unwrap();
+    let changed: Vec<(u32, u32)> = (0..180)
+        .flat_map(|y| (0..320).map(move |x| (x, y)))
+        .filter(|&(x, y)| px(&no_box, x, y) != px(&plain, x, y))
+        .collect();
+    assert!(changed.iter().all(|&(x, y)| px(&no_box, x, y) == 255));
+    let (max_x, max_y) = changed
+        .iter()
+        .fold((0, 0), |(a, b), &(x, y)| (a.max(x), b.max(y)));
+    assert_eq!((max_x, max_y), (320 - 8 - 1, 180 - 8 - 1));
+}
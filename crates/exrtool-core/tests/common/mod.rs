@@ -0,0 +1,52 @@
+//! Fixtures shared by the integration tests.
+#![allow(dead_code)]
+
+use exrtool_core::metadata::{edit_metadata, MetadataValue, WriteOptions};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Mid grey, opaque.
+pub const GREY: [f32; 4] = [0.18, 0.18, 0.18, 1.0];
+
+/// An empty directory `exrtool-<suite>-<name>-<pid>` with a `renders`
+/// subdirectory for [`frames`].
+pub fn scratch(suite: &str, name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("exrtool-{}-{}-{}", suite, name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("renders")).unwrap();
+    dir
+}
+
+/// A `width`x`height` frame filled with `rgba`.
+pub fn frame(path: &Path, width: u32, height: u32, rgba: [f32; 4]) {
+    let mut img = image::Rgba32FImage::new(width, height);
+    for p in img.pixels_mut() {
+        *p = image::Rgba(rgba);
+    }
+    img.save(path).unwrap();
+}
+
+/// `renders/<name>.<n>.exr` under `dir` for each of `numbers`.
+pub fn frames(
+    dir: &Path,
+    name: &str,
+    numbers: &[u32],
+    (width, height): (u32, u32),
+    rgba: [f32; 4],
+) {
+    for n in numbers {
+        let path = dir.join("renders").join(format!("{}.{}.exr", name, n));
+        frame(&path, width, height, rgba);
+    }
+}
+
+/// A 4x4 plate with `attrs` written to its header.
+pub fn plate(path: &Path, attrs: &[(&str, MetadataValue)]) {
+    image::Rgba32FImage::new(4, 4).save(path).unwrap();
+    let set: HashMap<String, MetadataValue> = attrs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.clone()))
+        .collect();
+    edit_metadata(path, &set, &[], &WriteOptions::default()).unwrap();
+}
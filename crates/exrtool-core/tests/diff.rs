@@ -0,0 +1,115 @@
+mod common;
+
+use common::{plate, scratch};
+use exrtool_core::diff::{diff_files, diff_sequence, DiffOptions};
+use exrtool_core::header::describe_value;
+use exrtool_core::metadata::MetadataValue;
+
+fn text(s: &str) -> MetadataValue {
+    MetadataValue::Text(s.into())
+}
+
+#[test]
+fn pairs_report_changed_and_missing_attributes() {
+    let dir = scratch("diff", "pair");
+    let (a, b) = (dir.join("a.exr"), dir.join("b.exr"));
+    plate(&a, &[("owner", text("ana")), ("renderTime", text("12s"))]);
+    plate(
+        &b,
+        &[
+            ("owner", text("bo")),
+            ("renderTime", text("15s")),
+            ("comments", text("v2")),
+        ],
+    );
+
+    let diff = diff_files(&a, &b, &DiffOptions::default()).unwrap();
+    let keys: Vec<&str> = diff.differences.iter().map(|d| d.key.as_str()).collect();
+    assert_eq!(keys, ["comments", "owner", "renderTime"]);
+    assert_eq!(diff.differences[0].a, None);
+    assert_eq!(diff.differences[1].b, Some(text("bo")));
+
+    let opts = DiffOptions {
+        ignore: vec!["render*".into(), "comments".into()],
+    };
+    let diff = diff_files(&a, &b, &opts).unwrap();
+    assert_eq!(diff.differences.len(), 1);
+    assert!(diff_files(&a, &a, &DiffOptions::default())
+        .unwrap()
+        .differences
+        .is_empty());
+    assert!(diff_files(
+        &a,
+        &b,
+        &DiffOptions {
+            ignore: vec!["[".into()]
+        }
+    )
+    .is_err());
+}
+
+#[test]
+fn sequences_list_varying_attributes_with_ranges() {
+    let dir = scratch("diff", "seq");
+    let mut files = Vec::new();
+    for (i, tc) in ["01:00:00:00", "01:00:00:01", "01:00:00:02"]
+        .iter()
+        .enumerate()
+    {
+        let path = dir.join(format!("sh.{}.exr", 1001 + i));
+        let mut attrs = vec![
+            ("timeCode", MetadataValue::TimeCode(tc.parse().unwrap())),
+            ("owner", text("ana")),
+        ];
+        if i == 1 {
+            attrs.push(("comments", text("retake")));
+        }
+        plate(&path, &attrs);
+        files.push(path);
+    }
+    let broken = dir.join("sh.1004.exr");
+    std::fs::write(&broken, b"not an exr").unwrap();
+    files.push(broken.clone());
+
+    let diff = diff_sequence(&files, &DiffOptions::default()).unwrap();
+    assert_eq!(diff.frames, 3);
+    assert_eq!(diff.failed.len(), 1);
+    assert_eq!(diff.failed[0].0, broken);
+    let keys: Vec<&str> = diff.varying.iter().map(|v| v.key.as_str()).collect();
+    assert_eq!(keys, ["comments", "timeCode"]);
+    assert!(diff.constant > 0);
+
+    let comments = &diff.varying[0];
+    assert_eq!(comments.values[0].value, None);
+    assert_eq!(comments.values[0].count, 2);
+    assert_eq!(comments.values[0].first, files[0]);
+    assert_eq!(comments.values[1].first, files[1]);
+    assert_eq!(comments.min, None);
+
+    let tc = &diff.varying[1];
+    assert_eq!(tc.values.len(), 3);
+    assert_eq!(tc.min.as_ref().unwrap().to_string(), "01:00:00:00");
+    assert_eq!(tc.max.as_ref().unwrap().to_string(), "01:00:00:02");
+
+    let opts = DiffOptions {
+        ignore: vec!["timeCode".into()],
+    };
+    let diff = diff_sequence(&files, &opts).unwrap();
+    assert_eq!(diff.varying.len(), 1);
+}
+
+#[test]
+fn layout_attributes_read_as_text() {
+    assert_eq!(describe_value("compression", &[3]), text("zip"));
+    let mut chlist = Vec::new();
+    for (name, ty) in [("A", 1u32), ("Z", 2)] {
+        chlist.extend_from_slice(name.as_bytes());
+        chlist.push(0);
+        chlist.extend_from_slice(&ty.to_le_bytes());
+        chlist.extend_from_slice(&[0, 0, 0, 0]);
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+        chlist.extend_from_slice(&1i32.to_le_bytes());
+    }
+    chlist.push(0);
+    assert_eq!(describe_value("chlist", &chlist), text("A:half,Z:float"));
+}
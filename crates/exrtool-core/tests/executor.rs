@@ -0,0 +1,224 @@
+use anyhow::bail;
+use exrtool_core::executor::{execute, parse_size, ExecOptions, Outcome, Task};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+struct Item(u32);
+
+impl Task for Item {
+    fn key(&self) -> String {
+        format!("item{}", self.0)
+    }
+
+    fn memory(&self) -> u64 {
+        100
+    }
+}
+
+fn items(n: u32) -> Vec<Item> {
+    (0..n).map(Item).collect()
+}
+
+fn checkpoint_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "exrtool-exec-{}-{}.checkpoint",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn sizes_parse() {
+    assert_eq!(parse_size("1048576").unwrap(), 1 << 20);
+    assert_eq!(parse_size("512M").unwrap(), 512 << 20);
+    assert_eq!(parse_size("8g").unwrap(), 8 << 30);
+    assert_eq!(parse_size("1.5GB").unwrap(), 3 << 29);
+    assert_eq!(parse_size("2KiB").unwrap(), 2048);
+    assert!(parse_size("lots").is_err());
+    assert!(parse_size("-1G").is_err());
+}
+
+#[test]
+fn every_item_is_reported_once() {
+    let list = items(50);
+    let mut seen = [0; 50];
+    let mut last = 0;
+    let summary = execute(
+        &list,
+        &ExecOptions {
+            threads: 4,
+            continue_on_error: true,
+            ..Default::default()
+        },
+        |item| {
+            if item.0 % 10 == 3 {
+                bail!("bad {}", item.0);
+            }
+            Ok(item.0 * 2)
+        },
+        |p| {
+            seen[p.index] += 1;
+            assert_eq!(p.completed, last + 1);
+            last = p.completed;
+            assert_eq!(p.total, 50);
+            match p.outcome {
+                Outcome::Done(v) => assert_eq!(v, p.item.0 * 2),
+                Outcome::Failed(e) => assert_eq!(e, format!("bad {}", p.item.0)),
+                other => panic!("unexpected {:?}", other),
+            }
+        },
+    )
+    .unwrap();
+    assert!(seen.iter().all(|&n| n == 1));
+    assert_eq!((summary.done, summary.failed, summary.skipped), (45, 5, 0));
+}
+
+#[test]
+fn failures_skip_the_rest_unless_continuing() {
+    let list = items(6);
+    let mut outcomes = Vec::new();
+    let summary = execute(
+        &list,
+        &ExecOptions {
+            threads: 1,
+            ..Default::default()
+        },
+        |item| if item.0 == 2 { bail!("stop") } else { Ok(()) },
+        |p| outcomes.push((p.index, matches!(p.outcome, Outcome::Skipped))),
+    )
+    .unwrap();
+    assert_eq!((summary.done, summary.failed, summary.skipped), (2, 1, 3));
+    assert_eq!(
+        outcomes,
+        [
+            (0, false),
+            (1, false),
+            (2, false),
+            (3, true),
+            (4, true),
+            (5, true)
+        ]
+    );
+}
+
+#[test]
+fn ordered_delivery_and_memory_limit() {
+    let list = items(24);
+    let running = AtomicU64::new(0);
+    let peak = AtomicU64::new(0);
+    let mut order = Vec::new();
+    execute(
+        &list,
+        &ExecOptions {
+            threads: 6,
+            memory_limit: Some(250),
+            ordered: true,
+            ..Default::default()
+        },
+        |item| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            // later items finish first
+            std::thread::sleep(Duration::from_millis(((24 - item.0) % 5) as u64 * 3));
+            running.fetch_sub(1, Ordering::SeqCst);
+            Ok(item.0)
+        },
+        |p| {
+            if let Outcome::Done(v) = p.outcome {
+                order.push(v);
+            }
+        },
+    )
+    .unwrap();
+    assert_eq!(order, (0..24).collect::<Vec<_>>());
+    // 100 bytes each under a 250 byte cap
+    assert!(peak.load(Ordering::SeqCst) <= 2);
+}
+
+#[test]
+fn checkpoints_resume_interrupted_batches() {
+    let path = checkpoint_path("resume");
+    let list = items(10);
+    let opts = ExecOptions {
+        threads: 2,
+        checkpoint: Some(path.clone()),
+        continue_on_error: true,
+        ..Default::default()
+    };
+    let first = execute(
+        &list,
+        &opts,
+        |item| if item.0 >= 7 { bail!("later") } else { Ok(()) },
+        |_| {},
+    )
+    .unwrap();
+    assert_eq!(first.failed, 3);
+    assert!(path.exists());
+
+    // an interruption mid-write leaves a partial line behind
+    let mut text = std::fs::read_to_string(&path).unwrap();
+    text.push_str("ite");
+    std::fs::write(&path, text).unwrap();
+
+    let mut ran = Vec::new();
+    let second = execute(
+        &list,
+        &opts,
+        |item| Ok(item.0),
+        |p| {
+            if let Outcome::Done(v) = p.outcome {
+                ran.push(v);
+            }
+        },
+    )
+    .unwrap();
+    ran.sort();
+    assert_eq!(ran, [7, 8, 9]);
+    assert_eq!((second.resumed, second.done), (7, 3));
+    // a completed batch leaves no checkpoint
+    assert!(!path.exists());
+
+    std::fs::write(&path, "exrtool-checkpoint 1 0000000000000000\nitem1\n").unwrap();
+    let err = execute(&list, &opts, |_| Ok(()), |_| {}).unwrap_err();
+    assert!(err.to_string().contains("different batch"));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn cancelling_stops_new_items() {
+    let path = checkpoint_path("cancel");
+    let cancel = Arc::new(AtomicBool::new(false));
+    let list = items(8);
+    let opts = ExecOptions {
+        threads: 1,
+        checkpoint: Some(path.clone()),
+        cancel: Some(cancel.clone()),
+        ..Default::default()
+    };
+    let summary = execute(
+        &list,
+        &opts,
+        |_| {
+            std::thread::sleep(Duration::from_millis(10));
+            Ok(())
+        },
+        |p| {
+            if p.completed == 3 {
+                cancel.store(true, Ordering::SeqCst);
+            }
+        },
+    )
+    .unwrap();
+    assert!(summary.cancelled);
+    assert!(summary.done >= 3 && summary.skipped > 0);
+    assert_eq!(summary.done + summary.skipped, 8);
+
+    cancel.store(false, Ordering::SeqCst);
+    let resumed = execute(&list, &opts, |_| Ok(()), |_| {}).unwrap();
+    assert_eq!(resumed.resumed, summary.done);
+    assert!(!path.exists());
+}
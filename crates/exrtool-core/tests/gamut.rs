@@ -0,0 +1,107 @@
+use exrtool_core::gamut::{compress_distance, rgc_compress, GamutMapping, RgcParams};
+use exrtool_core::{
+    generate_preview_with, make_3d_lut_cube_with, parse_cube, ClipMode, LoadedExr, Lut3dOptions,
+    PreviewPipeline, PreviewQuality, Primaries, TransferFn,
+};
+
+#[test]
+fn compression_curve_maps_limit_to_boundary() {
+    let p = RgcParams::default();
+    for c in 0..3 {
+        let (thr, lim) = (p.threshold[c], p.limit[c]);
+        assert_eq!(compress_distance(thr * 0.5, thr, lim, p.power), thr * 0.5);
+        assert!((compress_distance(lim, thr, lim, p.power) - 1.0).abs() < 1e-9);
+        // monotonic above the threshold
+        let mut prev = thr;
+        for i in 1..100 {
+            let d = compress_distance(thr + i as f64 * 0.02, thr, lim, p.power);
+            assert!(d > prev);
+            prev = d;
+        }
+    }
+}
+
+#[test]
+fn rgc_leaves_in_gamut_colours_untouched() {
+    let p = RgcParams::default();
+    let rgb = [0.5, 0.3, 0.2];
+    assert_eq!(rgc_compress(rgb, &p), rgb);
+    // distance 1.147 on red (cyan limit) lands exactly on zero
+    let out = rgc_compress([1.0 - 1.147, 1.0, 1.0], &p);
+    assert!(out[0].abs() < 1e-9, "{:?}", out);
+}
+
+fn ap0_to_srgb(clip: ClipMode) -> exrtool_core::Lut {
+    let mut opts = Lut3dOptions::new(
+        Primaries::ACES2065_1D60,
+        TransferFn::Linear,
+        Primaries::SrgbD65,
+        TransferFn::Linear,
+    );
+    opts.size = 9;
+    opts.clip = clip;
+    parse_cube(&make_3d_lut_cube_with(&opts, |_| true).unwrap()).unwrap()
+}
+
+#[test]
+fn clip_modes_change_lut_output() {
+    // saturated AP0 green is far outside sRGB
+    let px = [0.05, 0.5, 0.05];
+    let clipped = ap0_to_srgb(ClipMode::Clip).apply(px);
+    let raw = ap0_to_srgb(ClipMode::NoClip).apply(px);
+    let compressed = ap0_to_srgb(ClipMode::Compress(RgcParams::default())).apply(px);
+    assert!(raw.iter().any(|&v| v < 0.0), "{:?}", raw);
+    assert!(clipped.iter().all(|&v| (0.0..=1.0).contains(&v)));
+    assert!(compressed.iter().all(|&v| (0.0..=1.0).contains(&v)));
+    // compression keeps some of the red/blue that hard clipping throws away
+    assert!(compressed[0] > clipped[0] || compressed[2] > clipped[2]);
+}
+
+#[test]
+fn invalid_rgc_params_are_rejected() {
+    let mut p = RgcParams::default();
+    p.limit[1] = 0.9;
+    let mut opts = Lut3dOptions::new(
+        Primaries::ACEScgD60,
+        TransferFn::Linear,
+        Primaries::SrgbD65,
+        TransferFn::Srgb,
+    );
+    opts.clip = ClipMode::Compress(p);
+    assert!(make_3d_lut_cube_with(&opts, |_| true).is_err());
+}
+
+#[test]
+fn overlay_marks_out_of_gamut_pixels() {
+    // 8x1 image: left half saturated ACEScg green, right half grey
+    let mut rgba_f32 = Vec::new();
+    for x in 0..8 {
+        let px = if x < 4 {
+            [0.0, 0.5, 0.0, 1.0]
+        } else {
+            [0.18, 0.18, 0.18, 1.0]
+        };
+        rgba_f32.extend_from_slice(&px);
+    }
+    let img = LoadedExr {
+        width: 8,
+        height: 1,
+        rgba_f32,
+    };
+    let mut pipeline = PreviewPipeline::new(0.0, 1.0, None);
+    pipeline.gamut = Some(GamutMapping {
+        working: Primaries::ACEScgD60,
+        target: Primaries::SrgbD65,
+        mode: ClipMode::Clip,
+    });
+    pipeline.oog_overlay = true;
+    let preview = generate_preview_with(&img, 8, &pipeline, PreviewQuality::Fast);
+    let px = |x: usize| preview.rgba8[x * 4..x * 4 + 3].to_vec();
+    assert_eq!(px(0), [255, 0, 255]);
+    assert_ne!(px(5), [255, 0, 255]);
+
+    pipeline.oog_overlay = false;
+    let plain = generate_preview_with(&img, 8, &pipeline, PreviewQuality::Fast);
+    assert_ne!(plain.rgba8[..3], [255, 0, 255]);
+    assert_eq!(preview.rgba8[16..], plain.rgba8[16..]);
+}
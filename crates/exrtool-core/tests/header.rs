@@ -0,0 +1,243 @@
+use exrtool_core::header::{decode_value, encode_value, rewrite_headers, ExrHeaders};
+use exrtool_core::metadata::{MetadataValue, TimeCode, ValueKind};
+use std::fs;
+use std::path::PathBuf;
+
+fn temp(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("exrtool-header-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+/// Scanline EXR written through the `image` crate, so no feature is needed.
+fn scanline(name: &str) -> PathBuf {
+    let path = temp(name);
+    let img = image::Rgba32FImage::from_fn(37, 21, |x, y| {
+        image::Rgba([x as f32 / 37.0, y as f32 / 21.0, 0.25, 1.0])
+    });
+    img.save(&path).unwrap();
+    path
+}
+
+fn tail(path: &std::path::Path, start: u64) -> Vec<u8> {
+    fs::read(path).unwrap()[start as usize..].to_vec()
+}
+
+#[test]
+fn values_roundtrip_through_exr_encoding() {
+    let tc = TimeCode {
+        hours: 1,
+        minutes: 2,
+        seconds: 3,
+        frames: 4,
+        ..TimeCode::default()
+    };
+    let (ty, data) = encode_value(&MetadataValue::TimeCode(tc));
+    assert_eq!(ty, "timecode");
+    // BCD packed, frames in the low byte
+    assert_eq!(&data[..4], &0x0102_0304u32.to_le_bytes());
+
+    for (kind, text) in [
+        (ValueKind::Text, "plate"),
+        (ValueKind::Int, "-7"),
+        (ValueKind::Float, "0.5"),
+        (ValueKind::Double, "0.1"),
+        (ValueKind::Rational, "24000/1001"),
+        (ValueKind::Timecode, "23:59:59;29"),
+        (ValueKind::Timecode, "10:00:00:12.1"),
+        (ValueKind::V2f, "0.3127,0.329"),
+        (ValueKind::V2i, "-4,8"),
+        (ValueKind::Box2i, "0,0,1919,1079"),
+        (
+            ValueKind::Chromaticities,
+            "0.64,0.33,0.3,0.6,0.15,0.06,0.3127,0.329",
+        ),
+    ] {
+        let v = MetadataValue::parse(kind, text).unwrap();
+        let (ty, data) = encode_value(&v);
+        assert_eq!(decode_value(ty, &data), Some(v), "{}", text);
+    }
+    assert_eq!(decode_value("int", &[1, 2]), None);
+    assert_eq!(decode_value("m44f", &[0; 64]), None);
+}
+
+#[test]
+fn header_rewrite_keeps_chunks_verbatim() {
+    let path = scanline("plate.exr");
+    let before = ExrHeaders::read_file(&path).unwrap();
+    assert_eq!(before.headers.len(), 1);
+    let chunks = tail(&path, before.data_start);
+
+    rewrite_headers(&path, &path, true, |headers| {
+        headers[0].set("ShotName", &"sh010".into());
+        headers[0].set("framesPerSecond", &MetadataValue::Rational(24, 1));
+        Ok(())
+    })
+    .unwrap();
+
+    let after = ExrHeaders::read_file(&path).unwrap();
+    let grown = after.data_start - before.data_start;
+    assert!(grown > 0);
+    assert_eq!(tail(&path, after.data_start), chunks);
+    for (a, b) in after.offsets[0].iter().zip(&before.offsets[0]) {
+        assert_eq!(a - b, grown);
+    }
+    assert_eq!(after.headers[0].value("ShotName"), Some("sh010".into()));
+    // the pixels still decode
+    let img = image::open(&path).unwrap().into_rgba32f();
+    assert_eq!(img.get_pixel(36, 20)[2], 0.25);
+
+    // shrinking the header moves the offsets back
+    rewrite_headers(&path, &path, true, |headers| {
+        headers[0].remove("ShotName");
+        headers[0].remove("framesPerSecond");
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(ExrHeaders::read_file(&path).unwrap(), before);
+}
+
+#[test]
+fn rewrite_to_another_path_and_failed_edits() {
+    let path = scanline("source.exr");
+    let original = fs::read(&path).unwrap();
+    let out = temp("copy.exr");
+    rewrite_headers(&path, &out, false, |headers| {
+        headers[0].set("owner", &"alice".into());
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(fs::read(&path).unwrap(), original);
+    assert_eq!(
+        ExrHeaders::read_file(&out).unwrap().headers[0].value("owner"),
+        Some("alice".into())
+    );
+
+    // an edit that fails or changes the chunk layout leaves the file alone
+    assert!(rewrite_headers(&path, &path, false, |_| anyhow::bail!("no")).is_err());
+    assert!(rewrite_headers(&path, &path, false, |headers| {
+        headers[0].set(
+            "dataWindow",
+            &MetadataValue::Box2i {
+                min: [0, 0],
+                max: [36, 99],
+            },
+        );
+        Ok(())
+    })
+    .is_err());
+    assert_eq!(fs::read(&path).unwrap(), original);
+    assert!(ExrHeaders::read_file(&temp("missing.exr")).is_err());
+}
+
+#[cfg(feature = "use_exr_crate")]
+mod exr_files {
+    use super::*;
+    use exr::math::RoundingMode;
+    use exr::prelude::*;
+    use exrtool_core::metadata::{edit_metadata, read_attributes, WriteOptions};
+    use std::collections::HashMap;
+
+    fn tiled() -> Encoding {
+        Encoding {
+            compression: Compression::ZIP16,
+            blocks: Blocks::Tiles(Vec2(16, 16)),
+            line_order: LineOrder::Increasing,
+        }
+    }
+
+    #[test]
+    fn chunk_counts_match_the_exr_crate() {
+        let size = Vec2(37, 23);
+        let path = temp("multipart.exr");
+        let part = |name: &str, encoding: Encoding| {
+            Layer::new(
+                size,
+                LayerAttributes::named(name),
+                encoding,
+                SpecificChannels::rgba(|p: Vec2<usize>| (p.x() as f32, 0.5f32, 0.25f32, 1.0f32)),
+            )
+        };
+        let image = Image::empty(ImageAttributes::new(IntegerBounds::from_dimensions(size)))
+            .with_layer(part("tiles", tiled()))
+            .with_layer(part("lines", Encoding::SMALL_LOSSLESS));
+        image.write().to_file(&path).unwrap();
+
+        let meta = exr::meta::MetaData::read_from_file(&path, false).unwrap();
+        let raw = ExrHeaders::read_file(&path).unwrap();
+        assert_eq!(raw.headers.len(), 2);
+        for (h, t) in meta.headers.iter().zip(&raw.offsets) {
+            assert_eq!(h.chunk_count, t.len());
+        }
+        // without chunkCount the count is derived from the layout
+        for h in &raw.headers {
+            let mut h = h.clone();
+            let expected = h.chunk_count(raw.version).unwrap();
+            h.remove("chunkCount");
+            assert_eq!(h.chunk_count(raw.version).unwrap(), expected);
+        }
+
+        let before = read_all_data_from_file(&path).unwrap();
+        let set = HashMap::from([
+            ("timeCode".to_string(), MetadataValue::from("10:00:00:01")),
+            ("Vendor".to_string(), MetadataValue::from("acme")),
+        ]);
+        let opts = WriteOptions {
+            verify: true,
+            ..WriteOptions::default()
+        };
+        edit_metadata(&path, &set, &[], &opts).unwrap();
+
+        let raw = ExrHeaders::read_file(&path).unwrap();
+        // timeCode is shared by every part, custom attributes go to the first
+        for h in &raw.headers {
+            assert_eq!(
+                h.value("timeCode").map(|v| v.to_string()).as_deref(),
+                Some("10:00:00:01")
+            );
+        }
+        assert!(raw.headers[0].get("Vendor").is_some());
+        assert!(raw.headers[1].get("Vendor").is_none());
+        assert_eq!(
+            read_attributes(&path).unwrap().get("Vendor"),
+            Some(&"acme".into())
+        );
+        let after = read_all_data_from_file(&path).unwrap();
+        for (a, b) in before.layer_data.iter().zip(&after.layer_data) {
+            assert_eq!(a.channel_data, b.channel_data);
+        }
+    }
+
+    #[test]
+    fn mip_map_tile_counts() {
+        let size = Vec2(37, 23);
+        let levels: Vec<_> = exr::meta::mip_map_levels(RoundingMode::Up, size).collect();
+        let channel = AnyChannel::new(
+            "Y",
+            Levels::Mip {
+                level_data: levels
+                    .iter()
+                    .map(|(_, s)| FlatSamples::F32(vec![0.5; s.area()]))
+                    .collect(),
+                rounding_mode: RoundingMode::Up,
+            },
+        );
+        let layer = Layer::new(
+            size,
+            LayerAttributes::named("mips"),
+            tiled(),
+            AnyChannels::sort(SmallVec::from_vec(vec![channel])),
+        );
+        let path = temp("mips.exr");
+        Image::from_layer(layer).write().to_file(&path).unwrap();
+
+        let meta = exr::meta::MetaData::read_from_file(&path, false).unwrap();
+        let raw = ExrHeaders::read_file(&path).unwrap();
+        let mut h = raw.headers[0].clone();
+        h.remove("chunkCount");
+        assert_eq!(
+            h.chunk_count(raw.version).unwrap(),
+            meta.headers[0].chunk_count
+        );
+    }
+}
@@ -1,73 +1,140 @@
-use exrtool_core::{make_3d_lut_cube, parse_cube, Primaries, TransferFn};
-use nalgebra::{Matrix3, Vector3};
-
-fn xy_to_xyz(x: f64, y: f64) -> Vector3<f64> {
-    Vector3::new(x / y, 1.0, (1.0 - x - y) / y)
-}
-
-fn primaries_data(p: Primaries) -> (Vector3<f64>, Vector3<f64>, Vector3<f64>, Vector3<f64>) {
-    match p {
-        Primaries::SrgbD65 => (
-            xy_to_xyz(0.640, 0.330),
-            xy_to_xyz(0.300, 0.600),
-            xy_to_xyz(0.150, 0.060),
-            xy_to_xyz(0.3127, 0.3290),
-        ),
-        Primaries::Rec2020D65 => (
-            xy_to_xyz(0.708, 0.292),
-            xy_to_xyz(0.170, 0.797),
-            xy_to_xyz(0.131, 0.046),
-            xy_to_xyz(0.3127, 0.3290),
-        ),
-        Primaries::ACEScgD60 => (
-            xy_to_xyz(0.713, 0.293),
-            xy_to_xyz(0.165, 0.830),
-            xy_to_xyz(0.128, 0.044),
-            xy_to_xyz(0.32168, 0.33767),
-        ),
-        Primaries::ACES2065_1D60 => (
-            xy_to_xyz(0.73470, 0.26530),
-            xy_to_xyz(0.00000, 1.00000),
-            xy_to_xyz(0.00010, -0.07700),
-            xy_to_xyz(0.32168, 0.33767),
-        ),
-    }
-}
-
-fn rgb_to_rgb_matrix_manual(src: Primaries, dst: Primaries) -> Matrix3<f64> {
-    let (xr_s, xg_s, xb_s, w_s) = primaries_data(src);
-    let m_src = Matrix3::from_columns(&[xr_s, xg_s, xb_s]);
-    let s_src = m_src.try_inverse().unwrap() * w_s;
-    let m_src = m_src * Matrix3::from_diagonal(&s_src);
-
-    let (xr_d, xg_d, xb_d, w_d) = primaries_data(dst);
-    let m_dst = Matrix3::from_columns(&[xr_d, xg_d, xb_d]);
-    let s_dst = m_dst.try_inverse().unwrap() * w_d;
-    let m_dst = m_dst * Matrix3::from_diagonal(&s_dst);
-
-    m_dst.try_inverse().unwrap() * m_src
-}
-
-#[test]
-fn matrix_srgb_to_rec2020_matches_manual() {
-    let lut_str = make_3d_lut_cube(
-        Primaries::SrgbD65,
-        TransferFn::Linear,
-        Primaries::Rec2020D65,
-        TransferFn::Linear,
-        2,
-        0,
-    );
-    let lut = parse_cube(&lut_str).unwrap();
-    let m = rgb_to_rgb_matrix_manual(Primaries::SrgbD65, Primaries::Rec2020D65);
-
-    let basis = [[1.0_f32, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
-    for b in basis.iter() {
-        let out = lut.apply(*b);
-        let v = Vector3::new(b[0] as f64, b[1] as f64, b[2] as f64);
-        let expected = m * v;
-        assert!((out[0] as f64 - expected.x).abs() < 1e-6);
-        assert!((out[1] as f64 - expected.y).abs() < 1e-6);
-        assert!((out[2] as f64 - expected.z).abs() < 1e-6);
-    }
-}
+use exrtool_core::{make_3d_lut_cube, parse_cube, Primaries, TransferFn};
+use nalgebra::{Matrix3, Vector3};
+
+fn xy_to_xyz(x: f64, y: f64) -> Vector3<f64> {
+    Vector3::new(x / y, 1.0, (1.0 - x - y) / y)
+}
+
+fn primaries_data(p: Primaries) -> (Vector3<f64>, Vector3<f64>, Vector3<f64>, Vector3<f64>) {
+    match p {
+        Primaries::SrgbD65 => (
+            xy_to_xyz(0.640, 0.330),
+            xy_to_xyz(0.300, 0.600),
+            xy_to_xyz(0.150, 0.060),
+            xy_to_xyz(0.3127, 0.3290),
+        ),
+        Primaries::Rec2020D65 => (
+            xy_to_xyz(0.708, 0.292),
+            xy_to_xyz(0.170, 0.797),
+            xy_to_xyz(0.131, 0.046),
+            xy_to_xyz(0.3127, 0.3290),
+        ),
+        Primaries::P3D65 => (
+            xy_to_xyz(0.680, 0.320),
+            xy_to_xyz(0.265, 0.690),
+            xy_to_xyz(0.150, 0.060),
+            xy_to_xyz(0.3127, 0.3290),
+        ),
+        Primaries::ACEScgD60 => (
+            xy_to_xyz(0.713, 0.293),
+            xy_to_xyz(0.165, 0.830),
+            xy_to_xyz(0.128, 0.044),
+            xy_to_xyz(0.32168, 0.33767),
+        ),
+        Primaries::ACES2065_1D60 => (
+            xy_to_xyz(0.73470, 0.26530),
+            xy_to_xyz(0.00000, 1.00000),
+            xy_to_xyz(0.00010, -0.07700),
+            xy_to_xyz(0.32168, 0.33767),
+        ),
+    }
+}
+
+fn rgb_to_rgb_matrix_manual(src: Primaries, dst: Primaries) -> Matrix3<f64> {
+    let (xr_s, xg_s, xb_s, w_s) = primaries_data(src);
+    let m_src = Matrix3::from_columns(&[xr_s, xg_s, xb_s]);
+    let s_src = m_src.try_inverse().unwrap() * w_s;
+    let m_src = m_src * Matrix3::from_diagonal(&s_src);
+
+    let (xr_d, xg_d, xb_d, w_d) = primaries_data(dst);
+    let m_dst = Matrix3::from_columns(&[xr_d, xg_d, xb_d]);
+    let s_dst = m_dst.try_inverse().unwrap() * w_d;
+    let m_dst = m_dst * Matrix3::from_diagonal(&s_dst);
+
+    m_dst.try_inverse().unwrap() * m_src
+}
+
+#[test]
+fn matrix_srgb_to_rec2020_matches_manual() {
+    let lut_str = make_3d_lut_cube(
+        Primaries::SrgbD65,
+        TransferFn::Linear,
+        Primaries::Rec2020D65,
+        TransferFn::Linear,
+        2,
+        0,
+    );
+    let lut = parse_cube(&lut_str).unwrap();
+    let m = rgb_to_rgb_matrix_manual(Primaries::SrgbD65, Primaries::Rec2020D65);
+
+    let basis = [[1.0_f32, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    for b in basis.iter() {
+        let out = lut.apply(*b);
+        let v = Vector3::new(b[0] as f64, b[1] as f64, b[2] as f64);
+        let expected = m * v;
+        assert!((out[0] as f64 - expected.x).abs() < 1e-6);
+        assert!((out[1] as f64 - expected.y).abs() < 1e-6);
+        assert!((out[2] as f64 - expected.z).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn bradford_d65_to_d50_matches_reference() {
+    use exrtool_core::{chromatic_adaptation_matrix, ChromaticAdaptation};
+    // Lindbloom's published Bradford D65 -> D50 matrix
+    let expected = Matrix3::new(
+        1.0478112, 0.0228866, -0.0501270, 0.0295424, 0.9904844, -0.0170491, -0.0092345, 0.0150436,
+        0.7521316,
+    );
+    let d65 = Vector3::new(0.95047, 1.0, 1.08883);
+    let d50 = Vector3::new(0.96422, 1.0, 0.82521);
+    let m = chromatic_adaptation_matrix(d65, d50, ChromaticAdaptation::Bradford);
+    assert!((m - expected).abs().max() < 1e-5, "{}", m);
+}
+
+#[test]
+fn every_cat_maps_white_to_white() {
+    use exrtool_core::{rgb_conversion_matrix, ChromaticAdaptation};
+    let white = Vector3::new(1.0, 1.0, 1.0);
+    let mut seen = Vec::new();
+    for cat in ChromaticAdaptation::ALL {
+        let m = rgb_conversion_matrix(Primaries::ACEScgD60, Primaries::SrgbD65, cat);
+        let w = m * white;
+        if cat == ChromaticAdaptation::None {
+            // D60 sim: the ACES white stays slightly warm in sRGB
+            assert!(w.x > w.z, "{}", w);
+        } else {
+            assert!((w - white).abs().max() < 1e-6, "{:?}: {}", cat, w);
+        }
+        assert!(
+            seen.iter()
+                .all(|s: &Matrix3<f64>| (s - m).abs().max() > 1e-4),
+            "{:?}",
+            cat
+        );
+        seen.push(m);
+    }
+}
+
+#[test]
+fn lut_uses_selected_cat() {
+    use exrtool_core::{
+        make_3d_lut_cube_with, rgb_conversion_matrix, ChromaticAdaptation, ClipMode, Lut3dOptions,
+    };
+    let mut opts = Lut3dOptions::new(
+        Primaries::ACEScgD60,
+        TransferFn::Linear,
+        Primaries::Rec2020D65,
+        TransferFn::Linear,
+    );
+    opts.size = 2;
+    opts.clip = ClipMode::NoClip;
+    opts.cat = ChromaticAdaptation::Cat02;
+    let lut = parse_cube(&make_3d_lut_cube_with(&opts, |_| true).unwrap()).unwrap();
+    let m = rgb_conversion_matrix(opts.src_prim, opts.dst_prim, ChromaticAdaptation::Cat02);
+    let out = lut.apply([0.0, 1.0, 0.0]);
+    let expected = m * Vector3::new(0.0, 1.0, 0.0);
+    assert!((out[0] as f64 - expected.x).abs() < 1e-6);
+    assert!((out[1] as f64 - expected.y).abs() < 1e-6);
+    assert!((out[2] as f64 - expected.z).abs() < 1e-6);
+}
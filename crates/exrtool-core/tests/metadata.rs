@@ -0,0 +1,303 @@
+use exrtool_core::metadata::{canonical_key, coerce_for_key, MetadataValue, PartTarget, ValueKind};
+
+#[test]
+fn decimal_rates_become_exact_rationals() {
+    for (text, n, d) in [
+        ("24", 24, 1),
+        ("23.976", 24000, 1001),
+        ("29.97", 30000, 1001),
+        ("59.94", 60000, 1001),
+        ("12.5", 25, 2),
+        ("30000/1001", 30000, 1001),
+    ] {
+        assert_eq!(
+            MetadataValue::parse(ValueKind::Rational, text).unwrap(),
+            MetadataValue::Rational(n, d),
+            "{}",
+            text
+        );
+    }
+}
+
+#[test]
+fn standard_keys_are_coerced_to_their_type() {
+    assert_eq!(canonical_key("FramesPerSecond"), "framesPerSecond");
+    assert_eq!(
+        coerce_for_key("FramesPerSecond", &MetadataValue::Float(25.0)).unwrap(),
+        MetadataValue::Rational(25, 1)
+    );
+    assert_eq!(
+        coerce_for_key("whiteLuminance", &"100".into()).unwrap(),
+        MetadataValue::Float(100.0)
+    );
+    assert!(coerce_for_key("timeCode", &"soon".into()).is_err());
+    // custom attributes keep whatever type they were given
+    assert_eq!(
+        coerce_for_key("Take", &MetadataValue::Int(3)).unwrap(),
+        MetadataValue::Int(3)
+    );
+}
+
+#[test]
+fn part_targets_parse() {
+    for (text, target) in [
+        ("first", PartTarget::First),
+        ("all", PartTarget::All),
+        ("shared", PartTarget::Shared),
+        ("beauty", PartTarget::Name("beauty".into())),
+        ("name:all", PartTarget::Name("all".into())),
+    ] {
+        assert_eq!(text.parse::<PartTarget>().unwrap(), target);
+    }
+    assert!("".parse::<PartTarget>().is_err());
+    assert_eq!(PartTarget::Name("all".into()).to_string(), "name:all");
+}
+
+#[cfg(feature = "use_exr_crate")]
+mod write {
+    use super::*;
+    use exrtool_core::metadata::{edit_metadata, read_attributes, write_metadata, WriteOptions};
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    fn plate(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("exrtool-meta-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        exr::prelude::write_rgba_file(&path, 4, 4, |_, _| (0.5f32, 0.5f32, 0.5f32, 1.0f32))
+            .unwrap();
+        path
+    }
+
+    fn set(pairs: &[(&str, MetadataValue)]) -> HashMap<String, MetadataValue> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn standard_attributes_get_their_exr_types() {
+        use exr::meta::MetaData;
+
+        let path = plate("standard.exr");
+        // a text copy as older versions wrote it
+        {
+            use exr::prelude::*;
+            let mut image = read_all_data_from_file(&path).unwrap();
+            image.layer_data[0].attributes.other.insert(
+                Text::from("FramesPerSecond"),
+                AttributeValue::Text("24".into()),
+            );
+            image.write().to_file(&path).unwrap();
+        }
+        assert!(read_attributes(&path)
+            .unwrap()
+            .contains_key("FramesPerSecond"));
+
+        write_metadata(
+            &path,
+            &set(&[
+                ("FramesPerSecond", MetadataValue::Float(23.976)),
+                ("timeCode", "01:00:00:12".into()),
+                (
+                    "chromaticities",
+                    "0.64,0.33,0.3,0.6,0.15,0.06,0.3127,0.329".into(),
+                ),
+                ("whiteLuminance", "100".into()),
+                ("owner", "alice".into()),
+                ("comments", "plate".into()),
+                ("capDate", "2024:01:02 03:04:05".into()),
+                ("utcOffset", MetadataValue::Int(-3600)),
+            ]),
+            None,
+        )
+        .unwrap();
+
+        let meta = MetaData::read_from_file(&path, false).unwrap();
+        let h = &meta.headers[0];
+        assert_eq!(h.own_attributes.frames_per_second, Some((24000, 1001)));
+        assert_eq!(h.own_attributes.white_luminance, Some(100.0));
+        assert_eq!(h.own_attributes.utc_offset, Some(-3600.0));
+        let tc = h.shared_attributes.time_code.unwrap();
+        assert_eq!((tc.hours, tc.frame), (1, 12));
+        assert_eq!(h.shared_attributes.chromaticities.unwrap().white.0, 0.3127);
+        assert!(!h
+            .own_attributes
+            .other
+            .contains_key(&exr::meta::attribute::Text::from("FramesPerSecond")));
+
+        let attrs = read_attributes(&path).unwrap();
+        assert_eq!(
+            attrs.get("framesPerSecond"),
+            Some(&MetadataValue::Rational(24000, 1001))
+        );
+        assert_eq!(
+            attrs.get("timeCode").map(|v| v.to_string()).as_deref(),
+            Some("01:00:00:12")
+        );
+        assert_eq!(attrs.get("owner"), Some(&"alice".into()));
+        assert_eq!(attrs.get("capDate"), Some(&"2024:01:02 03:04:05".into()));
+        assert!(!attrs.contains_key("FramesPerSecond"));
+
+        assert!(write_metadata(&path, &set(&[("timeCode", "noon".into())]), None).is_err());
+    }
+
+    #[test]
+    fn type_changes_need_force() {
+        let path = plate("force.exr");
+        write_metadata(&path, &set(&[("Take", MetadataValue::Int(3))]), None).unwrap();
+        // same type is fine
+        write_metadata(&path, &set(&[("Take", MetadataValue::Int(4))]), None).unwrap();
+
+        let err = write_metadata(&path, &set(&[("Take", "4b".into())]), None).unwrap_err();
+        assert!(err.to_string().contains("int"), "{}", err);
+        assert_eq!(
+            read_attributes(&path).unwrap().get("Take"),
+            Some(&MetadataValue::Int(4))
+        );
+
+        let opts = WriteOptions {
+            force_type: true,
+            ..WriteOptions::default()
+        };
+        edit_metadata(&path, &set(&[("Take", "4b".into())]), &[], &opts).unwrap();
+        assert_eq!(
+            read_attributes(&path).unwrap().get("Take"),
+            Some(&"4b".into())
+        );
+    }
+
+    mod parts {
+        use super::*;
+        use exr::prelude::*;
+        use exrtool_core::header::ExrHeaders;
+
+        fn multipart(name: &str) -> PathBuf {
+            let path = plate(name);
+            let size = Vec2(8, 4);
+            let part = |name: &str| {
+                Layer::new(
+                    size,
+                    LayerAttributes::named(name),
+                    Encoding::SMALL_LOSSLESS,
+                    SpecificChannels::rgba(|_: Vec2<usize>| (0.5f32, 0.5f32, 0.5f32, 1.0f32)),
+                )
+            };
+            Image::empty(ImageAttributes::new(IntegerBounds::from_dimensions(size)))
+                .with_layer(part("beauty"))
+                .with_layer(part("diffuse"))
+                .write()
+                .to_file(&path)
+                .unwrap();
+            path
+        }
+
+        fn has(path: &std::path::Path, part: usize, key: &str) -> bool {
+            ExrHeaders::read_file(path).unwrap().headers[part]
+                .get(key)
+                .is_some()
+        }
+
+        fn opts(part: PartTarget) -> WriteOptions {
+            WriteOptions {
+                part,
+                ..WriteOptions::default()
+            }
+        }
+
+        #[test]
+        fn named_all_and_shared_targets() {
+            let path = multipart("parts.exr");
+            let placed = edit_metadata(
+                &path,
+                &set(&[
+                    ("Vendor", "acme".into()),
+                    ("timeCode", "01:00:00:00".into()),
+                ]),
+                &[],
+                &opts(PartTarget::Name("diffuse".into())),
+            )
+            .unwrap();
+            assert!(!has(&path, 0, "Vendor") && has(&path, 1, "Vendor"));
+            // shared standard attributes go everywhere, whatever the target
+            assert!(has(&path, 0, "timeCode") && has(&path, 1, "timeCode"));
+            assert_eq!(placed.len(), 2);
+            assert_eq!(placed[0].key, "Vendor");
+            assert_eq!(placed[0].parts, ["diffuse"]);
+            assert!(placed[1].shared);
+            assert_eq!(
+                placed[1].to_string(),
+                "+ timeCode -> shared [beauty, diffuse]"
+            );
+
+            let placed = edit_metadata(
+                &path,
+                &set(&[("framesPerSecond", MetadataValue::Float(25.0))]),
+                &["Vendor".to_string()],
+                &opts(PartTarget::All),
+            )
+            .unwrap();
+            assert!(!has(&path, 1, "Vendor"));
+            assert_eq!(placed[0].to_string(), "- Vendor <- [diffuse]");
+            assert_eq!(placed[1].parts, ["beauty", "diffuse"]);
+            assert!(!placed[1].shared);
+
+            let placed = edit_metadata(
+                &path,
+                &set(&[("Show", "demo".into())]),
+                &[],
+                &opts(PartTarget::Shared),
+            )
+            .unwrap();
+            assert!(placed[0].shared);
+            assert!(has(&path, 0, "Show") && has(&path, 1, "Show"));
+            let err = edit_metadata(
+                &path,
+                &set(&[("owner", "alice".into())]),
+                &[],
+                &opts(PartTarget::Shared),
+            )
+            .unwrap_err();
+            assert!(err.to_string().contains("per-part"), "{}", err);
+
+            let err = edit_metadata(
+                &path,
+                &set(&[("Vendor", "acme".into())]),
+                &[],
+                &opts(PartTarget::Name("specular".into())),
+            )
+            .unwrap_err();
+            assert!(err.to_string().contains("beauty, diffuse"), "{}", err);
+        }
+
+        #[test]
+        fn reencode_honours_the_target() {
+            let path = multipart("parts-reencode.exr");
+            let opts = WriteOptions {
+                reencode: true,
+                ..opts(PartTarget::Name("diffuse".into()))
+            };
+            let placed = edit_metadata(
+                &path,
+                &set(&[("Vendor", "acme".into()), ("owner", "alice".into())]),
+                &[],
+                &opts,
+            )
+            .unwrap();
+            assert_eq!(placed[1].parts, ["diffuse"]);
+            let meta = exr::meta::MetaData::read_from_file(&path, false).unwrap();
+            let (a, b) = (
+                &meta.headers[0].own_attributes,
+                &meta.headers[1].own_attributes,
+            );
+            assert!(a.owner.is_none());
+            assert_eq!(
+                b.owner.as_ref().map(|t| t.to_string()).as_deref(),
+                Some("alice")
+            );
+            assert!(b.other.contains_key(&Text::from("Vendor")));
+            assert!(!a.other.contains_key(&Text::from("Vendor")));
+        }
+    }
+}
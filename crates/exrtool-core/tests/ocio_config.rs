@@ -0,0 +1,283 @@
+use exrtool_core::ocio_config::Config;
+use std::fs;
+use std::path::PathBuf;
+
+const CONFIG: &str = r#"
+ocio_profile_version: 1
+search_path: $LUTDIR
+environment:
+  LUTDIR: luts
+roles:
+  scene_linear: lin_srgb
+  data: raw
+displays:
+  sRGB:
+    - !<View> {name: Standard, colorspace: srgb_display}
+    - !<View> {name: Graded, colorspace: srgb_display, looks: +bright}
+  Hidden:
+    - !<View> {name: Raw, colorspace: raw}
+active_displays: [sRGB]
+looks:
+  - !<Look>
+    name: bright
+    process_space: lin_srgb
+    transform: !<ExponentTransform> {value: [0.5, 0.5, 0.5, 1]}
+colorspaces:
+  - !<ColorSpace>
+    name: lin_srgb
+    family: Linear
+  - !<ColorSpace>
+    name: scaled
+    aliases: [Scaled Alias]
+    to_reference: !<MatrixTransform> {matrix: [2, 0, 0, 0, 0, 3, 0, 0, 0, 0, 4, 0, 0, 0, 0, 1], offset: [0.1, 0, 0, 0]}
+  - !<ColorSpace>
+    name: srgb_display
+    from_reference: !<ExponentWithLinearTransform> {gamma: 2.4, offset: 0.055, direction: inverse}
+  - !<ColorSpace>
+    name: log2
+    from_reference: !<LogTransform> {base: 2}
+  - !<ColorSpace>
+    name: cube_space
+    from_reference: !<FileTransform> {src: half.cube}
+  - !<ColorSpace>
+    name: spi_space
+    from_reference: !<FileTransform> {src: double.spi1d}
+  - !<ColorSpace>
+    name: grouped
+    from_reference: !<GroupTransform>
+      children:
+        - !<MatrixTransform> {matrix: [2, 0, 0, 0, 0, 2, 0, 0, 0, 0, 2, 0, 0, 0, 0, 1]}
+        - !<ExponentTransform> {value: [2, 2, 2, 1]}
+  - !<ColorSpace>
+    name: via_scaled
+    to_reference: !<ColorSpaceTransform> {src: scaled, dst: lin_srgb}
+  - !<ColorSpace>
+    name: builtin
+    to_reference: !<BuiltinTransform> {style: ACEScct_to_ACES2065-1}
+  - !<ColorSpace>
+    name: raw
+    isdata: true
+"#;
+
+const HALF_CUBE: &str = "LUT_3D_SIZE 2\n0 0 0\n0.5 0 0\n0 0.5 0\n0.5 0.5 0\n0 0 0.5\n0.5 0 0.5\n0 0.5 0.5\n0.5 0.5 0.5\n";
+const DOUBLE_SPI1D: &str = "Version 1\nFrom 0.0 1.0\nLength 3\nComponents 1\n{\n0.0\n1.0\n2.0\n}\n";
+
+fn load(name: &str) -> Config {
+    let dir: PathBuf =
+        std::env::temp_dir().join(format!("exrtool-ocio-{}-{}", std::process::id(), name));
+    fs::create_dir_all(dir.join("luts")).unwrap();
+    fs::write(dir.join("luts/half.cube"), HALF_CUBE).unwrap();
+    fs::write(dir.join("luts/double.spi1d"), DOUBLE_SPI1D).unwrap();
+    fs::write(dir.join("config.ocio"), CONFIG).unwrap();
+    Config::from_file(&dir.join("config.ocio")).unwrap()
+}
+
+fn run(cfg: &Config, src: &str, dst: &str, rgb: [f32; 3]) -> [f32; 3] {
+    let mut v = rgb;
+    cfg.processor(src, dst).unwrap().apply_rgb(&mut v);
+    v
+}
+
+fn assert_close(a: [f32; 3], b: [f32; 3]) {
+    for c in 0..3 {
+        assert!((a[c] - b[c]).abs() < 1e-4, "{:?} != {:?}", a, b);
+    }
+}
+
+#[test]
+fn lists_config_contents() {
+    let cfg = load("list");
+    assert_eq!(cfg.version(), 1);
+    assert_eq!(cfg.colorspaces().len(), 10);
+    assert_eq!(cfg.displays(), ["sRGB"]);
+    assert_eq!(cfg.views("sRGB"), ["Standard", "Graded"]);
+    assert_eq!(cfg.looks(), ["bright"]);
+    assert_eq!(cfg.role("SCENE_LINEAR").as_deref(), Some("lin_srgb"));
+    assert_eq!(cfg.family("lin_srgb").as_deref(), Some("Linear"));
+    assert!(cfg.is_display_colorspace("srgb_display"));
+    assert!(!cfg.is_display_colorspace("lin_srgb"));
+    assert!(!cfg.is_display_colorspace("Scaled Alias"));
+}
+
+#[test]
+fn matrix_and_aliases() {
+    let cfg = load("matrix");
+    assert_close(
+        run(&cfg, "Scaled Alias", "lin_srgb", [1.0, 1.0, 1.0]),
+        [2.1, 3.0, 4.0],
+    );
+    assert_close(
+        run(&cfg, "scene_linear", "scaled", [2.1, 3.0, 4.0]),
+        [1.0, 1.0, 1.0],
+    );
+    // ColorSpaceTransform chains through another space
+    assert_close(
+        run(&cfg, "via_scaled", "lin_srgb", [1.0, 1.0, 1.0]),
+        [2.1, 3.0, 4.0],
+    );
+}
+
+#[test]
+fn exponent_with_linear_matches_srgb() {
+    let cfg = load("srgb");
+    let out = run(&cfg, "lin_srgb", "srgb_display", [0.18, 0.001, 1.0]);
+    assert_close(out, [0.46135, 0.012920, 1.0]);
+    assert_close(
+        run(&cfg, "srgb_display", "lin_srgb", out),
+        [0.18, 0.001, 1.0],
+    );
+}
+
+#[test]
+fn log_group_and_files() {
+    let cfg = load("ops");
+    assert_close(
+        run(&cfg, "lin_srgb", "log2", [0.25, 1.0, 4.0]),
+        [-2.0, 0.0, 2.0],
+    );
+    assert_close(
+        run(&cfg, "log2", "lin_srgb", [-2.0, 0.0, 2.0]),
+        [0.25, 1.0, 4.0],
+    );
+    assert_close(
+        run(&cfg, "lin_srgb", "grouped", [0.25, 0.5, 0.1]),
+        [0.25, 1.0, 0.04],
+    );
+    assert_close(
+        run(&cfg, "grouped", "lin_srgb", [0.25, 1.0, 0.04]),
+        [0.25, 0.5, 0.1],
+    );
+    assert_close(
+        run(&cfg, "lin_srgb", "cube_space", [0.2, 0.4, 1.0]),
+        [0.1, 0.2, 0.5],
+    );
+    assert_close(
+        run(&cfg, "lin_srgb", "spi_space", [0.25, 0.5, 0.75]),
+        [0.5, 1.0, 1.5],
+    );
+    assert_close(
+        run(&cfg, "spi_space", "lin_srgb", [0.5, 1.0, 1.5]),
+        [0.25, 0.5, 0.75],
+    );
+    assert!(cfg.processor("cube_space", "lin_srgb").is_err());
+}
+
+#[test]
+fn display_view_with_look() {
+    let cfg = load("view");
+    let mut std = [0.25f32, 0.25, 0.25];
+    cfg.processor_display_view("sRGB", "Standard")
+        .unwrap()
+        .apply_rgb(&mut std);
+    let mut graded = [0.25f32, 0.25, 0.25];
+    cfg.processor_display_view("sRGB", "Graded")
+        .unwrap()
+        .apply_rgb(&mut graded);
+    // the look is x^0.5, so Graded shows 0.5 through the sRGB encode
+    assert_close(
+        graded,
+        run(&cfg, "lin_srgb", "srgb_display", [0.5, 0.5, 0.5]),
+    );
+    assert!(graded[0] > std[0]);
+    assert!(cfg.processor_display_view("sRGB", "Missing").is_err());
+}
+
+#[test]
+fn data_spaces_and_errors() {
+    let cfg = load("errors");
+    assert_close(
+        run(&cfg, "raw", "srgb_display", [2.0, -1.0, 0.5]),
+        [2.0, -1.0, 0.5],
+    );
+    let err = cfg.processor("builtin", "lin_srgb").err().unwrap();
+    assert!(
+        format!("{:#}", err).contains("BuiltinTransform"),
+        "{:#}",
+        err
+    );
+    assert!(cfg.processor("nope", "lin_srgb").is_err());
+}
+
+#[test]
+fn v2_view_transforms_and_shared_views() {
+    let text = r#"
+ocio_profile_version: 2
+roles: {scene_linear: lin}
+shared_views:
+  - !<View> {name: Film, view_transform: film, display_colorspace: <USE_DISPLAY_NAME>}
+displays:
+  sRGB:
+    - !<Views> [Film]
+view_transforms:
+  - !<ViewTransform>
+    name: film
+    from_scene_reference: !<MatrixTransform> {matrix: [0.5, 0, 0, 0, 0, 0.5, 0, 0, 0, 0, 0.5, 0, 0, 0, 0, 1]}
+display_colorspaces:
+  - !<ColorSpace>
+    name: sRGB
+    from_display_reference: !<ExponentTransform> {value: [0.5, 0.5, 0.5, 1]}
+colorspaces:
+  - !<ColorSpace>
+    name: lin
+"#;
+    let cfg = Config::from_yaml_str(text, std::path::Path::new(".")).unwrap();
+    assert_eq!(cfg.views("sRGB"), ["Film"]);
+    assert!(cfg.is_display_colorspace("sRGB"));
+    assert!(!cfg.is_display_colorspace("lin"));
+    let mut v = [0.5f32, 0.5, 0.5];
+    cfg.processor_display_view("sRGB", "Film")
+        .unwrap()
+        .apply_rgb(&mut v);
+    assert_close(v, [0.5, 0.5, 0.5]);
+    // scene → display colour space conversions go through the view transform too
+    let mut w = [0.5f32, 0.5, 0.5];
+    cfg.processor("lin", "sRGB").unwrap().apply_rgb(&mut w);
+    assert_close(w, v);
+}
+
+#[test]
+fn context_vars_select_shot_luts() {
+    let dir = std::env::temp_dir().join(format!("exrtool-ocio-{}-ctx", std::process::id()));
+    let halve = "From 0.0 1.0\nLength 2\nComponents 1\n{\n0.0\n0.5\n}\n";
+    for (shot, lut) in [("sh010", halve), ("sh020", DOUBLE_SPI1D)] {
+        fs::create_dir_all(dir.join(shot)).unwrap();
+        fs::write(dir.join(shot).join("grade.spi1d"), lut).unwrap();
+    }
+    let text = r#"
+ocio_profile_version: 1
+search_path: ${SHOT}
+environment: {SHOT: sh010}
+roles: {scene_linear: lin}
+colorspaces:
+  - !<ColorSpace> {name: lin}
+  - !<ColorSpace>
+    name: graded
+    from_reference: !<FileTransform> {src: grade.spi1d}
+"#;
+    let mut cfg = Config::from_yaml_str(text, &dir).unwrap();
+    assert_close(
+        run(&cfg, "lin", "graded", [0.5, 0.5, 0.5]),
+        [0.25, 0.25, 0.25],
+    );
+    cfg.set_context_var("SHOT", "sh020");
+    assert_close(run(&cfg, "lin", "graded", [0.5, 0.5, 0.5]), [1.0, 1.0, 1.0]);
+}
+
+#[test]
+fn explicit_looks_are_applied_in_order() {
+    let cfg = load("looks");
+    let mut v = [0.25f32, 0.25, 0.25];
+    cfg.processor_with_looks("lin_srgb", "scaled", "+bright")
+        .unwrap()
+        .apply_rgb(&mut v);
+    // 0.25^0.5 = 0.5, then into `scaled`
+    assert_close(v, run(&cfg, "lin_srgb", "scaled", [0.5, 0.5, 0.5]));
+    let mut w = [0.5f32, 0.5, 0.5];
+    cfg.processor_with_looks("lin_srgb", "lin_srgb", "-bright")
+        .unwrap()
+        .apply_rgb(&mut w);
+    assert_close(w, [0.25, 0.25, 0.25]);
+    assert!(cfg
+        .processor_with_looks("lin_srgb", "scaled", "missing")
+        .is_err());
+}
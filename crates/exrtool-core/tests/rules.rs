@@ -0,0 +1,547 @@
+use exrtool_core::metadata::{HeaderInfo, MetadataValue, TimeCode, ValueKind};
+use exrtool_core::rules::{Condition, MetadataChange, Rule, RuleEngine, RuleFile};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+fn attrs(pairs: &[(&str, &str)]) -> BTreeMap<String, MetadataValue> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), MetadataValue::from(*v)))
+        .collect()
+}
+
+fn change(key: &str, before: Option<&str>, after: Option<&str>) -> MetadataChange {
+    MetadataChange {
+        key: key.into(),
+        before: before.map(MetadataValue::from),
+        after: after.map(MetadataValue::from),
+        parts: Vec::new(),
+    }
+}
+
+#[test]
+fn sample_rules_produce_expected_diff() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../docs/rules.yml");
+    let engine = RuleEngine::from_path(&path).unwrap();
+    let current = attrs(&[
+        ("owner", "bob"),
+        ("software", "Nuke 15"),
+        ("ImageDescription", "old"),
+    ]);
+    let plan = engine.plan("sh010_0042.exr", &current);
+    assert!(plan.notes.is_empty(), "{:?}", plan.notes);
+    assert_eq!(
+        plan.changes,
+        vec![
+            change("ClipName", None, Some("sh010_0042")),
+            change("FrameNumber", None, Some("0042")),
+            change("ImageDescription", Some("old"), Some("Created by exrtool")),
+            change("ShotName", None, Some("sh010")),
+            change("XMP:CreatorTool", None, Some("Nuke 15")),
+            MetadataChange {
+                key: "framesPerSecond".into(),
+                before: None,
+                after: Some(MetadataValue::Rational(24000, 1001)),
+                parts: Vec::new(),
+            },
+            change("owner", Some("bob"), None),
+        ]
+    );
+    let (set, unset) = plan.edits();
+    assert_eq!(set.len(), 6);
+    assert_eq!(unset, ["owner"]);
+}
+
+#[test]
+fn rules_run_in_order_and_report_misses() {
+    let file = RuleFile {
+        part: None,
+        rules: vec![
+            Rule::Copy {
+                from: "Missing".into(),
+                to: "B".into(),
+                when: vec![],
+            },
+            Rule::Set {
+                key: "A".into(),
+                value: "1".into(),
+                value_type: ValueKind::Text,
+                when: vec![],
+            },
+            Rule::Copy {
+                from: "A".into(),
+                to: "B".into(),
+                when: vec![],
+            },
+            Rule::Unset {
+                key: "A".into(),
+                when: vec![],
+            },
+            Rule::FromFilename {
+                pattern: r"^(?P<shot>sh\d+)_".into(),
+                mapping: HashMap::from([("shot".into(), "Shot".into())]),
+                when: vec![],
+            },
+        ],
+    };
+    let engine = RuleEngine::new(&file).unwrap();
+    let plan = engine.plan("plate.exr", &BTreeMap::new());
+    assert_eq!(plan.changes, vec![change("B", None, Some("1"))]);
+    assert_eq!(plan.notes.len(), 2);
+
+    // nothing to do when the file already matches
+    let plan = engine.plan("plate.exr", &attrs(&[("B", "1")]));
+    assert!(plan.changes.is_empty());
+}
+
+#[test]
+fn invalid_rules_are_rejected() {
+    let bad_regex = RuleFile {
+        part: None,
+        rules: vec![Rule::FromFilename {
+            pattern: "(".into(),
+            mapping: HashMap::new(),
+            when: vec![],
+        }],
+    };
+    assert!(RuleEngine::new(&bad_regex).is_err());
+    let missing_group = RuleFile {
+        part: None,
+        rules: vec![Rule::FromFilename {
+            pattern: r"(?P<shot>\w+)".into(),
+            mapping: HashMap::from([("seq".into(), "Seq".into())]),
+            when: vec![],
+        }],
+    };
+    assert!(RuleEngine::new(&missing_group).is_err());
+    let empty_key = RuleFile {
+        part: None,
+        rules: vec![Rule::Unset {
+            key: " ".into(),
+            when: vec![],
+        }],
+    };
+    assert!(RuleEngine::new(&empty_key).is_err());
+}
+
+const CONDITIONAL: &str = r#"
+rules:
+  - type: from_filename
+    pattern: '(?P<shot>[a-z]+\d+)_(?P<frame>\d+)\.exr'
+    mapping: {}
+  - type: set
+    key: Clip
+    value: '{shot}_{frame:06}'
+  - type: set
+    key: Size
+    value: '{width}x{height}'
+    when: [{resolution: {width: 3840}}]
+  - type: set
+    key: Vendor
+    value: '{attr:owner} ({stem})'
+    when:
+      - exists: owner
+      - not: {equals: {key: owner, value: bob}}
+  - type: set
+    key: fps
+    value: 24000/1001
+    value_type: rational
+    when: [{filename: 'sh0*'}]
+  - type: set
+    key: startTC
+    value: '01:00:00:{frame}'
+    value_type: timecode
+    when: [{layer: 'diff*'}]
+  - type: set
+    key: Stamp
+    value: '{now:%Y}'
+  - type: unset
+    key: comments
+    when: [{matches: {key: comments, pattern: '^tmp'}}]
+"#;
+
+fn header(pairs: &[(&str, &str)], width: usize, layers: &[&str]) -> HeaderInfo {
+    HeaderInfo {
+        attributes: attrs(pairs),
+        width: Some(width),
+        height: Some(width * 9 / 16),
+        layers: layers.iter().map(|l| l.to_string()).collect(),
+    }
+}
+
+fn after<'a>(plan: &'a exrtool_core::rules::RulePlan, key: &str) -> Option<&'a MetadataValue> {
+    plan.changes
+        .iter()
+        .find(|c| c.key == key)
+        .and_then(|c| c.after.as_ref())
+}
+
+#[test]
+fn conditions_and_templates() {
+    let file: RuleFile = serde_yaml::from_str(CONDITIONAL).unwrap();
+    let engine = RuleEngine::new(&file).unwrap();
+
+    let info = header(
+        &[("owner", "alice"), ("comments", "tmp render")],
+        3840,
+        &["diffuse"],
+    );
+    let plan = engine.plan_for("sh010_12.exr", &info);
+    assert!(plan.notes.is_empty(), "{:?}", plan.notes);
+    assert_eq!(after(&plan, "Clip"), Some(&"sh010_000012".into()));
+    assert_eq!(after(&plan, "Size"), Some(&"3840x2160".into()));
+    assert_eq!(after(&plan, "Vendor"), Some(&"alice (sh010_12)".into()));
+    assert_eq!(
+        after(&plan, "fps"),
+        Some(&MetadataValue::Rational(24000, 1001))
+    );
+    assert_eq!(
+        after(&plan, "startTC"),
+        Some(&MetadataValue::TimeCode(TimeCode {
+            hours: 1,
+            frames: 12,
+            ..TimeCode::default()
+        }))
+    );
+    let year = after(&plan, "Stamp").unwrap().to_string();
+    assert!(year.len() == 4 && year.parse::<u32>().is_ok(), "{}", year);
+    assert!(plan
+        .changes
+        .iter()
+        .any(|c| c.key == "comments" && c.after.is_none()));
+
+    // none of the guarded rules fire for this file
+    let info = header(&[("owner", "bob")], 1920, &["beauty"]);
+    let plan = engine.plan_for("ab20_7.exr", &info);
+    let keys: Vec<&str> = plan.changes.iter().map(|c| c.key.as_str()).collect();
+    assert_eq!(keys, ["Clip", "Stamp"]);
+}
+
+#[test]
+fn template_failures_are_errors() {
+    let file: RuleFile = serde_yaml::from_str(
+        r#"
+rules:
+  - {type: set, key: A, value: '{shot}'}
+  - {type: set, key: B, value: '{attr:missing}'}
+  - {type: set, key: C, value: '{stem}x', value_type: int}
+  - {type: set, key: D, value: '{{literal}}'}
+"#,
+    )
+    .unwrap();
+    let plan = RuleEngine::new(&file)
+        .unwrap()
+        .plan("plate.exr", &BTreeMap::new());
+    assert!(plan.notes.is_empty(), "{:?}", plan.notes);
+    assert_eq!(plan.errors.len(), 3, "{:?}", plan.errors);
+    assert_eq!(plan.changes, vec![change("D", None, Some("{literal}"))]);
+}
+
+#[test]
+fn invalid_templates_and_values_are_rejected() {
+    let set = |value: &str, value_type| RuleFile {
+        part: None,
+        rules: vec![Rule::Set {
+            key: "K".into(),
+            value: value.into(),
+            value_type,
+            when: vec![],
+        }],
+    };
+    for (value, kind) in [
+        ("{unclosed", ValueKind::Text),
+        ("a}b", ValueKind::Text),
+        ("{frame:4}", ValueKind::Text),
+        ("{attr}", ValueKind::Text),
+        ("{now:%Q}", ValueKind::Text),
+        ("24/0", ValueKind::Rational),
+        ("1,2,3", ValueKind::V2f),
+        ("25:00:00:00", ValueKind::Timecode),
+    ] {
+        assert!(RuleEngine::new(&set(value, kind)).is_err(), "{}", value);
+    }
+    let bad_glob = RuleFile {
+        part: None,
+        rules: vec![Rule::Unset {
+            key: "K".into(),
+            when: vec![Condition::Filename("[".into())],
+        }],
+    };
+    assert!(RuleEngine::new(&bad_glob).is_err());
+}
+
+#[test]
+fn typed_values_parse_and_print() {
+    let cases = [
+        (ValueKind::Int, " 42 ", "42"),
+        (ValueKind::Float, "0.5", "0.5"),
+        (ValueKind::Rational, "24", "24/1"),
+        (ValueKind::Timecode, "10:00:01;02", "10:00:01;02"),
+        (ValueKind::V2f, "1.5 2", "1.5,2"),
+        (ValueKind::V2i, "-1,2", "-1,2"),
+        (ValueKind::Box2i, "0,0,1919,1079", "0,0,1919,1079"),
+    ];
+    for (kind, text, shown) in cases {
+        let v = MetadataValue::parse(kind, text).unwrap();
+        assert_eq!(v.kind(), kind);
+        assert_eq!(v.to_string(), shown);
+    }
+    let mut c = MetadataChange {
+        key: "fps".into(),
+        before: None,
+        after: Some(MetadataValue::Rational(25, 1)),
+        parts: Vec::new(),
+    };
+    assert_eq!(c.to_string(), "+ fps: 25/1 (rational)");
+    c.parts = vec!["beauty".into(), "diffuse".into()];
+    assert_eq!(c.to_string(), "+ fps: 25/1 (rational) [beauty, diffuse]");
+}
+
+#[cfg(feature = "use_exr_crate")]
+#[test]
+fn apply_writes_attributes_and_backup() {
+    use exrtool_core::metadata::read_attributes;
+    use exrtool_core::rules::{FileStatus, RuleRunOptions};
+
+    let dir = std::env::temp_dir().join(format!("exrtool-rules-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("sh020_0007.exr");
+    exr::prelude::write_rgba_file(&path, 4, 4, |_, _| (0.5f32, 0.5f32, 0.5f32, 1.0f32)).unwrap();
+
+    let file = RuleFile {
+        part: None,
+        rules: vec![
+            Rule::Set {
+                key: "Owner".into(),
+                value: "alice".into(),
+                value_type: ValueKind::Text,
+                when: vec![],
+            },
+            Rule::FromFilename {
+                pattern: r"(?P<shot>[^_]+)_(?P<frame>\d+)\.exr".into(),
+                mapping: HashMap::from([("shot".into(), "ShotName".into())]),
+                when: vec![],
+            },
+        ],
+    };
+    let engine = RuleEngine::new(&file).unwrap();
+
+    let dry = engine.apply(
+        &path,
+        &RuleRunOptions {
+            dry_run: true,
+            backup: true,
+            force_type: false,
+            verify: true,
+            part: None,
+        },
+    );
+    assert_eq!(dry.status, FileStatus::DryRun);
+    assert_eq!(dry.changes.len(), 2);
+    assert!(!read_attributes(&path).unwrap().contains_key("ShotName"));
+
+    let report = engine.apply(
+        &path,
+        &RuleRunOptions {
+            dry_run: false,
+            backup: true,
+            force_type: false,
+            verify: true,
+            part: None,
+        },
+    );
+    let bak = path.with_extension("exr.bak");
+    assert_eq!(
+        report.status,
+        FileStatus::Written {
+            backup: Some(bak.clone())
+        }
+    );
+    assert!(bak.exists());
+    let after = read_attributes(&path).unwrap();
+    assert_eq!(after.get("owner"), Some(&MetadataValue::from("alice")));
+    assert_eq!(after.get("ShotName"), Some(&MetadataValue::from("sh020")));
+
+    let again = engine.apply(&path, &RuleRunOptions::default());
+    assert_eq!(again.status, FileStatus::Unchanged);
+}
+
+#[cfg(feature = "use_exr_crate")]
+#[test]
+fn typed_values_are_written_with_their_type() {
+    use exrtool_core::metadata::read_attributes;
+    use exrtool_core::rules::{FileStatus, RuleRunOptions};
+
+    let dir = std::env::temp_dir().join(format!("exrtool-rules-typed-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("plate.exr");
+    exr::prelude::write_rgba_file(&path, 8, 4, |_, _| (0.5f32, 0.5f32, 0.5f32, 1.0f32)).unwrap();
+
+    let file: RuleFile = serde_yaml::from_str(
+        r#"
+rules:
+  - {type: set, key: Fps, value: 24000/1001, value_type: rational}
+  - {type: set, key: Take, value: '3', value_type: int}
+  - {type: set, key: Crop, value: '0,0,{width},{height}', value_type: box2i, when: [{resolution: {width: 8, height: 4}}]}
+"#,
+    )
+    .unwrap();
+    let report = RuleEngine::new(&file)
+        .unwrap()
+        .apply(&path, &RuleRunOptions::default());
+    assert_eq!(report.status, FileStatus::Written { backup: None });
+    let after = read_attributes(&path).unwrap();
+    assert_eq!(
+        after.get("Fps"),
+        Some(&MetadataValue::Rational(24000, 1001))
+    );
+    assert_eq!(after.get("Take"), Some(&MetadataValue::Int(3)));
+    assert_eq!(
+        after.get("Crop"),
+        Some(&MetadataValue::Box2i {
+            min: [0, 0],
+            max: [8, 4]
+        })
+    );
+}
+
+#[test]
+fn rule_files_choose_a_part() {
+    use exrtool_core::metadata::PartTarget;
+    let file: RuleFile =
+        serde_yaml::from_str("part: beauty\nrules: [{type: unset, key: Owner}]").unwrap();
+    assert_eq!(file.part, Some(PartTarget::Name("beauty".into())));
+    let file: RuleFile = serde_yaml::from_str("part: all\nrules: []").unwrap();
+    assert_eq!(file.part, Some(PartTarget::All));
+    assert_eq!(
+        serde_yaml::to_string(&file).unwrap(),
+        "part: all\nrules: []\n"
+    );
+    assert!(serde_yaml::from_str::<RuleFile>("rules: []")
+        .unwrap()
+        .part
+        .is_none());
+}
+
+#[cfg(feature = "use_exr_crate")]
+#[test]
+fn apply_reports_placements() {
+    use exrtool_core::metadata::PartTarget;
+    use exrtool_core::rules::{FileStatus, RuleRunOptions};
+
+    let dir = std::env::temp_dir().join(format!("exrtool-rules-parts-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("plate.exr");
+    exr::prelude::write_rgba_file(&path, 4, 4, |_, _| (0.5f32, 0.5f32, 0.5f32, 1.0f32)).unwrap();
+
+    let file: RuleFile =
+        serde_yaml::from_str("part: shared\nrules: [{type: set, key: Show, value: demo}]").unwrap();
+    let engine = RuleEngine::new(&file).unwrap();
+    // the command line wins over the rule file
+    let missing = engine.apply(
+        &path,
+        &RuleRunOptions {
+            part: Some(PartTarget::Name("beauty".into())),
+            ..RuleRunOptions::default()
+        },
+    );
+    assert!(matches!(missing.status, FileStatus::Failed { .. }));
+
+    let report = engine.apply(&path, &RuleRunOptions::default());
+    assert_eq!(report.status, FileStatus::Written { backup: None });
+    assert_eq!(report.placements.len(), 1);
+    assert_eq!(report.placements[0].to_string(), "+ Show -> shared [#0]");
+}
+
+#[cfg(feature = "use_exr_crate")]
+#[test]
+fn apply_plans_each_targeted_part() {
+    use exr::prelude::*;
+    use exrtool_core::header::ExrHeaders;
+    use exrtool_core::metadata::{edit_metadata_parts, PartEdit, PartTarget, WriteOptions};
+    use exrtool_core::rules::{FileStatus, RuleRunOptions};
+
+    let dir = std::env::temp_dir().join(format!("exrtool-rules-multi-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("plate.exr");
+    let size = Vec2(8, 4);
+    let part = |name: &str| {
+        Layer::new(
+            size,
+            LayerAttributes::named(name),
+            Encoding::SMALL_LOSSLESS,
+            SpecificChannels::rgba(|_: Vec2<usize>| (0.5f32, 0.5f32, 0.5f32, 1.0f32)),
+        )
+    };
+    Image::empty(ImageAttributes::new(IntegerBounds::from_dimensions(size)))
+        .with_layer(part("beauty"))
+        .with_layer(part("diffuse"))
+        .write()
+        .to_file(&path)
+        .unwrap();
+    // only the second part has an artist
+    edit_metadata_parts(
+        &path,
+        &[PartEdit {
+            part: PartTarget::Name("diffuse".into()),
+            set: HashMap::from([("Artist".into(), "carol".into())]),
+            unset: vec![],
+        }],
+        &WriteOptions::default(),
+    )
+    .unwrap();
+    let value =
+        |part: usize, key: &str| ExrHeaders::read_file(&path).unwrap().headers[part].value(key);
+
+    let engine = |yaml: &str| RuleEngine::new(&serde_yaml::from_str(yaml).unwrap()).unwrap();
+    let run = |part: &str, backup: bool| RuleRunOptions {
+        backup,
+        part: Some(part.parse().unwrap()),
+        ..RuleRunOptions::default()
+    };
+
+    // conditions and "before" values come from the targeted part, not part 0
+    let report = engine("rules: [{type: set, key: Artist, value: dave, when: [{exists: Artist}]}]")
+        .apply(&path, &run("diffuse", false));
+    assert_eq!(report.status, FileStatus::Written { backup: None });
+    assert_eq!(report.changes.len(), 1);
+    assert_eq!(report.changes[0].before, Some("carol".into()));
+    assert_eq!(report.changes[0].parts, ["diffuse"]);
+    assert_eq!(value(1, "Artist"), Some("dave".into()));
+
+    // parts with different results are written in one go, each with its own edit
+    let report = engine("rules: [{type: set, key: Seen, value: 'yes', when: [{exists: Artist}]}]")
+        .apply(&path, &run("all", true));
+    let bak = path.with_extension("exr.bak");
+    assert_eq!(
+        report.status,
+        FileStatus::Written {
+            backup: Some(bak.clone())
+        }
+    );
+    assert!(bak.exists());
+    assert_eq!(value(0, "Seen"), None);
+    assert_eq!(value(1, "Seen"), Some("yes".into()));
+    let shared = engine(
+        "part: shared\nrules: [{type: set, key: Seen2, value: 'yes', when: [{exists: Artist}]}]",
+    )
+    .apply(&path, &RuleRunOptions::default());
+    assert!(matches!(shared.status, FileStatus::Failed { .. }));
+
+    // a template that cannot be expanded fails the file and writes nothing
+    std::fs::remove_file(&bak).unwrap();
+    let report = engine("rules: [{type: set, key: Owner, value: '{attr:Artist}'}]")
+        .apply(&path, &run("all", true));
+    match &report.status {
+        FileStatus::Failed { error } => assert!(error.contains("Artist"), "{}", error),
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(value(1, "Owner"), None);
+    assert!(!bak.exists());
+
+    // a failed write leaves no backup behind
+    let report = engine("rules: [{type: set, key: Artist, value: '3', value_type: int}]")
+        .apply(&path, &run("diffuse", true));
+    assert!(matches!(report.status, FileStatus::Failed { .. }));
+    assert!(!bak.exists());
+}
@@ -0,0 +1,245 @@
+mod common;
+
+use common::plate;
+use exrtool_core::metadata::{read_part_attributes, MetadataValue, PartAttributes, WriteOptions};
+use exrtool_core::sidecar::{
+    export_file, plan_import, xmp_packet, ImportOptions, MetadataTable, SidecarRecord, SidecarValue,
+};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+fn typed(kind: &str, value: &str) -> SidecarValue {
+    SidecarValue::Typed {
+        kind: kind.parse().unwrap(),
+        value: value.into(),
+    }
+}
+
+#[test]
+fn tables_roundtrip_with_typed_columns() {
+    let records = vec![
+        SidecarRecord {
+            file: "a.exr".into(),
+            part: "#0".into(),
+            attributes: BTreeMap::from([
+                ("owner".to_string(), SidecarValue::Text("ana".into())),
+                ("framesPerSecond".to_string(), typed("rational", "24/1")),
+            ]),
+        },
+        SidecarRecord {
+            file: "b.exr".into(),
+            part: "#0".into(),
+            attributes: BTreeMap::from([("shot:take".to_string(), typed("int", "3"))]),
+        },
+    ];
+    let table = MetadataTable::from_records(&records);
+    assert_eq!(
+        table.columns,
+        [
+            "file",
+            "part",
+            "framesPerSecond:rational",
+            "owner",
+            "shot:take:int"
+        ]
+    );
+    assert_eq!(table.rows[1], ["b.exr", "#0", "", "", "3"]);
+    assert_eq!(table.to_records().unwrap(), records);
+
+    // a suffix that is not a type belongs to the name
+    let table = MetadataTable {
+        columns: vec!["part".into(), "file".into(), "shot:take".into()],
+        rows: vec![vec!["#0".into(), "a.exr".into(), "3".into()]],
+    };
+    let parsed = table.to_records().unwrap();
+    assert_eq!(
+        parsed[0].attributes["shot:take"],
+        SidecarValue::Text("3".into())
+    );
+
+    let short = MetadataTable {
+        rows: vec![vec!["#0".into()]],
+        ..table.clone()
+    };
+    assert!(short.to_records().is_err());
+    let no_file = MetadataTable {
+        columns: vec!["part".into()],
+        rows: vec![],
+    };
+    assert!(no_file.to_records().is_err());
+}
+
+#[test]
+fn json_values_are_plain_or_typed() {
+    // JSON is valid YAML, which keeps the test free of a JSON dependency
+    let records: Vec<SidecarRecord> = serde_yaml::from_str(
+        r##"[{"file": "a.exr", "part": "#0", "attributes": {
+            "owner": "ana",
+            "framesPerSecond": "23.976",
+            "lensMm": {"type": "float", "value": "35"}}}]"##,
+    )
+    .unwrap();
+    let attrs = &records[0].attributes;
+    assert_eq!(
+        attrs["framesPerSecond"]
+            .to_value("framesPerSecond")
+            .unwrap(),
+        MetadataValue::Rational(24000, 1001)
+    );
+    assert_eq!(
+        attrs["lensMm"].to_value("lensMm").unwrap(),
+        MetadataValue::Float(35.0)
+    );
+    assert_eq!(
+        attrs["owner"].to_value("owner").unwrap(),
+        MetadataValue::Text("ana".into())
+    );
+}
+
+#[test]
+fn imports_only_changed_attributes() {
+    let dir = std::env::temp_dir().join(format!("exrtool-sidecar-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("plate.exr");
+    plate(
+        &path,
+        &[
+            ("owner", "ana".into()),
+            ("vendor", "acme".into()),
+            ("lensMm", MetadataValue::Float(35.0)),
+        ],
+    );
+    let mut records = export_file(&path).unwrap();
+    assert_eq!(records.len(), 1);
+    assert!(!records[0].attributes.contains_key("channels"));
+    records[0].file = PathBuf::from("plate.exr");
+
+    let opts = ImportOptions::default();
+    assert!(plan_import(&records[0], &dir, &opts).unwrap().is_empty());
+
+    let mut edited = records[0].clone();
+    edited
+        .attributes
+        .insert("owner".into(), SidecarValue::Text("bo".into()));
+    edited.attributes.remove("vendor");
+    let change = plan_import(&edited, &dir, &opts).unwrap();
+    assert_eq!(change.file, path);
+    assert_eq!(change.set.keys().collect::<Vec<_>>(), ["owner"]);
+    assert!(change.unset.is_empty());
+
+    let change = plan_import(
+        &edited,
+        &dir,
+        &ImportOptions {
+            remove_missing: true,
+        },
+    )
+    .unwrap();
+    assert_eq!(change.unset, ["vendor"]);
+    change.apply(&WriteOptions::default()).unwrap();
+    let attrs = &read_part_attributes(&path).unwrap()[0].attributes;
+    assert_eq!(attrs["owner"], MetadataValue::Text("bo".into()));
+    assert!(!attrs.contains_key("vendor"));
+    assert!(attrs.contains_key("displayWindow"));
+
+    let mut layout = edited.clone();
+    layout
+        .attributes
+        .insert("compression".into(), SidecarValue::Text("zip".into()));
+    assert!(plan_import(&layout, &dir, &opts).is_err());
+    let mut wrong_part = edited;
+    wrong_part.part = "beauty".into();
+    assert!(plan_import(&wrong_part, &dir, &opts).is_err());
+}
+
+#[test]
+fn xmp_maps_well_known_attributes() {
+    let parts = vec![PartAttributes {
+        name: "#0".into(),
+        attributes: BTreeMap::from([
+            ("owner".to_string(), MetadataValue::Text("R&D <vfx>".into())),
+            ("capDate".to_string(), "2024:05:06 07:08:09".into()),
+            (
+                "framesPerSecond".to_string(),
+                MetadataValue::Rational(30000, 1001),
+            ),
+            (
+                "timeCode".to_string(),
+                MetadataValue::TimeCode("01:00:00;00".parse().unwrap()),
+            ),
+        ]),
+    }];
+    let xmp = xmp_packet(&parts);
+    assert!(
+        xmp.contains("<rdf:li>R&amp;D &lt;vfx&gt;</rdf:li>"),
+        "{}",
+        xmp
+    );
+    assert!(xmp.contains("<xmp:CreateDate>2024-05-06T07:08:09</xmp:CreateDate>"));
+    assert!(xmp.contains("<xmpDM:videoFrameRate>29.97</xmpDM:videoFrameRate>"));
+    assert!(xmp.contains("<xmpDM:timeValue>01:00:00;00</xmpDM:timeValue>"));
+    assert!(xmp.contains("<xmpDM:timeFormat>2997DropTimecode</xmpDM:timeFormat>"));
+    assert!(xmp.contains("<exr:name>framesPerSecond</exr:name><exr:type>rational</exr:type>"));
+    assert!(xmp.trim_end().ends_with("<?xpacket end=\"w\"?>"));
+}
+
+#[cfg(feature = "use_exr_crate")]
+#[test]
+fn imports_edits_to_several_parts_in_one_write() {
+    use exr::prelude::*;
+    use exrtool_core::metadata::write_with_backup;
+    use exrtool_core::sidecar::{apply_changes, group_by_file};
+
+    let dir = std::env::temp_dir().join(format!("exrtool-sidecar-multi-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("plate.exr");
+    let size = Vec2(8, 4);
+    let part = |name: &str| {
+        Layer::new(
+            size,
+            LayerAttributes::named(name),
+            Encoding::SMALL_LOSSLESS,
+            SpecificChannels::rgba(|_: Vec2<usize>| (0.5f32, 0.5f32, 0.5f32, 1.0f32)),
+        )
+    };
+    Image::empty(ImageAttributes::new(IntegerBounds::from_dimensions(size)))
+        .with_layer(part("beauty"))
+        .with_layer(part("diffuse"))
+        .write()
+        .to_file(&path)
+        .unwrap();
+    let original = std::fs::read(&path).unwrap();
+
+    let mut records = export_file(&path).unwrap();
+    assert_eq!(records.len(), 2);
+    for (r, owner) in records.iter_mut().zip(["ana", "bo"]) {
+        r.file = PathBuf::from("plate.exr");
+        r.attributes
+            .insert("owner".into(), SidecarValue::Text(owner.into()));
+    }
+    let changes: Vec<_> = records
+        .iter()
+        .map(|r| plan_import(r, &dir, &ImportOptions::default()).unwrap())
+        .collect();
+    let groups = group_by_file(&changes);
+    assert_eq!(groups.len(), 1);
+    let (file, group) = &groups[0];
+    assert_eq!(group.len(), 2);
+
+    let (placements, bak) = write_with_backup(file, true, || {
+        apply_changes(file, group, &WriteOptions::default())
+    })
+    .unwrap();
+    assert_eq!(placements.len(), 2);
+    // the backup is the untouched file, not the result of the first part's edit
+    assert_eq!(std::fs::read(bak.unwrap()).unwrap(), original);
+    let parts = read_part_attributes(&path).unwrap();
+    assert_eq!(
+        parts[0].attributes["owner"],
+        MetadataValue::Text("ana".into())
+    );
+    assert_eq!(
+        parts[1].attributes["owner"],
+        MetadataValue::Text("bo".into())
+    );
+}
@@ -0,0 +1,86 @@
+use exrtool_core::slate::{frame_range_label, AspectMask, Slate};
+use exrtool_core::PreviewImage;
+use std::path::PathBuf;
+
+fn grey(width: u32, height: u32) -> PreviewImage {
+    PreviewImage {
+        width,
+        height,
+        rgba8: [100, 100, 100, 255].repeat((width * height) as usize),
+    }
+}
+
+#[test]
+fn masks_parse_and_cover_the_bars() {
+    let m: AspectMask = "16:9".parse().unwrap();
+    assert!((m.aspect - 16.0 / 9.0).abs() < 1e-6);
+    assert_eq!(m.opacity, 1.0);
+    let m: AspectMask = "2.39@0.5".parse().unwrap();
+    assert_eq!((m.aspect, m.opacity), (2.39, 0.5));
+    assert_eq!(m.to_string(), "2.39@0.5");
+    for bad in ["", "wide", "0", "16:0", "2.39@2", "2.39@x"] {
+        assert!(bad.parse::<AspectMask>().is_err(), "{}", bad);
+    }
+
+    // letterbox a 16:9 frame, pillarbox a 4:3 frame to 1:1
+    let scope: AspectMask = "2.39".parse().unwrap();
+    assert_eq!(scope.visible(1920, 1080), (0, 138, 1920, 941));
+    let square: AspectMask = "1".parse().unwrap();
+    assert_eq!(square.visible(400, 300), (50, 0, 350, 300));
+
+    let mut img = grey(20, 20);
+    AspectMask {
+        aspect: 2.0,
+        opacity: 0.5,
+    }
+    .apply(&mut img);
+    let px = |x: u32, y: u32| img.rgba8[((y * 20 + x) * 4) as usize];
+    assert_eq!((px(0, 0), px(0, 4), px(0, 5), px(0, 15)), (50, 50, 100, 50));
+    assert_eq!(img.rgba8[3], 255, "alpha is kept");
+}
+
+#[test]
+fn slates_fill_in_defaults_and_render() {
+    let files: Vec<PathBuf> = (1001..=1024)
+        .map(|n| PathBuf::from(format!("shots/sh020_comp_v003.{}.exr", n)))
+        .collect();
+    assert_eq!(frame_range_label(&files), "1001-1024 (24 frames)");
+    assert_eq!(frame_range_label(&[PathBuf::from("a.exr")]), "1 frames");
+
+    let slate = Slate {
+        project: Some("Demo".into()),
+        artist: Some("Jane".into()),
+        date: Some("2026-01-02".into()),
+        ..Default::default()
+    }
+    .with_defaults(&files, "acescg:srgb");
+    let rows = slate.rows();
+    let labels: Vec<_> = rows.iter().map(|(l, _)| *l).collect();
+    assert_eq!(
+        labels,
+        ["PROJECT", "SHOT", "ARTIST", "DATE", "COLOUR", "FRAMES"]
+    );
+    assert_eq!(rows[1].1, "sh020_comp_v003");
+    assert_eq!(slate.hold, 1);
+
+    // given values win over the defaults
+    let given = Slate {
+        shot: Some("SH020".into()),
+        ..Default::default()
+    }
+    .with_defaults(&files, "raw");
+    assert_eq!(given.shot.as_deref(), Some("SH020"));
+
+    let img = slate.render(1920, 1080).unwrap();
+    assert_eq!((img.width, img.height), (1920, 1080));
+    assert_eq!(&img.rgba8[..4], &[0, 0, 0, 255]);
+    assert!(img.rgba8.chunks(4).any(|p| p[0] == 255));
+    // long rows shrink the text instead of running off the frame
+    let long = Slate {
+        notes: Some("x".repeat(200)),
+        ..slate.clone()
+    };
+    let img = long.render(320, 180).unwrap();
+    assert!(img.rgba8.chunks(4).any(|p| p[0] == 255));
+    assert!(slate.render(0, 10).is_err());
+}
@@ -0,0 +1,171 @@
+mod common;
+
+use common::{frames, scratch};
+use exrtool_core::batch::{JobFile, OutputFormat, RunOptions};
+use exrtool_core::stills::{icc_profile, DpxInfo};
+use exrtool_core::video::ColorTags;
+use std::path::Path;
+
+/// (1.0, 0.5, 0.0), which `raw` writes as code values.
+const PLATE: [f32; 4] = [1.0, 0.5, 0.0, 1.0];
+
+fn run(dir: &Path, job: &str) {
+    let yaml = format!(
+        "version: 1\njobs:\n- sequence: renders/plate.####.exr\n  colour: {{display: raw}}\n{}",
+        job
+    );
+    let file = JobFile::from_str_with_base(&yaml, dir).unwrap();
+    let report = file.run(&RunOptions::default(), |_, _, _| {}).unwrap();
+    let summary = report.summary();
+    assert_eq!(summary.failed, 0, "{:?}", report.items);
+}
+
+fn contains(data: &[u8], needle: &[u8]) -> bool {
+    data.windows(needle.len()).any(|w| w == needle)
+}
+
+fn be32(data: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+#[test]
+fn formats_parse() {
+    for (name, format) in [
+        ("png16", OutputFormat::Png16),
+        ("TIF16", OutputFormat::Tiff16),
+        ("tiff32", OutputFormat::TiffFloat),
+        ("dpx", OutputFormat::Dpx),
+    ] {
+        assert_eq!(name.parse::<OutputFormat>().unwrap(), format);
+    }
+    assert_eq!(OutputFormat::TiffFloat.to_string(), "tiff-float");
+    assert_eq!(OutputFormat::Dpx.extension(), "dpx");
+    assert!("exr"
+        .parse::<OutputFormat>()
+        .unwrap_err()
+        .to_string()
+        .contains("png16"));
+    let file = JobFile::from_str_with_base(
+        "version: 1\njobs: [{input: a.exr, format: tiff-float, tags: none}]",
+        Path::new(""),
+    )
+    .unwrap();
+    assert_eq!(file.jobs[0].format, OutputFormat::TiffFloat);
+}
+
+#[test]
+fn icc_profiles_follow_the_tags() {
+    let srgb = icc_profile(&ColorTags::preset("srgb").unwrap()).unwrap();
+    assert_eq!(be32(&srgb, 0) as usize, srgb.len());
+    assert_eq!(&srgb[36..40], b"acsp");
+    assert_eq!(&srgb[12..24], b"mntrRGB XYZ ");
+    assert!(contains(&srgb, b"para"));
+    assert_eq!(srgb.len() % 4, 0);
+    // rXYZ of sRGB adapted to D50 is about (0.436, 0.222, 0.014)
+    let tags = be32(&srgb, 128) as usize;
+    let r = (0..tags)
+        .map(|i| 132 + i * 12)
+        .find(|&e| &srgb[e..e + 4] == b"rXYZ")
+        .map(|e| be32(&srgb, e + 4) as usize)
+        .unwrap();
+    let x = be32(&srgb, r + 8) as i32 as f64 / 65536.0;
+    assert!((x - 0.4361).abs() < 1e-3, "{}", x);
+
+    assert!(icc_profile(&ColorTags::preset("rec2020").unwrap()).is_some());
+    assert!(icc_profile(&ColorTags::preset("pq").unwrap()).is_none());
+    assert!(icc_profile(&ColorTags::default()).is_none());
+
+    let dpx = DpxInfo::new(&ColorTags::preset("rec709").unwrap(), Some(1001));
+    assert_eq!((dpx.transfer, dpx.colorimetric), (6, 6));
+    let dpx = DpxInfo::new(&ColorTags::preset("srgb").unwrap(), None);
+    assert_eq!((dpx.transfer, dpx.colorimetric), (0, 6));
+}
+
+#[test]
+fn sixteen_bit_and_float_outputs_keep_precision() {
+    let dir = scratch("stills", "deep");
+    frames(&dir, "plate", &[1001, 1002], (4, 2), PLATE);
+    run(
+        &dir,
+        "  format: png16\n  output: out/plate.#####.{ext}\n  tags: srgb\n",
+    );
+    let png = dir.join("out/plate.01001.png");
+    assert!(dir.join("out/plate.01002.png").exists());
+    assert!(contains(&std::fs::read(&png).unwrap(), b"iCCP"));
+    let img = image::open(&png).unwrap();
+    assert_eq!(img.color(), image::ColorType::Rgba16);
+    assert_eq!(img.to_rgba16().get_pixel(0, 0).0, [65535, 32768, 0, 65535]);
+
+    run(&dir, "  format: tiff16\n  output: out/{stem}.{ext}\n");
+    let img = image::open(dir.join("out/plate.1001.tif")).unwrap();
+    assert_eq!(img.color(), image::ColorType::Rgba16);
+    assert_eq!(img.to_rgba16().get_pixel(3, 1).0[1], 32768);
+
+    run(
+        &dir,
+        "  format: tiff-float\n  output: out/{stem}_f.{ext}\n  tags: rec709\n",
+    );
+    let file = std::fs::File::open(dir.join("out/plate.1001_f.tif")).unwrap();
+    let mut tiff = tiff::decoder::Decoder::new(file).unwrap();
+    assert_eq!(tiff.dimensions().unwrap(), (4, 2));
+    let icc = tiff
+        .get_tag_u8_vec(tiff::tags::Tag::Unknown(34675))
+        .unwrap();
+    assert_eq!(&icc[36..40], b"acsp");
+    match tiff.read_image().unwrap() {
+        tiff::decoder::DecodingResult::F32(data) => assert_eq!(data[..4], [1.0, 0.5, 0.0, 1.0]),
+        _ => panic!("expected float samples"),
+    }
+}
+
+#[test]
+fn jpegs_take_quality_and_icc() {
+    let dir = scratch("stills", "jpeg");
+    frames(&dir, "plate", &[1007], (4, 2), PLATE);
+    run(
+        &dir,
+        "  format: jpg\n  jpeg_quality: 40\n  output: out/{stem}.{ext}\n  tags: srgb\n",
+    );
+    let data = std::fs::read(dir.join("out/plate.1007.jpg")).unwrap();
+    assert_eq!(&data[..2], &[0xFF, 0xD8]);
+    assert!(contains(&data, b"ICC_PROFILE\0\x01\x01"));
+    let img = image::load_from_memory(&data).unwrap().to_rgb8();
+    assert!(img.get_pixel(0, 0).0[0] > 240);
+
+    let file = JobFile::from_str_with_base(
+        "version: 1\njobs: [{input: renders/plate.1007.exr, format: jpeg, jpeg_quality: 0}]",
+        &dir,
+    )
+    .unwrap();
+    assert!(file.run(&RunOptions::default(), |_, _, _| {}).is_err());
+}
+
+#[test]
+fn dpx_headers_carry_colour_and_frame_numbers() {
+    let dir = scratch("stills", "dpx");
+    frames(&dir, "plate", &[1001], (4, 2), PLATE);
+    run(
+        &dir,
+        "  format: dpx\n  output: out/{stem}.{ext}\n  tags: rec709\n",
+    );
+    let data = std::fs::read(dir.join("out/plate.1001.dpx")).unwrap();
+    assert_eq!(&data[..4], b"SDPX");
+    assert_eq!(be32(&data, 4), 2048);
+    assert_eq!(be32(&data, 16) as usize, data.len());
+    assert_eq!(data.len(), 2048 + 4 * 2 * 4);
+    assert_eq!((be32(&data, 772), be32(&data, 776)), (4, 2));
+    // descriptor RGB, BT.709 transfer and colorimetry, 10 bits, method A
+    assert_eq!(data[800..804], [50, 6, 6, 10]);
+    assert_eq!(u16::from_be_bytes([data[804], data[805]]), 1);
+    assert_eq!(be32(&data, 1712), 1001);
+    let word = be32(&data, 2048);
+    assert_eq!(
+        (word >> 22, (word >> 12) & 0x3FF, (word >> 2) & 0x3FF),
+        (1023, 512, 0)
+    );
+
+    // raw output without tags is user-defined
+    run(&dir, "  format: dpx\n  output: out/raw.{ext}\n");
+    let data = std::fs::read(dir.join("out/raw.dpx")).unwrap();
+    assert_eq!(data[801..803], [0, 0]);
+}
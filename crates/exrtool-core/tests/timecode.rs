@@ -0,0 +1,241 @@
+use exrtool_core::header::ExrHeaders;
+use exrtool_core::metadata::{edit_metadata, MetadataValue, WriteOptions};
+use exrtool_core::timecode::{
+    check_continuity, frame_number, plan_stamps, read_timecode, stamp_attributes, ContinuityIssue,
+    FrameRate, StampOptions, StampStart, TimeCode,
+};
+use std::path::PathBuf;
+
+fn tc(text: &str) -> TimeCode {
+    text.parse().unwrap()
+}
+
+#[test]
+fn rates_parse_from_editorial_spellings() {
+    for (text, rate) in [
+        ("23.976", FrameRate::Fps23_976),
+        ("23.98", FrameRate::Fps23_976),
+        ("24000/1001", FrameRate::Fps23_976),
+        ("25", FrameRate::Fps25),
+        ("29.97", FrameRate::Fps29_97),
+        ("59.94", FrameRate::Fps59_94),
+        ("60", FrameRate::Fps60),
+    ] {
+        assert_eq!(text.parse::<FrameRate>().unwrap(), rate, "{}", text);
+    }
+    assert!("48".parse::<FrameRate>().is_err());
+    for rate in FrameRate::ALL {
+        assert_eq!(rate.to_string().parse::<FrameRate>().unwrap(), rate);
+    }
+}
+
+#[test]
+fn drop_frame_skips_labels() {
+    let r = FrameRate::Fps29_97;
+    for (frame, label) in [
+        (0, "00:00:00;00"),
+        (1799, "00:00:59;29"),
+        (1800, "00:01:00;02"),
+        (17981, "00:09:59;29"),
+        (17982, "00:10:00;00"),
+        (107892, "01:00:00;00"),
+    ] {
+        let t = TimeCode::from_frames(frame, r, true).unwrap();
+        assert_eq!(t.to_string(), label);
+        assert_eq!(t.to_frames(r).unwrap(), frame);
+    }
+    assert!(tc("00:01:00;01").validate(r).is_err());
+    assert!(tc("00:10:00;01").validate(r).is_ok());
+    assert!(TimeCode::from_frames(0, FrameRate::Fps25, true).is_err());
+
+    // 59.94 drops four labels, i.e. two frame pairs
+    let r = FrameRate::Fps59_94;
+    let t = TimeCode::from_frames(3600, r, true).unwrap();
+    assert_eq!(t.display_at(r), "00:01:00;04");
+    assert_eq!((t.frames, t.field_phase), (2, false));
+    assert_eq!(TimeCode::parse_at("00:01:00;04", r).unwrap(), t);
+    assert!(TimeCode::parse_at("00:01:00;03", r).is_err());
+}
+
+#[test]
+fn every_rate_roundtrips_a_day() {
+    for rate in FrameRate::ALL {
+        for drop in [false, true] {
+            if drop && !rate.supports_drop_frame() {
+                continue;
+            }
+            let day = rate.frames_per_day(drop);
+            let mut prev = None;
+            for frame in (0..day).step_by(997).chain(day - 3..day + 2) {
+                let t = TimeCode::from_frames(frame, rate, drop).unwrap();
+                assert_eq!(t.to_frames(rate).unwrap(), frame % day, "{} {}", rate, t);
+                assert!(t.frames < 40, "{} {}", rate, t);
+                assert_ne!(Some(t), prev);
+                prev = Some(t);
+            }
+        }
+    }
+    // high rates store frame pairs with the field phase bit
+    let t = TimeCode::parse_at("01:00:00:45", FrameRate::Fps50).unwrap();
+    assert_eq!(t.to_string(), "01:00:00:22.1");
+    assert_eq!(t.display_at(FrameRate::Fps50), "01:00:00:45");
+    assert!(TimeCode::parse_at("01:00:00:25", FrameRate::Fps25).is_err());
+}
+
+#[test]
+fn stamps_count_from_start_or_frame_number() {
+    assert_eq!(
+        frame_number(&PathBuf::from("sh010_v2.1001.exr")),
+        Some(1001)
+    );
+    assert_eq!(frame_number(&PathBuf::from("plate.exr")), None);
+
+    let files: Vec<PathBuf> = ["a.1001.exr", "a.1002.exr", "a.1005.exr"]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+    let opts = StampOptions {
+        rate: FrameRate::Fps24,
+        drop_frame: false,
+        start: StampStart::Timecode(tc("01:00:00:00")),
+    };
+    let plan = plan_stamps(&files, &opts).unwrap();
+    let labels: Vec<String> = plan.iter().map(|(_, t)| t.to_string()).collect();
+    // the gap in frame numbers stays a gap
+    assert_eq!(labels, ["01:00:00:00", "01:00:00:01", "01:00:00:04"]);
+
+    let plan = plan_stamps(
+        &files,
+        &StampOptions {
+            start: StampStart::FrameNumber,
+            ..opts
+        },
+    )
+    .unwrap();
+    assert_eq!(plan[0].1.to_string(), "00:00:41:17");
+
+    let unnumbered = vec![PathBuf::from("x.exr"), PathBuf::from("y.exr")];
+    assert_eq!(
+        plan_stamps(&unnumbered, &opts).unwrap()[1].1.to_string(),
+        "01:00:00:01"
+    );
+    assert!(plan_stamps(
+        &unnumbered,
+        &StampOptions {
+            start: StampStart::FrameNumber,
+            ..opts
+        }
+    )
+    .is_err());
+    // non-drop start with drop-frame counting
+    assert!(plan_stamps(
+        &files,
+        &StampOptions {
+            rate: FrameRate::Fps29_97,
+            drop_frame: true,
+            ..opts
+        }
+    )
+    .is_err());
+
+    let attrs = stamp_attributes(tc("01:00:00:00"), FrameRate::Fps23_976);
+    assert_eq!(
+        attrs["framesPerSecond"],
+        MetadataValue::Rational(24000, 1001)
+    );
+}
+
+#[test]
+fn continuity_reports_jumps_once() {
+    let r = FrameRate::Fps25;
+    let seq = |entries: &[(&str, Option<&str>)]| -> Vec<(PathBuf, Option<TimeCode>)> {
+        entries
+            .iter()
+            .map(|(p, t)| (PathBuf::from(p), t.map(tc)))
+            .collect()
+    };
+    let ok = seq(&[
+        ("s.0100.exr", Some("00:00:59:24")),
+        ("s.0101.exr", Some("00:01:00:00")),
+        ("s.0103.exr", Some("00:01:00:02")),
+    ]);
+    assert!(check_continuity(&ok, r).is_empty());
+
+    let broken = seq(&[
+        ("s.0100.exr", Some("00:00:10:00")),
+        ("s.0101.exr", Some("00:00:10:05")),
+        ("s.0102.exr", Some("00:00:10:06")),
+        ("s.0103.exr", None),
+        ("s.0104.exr", Some("00:00:10:27")),
+    ]);
+    let issues = check_continuity(&broken, r);
+    assert_eq!(issues.len(), 3, "{:?}", issues);
+    assert_eq!(
+        issues[0],
+        ContinuityIssue::Jump {
+            path: "s.0101.exr".into(),
+            expected: tc("00:00:10:01"),
+            found: tc("00:00:10:05"),
+        }
+    );
+    assert_eq!(
+        issues[0].to_string(),
+        "s.0101.exr: expected 00:00:10:01, found 00:00:10:05"
+    );
+    assert!(matches!(issues[1], ContinuityIssue::Missing { .. }));
+    assert!(matches!(issues[2], ContinuityIssue::Invalid { .. }));
+
+    // midnight wraps
+    let wrap = seq(&[
+        ("a.exr", Some("23:59:59:24")),
+        ("b.exr", Some("00:00:00:00")),
+    ]);
+    assert!(check_continuity(&wrap, r).is_empty());
+}
+
+#[test]
+fn stamped_files_read_back_continuous() {
+    let dir = std::env::temp_dir().join(format!("exrtool-tc-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let files: Vec<PathBuf> = (1001..1004)
+        .map(|n| {
+            let path = dir.join(format!("plate.{}.exr", n));
+            image::Rgba32FImage::new(4, 4).save(&path).unwrap();
+            path
+        })
+        .collect();
+    let rate = FrameRate::Fps59_94;
+    let plan = plan_stamps(
+        &files,
+        &StampOptions {
+            rate,
+            drop_frame: true,
+            start: StampStart::Timecode(TimeCode::parse_at("00:00:59;58", rate).unwrap()),
+        },
+    )
+    .unwrap();
+    for (path, t) in &plan {
+        edit_metadata(
+            path,
+            &stamp_attributes(*t, rate),
+            &[],
+            &WriteOptions::default(),
+        )
+        .unwrap();
+    }
+    let read: Vec<_> = files
+        .iter()
+        .map(|f| (f.clone(), read_timecode(f).unwrap()))
+        .collect();
+    assert!(check_continuity(&read, rate).is_empty());
+    assert_eq!(
+        read[2].1.unwrap().display_at(rate),
+        "00:01:00;04",
+        "drop-frame skips 00:01:00;00-03"
+    );
+    let h = &ExrHeaders::read_file(&files[0]).unwrap().headers[0];
+    assert_eq!(
+        h.value("framesPerSecond"),
+        Some(MetadataValue::Rational(60000, 1001))
+    );
+}
@@ -0,0 +1,597 @@
+mod common;
+
+use common::{frame, scratch};
+use exrtool_core::aces::AcesOdt;
+use exrtool_core::slate::Slate;
+use exrtool_core::video::{
+    audio_tracks, export_video, ffmpeg_args, sequence_files, AudioInput, AudioStart, AudioTrack,
+    BitDepth, CodecProfile, ColorTags, FitMode, FrameRange, RateControl, Resize, VideoColorspace,
+    VideoExportSettings,
+};
+use exrtool_core::{DisplayTransform, Primaries};
+use std::path::{Path, PathBuf};
+
+fn args(settings: &VideoExportSettings) -> Vec<String> {
+    ffmpeg_args(settings, 1920, 1080, &[], Path::new("out.mov"))
+        .unwrap()
+        .into_iter()
+        .map(|a| a.into_string().unwrap())
+        .collect()
+}
+
+#[test]
+fn prores_arguments() {
+    let settings = VideoExportSettings {
+        fps: 23.976,
+        codec: CodecProfile::find("prores", Some("4444")).unwrap(),
+        alpha: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        args(&settings).join(" "),
+        "-y -f rawvideo -pix_fmt rgba64le -s 1920x1080 -r 24000/1001 -i - \
+         -c:v prores_ks -profile:v 4 -pix_fmt yuva444p10le \
+         -color_primaries bt709 -color_trc iec61966-2-1 -colorspace bt709 -color_range tv \
+         -vf scale=out_color_matrix=bt709:out_range=tv -movflags +write_colr out.mov"
+    );
+    let rate = |fps: f32| {
+        VideoExportSettings {
+            fps,
+            ..Default::default()
+        }
+        .fps_arg()
+    };
+    assert_eq!(rate(29.97), "30000/1001");
+    assert_eq!(rate(24.0), "24");
+    assert_eq!(rate(12.5), "12.5");
+
+    let hq = VideoExportSettings::default();
+    assert_eq!(
+        args(&hq).join(" "),
+        "-y -f rawvideo -pix_fmt rgb48le -s 1920x1080 -r 24 -i - \
+         -c:v prores_ks -profile:v 3 -pix_fmt yuv422p10le \
+         -color_primaries bt709 -color_trc iec61966-2-1 -colorspace bt709 -color_range tv \
+         -vf scale=out_color_matrix=bt709:out_range=tv -movflags +write_colr out.mov"
+    );
+}
+
+#[test]
+fn catalogue_codecs() {
+    let settings = VideoExportSettings {
+        codec: CodecProfile::find("libx264", None).unwrap(),
+        rate: Some("18".parse().unwrap()),
+        bit_depth: BitDepth::Eight,
+        ..Default::default()
+    };
+    let a = args(&settings);
+    assert_eq!(a[4], "rgb24");
+    assert_eq!(
+        a[11..19].join(" "),
+        "-c:v libx264 -profile:v high -pix_fmt yuv420p -crf 18"
+    );
+
+    let hevc = VideoExportSettings {
+        codec: CodecProfile::find("hevc", Some("10bit")).unwrap(),
+        rate: Some("bitrate=20M".parse().unwrap()),
+        ..Default::default()
+    };
+    let a = args(&hevc).join(" ");
+    assert!(a.contains("-c:v libx265 -profile:v main10 -pix_fmt yuv420p10le -b:v 20M"));
+    assert!(a.ends_with("-tag:v hvc1 out.mov"));
+    assert!(ffmpeg_args(&hevc, 1919, 1080, &[], Path::new("out.mp4")).is_err());
+
+    let dnx = VideoExportSettings {
+        codec: CodecProfile::find("dnxhr", Some("444")).unwrap(),
+        pix_fmt: Some("gbrp10le".into()),
+        ..Default::default()
+    };
+    assert!(args(&dnx)
+        .join(" ")
+        .contains("-c:v dnxhd -profile:v dnxhr_444 -pix_fmt gbrp10le"));
+    assert_eq!(
+        CodecProfile::find("dnxhd", None).unwrap().profile,
+        "dnxhr_hq"
+    );
+
+    // checked before anything is rendered
+    let invalid = [
+        VideoExportSettings {
+            codec: CodecProfile::find("dnxhr", Some("hqx")).unwrap(),
+            alpha: true,
+            ..Default::default()
+        },
+        VideoExportSettings {
+            rate: Some(RateControl::Crf(20)),
+            ..Default::default()
+        },
+        VideoExportSettings {
+            pix_fmt: Some("yuv420p".into()),
+            ..Default::default()
+        },
+    ];
+    for settings in &invalid {
+        assert!(settings.validate().is_err());
+        let err = export_video(
+            &[PathBuf::from("missing.exr")],
+            Path::new("x.mov"),
+            settings,
+            None,
+            |_| true,
+        )
+        .unwrap_err();
+        assert!(!format!("{:#}", err).contains("missing.exr"), "{:#}", err);
+    }
+
+    let bad = VideoExportSettings {
+        fps: 0.0,
+        ..Default::default()
+    };
+    assert!(ffmpeg_args(&bad, 16, 16, &[], Path::new("out.mov")).is_err());
+}
+
+#[test]
+fn colour_tags_follow_the_display_transform() {
+    let tags = |display, dst| ColorTags::for_display(display, dst).to_string();
+    let aces = |odt| DisplayTransform::Aces {
+        input: Primaries::ACEScgD60,
+        odt,
+    };
+    assert_eq!(
+        tags(DisplayTransform::Standard, Primaries::Rec2020D65),
+        "primaries=bt2020 trc=iec61966-2-1 matrix=bt2020nc range=tv"
+    );
+    assert_eq!(
+        tags(aces(AcesOdt::Rec709), Primaries::SrgbD65),
+        "primaries=bt709 trc=bt709 matrix=bt709 range=tv"
+    );
+    assert_eq!(
+        tags(aces(AcesOdt::P3D65), Primaries::SrgbD65),
+        "primaries=smpte432 trc=- matrix=bt709 range=tv"
+    );
+    assert_eq!(
+        tags(aces(AcesOdt::Rec2100Pq1000), Primaries::SrgbD65),
+        "primaries=bt2020 trc=smpte2084 matrix=bt2020nc range=tv"
+    );
+    assert_eq!(
+        tags(DisplayTransform::Raw, Primaries::SrgbD65),
+        "primaries=- trc=- matrix=bt709 range=tv"
+    );
+
+    // overrides replace only the fields they name
+    let settings = VideoExportSettings {
+        display: aces(AcesOdt::Rec709),
+        color_tags: "hlg,range=pc".parse().unwrap(),
+        ..Default::default()
+    };
+    assert_eq!(
+        settings.resolved_color_tags().to_string(),
+        "primaries=bt2020 trc=arib-std-b67 matrix=bt2020nc range=pc"
+    );
+    let settings = VideoExportSettings {
+        color_tags: "trc=bt709".parse().unwrap(),
+        ..Default::default()
+    };
+    let a = ffmpeg_args(&settings, 16, 16, &[], Path::new("out.mp4")).unwrap();
+    let a: Vec<_> = a.iter().map(|a| a.to_str().unwrap()).collect();
+    assert!(a
+        .join(" ")
+        .contains("-color_primaries bt709 -color_trc bt709"));
+
+    assert!("trc=gamma26".parse::<ColorTags>().is_err());
+    assert!("whitepoint=d65".parse::<ColorTags>().is_err());
+    assert!("dci".parse::<ColorTags>().is_err());
+}
+
+#[test]
+fn profiles_and_colorspaces_parse() {
+    for (name, n) in [
+        ("proxy", "0"),
+        ("LT", "1"),
+        ("422", "2"),
+        ("422hq", "3"),
+        ("xq", "5"),
+    ] {
+        let p = CodecProfile::find("prores", Some(name)).unwrap();
+        assert_eq!(p.profile, n);
+        assert_eq!(p.encoder(), "prores_ks");
+    }
+    assert_eq!(CodecProfile::find("prores_ks", None).unwrap().name, "422hq");
+    let err = CodecProfile::find("prores", Some("444")).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("proxy, lt, 422, 422hq, 4444, 4444xq"));
+    assert!(CodecProfile::find("vp9", None).is_err());
+    assert_eq!(
+        "crf=23".parse::<RateControl>().unwrap(),
+        RateControl::Crf(23)
+    );
+    assert_eq!(
+        "8000k".parse::<RateControl>().unwrap(),
+        RateControl::Bitrate("8000k".into())
+    );
+    for bad in ["52", "crf=x", "20MM", "0M", "q=3"] {
+        assert!(bad.parse::<RateControl>().is_err(), "{}", bad);
+    }
+    assert_eq!("8".parse::<BitDepth>().unwrap(), BitDepth::Eight);
+    assert!("10".parse::<BitDepth>().is_err());
+
+    let cs: VideoColorspace = "acescg:srgb".parse().unwrap();
+    assert_eq!((cs.src, cs.dst), (Primaries::ACEScgD60, Primaries::SrgbD65));
+    assert!(cs.lut().unwrap().is_some());
+    let linear: VideoColorspace = "linear:srgb".parse().unwrap();
+    assert!(linear.lut().unwrap().is_none());
+    // unknown spaces are an error rather than an identity transform
+    assert!("acescc:srgb".parse::<VideoColorspace>().is_err());
+    assert!("acescg".parse::<VideoColorspace>().is_err());
+}
+
+#[test]
+fn sequences_and_missing_ffmpeg() {
+    let dir = scratch("video", "seq");
+    frame(&dir.join("b.0002.exr"), 4, 2, [0.18, 0.18, 0.18, 0.5]);
+    frame(&dir.join("b.0001.EXR"), 4, 2, [0.18, 0.18, 0.18, 0.5]);
+    std::fs::write(dir.join("notes.txt"), b"").unwrap();
+    let files = sequence_files(&dir).unwrap();
+    let names: Vec<_> = files.iter().map(|f| f.file_name().unwrap()).collect();
+    assert_eq!(names, ["b.0001.EXR", "b.0002.exr"]);
+
+    let settings = VideoExportSettings {
+        ffmpeg: PathBuf::from("/nonexistent/ffmpeg"),
+        ..Default::default()
+    };
+    let err = export_video(&files, &dir.join("out.mov"), &settings, None, |_| true).unwrap_err();
+    assert!(err.to_string().contains("failed to start"));
+    assert!(export_video(&[], &dir.join("out.mov"), &settings, None, |_| true).is_err());
+}
+
+/// Stands in for ffmpeg: copies stdin to the output path (last argument).
+#[cfg(unix)]
+fn fake_ffmpeg(dir: &Path) -> PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+
+    let fake = dir.join("ffmpeg");
+    std::fs::write(&fake, "#!/bin/sh\nfor a; do out=$a; done\ncat > \"$out\"\n").unwrap();
+    std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755)).unwrap();
+    fake
+}
+
+#[cfg(unix)]
+#[test]
+fn frames_are_piped_as_raw_rgb() {
+    let dir = scratch("video", "raw");
+    let fake = fake_ffmpeg(&dir);
+    let files: Vec<PathBuf> = (1..=3).map(|n| dir.join(format!("f.{}.exr", n))).collect();
+    for (n, f) in files.iter().enumerate() {
+        let v = n as f32 * 0.25;
+        frame(f, 4, 2, [v, v, v, 0.5]);
+    }
+
+    let mut settings = VideoExportSettings {
+        ffmpeg: fake,
+        gamma: 1.0,
+        threads: 2,
+        ..Default::default()
+    };
+    let out = dir.join("out.raw");
+    let mut seen = Vec::new();
+    export_video(&files, &out, &settings, None, |p| {
+        seen.push(p.completed);
+        true
+    })
+    .unwrap();
+    assert_eq!(seen, [1, 2, 3]);
+    let raw = std::fs::read(&out).unwrap();
+    assert_eq!(raw.len(), 3 * 4 * 2 * 3 * 2);
+    let first_of = |i: usize| u16::from_le_bytes([raw[i * 48], raw[i * 48 + 1]]);
+    assert_eq!(first_of(0), 0);
+    // sRGB-encoded 0.25 and 0.5, at full 16-bit precision
+    assert!((first_of(1) as i32 - 35199).abs() < 32);
+    assert!((first_of(2) as i32 - 48192).abs() < 32);
+
+    settings.bit_depth = BitDepth::Eight;
+    settings.codec = CodecProfile::find("prores", Some("4444")).unwrap();
+    settings.alpha = true;
+    export_video(&files[..1], &out, &settings, None, |_| true).unwrap();
+    let raw = std::fs::read(&out).unwrap();
+    assert_eq!(raw.len(), 4 * 2 * 4);
+    assert_eq!(raw[3], 128);
+
+    // the slate is held in front of the frames; masks darken the bars
+    settings.slate = Some(Slate {
+        hold: 2,
+        ..Default::default()
+    });
+    settings.mask = Some("4:1".parse().unwrap());
+    export_video(&files[2..], &out, &settings, None, |_| true).unwrap();
+    let raw = std::fs::read(&out).unwrap();
+    assert_eq!(raw.len(), 3 * 4 * 2 * 4);
+    assert_eq!(raw[..4], [0, 0, 0, 255]);
+    let shot = &raw[2 * 32..];
+    assert!(shot[0] > 0, "visible row");
+    assert_eq!(shot[16..19], [0, 0, 0], "masked row");
+    settings.slate = None;
+    settings.mask = None;
+
+    // every frame has to match the first one's size
+    frame(&files[1], 8, 2, [0.5, 0.5, 0.5, 0.5]);
+    let err = export_video(&files, &out, &settings, None, |_| true).unwrap_err();
+    assert!(format!("{:#}", err).contains("expected 4x2"));
+}
+
+#[test]
+fn frame_ranges() {
+    let files: Vec<PathBuf> = (1001..=1010)
+        .map(|n| PathBuf::from(format!("sh010.{}.exr", n)))
+        .collect();
+    let numbers = |range: &FrameRange| -> Vec<String> {
+        range
+            .select(&files)
+            .unwrap()
+            .iter()
+            .map(|f| f.file_stem().unwrap().to_string_lossy()[6..].to_string())
+            .collect()
+    };
+    let cut = FrameRange {
+        first: Some(1003),
+        last: Some(1005),
+        ..Default::default()
+    };
+    assert_eq!(numbers(&cut), ["1003", "1004", "1005"]);
+    let handles = FrameRange { handles: 1, ..cut };
+    assert_eq!(numbers(&handles).len(), 5);
+    let stepped = FrameRange { step: 2, ..handles };
+    assert_eq!(numbers(&stepped), ["1002", "1004", "1006"]);
+    assert_eq!(stepped.to_string(), "1003-1005+1/2");
+    let open = FrameRange {
+        first: Some(1009),
+        handles: 5,
+        ..Default::default()
+    };
+    assert_eq!(
+        numbers(&open),
+        ["1004", "1005", "1006", "1007", "1008", "1009", "1010"]
+    );
+    assert_eq!(numbers(&FrameRange::default()).len(), 10);
+
+    let after = FrameRange {
+        first: Some(2000),
+        ..Default::default()
+    };
+    assert!(after.select(&files).is_err());
+    assert!(cut.select(&[PathBuf::from("plate.exr")]).is_err());
+    assert!(FrameRange {
+        step: 0,
+        ..Default::default()
+    }
+    .select(&files)
+    .is_err());
+}
+
+#[test]
+fn frame_layouts() {
+    let layout = |codec: &str, resize: Option<&str>, mode: &str, size: (u32, u32), pa: f32| {
+        let settings = VideoExportSettings {
+            codec: CodecProfile::find(codec, None).unwrap(),
+            resize: resize.map(|r| Resize {
+                mode: mode.parse().unwrap(),
+                ..r.parse().unwrap()
+            }),
+            ..Default::default()
+        };
+        let l = settings.frame_layout(size.0, size.1, pa);
+        (l.scaled, l.crop, l.canvas, l.offset)
+    };
+    // anamorphic 2:1 plate scaled down to max_size; 4:2:0 needs an even height
+    assert_eq!(layout("prores", None, "", (2048, 858), 2.0).0, (2048, 429));
+    assert_eq!(layout("h264", None, "", (2048, 858), 2.0).0, (2048, 428));
+    assert_eq!(
+        layout("prores", Some("1920x1080"), "pad", (2048, 858), 2.0),
+        ((1920, 402), (0, 0, 1920, 402), (1920, 1080), (0, 339))
+    );
+    assert_eq!(
+        layout("prores", Some("1920x1080"), "fill", (1920, 800), 1.0),
+        ((2592, 1080), (336, 0, 1920, 1080), (1920, 1080), (0, 0))
+    );
+    assert_eq!(
+        layout("prores", Some("1920x1080"), "crop", (2048, 858), 1.0),
+        ((2048, 858), (64, 0, 1920, 858), (1920, 1080), (0, 111))
+    );
+    assert_eq!(
+        layout("h264", Some("1920x1080"), "fit", (1001, 1000), 1.0).2,
+        (1080, 1080)
+    );
+
+    let odd = VideoExportSettings {
+        codec: CodecProfile::find("h264", None).unwrap(),
+        resize: Some("1921x1080".parse().unwrap()),
+        ..Default::default()
+    };
+    assert!(odd.validate().is_err());
+    assert!("1920".parse::<Resize>().is_err());
+    assert!("0x1080".parse::<Resize>().is_err());
+    assert!("stretch".parse::<FitMode>().is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn ranges_and_resizes_shape_the_movie() {
+    use exrtool_core::header::rewrite_headers;
+    use exrtool_core::metadata::MetadataValue;
+
+    let dir = scratch("video", "layout");
+    let files: Vec<PathBuf> = (1..=4).map(|n| dir.join(format!("f.{}.exr", n))).collect();
+    for f in &files {
+        frame(f, 4, 2, [0.5, 0.5, 0.5, 0.5]);
+    }
+    for f in &files {
+        rewrite_headers(f, f, false, |h| {
+            h[0].set("pixelAspectRatio", &MetadataValue::Float(2.0));
+            Ok(())
+        })
+        .unwrap();
+    }
+    let settings = VideoExportSettings {
+        ffmpeg: fake_ffmpeg(&dir),
+        bit_depth: BitDepth::Eight,
+        range: FrameRange {
+            first: Some(2),
+            last: Some(4),
+            step: 2,
+            ..Default::default()
+        },
+        resize: Some("8x8".parse().unwrap()),
+        ..Default::default()
+    };
+    let out = dir.join("out.raw");
+    let mut total = 0;
+    export_video(&files, &out, &settings, None, |p| {
+        total = p.total;
+        true
+    })
+    .unwrap();
+    assert_eq!(total, 2);
+    let raw = std::fs::read(&out).unwrap();
+    assert_eq!(raw.len(), 2 * 8 * 8 * 3);
+    // 4x2 at 2:1 pixel aspect shows as 8x2, padded to rows 3 and 4 of 8
+    let row = |y: usize| &raw[y * 24..(y + 1) * 24];
+    assert!(row(2).iter().all(|&v| v == 0));
+    assert!(row(3).iter().all(|&v| v > 0));
+    assert!(row(4).iter().all(|&v| v > 0));
+    assert!(row(5).iter().all(|&v| v == 0));
+}
+
+#[test]
+fn audio_arguments() {
+    let input: AudioInput = "C:\\mix, final.wav,start=01:00:00:00,channels=1+0"
+        .parse()
+        .unwrap();
+    assert_eq!(input.path, PathBuf::from("C:\\mix, final.wav"));
+    assert_eq!(
+        input.start,
+        Some(AudioStart::Timecode("01:00:00:00".parse().unwrap()))
+    );
+    assert_eq!(input.channels, [1, 0]);
+    assert_eq!(
+        "a.wav,start=1001".parse::<AudioInput>().unwrap().start,
+        Some(AudioStart::Frame(1001))
+    );
+    assert!("a.wav,channels=left".parse::<AudioInput>().is_err());
+    assert!("start=1001".parse::<AudioInput>().is_err());
+
+    let tracks = [
+        AudioTrack {
+            path: "dialog.wav".into(),
+            seek: 1.5,
+            channels: vec![1],
+        },
+        AudioTrack {
+            path: "music.wav".into(),
+            seek: -0.25,
+            channels: Vec::new(),
+        },
+    ];
+    let settings = VideoExportSettings::default();
+    let a = ffmpeg_args(&settings, 16, 16, &tracks, Path::new("out.mov"))
+        .unwrap()
+        .into_iter()
+        .map(|a| a.into_string().unwrap())
+        .collect::<Vec<_>>()
+        .join(" ");
+    assert!(a.contains("-i - -ss 1.500000 -i dialog.wav -i music.wav -c:v prores_ks"));
+    assert!(a.contains(
+        "-map 0:v -map 1:a:0 -filter:a:0 pan=mono|c0=c1,apad \
+         -map 2:a:0 -filter:a:1 adelay=250:all=1,apad -c:a pcm_s24le -shortest"
+    ));
+    let mp4 = ffmpeg_args(&settings, 16, 16, &tracks, Path::new("out.mp4")).unwrap();
+    assert!(mp4.iter().any(|a| a == "aac"));
+
+    let stepped = VideoExportSettings {
+        range: FrameRange {
+            step: 2,
+            ..Default::default()
+        },
+        audio: vec!["a.wav".parse().unwrap()],
+        ..Default::default()
+    };
+    assert!(stepped.validate().is_err());
+}
+
+#[test]
+fn audio_lines_up_with_the_frames() {
+    let dir = scratch("video", "audio");
+    let wav = dir.join("shot.wav");
+    std::fs::write(&wav, b"RIFF").unwrap();
+    let sequence: Vec<PathBuf> = (1001..=1004)
+        .map(|n| dir.join(format!("sh010.{}.exr", n)))
+        .collect();
+    for f in &sequence {
+        frame(f, 2, 2, [0.0, 0.0, 0.0, 0.5]);
+    }
+    let seek = |start: Option<&str>, hold: Option<u32>| {
+        let settings = VideoExportSettings {
+            audio: vec![AudioInput {
+                path: wav.clone(),
+                start: start.map(|s| s.parse().unwrap()),
+                channels: Vec::new(),
+            }],
+            slate: hold.map(|hold| Slate {
+                hold,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let tracks = audio_tracks(&settings, &sequence, &sequence[1..]).unwrap();
+        (tracks[0].seek * 24.0).round() as i64
+    };
+    // in frames at 24 fps: positive seeks into the file, negative delays it
+    assert_eq!(seek(None, None), 1);
+    assert_eq!(seek(None, Some(1)), 0);
+    assert_eq!(seek(Some("1004"), None), -2);
+    // frame 1002 is 00:00:41:18 at 24 fps
+    assert_eq!(seek(Some("00:00:41:10"), None), 8);
+
+    let missing = VideoExportSettings {
+        audio: vec!["missing.wav".parse().unwrap()],
+        ..Default::default()
+    };
+    assert!(audio_tracks(&missing, &sequence, &sequence).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn durations_are_checked_with_ffprobe() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = scratch("video", "probe");
+    let probe = dir.join("ffprobe");
+    std::fs::write(
+        &probe,
+        format!("#!/bin/sh\ncat {}\n", dir.join("streams").display()),
+    )
+    .unwrap();
+    std::fs::set_permissions(&probe, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let wav = dir.join("shot.wav");
+    std::fs::write(&wav, b"RIFF").unwrap();
+    let files: Vec<PathBuf> = (1..=3).map(|n| dir.join(format!("f.{}.exr", n))).collect();
+    for f in &files {
+        frame(f, 4, 2, [0.5, 0.5, 0.5, 0.5]);
+    }
+    let settings = VideoExportSettings {
+        ffmpeg: fake_ffmpeg(&dir),
+        audio: vec![AudioInput {
+            path: wav,
+            start: None,
+            channels: Vec::new(),
+        }],
+        ..Default::default()
+    };
+    let out = dir.join("out.mov");
+
+    // three frames at 24 fps
+    std::fs::write(dir.join("streams"), "video,0.125000\naudio,0.125000\n").unwrap();
+    export_video(&files, &out, &settings, None, |_| true).unwrap();
+
+    std::fs::write(dir.join("streams"), "video,0.125000\naudio,0.500000\n").unwrap();
+    let err = export_video(&files, &out, &settings, None, |_| true).unwrap_err();
+    assert!(err.to_string().contains("audio stream"), "{}", err);
+}